@@ -0,0 +1,13 @@
+CREATE TABLE solvencysnapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    channel_balance_sat BIG INT NOT NULL,
+    onchain_balance_sat BIG INT NOT NULL,
+    user_liabilities_sat BIG INT NOT NULL,
+    held_escrow_sat BIG INT NOT NULL,
+    coverage_basis_points BIG INT NOT NULL,
+    below_threshold BOOLEAN NOT NULL,
+    message VARCHAR NOT NULL,
+    signature VARCHAR NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
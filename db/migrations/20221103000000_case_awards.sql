@@ -0,0 +1,12 @@
+CREATE TABLE caseawards (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    case_id INTEGER NOT NULL,
+    bounty_id INTEGER NOT NULL,
+    poster_user_id INTEGER NOT NULL,
+    winner_user_id INTEGER NOT NULL,
+    amount_sat UNSIGNED BIG INT NOT NULL,
+    fee_sat UNSIGNED BIG INT NOT NULL,
+    credit_sat UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
@@ -0,0 +1,17 @@
+CREATE TABLE bountymilestones (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    bounty_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    description VARCHAR NOT NULL,
+    reward_sat UNSIGNED BIG INT NOT NULL,
+    awarded BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE casemilestones (
+    case_id INTEGER PRIMARY KEY NOT NULL,
+    milestone_id INTEGER NOT NULL
+);
+
+ALTER TABLE caseawards ADD COLUMN milestone_id INTEGER;
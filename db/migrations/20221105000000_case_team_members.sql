@@ -0,0 +1,9 @@
+CREATE TABLE caseteammembers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    case_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    share_basis_points INTEGER NOT NULL,
+    accepted BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
@@ -0,0 +1,12 @@
+CREATE TABLE bountyrevisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    bounty_id INTEGER NOT NULL,
+    title VARCHAR NOT NULL,
+    description VARCHAR NOT NULL,
+    criteria_changed BOOLEAN NOT NULL,
+    viewed BOOLEAN NOT NULL,
+    approved BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL,
+    reviewed_time_ms UNSIGNED BIG INT NOT NULL
+);
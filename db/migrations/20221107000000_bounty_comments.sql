@@ -0,0 +1,11 @@
+CREATE TABLE bountycomments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    bounty_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    parent_id INTEGER,
+    body VARCHAR NOT NULL,
+    hidden_by_admin BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL,
+    edited_time_ms UNSIGNED BIG INT NOT NULL
+);
@@ -0,0 +1,11 @@
+CREATE TABLE casemessages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    case_id INTEGER NOT NULL,
+    sender_user_id INTEGER NOT NULL,
+    recipient_user_id INTEGER NOT NULL,
+    body VARCHAR NOT NULL,
+    encrypted BOOLEAN NOT NULL,
+    read BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
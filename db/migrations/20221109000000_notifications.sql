@@ -0,0 +1,16 @@
+CREATE TABLE notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    user_id INTEGER NOT NULL,
+    event_type VARCHAR NOT NULL,
+    message VARCHAR NOT NULL,
+    link VARCHAR NOT NULL,
+    read BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE disablednotifications (
+    user_id INTEGER NOT NULL,
+    event_type VARCHAR NOT NULL,
+    PRIMARY KEY (user_id, event_type)
+);
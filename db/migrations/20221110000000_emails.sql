@@ -0,0 +1,18 @@
+CREATE TABLE emails (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    user_id INTEGER NOT NULL,
+    event_type VARCHAR NOT NULL,
+    message VARCHAR NOT NULL,
+    link VARCHAR NOT NULL,
+    sent BOOLEAN NOT NULL,
+    num_attempts INTEGER NOT NULL,
+    last_error VARCHAR NOT NULL,
+    next_attempt_time_ms UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE emailsettings (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    enabled BOOLEAN NOT NULL
+);
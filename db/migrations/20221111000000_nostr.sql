@@ -0,0 +1,17 @@
+CREATE TABLE nostrevents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    recipient_user_id INTEGER,
+    message VARCHAR NOT NULL,
+    link VARCHAR NOT NULL,
+    sent BOOLEAN NOT NULL,
+    num_attempts INTEGER NOT NULL,
+    last_error VARCHAR NOT NULL,
+    next_attempt_time_ms UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE usernostrkeys (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    public_key VARCHAR NOT NULL
+);
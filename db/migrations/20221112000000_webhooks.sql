@@ -0,0 +1,25 @@
+CREATE TABLE webhooks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    user_id INTEGER NOT NULL,
+    url VARCHAR NOT NULL,
+    secret VARCHAR NOT NULL,
+    all_events BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE webhookdeliveries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    webhook_id INTEGER NOT NULL,
+    event_type VARCHAR NOT NULL,
+    payload VARCHAR NOT NULL,
+    delivered BOOLEAN NOT NULL,
+    num_attempts INTEGER NOT NULL,
+    last_status_code INTEGER NOT NULL,
+    last_error VARCHAR NOT NULL,
+    next_attempt_time_ms UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE INDEX webhookdeliveries_webhook_id ON webhookdeliveries (webhook_id);
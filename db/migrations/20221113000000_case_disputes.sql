@@ -0,0 +1,33 @@
+CREATE TABLE casedisputes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    case_id INTEGER NOT NULL UNIQUE,
+    reason VARCHAR NOT NULL,
+    resolved BOOLEAN NOT NULL,
+    resolution VARCHAR NOT NULL,
+    submitter_share_basis_points INTEGER NOT NULL,
+    resolution_note VARCHAR NOT NULL,
+    resolved_by_user_id INTEGER,
+    created_time_ms UNSIGNED BIG INT NOT NULL,
+    resolved_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE disputeevidence (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    dispute_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    body VARCHAR NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE caseauditevents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    case_id INTEGER NOT NULL,
+    user_id INTEGER,
+    event_type VARCHAR NOT NULL,
+    details VARCHAR NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE INDEX caseauditevents_case_id ON caseauditevents (case_id);
@@ -0,0 +1,22 @@
+CREATE TABLE caserevisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    case_id INTEGER NOT NULL,
+    case_details VARCHAR NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE INDEX caserevisions_case_id ON caserevisions (case_id);
+
+CREATE TABLE caseresponses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    case_id INTEGER NOT NULL,
+    response_type VARCHAR NOT NULL,
+    reason VARCHAR NOT NULL,
+    note VARCHAR NOT NULL,
+    addressed BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE INDEX caseresponses_case_id ON caseresponses (case_id);
@@ -0,0 +1,21 @@
+CREATE TABLE bountyclaimsettings (
+    bounty_id INTEGER PRIMARY KEY NOT NULL,
+    claim_window_ms UNSIGNED BIG INT NOT NULL,
+    stake_sat UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE bountyclaims (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    bounty_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    stake_sat UNSIGNED BIG INT NOT NULL,
+    released BOOLEAN NOT NULL,
+    release_reason VARCHAR NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL,
+    expires_time_ms UNSIGNED BIG INT NOT NULL,
+    released_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+-- A bounty can only be reserved by one hunter at a time.
+CREATE UNIQUE INDEX bountyclaims_active_bounty_id ON bountyclaims (bounty_id) WHERE NOT released;
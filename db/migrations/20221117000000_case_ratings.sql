@@ -0,0 +1,14 @@
+CREATE TABLE caseratings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_id VARCHAR NOT NULL,
+    case_id INTEGER NOT NULL,
+    rater_user_id INTEGER NOT NULL,
+    ratee_user_id INTEGER NOT NULL,
+    rater_role VARCHAR NOT NULL,
+    score INTEGER NOT NULL,
+    review VARCHAR NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL,
+    UNIQUE(case_id, rater_user_id)
+);
+
+CREATE INDEX caseratings_ratee_user_id ON caseratings (ratee_user_id);
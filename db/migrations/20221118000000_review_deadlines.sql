@@ -0,0 +1,13 @@
+CREATE TABLE bountyreviewdeadlines (
+    bounty_id INTEGER PRIMARY KEY NOT NULL,
+    review_deadline_ms UNSIGNED BIG INT NOT NULL
+);
+
+-- A reminder is sent once for each deadline, so a resubmitted case gets a new one.
+CREATE TABLE casedeadlinereminders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    case_id INTEGER NOT NULL,
+    deadline_ms UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL,
+    UNIQUE(case_id, deadline_ms)
+);
@@ -0,0 +1,6 @@
+-- One token per login session. The token is replaced when the user logs in or out.
+CREATE TABLE csrftokens (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    token VARCHAR NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
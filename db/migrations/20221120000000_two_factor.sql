@@ -0,0 +1,23 @@
+-- The secret is only used for logins once the user has confirmed a code from it.
+CREATE TABLE twofactorsettings (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    secret VARCHAR NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    last_used_step UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+CREATE TABLE twofactorrecoverycodes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    code_hash VARCHAR NOT NULL,
+    used BOOLEAN NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
+
+-- A single row with the market's two-factor requirements.
+CREATE TABLE twofactorpolicy (
+    id INTEGER PRIMARY KEY NOT NULL,
+    require_for_withdrawals BOOLEAN NOT NULL,
+    require_for_admins BOOLEAN NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Single-use password reset tokens. Only a hash of each token is stored.
+CREATE TABLE passwordresettokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    token_hash VARCHAR NOT NULL,
+    method VARCHAR NOT NULL,
+    used BOOLEAN NOT NULL,
+    expiry_time_ms UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Deactivated users are kept, so that their cases and bounties still have an owner.
+CREATE TABLE deactivatedaccounts (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    bond_sat UNSIGNED BIG INT NOT NULL,
+    refund_sat UNSIGNED BIG INT NOT NULL,
+    created_time_ms UNSIGNED BIG INT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- The part of the reward held back from the poster while a dispute is open.
+ALTER TABLE casedisputes ADD COLUMN reserved_sat UNSIGNED BIG INT NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Csrf tokens are kept for each browser session in a private cookie.
+DROP TABLE csrftokens;
@@ -0,0 +1,5 @@
+-- Sessions that were logged in before a password reset are ended.
+CREATE TABLE sessionresets (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    reset_time_ms UNSIGNED BIG INT NOT NULL
+);
@@ -0,0 +1,191 @@
+use crate::base::BaseContext;
+use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{AccountInfo, AdminPromotionInput, RocketAuthUser, Webhook};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket::State;
+use rocket_auth::{AdminUser, User, Users};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    admins: Vec<RocketAuthUser>,
+    config_admin_username: String,
+    current_user_id: i32,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        config: &Config,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let admins = RocketAuthUser::all_admins(&mut db)
+            .await
+            .map_err(|_| "failed to get admins.")?;
+        Ok(Context {
+            base_context,
+            flash,
+            admins,
+            config_admin_username: config.admin_username.clone(),
+            current_user_id: user.id(),
+        })
+    }
+}
+
+#[post("/", data = "<admin_promotion_form>")]
+async fn promote(
+    admin_promotion_form: Form<AdminPromotionInput>,
+    mut db: Connection<Db>,
+    users: &State<Users>,
+    _user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let admin_promotion_input = admin_promotion_form.into_inner();
+
+    match promote_user(&mut db, users, admin_promotion_input).await {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/admins", index())),
+            "User successfully made an admin.",
+        ),
+        Err(e) => {
+            error_!("Promote admin error: {}", e);
+            Flash::error(Redirect::to(uri!("/admins", index())), e)
+        }
+    }
+}
+
+async fn promote_user(
+    db: &mut Connection<Db>,
+    users: &Users,
+    admin_promotion_input: AdminPromotionInput,
+) -> Result<(), String> {
+    let username = admin_promotion_input.username.trim().to_lowercase();
+    let mut user = users
+        .get_by_email(&username)
+        .await
+        .map_err(|_| "User not found.")?;
+    if user.is_admin {
+        return Err("User is already an admin.".to_string());
+    };
+    // Admin users cannot withdraw funds, so any balance would be stuck.
+    let account_info = AccountInfo::account_info_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to get account info.")?;
+    if account_info.account_balance_sat > 0 {
+        return Err("User must withdraw their balance before becoming an admin.".to_string());
+    };
+    // Open bounties, cases and claims can still move funds into the account.
+    let num_open_items = AccountInfo::num_open_items_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to get open bounties, cases and claims.")?;
+    if num_open_items > 0 {
+        return Err(
+            "User must close their bounties, cases and claims before becoming an admin."
+                .to_string(),
+        );
+    };
+
+    user.is_admin = true;
+    users
+        .modify(&user)
+        .await
+        .map_err(|_| "failed to promote user.")?;
+    Ok(())
+}
+
+#[delete("/<username>")]
+async fn demote(
+    username: &str,
+    mut db: Connection<Db>,
+    users: &State<Users>,
+    config: &State<Config>,
+    user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    match demote_user(&mut db, users, config, user, username).await {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/admins", index())),
+            "Admin successfully removed.",
+        ),
+        Err(e) => {
+            error_!("Demote admin({}) error: {}", username, e);
+            Flash::error(Redirect::to(uri!("/admins", index())), e)
+        }
+    }
+}
+
+async fn demote_user(
+    db: &mut Connection<Db>,
+    users: &Users,
+    config: &Config,
+    current_user: User,
+    username: &str,
+) -> Result<(), String> {
+    let username = username.trim().to_lowercase();
+    // The admin from the config is restored on every start.
+    if username == config.admin_username.trim().to_lowercase() {
+        return Err("The admin from the config cannot be removed.".to_string());
+    };
+    let mut user = users
+        .get_by_email(&username)
+        .await
+        .map_err(|_| "User not found.")?;
+    if user.id() == current_user.id() {
+        return Err("You cannot remove yourself as an admin.".to_string());
+    };
+    if !user.is_admin {
+        return Err("User is not an admin.".to_string());
+    };
+
+    user.is_admin = false;
+    users
+        .modify(&user)
+        .await
+        .map_err(|_| "failed to demote admin.")?;
+    // Only admins can receive the events of every user.
+    Webhook::disable_all_events_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to update webhooks.")?;
+    Ok(())
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    user: User,
+    admin_user: AdminUser,
+    config: &State<Config>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, user, Some(admin_user), config, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("admins", context))
+}
+
+pub fn admins_stage() -> AdHoc {
+    AdHoc::on_ignite("Admins Stage", |rocket| async {
+        rocket.mount("/admins", routes![index, promote, demote])
+    })
+}
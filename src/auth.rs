@@ -1,10 +1,16 @@
 use crate::base::BaseContext;
 use crate::config::Config;
+use crate::csrf;
 use crate::db::Db;
 use crate::lightning;
-use crate::models::{AdminSettings, UserAccount};
+use crate::models::{AdminSettings, TwoFactorSettings, UserAccount};
+use crate::rate_limit::LoginLimiter;
+use crate::reset_password;
+use crate::two_factor;
 use crate::util;
 use rocket::fairing::AdHoc;
+use rocket::http::{CookieJar, Status};
+use rocket::request::FlashMessage;
 use rocket::State;
 use rocket::{form::*, get, post, response::Redirect, routes};
 use rocket_auth::prelude::Error;
@@ -22,20 +28,82 @@ fn not_authorized() -> Redirect {
 }
 
 #[get("/login")]
-async fn get_login(mut db: Connection<Db>, user: Option<User>) -> Result<Template, String> {
+async fn get_login(
+    flash: Option<FlashMessage<'_>>,
+    mut db: Connection<Db>,
+    user: Option<User>,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
     let base_context = BaseContext::raw(&mut db, user.clone(), None)
         .await
         .map_err(|_| "failed to get base template.")?;
     Ok(Template::render(
         "login",
-        json!({ "base_context": base_context }),
+        json!({ "base_context": base_context, "flash": flash }),
     ))
 }
 
+#[derive(Responder)]
+enum LoginError {
+    Locked(Status),
+    Failed(String),
+}
+
 #[post("/login", data = "<form>")]
-async fn post_login(auth: Auth<'_>, form: Form<Login>) -> Result<Redirect, String> {
-    auth.login(&form).await.map_err(|_| "failed to login.")?;
-    Ok(Redirect::to("/"))
+async fn post_login(
+    auth: Auth<'_>,
+    form: Form<Login>,
+    mut db: Connection<Db>,
+    cookies: &CookieJar<'_>,
+    config: &State<Config>,
+    users: &State<Users>,
+    login_limiter: LoginLimiter<'_>,
+) -> Result<Redirect, LoginError> {
+    let email = form.email.to_lowercase();
+    let now = util::current_time_millis();
+
+    // Repeated failures from the same address lock the account for a while, even with
+    // the right password.
+    if login_limiter.is_locked(&email, now) {
+        return Err(LoginError::Locked(Status::TooManyRequests));
+    };
+    match auth.login(&form).await {
+        Ok(_) => {
+            login_limiter.clear(&email);
+            let user = users
+                .get_by_email(&email)
+                .await
+                .map_err(|_| LoginError::Failed("failed to get user.".to_string()))?;
+            let deactivated = UserAccount::is_deactivated(&mut db, user.id())
+                .await
+                .map_err(|_| LoginError::Failed("failed to get user account.".to_string()))?;
+            if deactivated {
+                auth.logout()
+                    .map_err(|_| LoginError::Failed("failed to logout.".to_string()))?;
+                return Err(LoginError::Failed(
+                    "This account was deactivated.".to_string(),
+                ));
+            };
+            // Start the new session with a new csrf token.
+            csrf::forget_session_token(cookies);
+            reset_password::remember_login_time(cookies, now);
+            // Users with two-factor enabled still have to enter a code.
+            two_factor::forget_verified_session(cookies);
+            let two_factor_enabled = TwoFactorSettings::enabled_for_user(&mut db, user.id())
+                .await
+                .map_err(|_| {
+                    LoginError::Failed("failed to get two-factor settings.".to_string())
+                })?;
+            if two_factor_enabled {
+                return Ok(Redirect::to("/two_factor/verify"));
+            };
+            Ok(Redirect::to("/"))
+        }
+        Err(_) => {
+            login_limiter.record_failure(&email, config.inner(), now);
+            Err(LoginError::Failed("failed to login.".to_string()))
+        }
+    }
 }
 
 #[get("/signup")]
@@ -91,8 +159,14 @@ async fn post_signup(
 }
 
 #[get("/logout")]
-async fn logout(auth: Auth<'_>, mut db: Connection<Db>) -> Result<Template, String> {
+async fn logout(
+    auth: Auth<'_>,
+    mut db: Connection<Db>,
+    cookies: &CookieJar<'_>,
+) -> Result<Template, String> {
     auth.logout().map_err(|_| "failed to logout.")?;
+    two_factor::forget_verified_session(cookies);
+    csrf::forget_session_token(cookies);
     let base_context = BaseContext::raw(&mut db, None, None)
         .await
         .map_err(|_| "failed to get base template.")?;
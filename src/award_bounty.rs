@@ -0,0 +1,338 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{
+    AccountInfo, Bounty, BountyMilestone, Case, CaseAward, CaseTeamMember, SplitAwardInfo,
+};
+use crate::user_account::ActiveUser;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::Flash;
+use rocket::response::Redirect;
+use rocket::serde::Serialize;
+use rocket_auth::AdminUser;
+use rocket_auth::User;
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    bounty: Bounty,
+    milestones: Vec<BountyMilestone>,
+    maybe_milestone: Option<BountyMilestone>,
+    reward_sat: u64,
+    already_awarded: bool,
+    pending_cases: Vec<Case>,
+    account_balance_sat: i64,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        bounty_id: &str,
+        maybe_milestone_id: Option<&str>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let bounty = Bounty::single_by_public_id(&mut db, bounty_id)
+            .await
+            .map_err(|_| "failed to get bounty.")?;
+        if bounty.user_id != user.id() {
+            return Err("Bounty belongs to a different user.".to_string());
+        };
+        let milestones = BountyMilestone::all_for_bounty(&mut db, bounty.id.unwrap())
+            .await
+            .map_err(|_| "failed to get bounty milestones.")?;
+        let maybe_milestone = milestones
+            .iter()
+            .find(|milestone| Some(milestone.public_id.as_str()) == maybe_milestone_id)
+            .cloned();
+        let (reward_sat, already_awarded, pending_cases) = match &maybe_milestone {
+            Some(milestone) => (
+                milestone.reward_sat,
+                milestone.awarded,
+                Case::all_pending_for_milestone(&mut db, milestone.id.unwrap())
+                    .await
+                    .map_err(|_| "failed to get pending cases.")?,
+            ),
+            None if milestones.is_empty() => (
+                bounty.price_sat,
+                CaseAward::num_for_bounty(&mut db, bounty.id.unwrap())
+                    .await
+                    .map_err(|_| "failed to get case awards.")?
+                    > 0,
+                Case::all_pending_for_bounty(&mut db, bounty.id.unwrap())
+                    .await
+                    .map_err(|_| "failed to get pending cases.")?,
+            ),
+            // Milestone bounties are awarded one milestone at a time.
+            None => (0, false, vec![]),
+        };
+        let account_balance_sat = AccountInfo::total_account_balance_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get account balance.")?;
+        Ok(Context {
+            base_context,
+            flash,
+            bounty,
+            milestones,
+            maybe_milestone,
+            reward_sat,
+            already_awarded,
+            pending_cases,
+            account_balance_sat,
+        })
+    }
+}
+
+#[post("/<id>", data = "<split_award_info>")]
+async fn award(
+    id: &str,
+    split_award_info: Form<SplitAwardInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let split_award_info = split_award_info.into_inner();
+    let milestone_id = split_award_info.milestone_public_id.clone();
+    match award_bounty(id, split_award_info, &mut db, active_user.user).await {
+        Ok(_) => Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Bounty reward awarded.",
+        ),
+        Err(e) => Flash::error(
+            Redirect::to(format!(
+                "/{}/{}?milestone={}",
+                "award_bounty",
+                id,
+                milestone_id.unwrap_or_default()
+            )),
+            e,
+        ),
+    }
+}
+
+async fn award_bounty(
+    id: &str,
+    split_award_info: SplitAwardInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty.")?;
+    if bounty.user_id != user.id() {
+        return Err("Bounty belongs to a different user.".to_string());
+    };
+
+    let milestones = BountyMilestone::all_for_bounty(db, bounty.id.unwrap())
+        .await
+        .map_err(|_| "failed to get bounty milestones.")?;
+    // Whether the reward was already paid is checked when the awards are inserted.
+    let (reward_sat, maybe_milestone) = if milestones.is_empty() {
+        (bounty.price_sat, None)
+    } else {
+        let milestone_public_id = split_award_info.milestone_public_id.unwrap_or_default();
+        let milestone = milestones
+            .into_iter()
+            .find(|milestone| milestone.public_id == milestone_public_id)
+            .ok_or("Select a milestone to award.")?;
+        (milestone.reward_sat, Some(milestone))
+    };
+
+    let mut winning_cases: Vec<(Case, u64)> = vec![];
+    for case_share in split_award_info.case_shares {
+        let share = case_share.share.unwrap_or(0);
+        if share == 0 {
+            continue;
+        }
+        let case = Case::single_by_public_id(db, &case_share.case_public_id)
+            .await
+            .map_err(|_| "failed to get case.")?;
+        if case.bounty_id != bounty.id.unwrap() {
+            return Err("Case belongs to a different bounty.".to_string());
+        };
+        if !case.paid || case.awarded || case.canceled_by_seller || case.canceled_by_buyer {
+            return Err("Case is not pending.".to_string());
+        };
+        if let Some(milestone) = &maybe_milestone {
+            let maybe_case_milestone = BountyMilestone::single_for_case(db, case.id.unwrap())
+                .await
+                .map_err(|_| "failed to get case milestone.")?;
+            if maybe_case_milestone.and_then(|m| m.id) != milestone.id {
+                return Err("Case was submitted for a different milestone.".to_string());
+            };
+        };
+        if winning_cases.iter().any(|(c, _)| c.id == case.id) {
+            return Err("Case is listed more than once.".to_string());
+        };
+        winning_cases.push((case, share));
+    }
+    if winning_cases.is_empty() {
+        return Err("Select at least one case to award.".to_string());
+    };
+
+    let shares: Vec<u64> = winning_cases.iter().map(|(_, share)| *share).collect();
+    let amounts_sat = split_reward_sat(reward_sat, &shares, &split_award_info.share_type)?;
+
+    let now = util::current_time_millis();
+    let maybe_milestone_id = maybe_milestone.and_then(|m| m.id);
+    let mut case_awards: Vec<CaseAward> = vec![];
+    for ((case, _), amount_sat) in winning_cases.iter().zip(amounts_sat) {
+        case_awards.extend(
+            case_awards_for_case(db, &bounty, case, amount_sat, maybe_milestone_id, now).await?,
+        );
+    }
+
+    CaseAward::do_split_award(db, bounty.user_id, maybe_milestone_id, case_awards).await
+}
+
+/// Turns the shares of the winning cases, given as percentages or as amounts, into the
+/// amounts of the reward that they get.
+pub fn split_reward_sat(
+    reward_sat: u64,
+    shares: &[u64],
+    share_type: &str,
+) -> Result<Vec<u64>, String> {
+    let max_share = match share_type {
+        "percent" => 100,
+        "amount" => reward_sat,
+        _ => return Err("Invalid share type.".to_string()),
+    };
+    if shares.iter().any(|share| *share > max_share) {
+        return Err("Share is larger than the reward.".to_string());
+    };
+    let total_share = shares
+        .iter()
+        .try_fold(0u64, |total, share| total.checked_add(*share))
+        .ok_or("Shares are too large.")?;
+    match share_type {
+        "percent" => {
+            if total_share != 100 {
+                return Err("Percentages must add up to 100.".to_string());
+            };
+            let mut amounts_sat = shares
+                .iter()
+                .map(|share| reward_sat.checked_mul(*share).map(|amount| amount / 100))
+                .collect::<Option<Vec<u64>>>()
+                .ok_or("Reward is too large to split.")?;
+            // Give any rounding remainder to the last winner.
+            let remainder_sat = reward_sat
+                .checked_sub(amounts_sat.iter().sum::<u64>())
+                .ok_or("Reward is too large to split.")?;
+            if let Some(last_amount_sat) = amounts_sat.last_mut() {
+                *last_amount_sat += remainder_sat;
+            }
+            Ok(amounts_sat)
+        }
+        _ => {
+            if total_share != reward_sat {
+                return Err(format!(
+                    "Amounts must add up to the reward of {} sats.",
+                    reward_sat
+                ));
+            };
+            Ok(shares.to_vec())
+        }
+    }
+}
+
+/// Splits the share of a case between the members of its team. Invitations
+/// that were never accepted do not get a share.
+pub async fn case_awards_for_case(
+    db: &mut Connection<Db>,
+    bounty: &Bounty,
+    case: &Case,
+    amount_sat: u64,
+    maybe_milestone_id: Option<i32>,
+    now: u64,
+) -> Result<Vec<CaseAward>, String> {
+    let team_members: Vec<CaseTeamMember> = CaseTeamMember::all_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get case team members.")?
+        .into_iter()
+        .filter(|member| member.accepted)
+        .collect();
+    let mut member_amounts_sat: Vec<(i32, u64)> = team_members
+        .iter()
+        .map(|member| {
+            (
+                member.user_id,
+                amount_sat * (member.share_basis_points as u64) / 10000,
+            )
+        })
+        .filter(|(_, member_amount_sat)| *member_amount_sat > 0)
+        .collect();
+    let submitter_amount_sat = amount_sat
+        .checked_sub(member_amounts_sat.iter().map(|(_, a)| a).sum::<u64>())
+        .ok_or("Case team shares add up to more than the reward.")?;
+    member_amounts_sat.insert(0, (case.buyer_user_id, submitter_amount_sat));
+
+    let mut case_awards: Vec<CaseAward> = vec![];
+    for (winner_user_id, member_amount_sat) in member_amounts_sat {
+        let fee_sat = divide_round_up(
+            member_amount_sat * (bounty.fee_rate_basis_points as u64),
+            10000,
+        );
+        case_awards.push(CaseAward {
+            id: None,
+            public_id: util::create_uuid(),
+            case_id: case.id.unwrap(),
+            bounty_id: bounty.id.unwrap(),
+            poster_user_id: bounty.user_id,
+            winner_user_id,
+            milestone_id: maybe_milestone_id,
+            amount_sat: member_amount_sat,
+            fee_sat,
+            credit_sat: member_amount_sat - fee_sat,
+            created_time_ms: now,
+        });
+    }
+    Ok(case_awards)
+}
+
+fn divide_round_up(dividend: u64, divisor: u64) -> u64 {
+    (dividend + divisor - 1) / divisor
+}
+
+#[get("/<id>?<milestone>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    id: &str,
+    milestone: Option<String>,
+    db: Connection<Db>,
+    active_user: ActiveUser,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(
+        db,
+        id,
+        milestone.as_deref(),
+        flash,
+        active_user.user,
+        admin_user,
+        csrf_token,
+    )
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("awardbounty", context))
+}
+
+pub fn award_bounty_stage() -> AdHoc {
+    AdHoc::on_ignite("Award Bounty Stage", |rocket| async {
+        rocket.mount("/award_bounty", routes![index, award])
+    })
+}
@@ -1,5 +1,6 @@
+use crate::csrf::CsrfToken;
 use crate::db::Db;
-use crate::models::{AccountInfo, AdminInfo, AdminSettings};
+use crate::models::{AccountInfo, AdminInfo, AdminSettings, CaseMessage, Notification};
 use rocket::serde::Serialize;
 use rocket_auth::AdminUser;
 use rocket_auth::User;
@@ -10,9 +11,12 @@ use rocket_db_pools::Connection;
 pub struct BaseContext {
     user: Option<User>,
     account_info: Option<AccountInfo>,
+    num_unread_messages: Option<u32>,
+    num_unread_notifications: Option<u32>,
     admin_user: Option<AdminUser>,
     admin_info: Option<AdminInfo>,
     admin_settings: Option<AdminSettings>,
+    csrf_token: String,
 }
 
 impl BaseContext {
@@ -29,6 +33,22 @@ impl BaseContext {
             ),
             None => None,
         };
+        let num_unread_messages = match user {
+            Some(ref u) => Some(
+                CaseMessage::num_unread_for_user(db, u.id())
+                    .await
+                    .map_err(|_| "failed to get number of unread messages.")?,
+            ),
+            None => None,
+        };
+        let num_unread_notifications = match user {
+            Some(ref u) => Some(
+                Notification::num_unread_for_user(db, u.id())
+                    .await
+                    .map_err(|_| "failed to get number of unread notifications.")?,
+            ),
+            None => None,
+        };
         let admin_info = match admin_user {
             Some(_) => Some(
                 AdminInfo::admin_info(db)
@@ -43,9 +63,22 @@ impl BaseContext {
         Ok(BaseContext {
             user,
             account_info,
+            num_unread_messages,
+            num_unread_notifications,
             admin_user,
             admin_info,
             admin_settings: Some(admin_settings),
+            csrf_token: "".to_string(),
         })
     }
+
+    /// Adds the token of the session for the forms on the page. Forms that change state
+    /// send it back, so that other sites cannot submit them.
+    pub fn with_csrf_token(self, csrf_token: CsrfToken) -> BaseContext {
+        let csrf_token = match self.user {
+            Some(_) => csrf_token.into_inner(),
+            None => "".to_string(),
+        };
+        BaseContext { csrf_token, ..self }
+    }
 }
@@ -1,12 +1,21 @@
 use crate::base::BaseContext;
+use crate::bounty_comments::{self, CommentDisplay};
+use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
-use crate::models::{Bounty, BountyDisplay};
+use crate::models::{
+    Bounty, BountyClaim, BountyClaimSettings, BountyComment, BountyDisplay, BountyMilestone,
+    BountyReviewDeadline, NostrEvent,
+};
+use crate::nostr_publisher;
 use crate::user_account::ActiveUser;
+use crate::util;
 use rocket::fairing::AdHoc;
 use rocket::request::FlashMessage;
 use rocket::response::Flash;
 use rocket::response::Redirect;
 use rocket::serde::Serialize;
+use rocket::State;
 use rocket_auth::AdminUser;
 use rocket_auth::User;
 use rocket_db_pools::Connection;
@@ -18,6 +27,13 @@ struct Context {
     base_context: BaseContext,
     flash: Option<(String, String)>,
     bounty_display: BountyDisplay,
+    milestones: Vec<BountyMilestone>,
+    completed: bool,
+    comments: Vec<CommentDisplay>,
+    maybe_claim_settings: Option<BountyClaimSettings>,
+    maybe_active_claim: Option<BountyClaim>,
+    maybe_review_deadline: Option<BountyReviewDeadline>,
+    review_deadline_days: u64,
     user: Option<User>,
     admin_user: Option<AdminUser>,
 }
@@ -29,10 +45,13 @@ impl Context {
         flash: Option<(String, String)>,
         user: Option<User>,
         admin_user: Option<AdminUser>,
+        config: &Config,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, user.clone(), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let bounty_display = BountyDisplay::single_by_public_id(&mut db, bounty_id)
             .await
             .map_err(|_| "failed to get admin settings.")?;
@@ -44,11 +63,53 @@ impl Context {
         {
             return Err("Bounty is not approved.".to_string());
         };
+        let milestones =
+            BountyMilestone::all_for_bounty(&mut db, bounty_display.bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get bounty milestones.")?;
+        // A milestone bounty is complete once its last milestone is awarded.
+        let completed =
+            !milestones.is_empty() && milestones.iter().all(|milestone| milestone.awarded);
+        let comments = BountyComment::all_for_bounty(&mut db, bounty_display.bounty.id.unwrap())
+            .await
+            .map_err(|_| "failed to get bounty comments.")?;
+        let comments = bounty_comments::thread_comments(
+            comments,
+            bounty_display.bounty.user_id,
+            user.as_ref(),
+            admin_user.is_some(),
+        );
+        let maybe_claim_settings =
+            BountyClaimSettings::single_for_bounty(&mut db, bounty_display.bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get claim settings.")?;
+        // A claim past its window no longer reserves the bounty, even before it is released.
+        let now = util::current_time_millis();
+        let maybe_active_claim =
+            BountyClaim::active_for_bounty(&mut db, bounty_display.bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get bounty claim.")?
+                .filter(|claim| claim.expires_time_ms > now);
+        let maybe_review_deadline =
+            BountyReviewDeadline::single_for_bounty(&mut db, bounty_display.bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get review deadline.")?;
+        let review_deadline_days = maybe_review_deadline
+            .as_ref()
+            .map(|deadline| deadline.review_deadline_ms / (24 * 60 * 60 * 1000))
+            .unwrap_or(config.default_review_deadline_days);
 
         Ok(Context {
             base_context,
             flash,
             bounty_display,
+            milestones,
+            completed,
+            comments,
+            maybe_claim_settings,
+            maybe_active_claim,
+            maybe_review_deadline,
+            review_deadline_days,
             user,
             admin_user,
         })
@@ -60,6 +121,7 @@ async fn submit(
     id: &str,
     mut db: Connection<Db>,
     active_user: ActiveUser,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match submit_bounty(&mut db, id, active_user.user).await {
         Ok(_) => Ok(Flash::success(
@@ -99,8 +161,10 @@ async fn approve(
     mut db: Connection<Db>,
     _user: User,
     _admin_user: AdminUser,
+    config: &State<Config>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
-    match approve_bounty(&mut db, id).await {
+    match approve_bounty(&mut db, id, config.inner()).await {
         Ok(_) => Ok(Flash::success(
             Redirect::to(uri!("/bounty", index(id))),
             "Marked as approved".to_string(),
@@ -112,7 +176,11 @@ async fn approve(
     }
 }
 
-async fn approve_bounty(db: &mut Connection<Db>, id: &str) -> Result<(), String> {
+async fn approve_bounty(
+    db: &mut Connection<Db>,
+    id: &str,
+    config: &Config,
+) -> Result<(), String> {
     let bounty = Bounty::single_by_public_id(db, id)
         .await
         .map_err(|_| "failed to get bounty")?;
@@ -126,6 +194,15 @@ async fn approve_bounty(db: &mut Connection<Db>, id: &str) -> Result<(), String>
     Bounty::mark_as_approved(db, id)
         .await
         .map_err(|_| "failed to approve bounty")?;
+    if nostr_publisher::nostr_enabled(config) {
+        NostrEvent::enqueue_note(
+            db,
+            &format!("New bounty: {}\nReward: {} sats", bounty.title, bounty.price_sat),
+            &format!("/bounty/{}", id),
+        )
+        .await
+        .map_err(|_| "failed to queue nostr note")?;
+    }
     Ok(())
 }
 
@@ -135,6 +212,7 @@ async fn reject(
     mut db: Connection<Db>,
     _user: User,
     _admin_user: AdminUser,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match reject_bounty(&mut db, id).await {
         Ok(_) => Ok(Flash::success(
@@ -171,6 +249,7 @@ async fn admin_deactivate(
     mut db: Connection<Db>,
     _user: User,
     _admin_user: AdminUser,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match deactivate_bounty_as_admin(&mut db, id).await {
         Ok(_) => Ok(Flash::success(
@@ -189,6 +268,7 @@ async fn deactivate(
     id: &str,
     mut db: Connection<Db>,
     active_user: ActiveUser,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match deactivate_bounty_as_seller(&mut db, id, active_user.user).await {
         Ok(_) => Ok(Flash::success(
@@ -244,9 +324,11 @@ async fn index(
     db: Connection<Db>,
     user: Option<User>,
     admin_user: Option<AdminUser>,
+    config: &State<Config>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, id, flash, user, admin_user)
+    let context = Context::raw(db, id, flash, user, admin_user, config.inner(), csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("bounty", context))
@@ -0,0 +1,191 @@
+use crate::csrf::CsrfChecked;
+use crate::db::Db;
+use crate::models::{Bounty, BountyClaim, BountyClaimSettings, BountyClaimSettingsInfo};
+use crate::user_account::ActiveUser;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket_auth::User;
+use rocket_db_pools::Connection;
+
+const MAX_CLAIM_WINDOW_HOURS: u64 = 30 * 24;
+
+#[put("/<id>/settings", data = "<settings_form>")]
+async fn update_settings(
+    id: &str,
+    settings_form: Form<BountyClaimSettingsInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let settings_info = settings_form.into_inner();
+
+    match update_claim_settings(id, settings_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Claim settings updated.",
+        )),
+        Err(e) => {
+            error_!("Update claim settings({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn update_claim_settings(
+    id: &str,
+    settings_info: BountyClaimSettingsInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let claim_window_hours = settings_info.claim_window_hours.unwrap_or(0);
+    let stake_sat = settings_info.stake_sat.unwrap_or(0);
+
+    if bounty.user_id != user.id() {
+        return Err("Bounty belongs to a different user.".to_string());
+    };
+    // An empty claim window turns claim mode off.
+    if claim_window_hours == 0 {
+        BountyClaimSettings::remove_for_bounty(db, bounty.id.unwrap())
+            .await
+            .map_err(|_| "failed to remove claim settings")?;
+        return Ok(());
+    };
+    if claim_window_hours > MAX_CLAIM_WINDOW_HOURS {
+        return Err(format!(
+            "Claim window cannot be longer than {} hours.",
+            MAX_CLAIM_WINDOW_HOURS
+        ));
+    };
+    if stake_sat > bounty.price_sat {
+        return Err("Claim stake cannot be more than the bounty price.".to_string());
+    };
+
+    let settings = BountyClaimSettings {
+        bounty_id: bounty.id.unwrap(),
+        claim_window_ms: claim_window_hours * 60 * 60 * 1000,
+        stake_sat,
+    };
+    BountyClaimSettings::set_for_bounty(settings, db)
+        .await
+        .map_err(|_| "failed to update claim settings")?;
+
+    Ok(())
+}
+
+#[post("/<id>")]
+async fn new(
+    id: &str,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match claim_bounty(id, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Bounty claimed.",
+        )),
+        Err(e) => {
+            error_!("Claim bounty({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn claim_bounty(id: &str, db: &mut Connection<Db>, user: User) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let settings = BountyClaimSettings::single_for_bounty(db, bounty.id.unwrap())
+        .await
+        .map_err(|_| "failed to get claim settings")?
+        .ok_or("Bounty cannot be claimed.")?;
+    let now = util::current_time_millis();
+
+    if !bounty.approved {
+        return Err("Bounty is not approved.".to_string());
+    };
+    if bounty.deactivated_by_seller || bounty.deactivated_by_admin {
+        return Err("Bounty is deactivated.".to_string());
+    };
+    if bounty.user_id == user.id() {
+        return Err("Bounty belongs to same user.".to_string());
+    };
+    if user.is_admin {
+        return Err("Admin user cannot claim a bounty.".to_string());
+    };
+
+    let claim = BountyClaim {
+        id: None,
+        public_id: util::create_uuid(),
+        bounty_id: bounty.id.unwrap(),
+        bounty_public_id: None,
+        bounty_title: None,
+        user_id: user.id(),
+        username: None,
+        stake_sat: settings.stake_sat,
+        released: false,
+        release_reason: "".to_string(),
+        created_time_ms: now,
+        expires_time_ms: now + settings.claim_window_ms,
+        released_time_ms: 0,
+    };
+    BountyClaim::insert(claim, db).await?;
+
+    Ok(())
+}
+
+#[delete("/<id>")]
+async fn release(
+    id: &str,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match release_claim(id, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Claim released.",
+        )),
+        Err(e) => {
+            error_!("Release claim({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn release_claim(id: &str, db: &mut Connection<Db>, user: User) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let now = util::current_time_millis();
+
+    let released =
+        BountyClaim::release_for_user(db, bounty.id.unwrap(), user.id(), "released", now)
+            .await
+            .map_err(|_| "failed to release claim")?;
+    if !released {
+        return Err("User does not hold a claim on the bounty.".to_string());
+    };
+
+    Ok(())
+}
+
+pub fn bounty_claims_stage() -> AdHoc {
+    AdHoc::on_ignite("Bounty Claims Stage", |rocket| async {
+        rocket.mount("/bounty_claims", routes![update_settings, new, release])
+    })
+}
@@ -0,0 +1,283 @@
+use crate::csrf::CsrfChecked;
+use crate::db::Db;
+use crate::models::{Bounty, BountyComment, BountyCommentEditInfo, BountyCommentInfo};
+use crate::user_account::ActiveUser;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use std::collections::HashMap;
+
+const MAX_COMMENT_LENGTH: usize = 2048;
+const MAX_COMMENT_DEPTH: usize = 5;
+const COMMENT_EDIT_WINDOW_MS: u64 = 15 * 60 * 1000;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CommentDisplay {
+    comment: BountyComment,
+    depth: usize,
+    by_poster: bool,
+    editable: bool,
+}
+
+/// Orders the comments of a bounty into threads, with each reply after its parent.
+pub fn thread_comments(
+    comments: Vec<BountyComment>,
+    poster_user_id: i32,
+    user: Option<&User>,
+    is_admin: bool,
+) -> Vec<CommentDisplay> {
+    let now = util::current_time_millis();
+    let comment_ids: Vec<i32> = comments.iter().map(|c| c.id.unwrap()).collect();
+    let mut replies: HashMap<Option<i32>, Vec<BountyComment>> = HashMap::new();
+    for comment in comments {
+        // Replies to a missing parent are shown at the top level.
+        let parent_id = comment.parent_id.filter(|id| comment_ids.contains(id));
+        replies.entry(parent_id).or_default().push(comment);
+    }
+
+    let mut displays = Vec::new();
+    let mut stack: Vec<(BountyComment, usize)> = replies
+        .remove(&None)
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .map(|c| (c, 0))
+        .collect();
+    while let Some((mut comment, depth)) = stack.pop() {
+        if let Some(children) = replies.remove(&comment.id) {
+            stack.extend(children.into_iter().rev().map(|c| (c, depth + 1)));
+        }
+        let editable = user.map(|u| u.id()) == Some(comment.user_id)
+            && !comment.hidden_by_admin
+            && now < comment.created_time_ms + COMMENT_EDIT_WINDOW_MS;
+        // Hidden comments keep their place in the thread, but only admins can read them.
+        if comment.hidden_by_admin && !is_admin {
+            comment.body = "".to_string();
+            comment.body_html = "".to_string();
+        }
+        displays.push(CommentDisplay {
+            by_poster: comment.user_id == poster_user_id,
+            depth: depth.min(MAX_COMMENT_DEPTH),
+            editable,
+            comment,
+        });
+    }
+    displays
+}
+
+#[post("/<id>", data = "<comment_form>")]
+async fn new(
+    id: &str,
+    comment_form: Form<BountyCommentInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let comment_info = comment_form.into_inner();
+
+    match create_comment(id, comment_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Comment posted.",
+        )),
+        Err(e) => {
+            error_!("Post comment({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn create_comment(
+    id: &str,
+    comment_info: BountyCommentInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let now = util::current_time_millis();
+
+    if !bounty.approved {
+        return Err("Bounty is not approved.".to_string());
+    };
+    if bounty.deactivated_by_seller || bounty.deactivated_by_admin {
+        return Err("Bounty is deactivated.".to_string());
+    };
+    if comment_info.body.is_empty() {
+        return Err("Comment cannot be empty.".to_string());
+    };
+    if comment_info.body.len() > MAX_COMMENT_LENGTH {
+        return Err("Comment length is too long.".to_string());
+    };
+    let parent_id = match comment_info.parent_public_id {
+        Some(parent_public_id) => {
+            let parent = BountyComment::single_by_public_id(db, &parent_public_id)
+                .await
+                .map_err(|_| "failed to get parent comment")?;
+            if parent.bounty_id != bounty.id.unwrap() {
+                return Err("Parent comment belongs to a different bounty.".to_string());
+            };
+            parent.id
+        }
+        None => None,
+    };
+
+    let comment = BountyComment {
+        id: None,
+        public_id: util::create_uuid(),
+        bounty_id: bounty.id.unwrap(),
+        user_id: user.id(),
+        username: None,
+        parent_id,
+        body: comment_info.body,
+        body_html: "".to_string(),
+        hidden_by_admin: false,
+        created_time_ms: now,
+        edited_time_ms: 0,
+    };
+    BountyComment::insert(comment, db)
+        .await
+        .map_err(|_| "failed to insert comment")?;
+
+    Ok(())
+}
+
+#[put("/<id>/<comment_id>", data = "<comment_form>")]
+async fn edit(
+    id: &str,
+    comment_id: &str,
+    comment_form: Form<BountyCommentEditInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let comment_info = comment_form.into_inner();
+
+    match edit_comment(comment_id, comment_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Comment updated.",
+        )),
+        Err(e) => {
+            error_!("Edit comment({}) error: {}", comment_id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn edit_comment(
+    comment_id: &str,
+    comment_info: BountyCommentEditInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let comment = BountyComment::single_by_public_id(db, comment_id)
+        .await
+        .map_err(|_| "failed to get comment")?;
+    let now = util::current_time_millis();
+
+    if comment.user_id != user.id() {
+        return Err("Comment belongs to a different user.".to_string());
+    };
+    if comment.hidden_by_admin {
+        return Err("Comment is hidden.".to_string());
+    };
+    if now >= comment.created_time_ms + COMMENT_EDIT_WINDOW_MS {
+        return Err("Comment can no longer be edited.".to_string());
+    };
+    if comment_info.body.is_empty() {
+        return Err("Comment cannot be empty.".to_string());
+    };
+    if comment_info.body.len() > MAX_COMMENT_LENGTH {
+        return Err("Comment length is too long.".to_string());
+    };
+
+    BountyComment::update_body(db, comment.id.unwrap(), &comment_info.body, now)
+        .await
+        .map_err(|_| "failed to update comment")?;
+
+    Ok(())
+}
+
+#[put("/<id>/<comment_id>/hide")]
+async fn hide(
+    id: &str,
+    comment_id: &str,
+    db: Connection<Db>,
+    _user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    set_hidden(id, comment_id, true, db).await
+}
+
+#[put("/<id>/<comment_id>/unhide")]
+async fn unhide(
+    id: &str,
+    comment_id: &str,
+    db: Connection<Db>,
+    _user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    set_hidden(id, comment_id, false, db).await
+}
+
+async fn set_hidden(
+    id: &str,
+    comment_id: &str,
+    hidden: bool,
+    mut db: Connection<Db>,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match set_comment_hidden(&mut db, comment_id, hidden).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            if hidden {
+                "Comment hidden."
+            } else {
+                "Comment restored."
+            },
+        )),
+        Err(e) => {
+            error_!("Hide comment({}) error: {}", comment_id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn set_comment_hidden(
+    db: &mut Connection<Db>,
+    comment_id: &str,
+    hidden: bool,
+) -> Result<(), String> {
+    let comment = BountyComment::single_by_public_id(db, comment_id)
+        .await
+        .map_err(|_| "failed to get comment")?;
+
+    BountyComment::set_hidden_by_admin(db, comment.id.unwrap(), hidden)
+        .await
+        .map_err(|_| "failed to update comment")?;
+
+    Ok(())
+}
+
+pub fn bounty_comments_stage() -> AdHoc {
+    AdHoc::on_ignite("Bounty Comments Stage", |rocket| async {
+        rocket.mount("/bounty_comments", routes![new, edit, hide, unhide])
+    })
+}
@@ -0,0 +1,277 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Bounty, BountyRevision};
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct DiffLine {
+    kind: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct RevisionDiff {
+    revision: BountyRevision,
+    previous_title: Option<String>,
+    description_diff: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    bounty: Bounty,
+    revision_diffs: Vec<RevisionDiff>,
+    maybe_pending_diff: Option<RevisionDiff>,
+    admin_user: Option<AdminUser>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        bounty_id: &str,
+        flash: Option<(String, String)>,
+        user: Option<User>,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, user.clone(), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let bounty = Bounty::single_by_public_id(&mut db, bounty_id)
+            .await
+            .map_err(|_| "failed to get bounty.")?;
+        let is_poster_or_admin =
+            user.as_ref().map(|u| u.id()) == Some(bounty.user_id) || admin_user.is_some();
+        if !(is_poster_or_admin || bounty.approved) {
+            return Err("Bounty is not approved.".to_string());
+        };
+        let revisions = BountyRevision::all_approved_for_bounty(&mut db, bounty.id.unwrap())
+            .await
+            .map_err(|_| "failed to get bounty revisions.")?;
+        let (live_title, live_description) =
+            BountyRevision::live_for_bounty(&mut db, bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get live bounty.")?;
+        // Only the poster and admins can see an edit that is waiting for review.
+        let maybe_pending_diff = if is_poster_or_admin {
+            BountyRevision::pending_for_bounty(&mut db, bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get pending revision.")?
+                .map(|revision| {
+                    revision_diff(
+                        revision,
+                        Some((live_title.as_str(), live_description.as_str())),
+                    )
+                })
+        } else {
+            None
+        };
+
+        let mut revision_diffs = Vec::new();
+        let mut previous: Option<&BountyRevision> = None;
+        for revision in revisions.iter() {
+            let previous_version = previous.map(|p| (p.title.as_str(), p.description.as_str()));
+            revision_diffs.push(revision_diff(revision.clone(), previous_version));
+            previous = Some(revision);
+        }
+        // Newest revision first.
+        revision_diffs.reverse();
+
+        Ok(Context {
+            base_context,
+            flash,
+            bounty,
+            revision_diffs,
+            maybe_pending_diff,
+            admin_user,
+        })
+    }
+}
+
+fn revision_diff(revision: BountyRevision, previous: Option<(&str, &str)>) -> RevisionDiff {
+    let (previous_title, previous_description) = match previous {
+        Some((title, description)) => (Some(title.to_string()), description),
+        None => (None, ""),
+    };
+    let description_diff = diff_lines(previous_description, &revision.description);
+    RevisionDiff {
+        previous_title: previous_title.filter(|title| title != &revision.title),
+        description_diff,
+        revision,
+    }
+}
+
+/// Line diff of two texts, using their longest common subsequence of lines.
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let (n, m) = (old_lines.len(), new_lines.len());
+
+    // lcs[i][j] is the length of the common subsequence of old_lines[i..] and new_lines[j..].
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let line = |kind: &str, text: &str| DiffLine {
+        kind: kind.to_string(),
+        text: text.to_string(),
+    };
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_lines[i] == new_lines[j] {
+            diff.push(line("unchanged", old_lines[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(line("removed", old_lines[i]));
+            i += 1;
+        } else {
+            diff.push(line("added", new_lines[j]));
+            j += 1;
+        }
+    }
+    diff.extend(old_lines[i..].iter().map(|text| line("removed", *text)));
+    diff.extend(new_lines[j..].iter().map(|text| line("added", *text)));
+    diff
+}
+
+#[put("/<id>/<revision_id>/approve")]
+async fn approve(
+    id: &str,
+    revision_id: &str,
+    mut db: Connection<Db>,
+    _user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match approve_revision(&mut db, id, revision_id).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty_revisions", id)),
+            "Edit approved".to_string(),
+        )),
+        Err(e) => {
+            error_!("Approve revision({}) error: {}", revision_id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty_revisions", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn approve_revision(
+    db: &mut Connection<Db>,
+    id: &str,
+    revision_id: &str,
+) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let revision = BountyRevision::single_by_public_id(db, revision_id)
+        .await
+        .map_err(|_| "failed to get revision")?;
+    let now = util::current_time_millis();
+
+    if revision.bounty_id != bounty.id.unwrap() {
+        return Err("Revision belongs to a different bounty.".to_string());
+    };
+    if revision.viewed {
+        return Err("Revision is already reviewed.".to_string());
+    };
+
+    BountyRevision::mark_as_approved(db, revision.id.unwrap(), now).await?;
+    Ok(())
+}
+
+#[put("/<id>/<revision_id>/reject")]
+async fn reject(
+    id: &str,
+    revision_id: &str,
+    mut db: Connection<Db>,
+    _user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match reject_revision(&mut db, id, revision_id).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty_revisions", id)),
+            "Edit rejected".to_string(),
+        )),
+        Err(e) => {
+            error_!("Reject revision({}) error: {}", revision_id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty_revisions", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn reject_revision(
+    db: &mut Connection<Db>,
+    id: &str,
+    revision_id: &str,
+) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let revision = BountyRevision::single_by_public_id(db, revision_id)
+        .await
+        .map_err(|_| "failed to get revision")?;
+    let now = util::current_time_millis();
+
+    if revision.bounty_id != bounty.id.unwrap() {
+        return Err("Revision belongs to a different bounty.".to_string());
+    };
+    if revision.viewed {
+        return Err("Revision is already reviewed.".to_string());
+    };
+
+    BountyRevision::mark_as_rejected(db, revision.id.unwrap(), now)
+        .await
+        .map_err(|_| "failed to reject revision")?;
+    Ok(())
+}
+
+#[get("/<id>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    id: &str,
+    db: Connection<Db>,
+    user: Option<User>,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, id, flash, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("bountyrevisions", context))
+}
+
+pub fn bounty_revisions_stage() -> AdHoc {
+    AdHoc::on_ignite("Bounty Revisions Stage", |rocket| async {
+        rocket.mount("/bounty_revisions", routes![index, approve, reject])
+    })
+}
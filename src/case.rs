@@ -1,11 +1,17 @@
 use crate::base::BaseContext;
 use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::lightning;
-use crate::models::{Bounty, Case, RocketAuthUser};
+use crate::models::{
+    Bounty, BountyMilestone, BountyRevision, Case, CaseAttachment, CaseAward,
+    CaseChangesRequestInfo, CaseDispute, CaseRating, CaseRejectionInfo, CaseResponse,
+    CaseReviewDeadline, CaseTeamMember, RocketAuthUser, UserSettings,
+};
 use crate::user_account::ActiveUser;
 use crate::util;
 use rocket::fairing::AdHoc;
+use rocket::form::Form;
 use rocket::request::FlashMessage;
 use rocket::response::Flash;
 use rocket::response::Redirect;
@@ -16,13 +22,36 @@ use rocket_auth::User;
 use rocket_db_pools::Connection;
 use rocket_dyn_templates::Template;
 
+/// The reasons a bounty poster can give when rejecting a case.
+const REJECTION_REASONS: [&str; 5] = [
+    "does_not_meet_criteria",
+    "incomplete",
+    "duplicate",
+    "out_of_scope",
+    "other",
+];
+const MAX_NOTE_LENGTH: usize = 1024;
+
 #[derive(Debug, Serialize)]
 #[serde(crate = "rocket::serde")]
 struct Context {
     base_context: BaseContext,
     flash: Option<(String, String)>,
     case: Case,
+    case_awards: Vec<CaseAward>,
+    maybe_milestone: Option<BountyMilestone>,
+    team_members: Vec<CaseTeamMember>,
     maybe_bounty: Option<Bounty>,
+    criteria_changed_since_case: bool,
+    maybe_dispute: Option<CaseDispute>,
+    case_responses: Vec<CaseResponse>,
+    maybe_open_changes_request: Option<CaseResponse>,
+    rejection_reasons: [&'static str; 5],
+    attachments: Vec<CaseAttachment>,
+    attachments_encrypted: bool,
+    case_ratings: Vec<CaseRating>,
+    can_rate: bool,
+    maybe_review_deadline: Option<CaseReviewDeadline>,
     maybe_seller_user: Option<RocketAuthUser>,
     user: Option<User>,
     admin_user: Option<AdminUser>,
@@ -38,19 +67,93 @@ impl Context {
         user: Option<User>,
         admin_user: Option<AdminUser>,
         config: &Config,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, user.clone(), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let case = Case::single_by_public_id(&mut db, case_id)
             .await
             .map_err(|_| "failed to get case.")?;
+        let case_awards = CaseAward::all_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case awards.")?;
+        let maybe_milestone = BountyMilestone::single_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case milestone.")?;
+        let team_members = CaseTeamMember::all_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case team members.")?;
         let maybe_bounty = Bounty::single(&mut db, case.bounty_id).await.ok();
         // .map_err(|_| "failed to get bounty.")?;
         // {
         //     Ok(bounty) => Some(bounty),
         //     Err(_) => None
         // };
+        let last_criteria_change_time_ms =
+            BountyRevision::last_criteria_change_time_ms(&mut db, case.bounty_id)
+                .await
+                .map_err(|_| "failed to get bounty revisions.")?;
+        // Warn the submitter when the bounty details changed after the case was submitted.
+        let criteria_changed_since_case = last_criteria_change_time_ms
+            .map(|change_time_ms| change_time_ms > case.created_time_ms)
+            .unwrap_or(false);
+        let maybe_dispute = CaseDispute::single_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get dispute.")?;
+        // Responses and attachments are private, so only the participants and admins see them.
+        let is_participant_or_admin = user
+            .as_ref()
+            .map(|u| u.id() == case.seller_user_id || u.id() == case.buyer_user_id)
+            .unwrap_or(false)
+            || admin_user.is_some();
+        let (case_responses, attachments) = if is_participant_or_admin {
+            (
+                CaseResponse::all_for_case(&mut db, case.id.unwrap())
+                    .await
+                    .map_err(|_| "failed to get case responses.")?,
+                CaseAttachment::all_for_case(&mut db, case.id.unwrap())
+                    .await
+                    .map_err(|_| "failed to get case attachments.")?,
+            )
+        } else {
+            (vec![], vec![])
+        };
+        // Attachments must be encrypted when the poster has a PGP key.
+        let attachments_encrypted = !UserSettings::single(&mut db, case.seller_user_id)
+            .await
+            .map_err(|_| "failed to get seller user settings.")?
+            .pgp_key
+            .is_empty();
+        let maybe_open_changes_request = case_responses
+            .iter()
+            .rev()
+            .find(|response| response.response_type == "changes_requested" && !response.addressed)
+            .cloned();
+        let case_ratings = CaseRating::all_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case ratings.")?;
+        // Each side can rate the other once, after the case is decided.
+        let is_decided =
+            case.paid && (case.awarded || case.canceled_by_seller || case.canceled_by_buyer);
+        let can_rate = match &user {
+            Some(u) => {
+                is_decided
+                    && (u.id() == case.seller_user_id || u.id() == case.buyer_user_id)
+                    && !case_ratings
+                        .iter()
+                        .any(|rating| rating.rater_user_id == u.id())
+            }
+            None => false,
+        };
+        let maybe_review_deadline = CaseReviewDeadline::single_for_case(
+            &mut db,
+            case.id.unwrap(),
+            config.default_review_deadline_days * 24 * 60 * 60 * 1000,
+        )
+        .await
+        .map_err(|_| "failed to get review deadline.")?;
         let maybe_seller_user = RocketAuthUser::single(&mut db, case.seller_user_id)
             .await
             .ok();
@@ -63,7 +166,20 @@ impl Context {
             base_context,
             flash,
             case,
+            case_awards,
+            maybe_milestone,
+            team_members,
             maybe_bounty,
+            criteria_changed_since_case,
+            maybe_dispute,
+            case_responses,
+            maybe_open_changes_request,
+            rejection_reasons: REJECTION_REASONS,
+            attachments,
+            attachments_encrypted,
+            case_ratings,
+            can_rate,
+            maybe_review_deadline,
             maybe_seller_user,
             user,
             admin_user,
@@ -91,30 +207,43 @@ async fn get_lightning_node_pubkey(config: &Config) -> Result<String, String> {
     Ok(get_info_resp.identity_pubkey)
 }
 
-#[put("/<id>/award")]
-async fn award(
+#[put("/<id>/seller_cancel", data = "<rejection_form>")]
+async fn seller_cancel(
     id: &str,
+    rejection_form: Form<CaseRejectionInfo>,
     mut db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
-    match mark_case_as_awarded(id, &mut db, active_user.user.clone(), admin_user.clone()).await {
+    let rejection_info = rejection_form.into_inner();
+
+    match mark_case_as_canceled_by_seller(
+        id,
+        rejection_info,
+        &mut db,
+        active_user.user.clone(),
+        admin_user.clone(),
+    )
+    .await
+    {
         Ok(_) => Ok(Flash::success(
             Redirect::to(format!("/{}/{}", "case", id)),
-            "Case has been awarded.",
+            "Case marked as canceled by seller.",
         )),
         Err(e) => {
             error_!("DB update({}) error: {}", id, e);
             Err(Flash::error(
                 Redirect::to(format!("/{}/{}", "case", id)),
-                "Failed to mark case as awarded.",
+                format!("Failed to mark case as canceled by seller: {}", e),
             ))
         }
     }
 }
 
-async fn mark_case_as_awarded(
+async fn mark_case_as_canceled_by_seller(
     case_id: &str,
+    rejection_info: CaseRejectionInfo,
     db: &mut Connection<Db>,
     user: User,
     _admin_user: Option<AdminUser>,
@@ -127,69 +256,104 @@ async fn mark_case_as_awarded(
         return Err("User is not the case seller.".to_string());
     };
     if case.awarded {
-        return Err("case has already been rewarded.".to_string());
+        return Err("case has already been awarded.".to_string());
     };
     if case.canceled_by_seller || case.canceled_by_buyer {
-        return Err("case is already widthdrawn.".to_string());
+        return Err("case has already been canceled.".to_string());
     }
+    if !REJECTION_REASONS.contains(&rejection_info.reason.as_str()) {
+        return Err("rejection reason is not valid.".to_string());
+    };
+    if rejection_info.reason == "other" && rejection_info.note.is_empty() {
+        return Err("a note is required for other reasons.".to_string());
+    };
+    if rejection_info.note.len() > MAX_NOTE_LENGTH {
+        return Err("note length is too long.".to_string());
+    };
 
-    Case::mark_as_awarded(&mut *db, case.id.unwrap())
+    let response = CaseResponse {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id: case.id.unwrap(),
+        response_type: "rejected".to_string(),
+        reason: rejection_info.reason,
+        note: rejection_info.note,
+        addressed: false,
+        created_time_ms: util::current_time_millis(),
+    };
+    CaseResponse::reject(response, db)
         .await
-        .map_err(|_| "failed to mark case as awarded.".to_string())
+        .map_err(|_| "failed to mark case as canceled by seller.".to_string())
 }
 
-#[put("/<id>/seller_cancel")]
-async fn seller_cancel(
+#[put("/<id>/request_changes", data = "<changes_form>")]
+async fn request_changes(
     id: &str,
+    changes_form: Form<CaseChangesRequestInfo>,
     mut db: Connection<Db>,
     active_user: ActiveUser,
-    admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
-    match mark_case_as_canceled_by_seller(
-        id,
-        &mut db,
-        active_user.user.clone(),
-        admin_user.clone(),
-    )
-    .await
-    {
+    let changes_info = changes_form.into_inner();
+
+    match request_case_changes(id, changes_info, &mut db, active_user.user).await {
         Ok(_) => Ok(Flash::success(
             Redirect::to(format!("/{}/{}", "case", id)),
-            "Case marked as canceled by seller.",
+            "Changes requested.",
         )),
         Err(e) => {
-            error_!("DB update({}) error: {}", id, e);
-            Err(Flash::error(
-                Redirect::to(format!("/{}/{}", "case", id)),
-                "Failed to mark case as canceled by seller.",
-            ))
+            error_!("Request changes({}) error: {}", id, e);
+            Err(Flash::error(Redirect::to(format!("/{}/{}", "case", id)), e))
         }
     }
 }
 
-async fn mark_case_as_canceled_by_seller(
+async fn request_case_changes(
     case_id: &str,
+    changes_info: CaseChangesRequestInfo,
     db: &mut Connection<Db>,
     user: User,
-    _admin_user: Option<AdminUser>,
 ) -> Result<(), String> {
     let case = Case::single_by_public_id(db, case_id)
         .await
         .map_err(|_| "failed to get case.")?;
+    let maybe_open_changes_request =
+        CaseResponse::open_changes_request_for_case(db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get open request for changes.")?;
 
     if case.seller_user_id != user.id() {
         return Err("User is not the case seller.".to_string());
     };
-    if case.awarded {
-        return Err("case has already been awarded.".to_string());
+    if !case.paid {
+        return Err("Case is not paid.".to_string());
+    };
+    if case.awarded || case.canceled_by_seller || case.canceled_by_buyer {
+        return Err("Case is no longer pending.".to_string());
+    };
+    if maybe_open_changes_request.is_some() {
+        return Err("Changes are already requested.".to_string());
+    };
+    if changes_info.note.is_empty() {
+        return Err("Note cannot be empty.".to_string());
+    };
+    if changes_info.note.len() > MAX_NOTE_LENGTH {
+        return Err("Note length is too long.".to_string());
     };
-    if case.canceled_by_seller || case.canceled_by_buyer {
-        return Err("case has already been canceled.".to_string());
-    }
 
-    Case::mark_as_canceled_by_seller(&mut *db, case.id.unwrap())
+    let response = CaseResponse {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id: case.id.unwrap(),
+        response_type: "changes_requested".to_string(),
+        reason: "".to_string(),
+        note: changes_info.note,
+        addressed: false,
+        created_time_ms: util::current_time_millis(),
+    };
+    CaseResponse::request_changes(response, case.seller_user_id, case.buyer_user_id, db)
         .await
-        .map_err(|_| "failed to mark case as canceled by seller.".to_string())
+        .map_err(|_| "failed to request changes.".to_string())
 }
 
 #[put("/<id>/buyer_cancel")]
@@ -198,6 +362,7 @@ async fn buyer_cancel(
     mut db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match mark_case_as_canceled_by_buyer(id, &mut db, active_user.user.clone(), admin_user.clone())
         .await
@@ -229,7 +394,7 @@ async fn mark_case_as_canceled_by_buyer(
     if case.buyer_user_id != user.id() {
         return Err("User is not the case buyer.".to_string());
     };
-    if case.awarded{
+    if case.awarded {
         return Err("case has already been rewarded".to_string());
     };
     if case.canceled_by_seller || case.canceled_by_buyer {
@@ -249,9 +414,10 @@ async fn index(
     user: Option<User>,
     admin_user: Option<AdminUser>,
     config: &State<Config>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, id, flash, user, admin_user, config)
+    let context = Context::raw(db, id, flash, user, admin_user, config, csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("case", context))
@@ -261,7 +427,7 @@ pub fn case_stage() -> AdHoc {
     AdHoc::on_ignite("Case Stage", |rocket| async {
         rocket.mount(
             "/case",
-            routes![index, award, seller_cancel, buyer_cancel],
+            routes![index, seller_cancel, request_changes, buyer_cancel],
         )
     })
 }
@@ -0,0 +1,313 @@
+use crate::csrf::CsrfChecked;
+use crate::db::Db;
+use crate::image_util;
+use crate::models::{Case, CaseAttachment, FileUploadForm, UserSettings};
+use crate::user_account::ActiveUser;
+use crate::util;
+use pgp::composed::{Deserializable, Message};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::fs::TempFile;
+use rocket::http::{ContentType, Header};
+use rocket::response::{Flash, Redirect};
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use std::fs;
+
+const MAX_ATTACHMENT_SIZE_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_ATTACHMENTS_PER_CASE: usize = 10;
+const MAX_TOTAL_SIZE_BYTES_PER_CASE: u64 = 20 * 1024 * 1024;
+
+/// Plain attachments are limited to screenshots, logs, documents and archives.
+const ALLOWED_CONTENT_TYPES: [ContentType; 9] = [
+    ContentType::PNG,
+    ContentType::JPEG,
+    ContentType::GIF,
+    ContentType::WEBP,
+    ContentType::Plain,
+    ContentType::PDF,
+    ContentType::ZIP,
+    ContentType::GZIP,
+    ContentType::TAR,
+];
+const IMAGE_CONTENT_TYPES: [ContentType; 4] = [
+    ContentType::PNG,
+    ContentType::JPEG,
+    ContentType::GIF,
+    ContentType::WEBP,
+];
+
+#[derive(Responder)]
+struct AttachmentDownload {
+    inner: Vec<u8>,
+    content_type: ContentType,
+    content_disposition: Header<'static>,
+}
+
+/// Attachments can only be seen by the bounty poster, the case submitter and admins.
+fn can_view(case: &Case, user: &User, admin_user: &Option<AdminUser>) -> bool {
+    case.seller_user_id == user.id() || case.buyer_user_id == user.id() || admin_user.is_some()
+}
+
+/// Finds the type of a file from its first bytes, since the type sent by the browser
+/// is chosen by the uploader.
+fn sniff_content_type(bytes: &[u8]) -> Option<ContentType> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(ContentType::PNG)
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some(ContentType::JPEG)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(ContentType::GIF)
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(ContentType::WEBP)
+    } else if bytes.starts_with(b"%PDF-") {
+        Some(ContentType::PDF)
+    } else if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        Some(ContentType::ZIP)
+    } else if bytes.starts_with(b"\x1f\x8b") {
+        Some(ContentType::GZIP)
+    } else if bytes.len() >= 262 && &bytes[257..262] == b"ustar" {
+        Some(ContentType::TAR)
+    } else if std::str::from_utf8(bytes).is_ok() && !bytes.contains(&0) {
+        Some(ContentType::Plain)
+    } else {
+        None
+    }
+}
+
+/// Removes the characters that could break out of the quoted filename in a
+/// Content-Disposition header.
+fn sanitize_filename(filename: &str) -> String {
+    filename
+        .chars()
+        .filter(|c| !(c.is_control() || *c == '"' || *c == '\\'))
+        .collect()
+}
+
+fn get_file_bytes(tmp_file: &TempFile) -> Result<Vec<u8>, String> {
+    let path = tmp_file.path().ok_or("Path not found.")?;
+    let bytes = fs::read(path).map_err(|_| "Unable to read bytes")?;
+    Ok(bytes)
+}
+
+#[post("/<id>", data = "<upload_form>")]
+async fn new(
+    id: &str,
+    upload_form: Form<FileUploadForm<'_>>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let file = upload_form.into_inner().file;
+
+    match upload_attachment(id, file, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "case", id)),
+            "Attachment added.",
+        )),
+        Err(e) => {
+            error_!("Upload attachment({}) error: {}", id, e);
+            Err(Flash::error(Redirect::to(format!("/{}/{}", "case", id)), e))
+        }
+    }
+}
+
+async fn upload_attachment(
+    id: &str,
+    tmp_file: TempFile<'_>,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case")?;
+    let attachments = CaseAttachment::all_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get case attachments")?;
+    let total_size_bytes = CaseAttachment::total_size_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get case attachments size")?;
+    let seller_pgp_key = UserSettings::single(db, case.seller_user_id)
+        .await
+        .map_err(|_| "failed to get seller user settings")?
+        .pgp_key;
+    let now = util::current_time_millis();
+
+    if case.buyer_user_id != user.id() {
+        return Err("Case belongs to a different user.".to_string());
+    };
+    if !case.paid {
+        return Err("Case is not paid.".to_string());
+    };
+    if case.awarded || case.canceled_by_seller || case.canceled_by_buyer {
+        return Err("Case is no longer pending.".to_string());
+    };
+    if attachments.len() >= MAX_ATTACHMENTS_PER_CASE {
+        return Err("Maximum number of attachments already exist.".to_string());
+    };
+    if tmp_file.len() == 0 {
+        return Err("File is empty.".to_string());
+    };
+    if tmp_file.len() > MAX_ATTACHMENT_SIZE_BYTES {
+        return Err("File is too large.".to_string());
+    };
+    if total_size_bytes + tmp_file.len() > MAX_TOTAL_SIZE_BYTES_PER_CASE {
+        return Err("Case attachments are too large.".to_string());
+    };
+
+    let name = sanitize_filename(tmp_file.name().unwrap_or("attachment"));
+    let bytes = get_file_bytes(&tmp_file)?;
+    // When the poster has a PGP key, the case is encrypted to it, and so must be the files.
+    let (data, content_type, extension, encrypted) = if !seller_pgp_key.is_empty() {
+        let (extension, message) = match std::str::from_utf8(&bytes) {
+            Ok(armored) => (
+                "asc",
+                Message::from_string(armored)
+                    .map(|(message, _)| message)
+                    .map_err(|_| "Invalid PGP message.")?,
+            ),
+            Err(_) => (
+                "gpg",
+                Message::from_bytes(&bytes[..]).map_err(|_| "Invalid PGP message.")?,
+            ),
+        };
+        if !matches!(message, Message::Encrypted { .. }) {
+            return Err("Attachment must be encrypted to the seller PGP key.".to_string());
+        };
+        (
+            bytes,
+            "application/pgp-encrypted".to_string(),
+            extension.to_string(),
+            true,
+        )
+    } else {
+        let content_type = sniff_content_type(&bytes)
+            .filter(|content_type| ALLOWED_CONTENT_TYPES.contains(content_type))
+            .ok_or("File type is not allowed.")?;
+        let extension = content_type
+            .extension()
+            .map(|extension| extension.as_str().to_string())
+            .unwrap_or_else(|| "bin".to_string());
+        let data = if IMAGE_CONTENT_TYPES.contains(&content_type) {
+            image_util::get_stripped_image_bytes(&bytes)
+                .map_err(|_| "failed to clear image metadata.")?
+        } else {
+            bytes
+        };
+        (data, content_type.to_string(), extension, false)
+    };
+
+    let attachment = CaseAttachment {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id: case.id.unwrap(),
+        user_id: user.id(),
+        filename: format!("{}.{}", name, extension),
+        content_type,
+        encrypted,
+        size_bytes: data.len() as u64,
+        created_time_ms: now,
+    };
+    CaseAttachment::insert(attachment, data, db)
+        .await
+        .map_err(|_| "failed to save attachment.")?;
+
+    Ok(())
+}
+
+#[get("/<id>/<attachment_id>")]
+async fn download(
+    id: &str,
+    attachment_id: &str,
+    mut db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+) -> Result<AttachmentDownload, String> {
+    let case = Case::single_by_public_id(&mut db, id)
+        .await
+        .map_err(|_| "failed to get case.")?;
+    let attachment = CaseAttachment::single_by_public_id(&mut db, attachment_id)
+        .await
+        .map_err(|_| "failed to get attachment.")?;
+
+    if !can_view(&case, &user, &admin_user) {
+        return Err("Case belongs to other users.".to_string());
+    };
+    if attachment.case_id != case.id.unwrap() {
+        return Err("Attachment belongs to a different case.".to_string());
+    };
+
+    let data = CaseAttachment::data_by_public_id(&mut db, attachment_id)
+        .await
+        .map_err(|_| "failed to get attachment data.")?;
+    let content_type =
+        ContentType::parse_flexible(&attachment.content_type).unwrap_or(ContentType::Binary);
+    // Always download instead of rendering, so an uploaded file never runs in the market page.
+    Ok(AttachmentDownload {
+        inner: data,
+        content_type,
+        content_disposition: Header::new(
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}\"",
+                sanitize_filename(&attachment.filename)
+            ),
+        ),
+    })
+}
+
+#[delete("/<id>/<attachment_id>")]
+async fn delete(
+    id: &str,
+    attachment_id: &str,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match delete_attachment(id, attachment_id, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "case", id)),
+            "Attachment deleted.",
+        )),
+        Err(e) => {
+            error_!("Delete attachment({}) error: {}", attachment_id, e);
+            Err(Flash::error(Redirect::to(format!("/{}/{}", "case", id)), e))
+        }
+    }
+}
+
+async fn delete_attachment(
+    id: &str,
+    attachment_id: &str,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case")?;
+    let attachment = CaseAttachment::single_by_public_id(db, attachment_id)
+        .await
+        .map_err(|_| "failed to get attachment")?;
+
+    if attachment.case_id != case.id.unwrap() {
+        return Err("Attachment belongs to a different case.".to_string());
+    };
+    if attachment.user_id != user.id() {
+        return Err("Attachment belongs to a different user.".to_string());
+    };
+    if case.awarded || case.canceled_by_seller || case.canceled_by_buyer {
+        return Err("Case is no longer pending.".to_string());
+    };
+
+    CaseAttachment::delete_with_public_id(attachment_id, db)
+        .await
+        .map_err(|_| "failed to delete attachment.")?;
+
+    Ok(())
+}
+
+pub fn case_attachments_stage() -> AdHoc {
+    AdHoc::on_ignite("Case Attachments Stage", |rocket| async {
+        rocket.mount("/case_attachments", routes![new, download, delete])
+    })
+}
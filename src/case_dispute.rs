@@ -0,0 +1,374 @@
+use crate::award_bounty;
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{
+    Bounty, BountyMilestone, Case, CaseAuditEvent, CaseDispute, CaseDisputeInfo, DisputeEvidence,
+    DisputeEvidenceInfo, DisputeResolutionInfo,
+};
+use crate::user_account::ActiveUser;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+const DISPUTE_WINDOW_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const MAX_DISPUTE_TEXT_LENGTH: usize = 4096;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    case: Case,
+    bounty: Bounty,
+    reward_sat: u64,
+    maybe_dispute: Option<CaseDispute>,
+    evidence: Vec<DisputeEvidence>,
+    audit_events: Vec<CaseAuditEvent>,
+    maybe_dispute_deadline_ms: Option<u64>,
+    can_open_dispute: bool,
+    is_participant: bool,
+    user: User,
+    admin_user: Option<AdminUser>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        case_id: &str,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let case = Case::single_by_public_id(&mut db, case_id)
+            .await
+            .map_err(|_| "failed to get case.")?;
+        let is_participant = is_participant(&case, &user);
+        if !is_participant && admin_user.is_none() {
+            return Err("Case belongs to other users.".to_string());
+        };
+        let bounty = Bounty::single(&mut db, case.bounty_id)
+            .await
+            .map_err(|_| "failed to get bounty.")?;
+        let reward_sat = reward_sat(&mut db, &case, &bounty).await?;
+        let maybe_dispute = CaseDispute::single_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get dispute.")?;
+        let evidence = match &maybe_dispute {
+            Some(dispute) => DisputeEvidence::all_for_dispute(&mut db, dispute.id.unwrap())
+                .await
+                .map_err(|_| "failed to get dispute evidence.")?,
+            None => vec![],
+        };
+        let audit_events = CaseAuditEvent::all_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case history.")?;
+        let maybe_dispute_deadline_ms = dispute_deadline_ms(&mut db, &case).await?;
+        let can_open_dispute = case.buyer_user_id == user.id()
+            && maybe_dispute.is_none()
+            && maybe_dispute_deadline_ms
+                .map(|deadline_ms| util::current_time_millis() < deadline_ms)
+                .unwrap_or(false);
+        Ok(Context {
+            base_context,
+            flash,
+            case,
+            bounty,
+            reward_sat,
+            maybe_dispute,
+            evidence,
+            audit_events,
+            maybe_dispute_deadline_ms,
+            can_open_dispute,
+            is_participant,
+            user,
+            admin_user,
+        })
+    }
+}
+
+/// A dispute is between the bounty poster and the case submitter.
+fn is_participant(case: &Case, user: &User) -> bool {
+    case.seller_user_id == user.id() || case.buyer_user_id == user.id()
+}
+
+/// The reward that a dispute is about: the milestone reward if the case was
+/// submitted for a milestone, otherwise the bounty reward.
+async fn reward_sat(db: &mut Connection<Db>, case: &Case, bounty: &Bounty) -> Result<u64, String> {
+    let maybe_milestone = BountyMilestone::single_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get case milestone.")?;
+    Ok(maybe_milestone
+        .map(|milestone| milestone.reward_sat)
+        .unwrap_or(bounty.price_sat))
+}
+
+/// A submitter can dispute a case that the poster rejected, for a while after the rejection.
+async fn dispute_deadline_ms(db: &mut Connection<Db>, case: &Case) -> Result<Option<u64>, String> {
+    if !(case.paid && case.canceled_by_seller) {
+        return Ok(None);
+    };
+    let maybe_canceled_time_ms =
+        CaseAuditEvent::last_time_for_case(db, case.id.unwrap(), "canceled_by_poster")
+            .await
+            .map_err(|_| "failed to get case history.")?;
+    Ok(maybe_canceled_time_ms.map(|canceled_time_ms| canceled_time_ms + DISPUTE_WINDOW_MS))
+}
+
+#[post("/<id>", data = "<dispute_form>")]
+async fn new(
+    id: &str,
+    dispute_form: Form<CaseDisputeInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let dispute_info = dispute_form.into_inner();
+
+    match open_dispute(id, dispute_info, &mut db, active_user.user).await {
+        Ok(_) => Flash::success(
+            Redirect::to(format!("/{}/{}", "case_dispute", id)),
+            "Dispute opened.",
+        ),
+        Err(e) => {
+            error_!("Open dispute({}) error: {}", id, e);
+            Flash::error(Redirect::to(format!("/{}/{}", "case_dispute", id)), e)
+        }
+    }
+}
+
+async fn open_dispute(
+    id: &str,
+    dispute_info: CaseDisputeInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case.")?;
+    let maybe_dispute = CaseDispute::single_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get dispute.")?;
+    let maybe_deadline_ms = dispute_deadline_ms(db, &case).await?;
+    let now = util::current_time_millis();
+
+    if case.buyer_user_id != user.id() {
+        return Err("Only the case submitter can open a dispute.".to_string());
+    };
+    if maybe_dispute.is_some() {
+        return Err("Case is already disputed.".to_string());
+    };
+    match maybe_deadline_ms {
+        Some(deadline_ms) if now < deadline_ms => (),
+        Some(_) => return Err("The time to dispute this case has passed.".to_string()),
+        None => return Err("Only cases rejected by the poster can be disputed.".to_string()),
+    };
+    if dispute_info.reason.is_empty() {
+        return Err("Reason cannot be empty.".to_string());
+    };
+    if dispute_info.reason.len() > MAX_DISPUTE_TEXT_LENGTH {
+        return Err("Reason length is too long.".to_string());
+    };
+
+    let dispute = CaseDispute {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id: case.id.unwrap(),
+        case_public_id: None,
+        reason: dispute_info.reason,
+        resolved: false,
+        resolution: "".to_string(),
+        submitter_share_basis_points: 0,
+        resolution_note: "".to_string(),
+        resolved_by_user_id: None,
+        reserved_sat: 0,
+        created_time_ms: now,
+        resolved_time_ms: 0,
+    };
+    CaseDispute::insert(dispute, case.seller_user_id, case.buyer_user_id, db)
+        .await
+        .map_err(|_| "failed to insert dispute.")?;
+
+    Ok(())
+}
+
+#[post("/<id>/evidence", data = "<evidence_form>")]
+async fn evidence(
+    id: &str,
+    evidence_form: Form<DisputeEvidenceInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let evidence_info = evidence_form.into_inner();
+
+    match add_evidence(id, evidence_info, &mut db, active_user.user).await {
+        Ok(_) => Flash::success(
+            Redirect::to(format!("/{}/{}", "case_dispute", id)),
+            "Evidence added.",
+        ),
+        Err(e) => {
+            error_!("Add evidence({}) error: {}", id, e);
+            Flash::error(Redirect::to(format!("/{}/{}", "case_dispute", id)), e)
+        }
+    }
+}
+
+async fn add_evidence(
+    id: &str,
+    evidence_info: DisputeEvidenceInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case.")?;
+    let dispute = CaseDispute::single_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get dispute.")?
+        .ok_or("Case is not disputed.")?;
+    let now = util::current_time_millis();
+
+    if !is_participant(&case, &user) {
+        return Err("Case belongs to other users.".to_string());
+    };
+    if dispute.resolved {
+        return Err("Dispute is already resolved.".to_string());
+    };
+    if evidence_info.body.is_empty() {
+        return Err("Evidence cannot be empty.".to_string());
+    };
+    if evidence_info.body.len() > MAX_DISPUTE_TEXT_LENGTH {
+        return Err("Evidence length is too long.".to_string());
+    };
+
+    let evidence = DisputeEvidence {
+        id: None,
+        public_id: util::create_uuid(),
+        dispute_id: dispute.id.unwrap(),
+        user_id: user.id(),
+        username: None,
+        body: evidence_info.body,
+        created_time_ms: now,
+    };
+    DisputeEvidence::insert(evidence, db)
+        .await
+        .map_err(|_| "failed to insert evidence.")?;
+
+    Ok(())
+}
+
+#[put("/<id>/resolve", data = "<resolution_form>")]
+async fn resolve(
+    id: &str,
+    resolution_form: Form<DisputeResolutionInfo>,
+    mut db: Connection<Db>,
+    user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let resolution_info = resolution_form.into_inner();
+
+    match resolve_dispute(id, resolution_info, &mut db, user).await {
+        Ok(_) => Flash::success(
+            Redirect::to(format!("/{}/{}", "case_dispute", id)),
+            "Dispute resolved.",
+        ),
+        Err(e) => {
+            error_!("Resolve dispute({}) error: {}", id, e);
+            Flash::error(Redirect::to(format!("/{}/{}", "case_dispute", id)), e)
+        }
+    }
+}
+
+async fn resolve_dispute(
+    id: &str,
+    resolution_info: DisputeResolutionInfo,
+    db: &mut Connection<Db>,
+    admin: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case.")?;
+    let bounty = Bounty::single(db, case.bounty_id)
+        .await
+        .map_err(|_| "failed to get bounty.")?;
+    let dispute = CaseDispute::single_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get dispute.")?
+        .ok_or("Case is not disputed.")?;
+    let maybe_milestone = BountyMilestone::single_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get case milestone.")?;
+    let now = util::current_time_millis();
+
+    if dispute.resolved {
+        return Err("Dispute is already resolved.".to_string());
+    };
+    if resolution_info.note.len() > MAX_DISPUTE_TEXT_LENGTH {
+        return Err("Note length is too long.".to_string());
+    };
+    // The submitter gets the whole reward, none of it, or a share of it.
+    let submitter_share_basis_points: u32 = match resolution_info.resolution.as_str() {
+        "award" => 10000,
+        "refund" => 0,
+        "split" => match resolution_info.submitter_share_percent {
+            Some(percent) if percent > 0 && percent < 100 => percent * 100,
+            _ => return Err("Submitter share must be between 1 and 99 percent.".to_string()),
+        },
+        _ => return Err("Invalid resolution.".to_string()),
+    };
+    // The submitter is paid from the reward reserved when the dispute was opened.
+    let amount_sat = dispute.reserved_sat * (submitter_share_basis_points as u64) / 10000;
+    let maybe_milestone_id = maybe_milestone.and_then(|m| m.id);
+    let case_awards = if amount_sat > 0 {
+        award_bounty::case_awards_for_case(db, &bounty, &case, amount_sat, maybe_milestone_id, now)
+            .await?
+    } else {
+        vec![]
+    };
+
+    let resolved_dispute = CaseDispute {
+        resolved: true,
+        resolution: resolution_info.resolution,
+        submitter_share_basis_points,
+        resolution_note: resolution_info.note,
+        resolved_by_user_id: Some(admin.id()),
+        resolved_time_ms: now,
+        ..dispute
+    };
+    CaseDispute::resolve(db, resolved_dispute, &case, maybe_milestone_id, case_awards).await
+}
+
+#[get("/<id>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    id: &str,
+    db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, id, flash, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("casedispute", context))
+}
+
+pub fn case_dispute_stage() -> AdHoc {
+    AdHoc::on_ignite("Case Dispute Stage", |rocket| async {
+        rocket.mount("/case_dispute", routes![index, new, evidence, resolve])
+    })
+}
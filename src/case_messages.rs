@@ -0,0 +1,204 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Case, CaseDispute, CaseMessage, CaseMessageInfo, UserSettings};
+use crate::user_account::ActiveUser;
+use crate::util;
+use pgp::composed::{Deserializable, Message};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+const MAX_MESSAGE_LENGTH: usize = 4096;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    case: Case,
+    messages: Vec<CaseMessage>,
+    is_participant: bool,
+    recipient_pgp_key: String,
+    user: User,
+    admin_user: Option<AdminUser>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        case_id: &str,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let case = Case::single_by_public_id(&mut db, case_id)
+            .await
+            .map_err(|_| "failed to get case.")?;
+        let is_participant = is_participant(&case, &user);
+        if !is_participant && !(admin_user.is_some() && dispute_is_open(&mut db, &case).await?) {
+            return Err("Conversation belongs to other users.".to_string());
+        };
+        let messages = CaseMessage::all_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case messages.")?;
+        let recipient_pgp_key = if is_participant {
+            UserSettings::single(&mut db, recipient_user_id(&case, &user))
+                .await
+                .map_err(|_| "failed to get recipient user settings.")?
+                .pgp_key
+        } else {
+            "".to_string()
+        };
+        Ok(Context {
+            base_context,
+            flash,
+            case,
+            messages,
+            is_participant,
+            recipient_pgp_key,
+            user,
+            admin_user,
+        })
+    }
+}
+
+/// The conversation of a case is between the bounty poster and the case submitter.
+fn is_participant(case: &Case, user: &User) -> bool {
+    case.seller_user_id == user.id() || case.buyer_user_id == user.id()
+}
+
+fn recipient_user_id(case: &Case, user: &User) -> i32 {
+    if case.seller_user_id == user.id() {
+        case.buyer_user_id
+    } else {
+        case.seller_user_id
+    }
+}
+
+/// Admins can only read a conversation while a dispute on the case is open.
+async fn dispute_is_open(db: &mut Connection<Db>, case: &Case) -> Result<bool, String> {
+    let maybe_dispute = CaseDispute::single_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get dispute.")?;
+    Ok(maybe_dispute.map(|dispute| !dispute.resolved).unwrap_or(false))
+}
+
+#[post("/<id>", data = "<message_form>")]
+async fn new(
+    id: &str,
+    message_form: Form<CaseMessageInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let message_info = message_form.into_inner();
+
+    match send_message(id, message_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "case_messages", id)),
+            "Message sent.",
+        )),
+        Err(e) => {
+            error_!("Send message({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "case_messages", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn send_message(
+    id: &str,
+    message_info: CaseMessageInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case")?;
+    let now = util::current_time_millis();
+
+    if !is_participant(&case, &user) {
+        return Err("Case belongs to other users.".to_string());
+    };
+    if !case.paid {
+        return Err("Case is not paid.".to_string());
+    };
+    if message_info.body.is_empty() {
+        return Err("Message cannot be empty.".to_string());
+    };
+    if message_info.body.len() > MAX_MESSAGE_LENGTH {
+        return Err("Message length is too long.".to_string());
+    };
+    let recipient_user_id = recipient_user_id(&case, &user);
+    if message_info.encrypted {
+        let recipient_settings = UserSettings::single(db, recipient_user_id)
+            .await
+            .map_err(|_| "failed to get recipient user settings")?;
+        if recipient_settings.pgp_key.is_empty() {
+            return Err("Recipient does not have a PGP key.".to_string());
+        };
+        Message::from_string(&message_info.body).map_err(|_| "Invalid PGP message.")?;
+    };
+
+    let message = CaseMessage {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id: case.id.unwrap(),
+        sender_user_id: user.id(),
+        sender_username: None,
+        recipient_user_id,
+        body: message_info.body,
+        encrypted: message_info.encrypted,
+        read: false,
+        created_time_ms: now,
+    };
+    CaseMessage::insert(message, db)
+        .await
+        .map_err(|_| "failed to insert message")?;
+
+    Ok(())
+}
+
+#[get("/<id>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    id: &str,
+    mut db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let case = Case::single_by_public_id(&mut db, id)
+        .await
+        .map_err(|_| "failed to get case.")?;
+    // Opening the conversation marks the messages sent to the user as read.
+    if is_participant(&case, &user) {
+        CaseMessage::mark_all_as_read(&mut db, case.id.unwrap(), user.id())
+            .await
+            .map_err(|_| "failed to mark messages as read.")?;
+    }
+    let context = Context::raw(db, id, flash, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("casemessages", context))
+}
+
+pub fn case_messages_stage() -> AdHoc {
+    AdHoc::on_ignite("Case Messages Stage", |rocket| async {
+        rocket.mount("/case_messages", routes![index, new])
+    })
+}
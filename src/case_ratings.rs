@@ -0,0 +1,107 @@
+use crate::csrf::CsrfChecked;
+use crate::db::Db;
+use crate::models::{Case, CaseRating, CaseRatingInfo};
+use crate::user_account::ActiveUser;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket_auth::User;
+use rocket_db_pools::Connection;
+
+const MIN_SCORE: u32 = 1;
+const MAX_SCORE: u32 = 5;
+const MAX_REVIEW_LENGTH: usize = 500;
+
+#[post("/<id>", data = "<rating_form>")]
+async fn new(
+    id: &str,
+    rating_form: Form<CaseRatingInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let rating_info = rating_form.into_inner();
+
+    match rate_case(id, rating_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "case", id)),
+            "Rating saved.",
+        )),
+        Err(e) => {
+            error_!("Rate case({}) error: {}", id, e);
+            Err(Flash::error(Redirect::to(format!("/{}/{}", "case", id)), e))
+        }
+    }
+}
+
+async fn rate_case(
+    id: &str,
+    rating_info: CaseRatingInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case")?;
+    let existing_ratings = CaseRating::all_for_case(db, case.id.unwrap())
+        .await
+        .map_err(|_| "failed to get case ratings")?;
+    let now = util::current_time_millis();
+
+    // The hunter rates the poster, and the poster rates the hunter.
+    let (rater_role, ratee_user_id) = if case.buyer_user_id == user.id() {
+        ("hunter", case.seller_user_id)
+    } else if case.seller_user_id == user.id() {
+        ("poster", case.buyer_user_id)
+    } else {
+        return Err("Case belongs to other users.".to_string());
+    };
+    if !case.paid {
+        return Err("Case is not paid.".to_string());
+    };
+    if !(case.awarded || case.canceled_by_seller || case.canceled_by_buyer) {
+        return Err("Case can only be rated after it is awarded or canceled.".to_string());
+    };
+    if existing_ratings
+        .iter()
+        .any(|rating| rating.rater_user_id == user.id())
+    {
+        return Err("You already rated this case.".to_string());
+    };
+    let score = rating_info.score.ok_or("Score is required.")?;
+    if !(MIN_SCORE..=MAX_SCORE).contains(&score) {
+        return Err(format!(
+            "Score must be between {} and {}.",
+            MIN_SCORE, MAX_SCORE
+        ));
+    };
+    if rating_info.review.len() > MAX_REVIEW_LENGTH {
+        return Err("Review length is too long.".to_string());
+    };
+
+    let rating = CaseRating {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id: case.id.unwrap(),
+        case_public_id: None,
+        rater_user_id: user.id(),
+        rater_username: None,
+        ratee_user_id,
+        rater_role: rater_role.to_string(),
+        score,
+        review: rating_info.review,
+        created_time_ms: now,
+    };
+    CaseRating::insert(rating, db)
+        .await
+        .map_err(|_| "failed to save rating.")?;
+
+    Ok(())
+}
+
+pub fn case_ratings_stage() -> AdHoc {
+    AdHoc::on_ignite("Case Ratings Stage", |rocket| async {
+        rocket.mount("/case_ratings", routes![new])
+    })
+}
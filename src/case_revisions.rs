@@ -0,0 +1,172 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Case, CaseResponse, CaseRevision, CaseRevisionInfo, UserSettings};
+use crate::user_account::ActiveUser;
+use crate::util;
+use pgp::composed::{Deserializable, Message};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    case: Case,
+    revisions: Vec<CaseRevision>,
+    responses: Vec<CaseResponse>,
+    maybe_open_changes_request: Option<CaseResponse>,
+    seller_pgp_key: String,
+    user: User,
+    admin_user: Option<AdminUser>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        case_id: &str,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let case = Case::single_by_public_id(&mut db, case_id)
+            .await
+            .map_err(|_| "failed to get case.")?;
+        // The poster, the submitter and any arbitrating admin can see the whole history.
+        if !(is_participant(&case, &user) || admin_user.is_some()) {
+            return Err("Case belongs to other users.".to_string());
+        };
+        let revisions = CaseRevision::all_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case revisions.")?;
+        let responses = CaseResponse::all_for_case(&mut db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get case responses.")?;
+        let maybe_open_changes_request =
+            CaseResponse::open_changes_request_for_case(&mut db, case.id.unwrap())
+                .await
+                .map_err(|_| "failed to get open request for changes.")?;
+        let seller_pgp_key = UserSettings::single(&mut db, case.seller_user_id)
+            .await
+            .map_err(|_| "failed to get seller user settings.")?
+            .pgp_key;
+        Ok(Context {
+            base_context,
+            flash,
+            case,
+            revisions,
+            responses,
+            maybe_open_changes_request,
+            seller_pgp_key,
+            user,
+            admin_user,
+        })
+    }
+}
+
+fn is_participant(case: &Case, user: &User) -> bool {
+    case.seller_user_id == user.id() || case.buyer_user_id == user.id()
+}
+
+#[post("/<id>", data = "<revision_form>")]
+async fn new(
+    id: &str,
+    revision_form: Form<CaseRevisionInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let revision_info = revision_form.into_inner();
+
+    match resubmit_case(id, revision_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "case", id)),
+            "Case resubmitted.",
+        )),
+        Err(e) => {
+            error_!("Resubmit case({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "case_revisions", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn resubmit_case(
+    id: &str,
+    revision_info: CaseRevisionInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let case = Case::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get case")?;
+    let maybe_open_changes_request =
+        CaseResponse::open_changes_request_for_case(db, case.id.unwrap())
+            .await
+            .map_err(|_| "failed to get open request for changes")?;
+    let now = util::current_time_millis();
+
+    if case.buyer_user_id != user.id() {
+        return Err("Case belongs to a different user.".to_string());
+    };
+    if case.awarded || case.canceled_by_seller || case.canceled_by_buyer {
+        return Err("Case is no longer pending.".to_string());
+    };
+    if maybe_open_changes_request.is_none() {
+        return Err("No changes were requested.".to_string());
+    };
+    Message::from_string(&revision_info.case_details).map_err(|_| "Invalid PGP message.")?;
+    if revision_info.case_details.is_empty() {
+        return Err("Case details cannot be empty.".to_string());
+    };
+    if revision_info.case_details.len() > 4096 {
+        return Err("Case details length is too long.".to_string());
+    };
+
+    let revision = CaseRevision {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id: case.id.unwrap(),
+        case_details: revision_info.case_details,
+        created_time_ms: now,
+    };
+    CaseRevision::resubmit(revision, case.seller_user_id, case.buyer_user_id, db).await?;
+
+    Ok(())
+}
+
+#[get("/<id>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    id: &str,
+    db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, id, flash, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("caserevisions", context))
+}
+
+pub fn case_revisions_stage() -> AdHoc {
+    AdHoc::on_ignite("Case Revisions Stage", |rocket| async {
+        rocket.mount("/case_revisions", routes![index, new])
+    })
+}
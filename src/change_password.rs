@@ -0,0 +1,138 @@
+use crate::base::BaseContext;
+use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{ChangePasswordInput, RocketAuthUser};
+use crate::rate_limit::LoginLimiter;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket::State;
+use rocket_auth::{AdminUser, Auth, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        Ok(Context {
+            base_context,
+            flash,
+        })
+    }
+}
+
+#[post("/change", data = "<change_password_form>")]
+async fn update(
+    change_password_form: Form<ChangePasswordInput>,
+    auth: Auth<'_>,
+    mut db: Connection<Db>,
+    config: &State<Config>,
+    login_limiter: LoginLimiter<'_>,
+    user: User,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let change_password_input = change_password_form.into_inner();
+
+    match change_password(
+        change_password_input,
+        &auth,
+        &mut db,
+        config,
+        &login_limiter,
+        user,
+    )
+    .await
+    {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/change_password", index())),
+            "Password successfully changed.",
+        ),
+        Err(e) => {
+            error_!("Change password error: {}", e);
+            Flash::error(Redirect::to(uri!("/change_password", index())), e)
+        }
+    }
+}
+
+async fn change_password(
+    change_password_input: ChangePasswordInput,
+    auth: &Auth<'_>,
+    db: &mut Connection<Db>,
+    config: &Config,
+    login_limiter: &LoginLimiter<'_>,
+    user: User,
+) -> Result<(), String> {
+    let now = util::current_time_millis();
+
+    if change_password_input.new_password != change_password_input.confirm_password {
+        return Err("New passwords do not match.".to_string());
+    };
+    if change_password_input.current.email.to_lowercase() != user.email() {
+        return Err("Current login does not match your account.".to_string());
+    };
+
+    // The current password is checked against the stored hash, without starting a new
+    // session, and wrong guesses count towards the login lockout.
+    if login_limiter.is_locked(user.email(), now) {
+        return Err("Too many failed logins. Try again later.".to_string());
+    };
+    let password_hash = RocketAuthUser::password_hash(db, user.id())
+        .await
+        .map_err(|_| "failed to get current password.")?;
+    let password_matches = argon2::verify_encoded(
+        &password_hash,
+        change_password_input.current.password.as_bytes(),
+    )
+    .unwrap_or(false);
+    if !password_matches {
+        login_limiter.record_failure(user.email(), config, now);
+        return Err("Current password is not correct.".to_string());
+    };
+    login_limiter.clear(user.email());
+    auth.change_password(&change_password_input.new_password)
+        .await
+        .map_err(|e| format!("failed to change password: {}", e))?;
+
+    Ok(())
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("changepassword", context))
+}
+
+pub fn change_password_stage() -> AdHoc {
+    AdHoc::on_ignite("Change Password Stage", |rocket| async {
+        rocket.mount("/change_password", routes![index, update])
+    })
+}
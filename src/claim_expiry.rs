@@ -0,0 +1,35 @@
+use crate::models::{BountyClaim, Notification};
+use crate::util;
+use sqlx::pool::PoolConnection;
+use sqlx::Sqlite;
+
+/// Releases the bounty claims that ran past their window, which returns their stakes.
+pub async fn release_expired_claims(mut conn: PoolConnection<Sqlite>) -> Result<(), String> {
+    let now = util::current_time_millis();
+    let expired_claims = BountyClaim::all_expired(&mut conn, now)
+        .await
+        .map_err(|_| "failed to get expired claims.")?;
+
+    for claim in expired_claims {
+        let released =
+            BountyClaim::release_for_connection(&mut conn, claim.id.unwrap(), "expired", now)
+                .await
+                .map_err(|_| "failed to release expired claim.")?;
+        if released {
+            Notification::insert_for_connection(
+                &mut conn,
+                claim.user_id,
+                "claim_expired",
+                &format!(
+                    "Bounty claim expired: {}",
+                    claim.bounty_title.unwrap_or_default()
+                ),
+                &format!("/bounty/{}", claim.bounty_public_id.unwrap_or_default()),
+            )
+            .await
+            .map_err(|_| "failed to insert notification.")?;
+        }
+    }
+
+    Ok(())
+}
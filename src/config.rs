@@ -13,6 +13,26 @@ pub struct Config {
     pub lnd_port: u32,
     pub lnd_tls_cert_path: String,
     pub lnd_macaroon_path: String,
+    pub min_solvency_coverage_basis_points: i64,
+    pub market_url: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: String,
+    pub smtp_password: String,
+    pub smtp_tls: bool,
+    pub email_from_address: String,
+    pub nostr_relays: Vec<String>,
+    pub nostr_secret_key: String,
+    pub default_review_deadline_days: u64,
+    pub review_deadline_reminder_hours: u64,
+    pub review_deadline_policy: String,
+    pub auth_rate_limit_per_minute: u32,
+    pub case_rate_limit_per_minute: u32,
+    pub withdrawal_rate_limit_per_minute: u32,
+    pub upload_rate_limit_per_minute: u32,
+    pub max_failed_logins: u32,
+    pub max_failed_logins_per_account: u32,
+    pub login_lockout_minutes: u64,
 }
 
 impl Default for Config {
@@ -25,6 +45,26 @@ impl Default for Config {
             lnd_port: 10009,
             lnd_tls_cert_path: "~/.lnd/tls.cert".into(),
             lnd_macaroon_path: "~/.lnd/data/chain/bitcoin/testnet/admin.macaroon".into(),
+            min_solvency_coverage_basis_points: 10000,
+            market_url: "http://localhost:8000".into(),
+            smtp_host: "".into(),
+            smtp_port: 587,
+            smtp_username: "".into(),
+            smtp_password: "".into(),
+            smtp_tls: true,
+            email_from_address: "satbounty@localhost".into(),
+            nostr_relays: vec![],
+            nostr_secret_key: "".into(),
+            default_review_deadline_days: 14,
+            review_deadline_reminder_hours: 48,
+            review_deadline_policy: "escalate".into(),
+            auth_rate_limit_per_minute: 10,
+            case_rate_limit_per_minute: 10,
+            withdrawal_rate_limit_per_minute: 5,
+            upload_rate_limit_per_minute: 20,
+            max_failed_logins: 5,
+            max_failed_logins_per_account: 20,
+            login_lockout_minutes: 15,
         }
     }
 }
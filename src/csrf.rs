@@ -0,0 +1,197 @@
+use crate::util;
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
+use rocket::http::{Cookie, CookieJar, Method, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::Data;
+use rocket_auth::User;
+
+const CSRF_FIELD_NAME: &str = "csrf_token";
+const CSRF_HEADER_NAME: &str = "X-CSRF-Token";
+const METHOD_FIELD_NAME: &str = "_method";
+const SESSION_COOKIE_NAME: &str = "csrf_session";
+// The token is the first form field, or the second one after `_method`, so it always
+// fits in the start of the body.
+const PEEK_LIMIT: usize = 512;
+
+/// The token of the browser session that sent a request.
+struct SessionToken(String);
+
+/// The token that was sent with a request, if any.
+struct SubmittedToken(Option<String>);
+
+/// Keeps a token for each browser session in a private cookie. It reads the token that
+/// was sent with a request before the form data is parsed.
+pub struct CsrfTokenReader;
+
+#[rocket::async_trait]
+impl Fairing for CsrfTokenReader {
+    fn info(&self) -> Info {
+        Info {
+            name: "CSRF token reader",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, data: &mut Data<'_>) {
+        let session_token = session_token(request.cookies());
+        request.local_cache(|| SessionToken(session_token));
+
+        if !matches!(
+            request.method(),
+            Method::Post | Method::Put | Method::Delete
+        ) {
+            return;
+        };
+        let maybe_token = match request.headers().get_one(CSRF_HEADER_NAME) {
+            Some(token) => Some(token.to_string()),
+            None => {
+                let is_multipart = request
+                    .content_type()
+                    .map(|content_type| content_type.is_form_data())
+                    .unwrap_or(false);
+                let body = data.peek(PEEK_LIMIT).await;
+                let fields = if is_multipart {
+                    leading_multipart_fields(body)
+                } else {
+                    leading_urlencoded_fields(body)
+                };
+                token_from_leading_fields(fields)
+            }
+        };
+        request.local_cache(|| SubmittedToken(maybe_token));
+    }
+}
+
+/// Gets the token of the browser session, or starts a new one.
+fn session_token(cookies: &CookieJar<'_>) -> String {
+    match cookies.get_private(SESSION_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_string(),
+        None => {
+            let token = util::create_uuid();
+            cookies.add_private(Cookie::new(SESSION_COOKIE_NAME, token.clone()));
+            token
+        }
+    }
+}
+
+/// Ends the token of the current browser session, when the user logs in or out.
+pub fn forget_session_token(cookies: &CookieJar<'_>) {
+    cookies.remove_private(Cookie::named(SESSION_COOKIE_NAME));
+}
+
+/// The first two fields of a form, as names and values.
+fn leading_urlencoded_fields(body: &[u8]) -> Vec<(String, String)> {
+    let body = String::from_utf8_lossy(body);
+    body.split('&')
+        .take(2)
+        .filter_map(|field| field.split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// The first two fields of a multipart form that end within the peeked bytes.
+fn leading_multipart_fields(body: &[u8]) -> Vec<(String, String)> {
+    let body = String::from_utf8_lossy(body);
+    let boundary = match body.split("\r\n").next() {
+        Some(boundary) if boundary.starts_with("--") => boundary,
+        _ => return vec![],
+    };
+    body.split(boundary)
+        .skip(1)
+        .take(2)
+        .filter_map(|part| {
+            let (headers, value) = part.split_once("\r\n\r\n")?;
+            let name = headers
+                .split("name=\"")
+                .nth(1)?
+                .split('"')
+                .next()?
+                .to_string();
+            let value = value.strip_suffix("\r\n")?.to_string();
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn token_from_leading_fields(fields: Vec<(String, String)>) -> Option<String> {
+    let mut fields = fields.into_iter();
+    let (name, value) = match fields.next()? {
+        (name, _) if name == METHOD_FIELD_NAME => fields.next()?,
+        field => field,
+    };
+    if name == CSRF_FIELD_NAME {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+fn tokens_match(submitted: &str, expected: &str) -> bool {
+    submitted.len() == expected.len()
+        && submitted
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Guard for state-changing routes. It checks that the form was rendered by the
+/// market for the current session, so other sites cannot submit it for the user.
+#[derive(Debug)]
+pub struct CsrfChecked;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CsrfChecked {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<CsrfChecked, ()> {
+        // Without a session there is nothing to forge, and the route rejects the request.
+        if !request.guard::<User>().await.is_success() {
+            return Outcome::Success(CsrfChecked);
+        };
+        let session_token = &request.local_cache(|| SessionToken("".to_string())).0;
+
+        match &request.local_cache(|| SubmittedToken(None)).0 {
+            Some(token) if !session_token.is_empty() && tokens_match(token, session_token) => {
+                Outcome::Success(CsrfChecked)
+            }
+            _ => Outcome::Failure((Status::Forbidden, ())),
+        }
+    }
+}
+
+/// Guard for pages with forms. It gives the token of the current session, which the
+/// forms send back to pass `CsrfChecked`.
+#[derive(Debug)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CsrfToken {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<CsrfToken, ()> {
+        let session_token = &request
+            .local_cache(|| SessionToken(session_token(request.cookies())))
+            .0;
+        Outcome::Success(CsrfToken(session_token.clone()))
+    }
+}
+
+#[catch(403)]
+fn forbidden() -> &'static str {
+    "Forbidden. If you submitted a form, go back, reload the page and try again."
+}
+
+pub fn csrf_stage() -> AdHoc {
+    AdHoc::on_ignite("CSRF Stage", |rocket| async {
+        rocket
+            .attach(CsrfTokenReader)
+            .register("/", catchers![forbidden])
+    })
+}
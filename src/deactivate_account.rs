@@ -1,9 +1,12 @@
 use crate::base::BaseContext;
 use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::lightning;
-use crate::models::{UserAccount, WithdrawalInfo};
+use crate::models::{DeactivationInfo, TwoFactorSettings, UserAccount, WithdrawalInfo};
+use crate::two_factor;
 use crate::user_account::ActiveUser;
+use crate::util;
 use rocket::fairing::AdHoc;
 use rocket::form::Form;
 use rocket::request::FlashMessage;
@@ -12,6 +15,7 @@ use rocket::response::Redirect;
 use rocket::serde::Serialize;
 use rocket::State;
 use rocket_auth::AdminUser;
+use rocket_auth::Auth;
 use rocket_auth::User;
 use rocket_auth::Users;
 use rocket_db_pools::Connection;
@@ -22,7 +26,9 @@ use rocket_dyn_templates::Template;
 struct Context {
     base_context: BaseContext,
     flash: Option<(String, String)>,
+    two_factor_enabled: bool,
     user_account: UserAccount,
+    deactivation_info: DeactivationInfo,
     maybe_account_user: Option<User>,
     user: User,
     admin_user: Option<AdminUser>,
@@ -36,15 +42,25 @@ impl Context {
         user_account: UserAccount,
         admin_user: Option<AdminUser>,
         users: &Users,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let deactivation_info = UserAccount::deactivation_info(&mut db, &user_account)
+            .await
+            .map_err(|_| "failed to get deactivation info.")?;
         let maybe_account_user = users.get_by_id(user_account.user_id).await.ok();
+        let two_factor_enabled = TwoFactorSettings::enabled_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get two-factor settings.")?;
         Ok(Context {
             base_context,
             flash,
+            two_factor_enabled,
             user_account,
+            deactivation_info,
             maybe_account_user,
             user,
             admin_user,
@@ -59,6 +75,8 @@ async fn new(
     active_user: ActiveUser,
     _admin_user: Option<AdminUser>,
     config: &State<Config>,
+    auth: Auth<'_>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     let withdrawal_info = withdrawal_form.into_inner();
     match withdraw_account_deactivation_funds(
@@ -70,10 +88,14 @@ async fn new(
     )
     .await
     {
-        Ok(_) => Ok(Flash::success(
-            Redirect::to("/my_account_balance"),
-            "Funds successfully withdrawn.",
-        )),
+        Ok(_) => {
+            // The account is gone, so end the session too.
+            auth.logout().ok();
+            Ok(Flash::success(
+                Redirect::to("/login"),
+                "Account deactivated and funds successfully withdrawn.",
+            ))
+        }
         Err(e) => {
             error_!("Withdrawal error: {}", e);
             Err(Flash::error(
@@ -88,12 +110,15 @@ async fn withdraw_account_deactivation_funds(
     withdrawal_info: WithdrawalInfo,
     user_account: UserAccount,
     db: &mut Connection<Db>,
-    _user: User,
+    user: User,
     config: Config,
 ) -> Result<(), String> {
+    let now = util::current_time_millis();
+
     if withdrawal_info.invoice_payment_request.is_empty() {
         return Err("Invoice payment request cannot be empty.".to_string());
     };
+    two_factor::check_withdrawal_code(db, &user, withdrawal_info.totp_code).await?;
 
     let mut lightning_client = lightning::get_lnd_lightning_client(
         config.lnd_host.clone(),
@@ -113,13 +138,20 @@ async fn withdraw_account_deactivation_funds(
     let amount_sat: u64 = decoded_pay_req.num_satoshis.try_into().unwrap();
     let invoice_payment_request = withdrawal_info.invoice_payment_request;
     let send_deactivation_funds_ret =
-        send_account_deactivation_funds(invoice_payment_request, config);
-    UserAccount::do_deactivation(amount_sat, user_account, db, send_deactivation_funds_ret)
-        .await
-        .map_err(|e| {
-            error_!("Failed deactivation: {}", e);
-            e
-        })?;
+        send_account_deactivation_funds(invoice_payment_request.clone(), config);
+    UserAccount::do_deactivation(
+        amount_sat,
+        invoice_payment_request,
+        user_account,
+        db,
+        send_deactivation_funds_ret,
+        now,
+    )
+    .await
+    .map_err(|e| {
+        error_!("Failed deactivation: {}", e);
+        e
+    })?;
 
     Ok(())
 }
@@ -160,6 +192,7 @@ async fn index(
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
     users: &State<Users>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
     let context = Context::raw(
@@ -169,6 +202,7 @@ async fn index(
         active_user.user_account,
         admin_user,
         users,
+        csrf_token,
     )
     .await
     .map_err(|_| "failed to get template context.")?;
@@ -1,4 +1,5 @@
 use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::models::{Bounty, BountyDisplay};
 use rocket::fairing::AdHoc;
@@ -24,10 +25,12 @@ impl Context {
         flash: Option<(String, String)>,
         user: User,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let bounty_display = BountyDisplay::single_by_public_id(&mut db, bounty_id)
             .await
             .map_err(|_| "failed to get bounty display.")?;
@@ -50,6 +53,7 @@ async fn delete(
     mut db: Connection<Db>,
     user: User,
     admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match delete_bounty(id, &mut db, user.clone(), admin_user.clone()).await {
         Ok(_) => Ok(Flash::success(
@@ -94,9 +98,10 @@ async fn index(
     db: Connection<Db>,
     user: User,
     admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, id, flash, user, admin_user)
+    let context = Context::raw(db, id, flash, user, admin_user, csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("deletebounty", context))
@@ -0,0 +1,169 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Bounty, BountyRevision, BountyRevisionInfo};
+use crate::user_account::ActiveUser;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    bounty: Bounty,
+    title: String,
+    description: String,
+    maybe_pending_revision: Option<BountyRevision>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        bounty_id: &str,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let bounty = Bounty::single_by_public_id(&mut db, bounty_id)
+            .await
+            .map_err(|_| "failed to get bounty.")?;
+        if bounty.user_id != user.id() {
+            return Err("Bounty belongs to other user".into());
+        };
+        let maybe_pending_revision =
+            BountyRevision::pending_for_bounty(&mut db, bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get pending revision.")?;
+        let (title, description) = match &maybe_pending_revision {
+            Some(revision) => (revision.title.clone(), revision.description.clone()),
+            None => BountyRevision::live_for_bounty(&mut db, bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get live bounty.")?,
+        };
+        Ok(Context {
+            base_context,
+            flash,
+            bounty,
+            title,
+            description,
+            maybe_pending_revision,
+        })
+    }
+}
+
+#[post("/<id>", data = "<revision_form>")]
+async fn new(
+    id: &str,
+    revision_form: Form<BountyRevisionInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let revision_info = revision_form.into_inner();
+
+    match propose_revision(id, revision_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Edit submitted for review.",
+        )),
+        Err(e) => {
+            error_!("Propose revision({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "edit_bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn propose_revision(
+    id: &str,
+    revision_info: BountyRevisionInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let (live_title, live_description) = BountyRevision::live_for_bounty(db, bounty.id.unwrap())
+        .await
+        .map_err(|_| "failed to get live bounty")?;
+    let now = util::current_time_millis();
+
+    if bounty.user_id != user.id() {
+        return Err("Bounty belongs to a different user.".to_string());
+    };
+    if !bounty.approved {
+        return Err("Only approved bounties can be edited.".to_string());
+    };
+    if bounty.deactivated_by_seller || bounty.deactivated_by_admin {
+        return Err("Bounty is deactivated.".to_string());
+    };
+    if revision_info.title.is_empty() {
+        return Err("Title cannot be empty.".to_string());
+    };
+    if revision_info.description.is_empty() {
+        return Err("Description cannot be empty.".to_string());
+    };
+    if revision_info.title.len() > 64 {
+        return Err("Title length is too long.".to_string());
+    };
+    if revision_info.description.len() > 4096 {
+        return Err("Description length is too long.".to_string());
+    };
+    if revision_info.title == live_title && revision_info.description == live_description {
+        return Err("Edit does not change the bounty.".to_string());
+    };
+
+    let revision = BountyRevision {
+        id: None,
+        public_id: util::create_uuid(),
+        bounty_id: bounty.id.unwrap(),
+        title: revision_info.title,
+        // The description holds the acceptance criteria for the bounty.
+        criteria_changed: revision_info.description != live_description,
+        description: revision_info.description,
+        viewed: false,
+        approved: false,
+        created_time_ms: now,
+        reviewed_time_ms: 0,
+    };
+    BountyRevision::insert_pending(revision, db).await?;
+
+    Ok(())
+}
+
+#[get("/<id>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    id: &str,
+    db: Connection<Db>,
+    active_user: ActiveUser,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, id, flash, active_user.user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("editbounty", context))
+}
+
+pub fn edit_bounty_stage() -> AdHoc {
+    AdHoc::on_ignite("Edit Bounty Stage", |rocket| async {
+        rocket.mount("/edit_bounty", routes![index, new])
+    })
+}
@@ -0,0 +1,117 @@
+use crate::config::Config;
+use crate::models::Email;
+use crate::util;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use rocket::serde::Serialize;
+use rocket_dyn_templates::tera::{Context, Tera};
+use sqlx::pool::PoolConnection;
+use sqlx::Sqlite;
+
+const MAX_EMAIL_ATTEMPTS: u32 = 5;
+const EMAIL_RETRY_BASE_DELAY_MS: u64 = 60 * 1000;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct EmailContext {
+    message: String,
+    link_url: String,
+}
+
+/// Loads the email templates, which live next to the page templates.
+pub fn email_templates() -> Result<Tera, String> {
+    Tera::new("templates/email/*.tera")
+        .map_err(|e| format!("failed to load email templates: {:?}", e))
+}
+
+/// Sends the queued emails that are due, and schedules a retry for each one that fails.
+pub async fn send_pending_emails(
+    config: Config,
+    templates: &Tera,
+    mut conn: PoolConnection<Sqlite>,
+) -> Result<(), String> {
+    // Email is turned off until an SMTP server is configured.
+    if config.smtp_host.is_empty() {
+        return Ok(());
+    }
+    let now = util::current_time_millis();
+    let emails = Email::all_due(&mut conn, now, MAX_EMAIL_ATTEMPTS)
+        .await
+        .map_err(|_| "failed to get pending emails.")?;
+
+    for email in emails {
+        match send_email(&config, templates, &email).await {
+            Ok(_) => Email::mark_as_sent(&mut conn, email.id.unwrap())
+                .await
+                .map_err(|_| "failed to mark email as sent.")?,
+            Err(e) => {
+                println!("failed to send email {}: {:?}", email.public_id, e);
+                let retry_delay_ms = EMAIL_RETRY_BASE_DELAY_MS << email.num_attempts;
+                Email::mark_as_failed(&mut conn, email.id.unwrap(), &e, now + retry_delay_ms)
+                    .await
+                    .map_err(|_| "failed to mark email as failed.")?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_email(config: &Config, templates: &Tera, email: &Email) -> Result<(), String> {
+    let to_address = email
+        .to_address
+        .clone()
+        .ok_or_else(|| "user not found.".to_string())?;
+    let email_context = EmailContext {
+        message: email.message.clone(),
+        link_url: format!("{}{}", config.market_url, email.link),
+    };
+    let context = Context::from_serialize(&email_context)
+        .map_err(|e| format!("failed to build email context: {:?}", e))?;
+    let body = templates
+        .render(&format!("{}.txt.tera", email.event_type), &context)
+        .map_err(|e| format!("failed to render email: {:?}", e))?;
+
+    let message = Message::builder()
+        .from(
+            config
+                .email_from_address
+                .parse()
+                .map_err(|e| format!("invalid from address: {:?}", e))?,
+        )
+        .to(to_address
+            .parse()
+            .map_err(|e| format!("invalid to address: {:?}", e))?)
+        .subject(email.message.clone())
+        .body(body)
+        .map_err(|e| format!("failed to build email: {:?}", e))?;
+
+    let transport = smtp_transport(config)?;
+    // The SMTP transport blocks, so keep it off the async worker threads.
+    rocket::tokio::task::spawn_blocking(move || transport.send(&message))
+        .await
+        .map_err(|e| format!("failed to join email task: {:?}", e))?
+        .map_err(|e| format!("failed to send email: {:?}", e))?;
+
+    Ok(())
+}
+
+fn smtp_transport(config: &Config) -> Result<SmtpTransport, String> {
+    let builder = if config.smtp_tls {
+        SmtpTransport::starttls_relay(&config.smtp_host)
+            .map_err(|e| format!("failed to create smtp transport: {:?}", e))?
+    } else {
+        // Plain SMTP, for a local mail server or test sink.
+        SmtpTransport::builder_dangerous(&config.smtp_host)
+    };
+    let builder = builder.port(config.smtp_port);
+    let builder = if config.smtp_username.is_empty() {
+        builder
+    } else {
+        builder.credentials(Credentials::new(
+            config.smtp_username.clone(),
+            config.smtp_password.clone(),
+        ))
+    };
+    Ok(builder.build())
+}
@@ -0,0 +1,87 @@
+use crate::base::BaseContext;
+use crate::db::Db;
+use crate::models::{FeeRevenueBounty, FeeRevenueInfo, FeeRevenuePeriod, FeeWithdrawal};
+use rocket::fairing::AdHoc;
+use rocket::request::FlashMessage;
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+const PAGE_SIZE: u32 = 10;
+const NUM_DAYS_SHOWN: u32 = 31;
+const NUM_MONTHS_SHOWN: u32 = 12;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    fee_revenue_info: FeeRevenueInfo,
+    fee_revenue_by_day: Vec<FeeRevenuePeriod>,
+    fee_revenue_by_month: Vec<FeeRevenuePeriod>,
+    fee_revenue_by_bounty: Vec<FeeRevenueBounty>,
+    fee_withdrawals: Vec<FeeWithdrawal>,
+    page_num: u32,
+}
+
+impl Context {
+    pub async fn raw(
+        flash: Option<(String, String)>,
+        mut db: Connection<Db>,
+        maybe_page_num: Option<u32>,
+        user: User,
+        admin_user: Option<AdminUser>,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?;
+        let page_num = maybe_page_num.unwrap_or(1);
+        let fee_revenue_info = FeeRevenueInfo::fee_revenue_info(&mut db)
+            .await
+            .map_err(|_| "failed to get fee revenue info.")?;
+        let fee_revenue_by_day = FeeRevenuePeriod::all_by_day(&mut db, NUM_DAYS_SHOWN, 1)
+            .await
+            .map_err(|_| "failed to get fee revenue by day.")?;
+        let fee_revenue_by_month = FeeRevenuePeriod::all_by_month(&mut db, NUM_MONTHS_SHOWN, 1)
+            .await
+            .map_err(|_| "failed to get fee revenue by month.")?;
+        let fee_revenue_by_bounty = FeeRevenueBounty::all(&mut db, PAGE_SIZE, page_num)
+            .await
+            .map_err(|_| "failed to get fee revenue by bounty.")?;
+        let fee_withdrawals = FeeWithdrawal::all(&mut db, PAGE_SIZE, 1)
+            .await
+            .map_err(|_| "failed to get fee withdrawals.")?;
+        Ok(Context {
+            base_context,
+            flash,
+            fee_revenue_info,
+            fee_revenue_by_day,
+            fee_revenue_by_month,
+            fee_revenue_by_bounty,
+            fee_withdrawals,
+            page_num,
+        })
+    }
+}
+
+#[get("/?<page_num>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    page_num: Option<u32>,
+    user: User,
+    admin_user: AdminUser,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(flash, db, page_num, user, Some(admin_user))
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("feerevenue", context))
+}
+
+pub fn fee_revenue_stage() -> AdHoc {
+    AdHoc::on_ignite("Fee Revenue Stage", |rocket| async {
+        rocket.mount("/fee_revenue", routes![index])
+    })
+}
@@ -0,0 +1,141 @@
+use crate::models::LedgerEntry;
+use rocket::http::{ContentType, Header};
+use rocket::time::Date;
+
+pub const USER_LEDGER_EVENT_TYPES: &[&str] = &[
+    "received_case",
+    "refunded_case",
+    "awarded_reward",
+    "paid_reward",
+    "claim_stake",
+    "claim_stake_returned",
+    "dispute_reserve",
+    "dispute_reserve_released",
+    "withdrawal",
+];
+pub const MARKET_LEDGER_EVENT_TYPES: &[&str] = &[
+    "received_case",
+    "refunded_case",
+    "processing_case",
+    "awarded_reward",
+    "paid_reward",
+    "claim_stake",
+    "claim_stake_returned",
+    "dispute_reserve",
+    "dispute_reserve_released",
+    "withdrawal",
+    "user_activation",
+];
+
+const CSV_COLUMNS: &[&str] = &[
+    "event_time_ms",
+    "event_date",
+    "event_type",
+    "event_id",
+    "user_id",
+    "username",
+    "amount_change_sat",
+    "bounty_public_id",
+    "case_public_id",
+];
+
+#[derive(Debug, Clone, Copy, FromFormField)]
+pub enum LedgerExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, FromForm)]
+pub struct LedgerExportParams {
+    pub format: LedgerExportFormat,
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    pub event_type: Option<String>,
+}
+
+#[derive(Responder)]
+pub struct LedgerExport {
+    inner: (ContentType, String),
+    content_disposition: Header<'static>,
+}
+
+impl LedgerExportParams {
+    /// Start (inclusive) and end (exclusive) of the requested range, in
+    /// unix milliseconds. Both dates are whole UTC days.
+    pub fn time_range_ms(&self) -> (i64, i64) {
+        let start_time_ms = self
+            .start_date
+            .map(|date| date.midnight().assume_utc().unix_timestamp() * 1000)
+            .unwrap_or(0);
+        let end_time_ms = self
+            .end_date
+            .and_then(|date| date.next_day())
+            .map(|date| date.midnight().assume_utc().unix_timestamp() * 1000)
+            .unwrap_or(i64::MAX);
+        (start_time_ms, end_time_ms)
+    }
+
+    pub fn event_type(&self, allowed_event_types: &[&str]) -> Result<Option<String>, String> {
+        match self.event_type.as_deref() {
+            None | Some("") => Ok(None),
+            Some(event_type) if allowed_event_types.contains(&event_type) => {
+                Ok(Some(event_type.to_string()))
+            }
+            Some(_) => Err("Invalid event type.".to_string()),
+        }
+    }
+}
+
+pub fn render(
+    ledger_entries: Vec<LedgerEntry>,
+    format: LedgerExportFormat,
+    filename_prefix: &str,
+) -> Result<LedgerExport, String> {
+    let (content_type, extension, body) = match format {
+        LedgerExportFormat::Csv => (ContentType::CSV, "csv", to_csv(&ledger_entries)),
+        LedgerExportFormat::Json => (
+            ContentType::JSON,
+            "json",
+            serde_json::to_string_pretty(&ledger_entries)
+                .map_err(|_| "failed to serialize ledger.")?,
+        ),
+    };
+    let content_disposition = Header::new(
+        "Content-Disposition",
+        format!("attachment; filename=\"{}.{}\"", filename_prefix, extension),
+    );
+    Ok(LedgerExport {
+        inner: (content_type, body),
+        content_disposition,
+    })
+}
+
+fn to_csv(ledger_entries: &[LedgerEntry]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for entry in ledger_entries {
+        let fields = [
+            entry.event_time_ms.to_string(),
+            entry.event_date.clone(),
+            entry.event_type.clone(),
+            entry.event_id.clone(),
+            entry.user_id.to_string(),
+            entry.username.clone().unwrap_or_default(),
+            entry.amount_change_sat.to_string(),
+            entry.bounty_public_id.clone().unwrap_or_default(),
+            entry.case_public_id.clone().unwrap_or_default(),
+        ];
+        let escaped_fields: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+        csv.push_str(&escaped_fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\r' || c == '\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -10,19 +10,31 @@ mod account_activation;
 mod activate_account;
 mod active_users;
 mod admin;
+mod admins;
 mod auth;
+mod award_bounty;
 mod base;
+mod change_password;
 mod config;
+mod csrf;
 mod db;
 mod deactivate_account;
 mod deactivated_bounties;
 mod delete_bounty;
 mod disabled_users;
+mod edit_bounty;
+mod email;
+mod fee_revenue;
 mod image_util;
+mod ledger_export;
 mod lightning;
 mod bounty;
 mod bounties;
+mod bounty_claims;
+mod bounty_comments;
+mod bounty_revisions;
 mod market_liabilities;
+mod message_inbox;
 mod models;
 mod my_account_balance;
 mod my_active_bounties;
@@ -31,24 +43,45 @@ mod my_paid_cases;
 mod my_pending_bounties;
 mod my_processing_cases;
 mod my_rejected_bounties;
+mod my_team_cases;
 mod my_unpaid_cases;
 mod my_unsubmitted_bounties;
 mod new_bounty;
+mod nostr_publisher;
+mod notifications;
+mod open_disputes;
 mod case;
+mod case_dispute;
 mod case_expiry;
+mod claim_expiry;
+mod case_attachments;
+mod case_messages;
+mod case_ratings;
+mod case_revisions;
 mod payment_processor;
+mod rate_limit;
+mod reset_password;
+mod review_deadline_expiry;
+mod review_deadlines;
 mod prepare_case;
 mod view_pending_bounties;
 mod routes;
 mod search;
+mod solvency;
+mod solvency_report;
 mod seller_history;
 mod top_sellers;
+mod totp;
+mod two_factor;
 mod update_fee_rate;
 mod update_bounty_images;
+mod update_email_settings;
 mod update_market_name;
 mod update_market_info;
 mod update_max_allowed_users;
+mod update_nostr_settings;
 mod update_pgp_info;
+mod update_two_factor_policy;
 mod update_user_bond_price;
 mod update_user_pgp_info;
 mod user;
@@ -56,7 +89,10 @@ mod user_account;
 mod user_account_expiry;
 mod user_profile;
 mod util;
+mod webhook_sender;
+mod webhooks;
 mod withdraw;
+mod withdraw_fees;
 mod withdrawal;
 use std::fs::{File, read_to_string};
 use std::io::prelude::*;
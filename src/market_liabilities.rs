@@ -1,6 +1,7 @@
 use crate::base::BaseContext;
 use crate::db::Db;
-use crate::models::{AccountBalanceChange, AccountInfo};
+use crate::ledger_export::{self, LedgerExport, LedgerExportParams};
+use crate::models::{AccountBalanceChange, AccountInfo, LedgerEntry};
 use rocket::fairing::AdHoc;
 use rocket::request::FlashMessage;
 use rocket::serde::Serialize;
@@ -18,6 +19,7 @@ struct Context {
     total_market_liabilities_sat: i64,
     account_balance_changes: Vec<AccountBalanceChange>,
     page_num: u32,
+    event_types: Vec<String>,
 }
 
 impl Context {
@@ -45,6 +47,10 @@ impl Context {
             total_market_liabilities_sat,
             account_balance_changes,
             page_num,
+            event_types: ledger_export::MARKET_LEDGER_EVENT_TYPES
+                .iter()
+                .map(|event_type| event_type.to_string())
+                .collect(),
         })
     }
 }
@@ -64,9 +70,24 @@ async fn index(
     Ok(Template::render("marketliabilities", context))
 }
 
+#[get("/export?<params..>")]
+async fn export(
+    mut db: Connection<Db>,
+    params: LedgerExportParams,
+    _user: User,
+    _admin_user: AdminUser,
+) -> Result<LedgerExport, String> {
+    let (start_time_ms, end_time_ms) = params.time_range_ms();
+    let maybe_event_type = params.event_type(ledger_export::MARKET_LEDGER_EVENT_TYPES)?;
+    let ledger_entries = LedgerEntry::all(&mut db, start_time_ms, end_time_ms, maybe_event_type)
+        .await
+        .map_err(|_| "failed to get ledger entries.")?;
+    ledger_export::render(ledger_entries, params.format, "market_ledger")
+}
+
 pub fn market_liabilities_stage() -> AdHoc {
     AdHoc::on_ignite("Market Liabilies Stage", |rocket| async {
-        rocket.mount("/market_liabilities", routes![index])
+        rocket.mount("/market_liabilities", routes![index, export])
         // .mount("/bounty", routes![new])
     })
 }
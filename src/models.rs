@@ -4,14 +4,19 @@ use crate::rocket::futures::TryStreamExt;
 use crate::util;
 use rocket::fs::TempFile;
 use rocket::serde::Serialize;
+use rocket_auth::Login;
 use rocket_db_pools::{sqlx, Connection};
 use sqlx::pool::PoolConnection;
 use sqlx::Acquire;
 use sqlx::Row;
 use sqlx::Sqlite;
+use sqlx::SqliteConnection;
 use std::future::Future;
 use std::result::Result;
 
+/// Posters need at least this many decided cases before canceling all of them is flagged.
+const MIN_DECIDED_CASES_FOR_CANCEL_FLAG: u32 = 3;
+
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
 pub struct Bounty {
@@ -35,6 +40,127 @@ pub struct InitialBountyInfo {
     pub title: String,
     pub description: String,
     pub price_sat: Option<u64>,
+    pub milestones: Vec<MilestoneInfo>,
+}
+
+#[derive(Debug, FromForm, Clone)]
+pub struct MilestoneInfo {
+    pub description: String,
+    pub reward_sat: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct BountyMilestone {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub bounty_id: i32,
+    pub position: u32,
+    pub description: String,
+    pub reward_sat: u64,
+    pub awarded: bool,
+    pub created_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct BountyClaimSettings {
+    pub bounty_id: i32,
+    pub claim_window_ms: u64,
+    pub stake_sat: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct BountyClaimSettingsInfo {
+    pub claim_window_hours: Option<u64>,
+    pub stake_sat: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct BountyClaim {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub bounty_id: i32,
+    pub bounty_public_id: Option<String>,
+    pub bounty_title: Option<String>,
+    pub user_id: i32,
+    pub username: Option<String>,
+    pub stake_sat: u64,
+    pub released: bool,
+    pub release_reason: String,
+    pub created_time_ms: u64,
+    pub expires_time_ms: u64,
+    pub released_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct BountyReviewDeadline {
+    pub bounty_id: i32,
+    pub review_deadline_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct BountyReviewDeadlineInfo {
+    pub review_deadline_days: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct BountyRevision {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub bounty_id: i32,
+    pub title: String,
+    pub description: String,
+    pub criteria_changed: bool,
+    pub viewed: bool,
+    pub approved: bool,
+    pub created_time_ms: u64,
+    pub reviewed_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct BountyRevisionInfo {
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct BountyRevisionCard {
+    pub revision: BountyRevision,
+    pub bounty_public_id: String,
+    pub live_title: String,
+    pub poster_username: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct BountyComment {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub bounty_id: i32,
+    pub user_id: i32,
+    pub username: Option<String>,
+    pub parent_id: Option<i32>,
+    pub body: String,
+    pub body_html: String,
+    pub hidden_by_admin: bool,
+    pub created_time_ms: u64,
+    pub edited_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct BountyCommentInfo {
+    pub body: String,
+    pub parent_public_id: Option<String>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct BountyCommentEditInfo {
+    pub body: String,
 }
 
 #[derive(FromForm)]
@@ -66,6 +192,7 @@ pub struct BountyCardDisplay {
     pub bounty: Bounty,
     pub image: Option<BountyImageDisplay>,
     pub user: RocketAuthUser,
+    pub poster_reputation: Option<Reputation>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -166,135 +293,662 @@ pub struct Case {
     pub payment_time_ms: u64,
 }
 
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseDispute {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub case_id: i32,
+    pub case_public_id: Option<String>,
+    pub reason: String,
+    pub resolved: bool,
+    pub resolution: String,
+    pub submitter_share_basis_points: u32,
+    pub resolution_note: String,
+    pub resolved_by_user_id: Option<i32>,
+    pub reserved_sat: u64,
+    pub created_time_ms: u64,
+    pub resolved_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct CaseDisputeInfo {
+    pub reason: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct DisputeResolutionInfo {
+    pub resolution: String,
+    pub submitter_share_percent: Option<u32>,
+    pub note: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DisputeEvidence {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub dispute_id: i32,
+    pub user_id: i32,
+    pub username: Option<String>,
+    pub body: String,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct DisputeEvidenceInfo {
+    pub body: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DisputeStats {
+    pub num_won: u32,
+    pub num_lost: u32,
+    pub num_split: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct TwoFactorSettings {
+    pub user_id: i32,
+    pub secret: String,
+    pub enabled: bool,
+    pub last_used_step: u64,
+    pub created_time_ms: u64,
+}
+
 #[derive(Debug, FromForm, Clone)]
-pub struct CaseInfo {
-    pub quantity: Option<u32>,
-    pub case_details: String,
+pub struct TwoFactorCodeInput {
+    pub code: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(crate = "rocket::serde")]
+pub struct TwoFactorPolicy {
+    pub require_for_withdrawals: bool,
+    pub require_for_admins: bool,
+}
+
+#[derive(Debug, FromForm)]
+pub struct TwoFactorPolicyInput {
+    pub require_for_withdrawals: bool,
+    pub require_for_admins: bool,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct CaseCard {
-    pub case: Case,
-    pub bounty: Option<Bounty>,
-    pub image: Option<BountyImage>,
-    pub user: Option<RocketAuthUser>,
+pub struct PasswordResetToken {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub method: String,
+    pub used: bool,
+    pub expiry_time_ms: u64,
+    pub created_time_ms: u64,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct AccountInfo {
-    pub account_balance_sat: i64,
-    pub account_balance_sat_text: String,
-    pub num_unawarded_cases: u32,
+pub struct SessionReset {
+    pub user_id: i32,
+    pub reset_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct PasswordResetRequestInput {
+    pub username: String,
+    pub method: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct PasswordResetInput {
+    pub token: String,
+    pub new_password: String,
+    pub confirm_password: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct AdminPromotionInput {
+    pub username: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct ChangePasswordInput {
+    pub current: Login,
+    pub new_password: String,
+    pub confirm_password: String,
 }
 
+/// A paid case that is waiting for the poster, and when the poster has to review it by.
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct AdminInfo {
-    pub num_pending_bounties: u32,
+pub struct CaseReviewDeadline {
+    pub case_id: i32,
+    pub case_public_id: String,
+    pub buyer_user_id: i32,
+    pub seller_user_id: i32,
+    pub deadline_ms: u64,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct AccountBalanceChange {
-    pub amount_change_sat: i64,
+pub struct ResponseTimeStats {
+    pub num_reviewed_cases: u32,
+    pub average_response_time_hours: Option<u64>,
+    pub num_missed_deadlines: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseAuditEvent {
+    pub id: Option<i32>,
+    pub case_id: i32,
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
     pub event_type: String,
-    pub event_id: String,
-    pub event_time_ms: u64,
+    pub details: String,
+    pub created_time_ms: u64,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct Withdrawal {
+pub struct CaseRevision {
     pub id: Option<i32>,
     pub public_id: String,
-    pub user_id: i32,
-    pub amount_sat: u64,
-    pub invoice_hash: String,
-    pub invoice_payment_request: String,
+    pub case_id: i32,
+    pub case_details: String,
     pub created_time_ms: u64,
 }
 
-#[derive(Debug, FromForm, Clone)]
-pub struct WithdrawalInfo {
-    pub invoice_payment_request: String,
+#[derive(Debug, FromForm)]
+pub struct CaseRevisionInfo {
+    pub case_details: String,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct SellerInfo {
-    pub username: String,
-    pub total_amount_sold_sat: u64,
+pub struct CaseResponse {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub case_id: i32,
+    pub response_type: String,
+    pub reason: String,
+    pub note: String,
+    pub addressed: bool,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct CaseRejectionInfo {
+    pub reason: String,
+    pub note: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct CaseChangesRequestInfo {
+    pub note: String,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct UserAccount {
+pub struct CaseRating {
     pub id: Option<i32>,
     pub public_id: String,
-    pub user_id: i32,
-    pub amount_owed_sat: u64,
-    pub paid: bool,
-    pub disabled: bool,
-    pub invoice_hash: String,
-    pub invoice_payment_request: String,
+    pub case_id: i32,
+    pub case_public_id: Option<String>,
+    pub rater_user_id: i32,
+    pub rater_username: Option<String>,
+    pub ratee_user_id: i32,
+    pub rater_role: String,
+    pub score: u32,
+    pub review: String,
     pub created_time_ms: u64,
-    pub payment_time_ms: u64,
 }
 
+#[derive(Debug, FromForm)]
+pub struct CaseRatingInfo {
+    pub score: Option<u32>,
+    pub review: String,
+}
+
+/// Aggregate of the ratings a user has received, and how they decided the cases on their bounties.
 #[derive(Serialize, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
-pub struct UserCard {
-    pub user: RocketAuthUser,
-    pub user_account: UserAccount,
+pub struct Reputation {
+    pub num_ratings: u32,
+    pub average_score: f64,
+    pub average_score_text: String,
+    pub num_decided_cases: u32,
+    pub num_canceled_cases: u32,
+    pub cancels_every_case: bool,
 }
 
-impl Default for AdminSettings {
-    fn default() -> AdminSettings {
-        AdminSettings {
-            id: None,
-            market_name: "Sat Bounty".to_string(),
-            market_info: "About this market...".to_string(),
-            market_info_raw: "About this market".to_string(),
-            fee_rate_basis_points: 500,
-            user_bond_price_sat: 1,
-            user_bond_price_sat_text: "1".to_string(),
-            pgp_key: "".to_string(),
-            max_allowed_users: 10000,
-        }
-    }
+/// A file attached to a case. The data is only loaded when the file is downloaded.
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseAttachment {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub case_id: i32,
+    pub user_id: i32,
+    pub filename: String,
+    pub content_type: String,
+    pub encrypted: bool,
+    pub size_bytes: u64,
+    pub created_time_ms: u64,
 }
 
-impl Default for UserSettings {
-    fn default() -> UserSettings {
-        UserSettings {
-            id: None,
-            pgp_key: "".to_string(),
-        }
-    }
+#[derive(Debug, FromForm, Clone)]
+pub struct CaseInfo {
+    pub quantity: Option<u32>,
+    pub case_details: String,
+    pub milestone_public_id: Option<String>,
+    pub co_authors: Vec<CoAuthorInfo>,
 }
 
-impl Bounty {
-    /// Returns the id of the inserted row.
-    pub async fn insert(
-        bounty: Bounty,
-        max_unapproved_bounties: u32,
-        db: &mut Connection<Db>,
-    ) -> Result<i32, String> {
-        let mut tx = db
-            .begin()
-            .await
-            .map_err(|_| "failed to begin transaction.")?;
+#[derive(Debug, FromForm, Clone)]
+pub struct CoAuthorInfo {
+    pub username: String,
+    pub share_percent: Option<u32>,
+}
 
-        let price_sat: i64 = bounty.price_sat.try_into().unwrap();
-        let created_time_ms: i64 = bounty.created_time_ms.try_into().unwrap();
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseTeamMember {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub case_id: i32,
+    pub user_id: i32,
+    pub username: Option<String>,
+    pub share_basis_points: u32,
+    pub accepted: bool,
+    pub created_time_ms: u64,
+}
 
-        let insert_result = sqlx::query!(
-            "INSERT INTO bounties (public_id, user_id, title, description, price_sat, fee_rate_basis_points, submitted, viewed, approved, deactivated_by_seller, deactivated_by_admin, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            bounty.public_id,
-            bounty.user_id,
-            bounty.title,
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct TeamCaseCard {
+    pub member: CaseTeamMember,
+    pub case_public_id: String,
+    pub case_awarded: bool,
+    pub bounty_public_id: Option<String>,
+    pub bounty_title: Option<String>,
+    pub submitter_username: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseAward {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub case_id: i32,
+    pub bounty_id: i32,
+    pub poster_user_id: i32,
+    pub winner_user_id: i32,
+    pub milestone_id: Option<i32>,
+    pub amount_sat: u64,
+    pub fee_sat: u64,
+    pub credit_sat: u64,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm, Clone)]
+pub struct CaseShareInfo {
+    pub case_public_id: String,
+    pub share: Option<u64>,
+}
+
+#[derive(Debug, FromForm, Clone)]
+pub struct SplitAwardInfo {
+    pub milestone_public_id: Option<String>,
+    pub share_type: String,
+    pub case_shares: Vec<CaseShareInfo>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseMessage {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub case_id: i32,
+    pub sender_user_id: i32,
+    pub sender_username: Option<String>,
+    pub recipient_user_id: i32,
+    pub body: String,
+    pub encrypted: bool,
+    pub read: bool,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct CaseMessageInfo {
+    pub body: String,
+    pub encrypted: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseMessageCard {
+    pub case_public_id: String,
+    pub bounty_title: Option<String>,
+    pub num_messages: u32,
+    pub num_unread_messages: u32,
+    pub last_message_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CaseCard {
+    pub case: Case,
+    pub bounty: Option<Bounty>,
+    pub image: Option<BountyImage>,
+    pub user: Option<RocketAuthUser>,
+    pub poster_reputation: Option<Reputation>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AccountInfo {
+    pub account_balance_sat: i64,
+    pub account_balance_sat_text: String,
+    pub num_unawarded_cases: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AdminInfo {
+    pub num_pending_bounties: u32,
+    pub solvency_alert: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AccountBalanceChange {
+    pub amount_change_sat: i64,
+    pub event_type: String,
+    pub event_id: String,
+    pub event_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct LedgerEntry {
+    pub event_time_ms: u64,
+    pub event_date: String,
+    pub event_type: String,
+    pub event_id: String,
+    pub user_id: i32,
+    pub username: Option<String>,
+    pub amount_change_sat: i64,
+    pub bounty_public_id: Option<String>,
+    pub case_public_id: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct SolvencySnapshot {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub channel_balance_sat: i64,
+    pub onchain_balance_sat: i64,
+    pub user_liabilities_sat: i64,
+    pub held_escrow_sat: i64,
+    pub coverage_basis_points: i64,
+    pub below_threshold: bool,
+    pub message: String,
+    pub signature: String,
+    pub created_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct ProofOfLiabilities {
+    pub user_liabilities_sat: i64,
+    pub held_escrow_sat: i64,
+    pub channel_balance_sat: i64,
+    pub onchain_balance_sat: i64,
+    pub created_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct Notification {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub user_id: i32,
+    pub event_type: String,
+    pub message: String,
+    pub link: String,
+    pub read: bool,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct NotificationPreferencesInput {
+    pub enabled_event_types: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct Email {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub user_id: i32,
+    pub to_address: Option<String>,
+    pub event_type: String,
+    pub message: String,
+    pub link: String,
+    pub sent: bool,
+    pub num_attempts: u32,
+    pub last_error: String,
+    pub next_attempt_time_ms: u64,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct EmailSettingsInput {
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct NostrEvent {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub recipient_user_id: Option<i32>,
+    pub recipient_public_key: Option<String>,
+    pub message: String,
+    pub link: String,
+    pub sent: bool,
+    pub num_attempts: u32,
+    pub last_error: String,
+    pub next_attempt_time_ms: u64,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct NostrKeyInput {
+    pub public_key: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct Webhook {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub user_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub all_events: bool,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct WebhookInfo {
+    pub url: String,
+    pub all_events: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct WebhookDelivery {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub payload: String,
+    pub delivered: bool,
+    pub num_attempts: u32,
+    pub last_status_code: u32,
+    pub last_error: String,
+    pub next_attempt_time_ms: u64,
+    pub created_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct Withdrawal {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub user_id: i32,
+    pub amount_sat: u64,
+    pub invoice_hash: String,
+    pub invoice_payment_request: String,
+    pub created_time_ms: u64,
+}
+
+#[derive(Debug, FromForm, Clone)]
+pub struct WithdrawalInfo {
+    pub invoice_payment_request: String,
+    pub totp_code: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct FeeWithdrawal {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub user_id: i32,
+    pub amount_sat: u64,
+    pub invoice_hash: String,
+    pub invoice_payment_request: String,
+    pub created_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct FeeRevenueInfo {
+    pub total_fee_revenue_sat: i64,
+    pub total_fee_withdrawals_sat: i64,
+    pub available_fee_revenue_sat: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct FeeRevenuePeriod {
+    pub period: String,
+    pub total_fee_sat: i64,
+    pub num_cases: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct FeeRevenueBounty {
+    pub bounty_public_id: String,
+    pub bounty_title: String,
+    pub total_fee_sat: i64,
+    pub num_cases: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct SellerInfo {
+    pub username: String,
+    pub total_amount_sold_sat: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct UserAccount {
+    pub id: Option<i32>,
+    pub public_id: String,
+    pub user_id: i32,
+    pub amount_owed_sat: u64,
+    pub paid: bool,
+    pub disabled: bool,
+    pub invoice_hash: String,
+    pub invoice_payment_request: String,
+    pub created_time_ms: u64,
+    pub payment_time_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct UserCard {
+    pub user: RocketAuthUser,
+    pub user_account: UserAccount,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DeactivationInfo {
+    pub bond_sat: u64,
+    pub account_balance_sat: i64,
+    pub refund_sat: i64,
+    pub num_unpaid_cases: u32,
+    pub num_processing_cases: u32,
+    pub num_active_bounties: u32,
+    pub num_open_disputes: u32,
+    pub num_active_claims: u32,
+}
+
+impl Default for AdminSettings {
+    fn default() -> AdminSettings {
+        AdminSettings {
+            id: None,
+            market_name: "Sat Bounty".to_string(),
+            market_info: "About this market...".to_string(),
+            market_info_raw: "About this market".to_string(),
+            fee_rate_basis_points: 500,
+            user_bond_price_sat: 1,
+            user_bond_price_sat_text: "1".to_string(),
+            pgp_key: "".to_string(),
+            max_allowed_users: 10000,
+        }
+    }
+}
+
+impl Default for UserSettings {
+    fn default() -> UserSettings {
+        UserSettings {
+            id: None,
+            pgp_key: "".to_string(),
+        }
+    }
+}
+
+impl Bounty {
+    /// Inserts the bounty with its milestones, if any. Returns the id of the inserted row.
+    pub async fn insert(
+        bounty: Bounty,
+        milestones: Vec<BountyMilestone>,
+        max_unapproved_bounties: u32,
+        db: &mut Connection<Db>,
+    ) -> Result<i32, String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        let price_sat: i64 = bounty.price_sat.try_into().unwrap();
+        let created_time_ms: i64 = bounty.created_time_ms.try_into().unwrap();
+
+        let insert_result = sqlx::query!(
+            "INSERT INTO bounties (public_id, user_id, title, description, price_sat, fee_rate_basis_points, submitted, viewed, approved, deactivated_by_seller, deactivated_by_admin, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            bounty.public_id,
+            bounty.user_id,
+            bounty.title,
             bounty.description,
             price_sat,
             bounty.fee_rate_basis_points,
@@ -305,3161 +959,8892 @@ impl Bounty {
             bounty.deactivated_by_admin,
             created_time_ms,
         )
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| "failed to insert new bounty.")?;
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to insert new bounty.")?;
+
+        let num_unapproved_bounties = sqlx::query!(
+            "
+select
+ COUNT(bounties.id) as num_unapproved_bounties
+from
+ bounties
+WHERE
+ bounties.user_id = ?
+AND
+ NOT bounties.approved
+;",
+            bounty.user_id,
+        )
+        .fetch_one(&mut *tx)
+        .map_ok(|r| r.num_unapproved_bounties as u32)
+        .await
+        .map_err(|_| "failed to get count of unapproved bounties.")?;
+
+        if num_unapproved_bounties > max_unapproved_bounties {
+            return Err(format!(
+                "more than {:?} unapproved bounties not allowed.",
+                max_unapproved_bounties
+            ));
+        }
+
+        let bounty_id = insert_result.last_insert_rowid() as i32;
+        for milestone in milestones {
+            let milestone = BountyMilestone {
+                bounty_id,
+                ..milestone
+            };
+            BountyMilestone::insert_for_connection(&mut *tx, milestone)
+                .await
+                .map_err(|_| "failed to insert milestone.")?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(bounty_id)
+    }
+
+    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<Bounty, sqlx::Error> {
+        let bounty = sqlx::query!("select * from bounties WHERE id = ?;", id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| Bounty {
+                id: Some(r.id.try_into().unwrap()),
+                public_id: r.public_id,
+                user_id: r.user_id.try_into().unwrap(),
+                title: r.title,
+                description: markdown::to_html(&r.description),
+                price_sat: r.price_sat.try_into().unwrap(),
+                fee_rate_basis_points: r.fee_rate_basis_points.try_into().unwrap(),
+                submitted: r.submitted,
+                viewed: r.viewed,
+		        approved: r.approved,
+                deactivated_by_seller: r.deactivated_by_seller,
+                deactivated_by_admin: r.deactivated_by_admin,
+                created_time_ms: r.created_time_ms.try_into().unwrap(),
+            })
+            .await?;
+
+        Ok(bounty)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<Bounty, sqlx::Error> {
+        let bounty = sqlx::query!("select * from bounties WHERE public_id = ?;", public_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| Bounty {
+                id: Some(r.id.try_into().unwrap()),
+                public_id: r.public_id,
+                user_id: r.user_id.try_into().unwrap(),
+                title: r.title,
+                description: markdown::to_html(&r.description),
+                price_sat: r.price_sat.try_into().unwrap(),
+                fee_rate_basis_points: r.fee_rate_basis_points.try_into().unwrap(),
+                submitted: r.submitted,
+                viewed: r.viewed,
+		        approved: r.approved,
+                deactivated_by_seller: r.deactivated_by_seller,
+                deactivated_by_admin: r.deactivated_by_admin,
+                created_time_ms: r.created_time_ms.try_into().unwrap(),
+            })
+            .await?;
+
+        Ok(bounty)
+    }
+
+    pub async fn mark_as_submitted(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE bounties SET submitted = true WHERE public_id = ?",
+            public_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn mark_as_approved(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE bounties SET viewed = true, approved = true WHERE public_id = ?",
+            public_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        let bounty = Bounty::single_by_public_id(db, public_id).await?;
+        Notification::insert_for_connection(
+            &mut **db,
+            bounty.user_id,
+            "bounty_approved",
+            &format!("Bounty approved: {}", bounty.title),
+            &format!("/bounty/{}", public_id),
+        )
+        .await?;
+        WebhookDelivery::enqueue_for_connection(
+            &mut **db,
+            &[bounty.user_id],
+            "bounty_approved",
+            serde_json::json!({
+                "bounty_id": bounty.public_id,
+                "title": bounty.title,
+                "reward_sat": bounty.price_sat,
+                "link": format!("/bounty/{}", public_id),
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn mark_as_rejected(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE bounties SET viewed = true, approved = false WHERE public_id = ?",
+            public_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        let bounty = Bounty::single_by_public_id(db, public_id).await?;
+        Notification::insert_for_connection(
+            &mut **db,
+            bounty.user_id,
+            "bounty_rejected",
+            &format!("Bounty rejected: {}", bounty.title),
+            &format!("/bounty/{}", public_id),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn mark_as_deactivated_by_seller(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+UPDATE bounties
+SET deactivated_by_seller = true
+WHERE
+ public_id = ?
+AND
+ approved
+AND NOT (deactivated_by_seller OR deactivated_by_admin)
+;",
+            public_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn mark_as_deactivated_by_admin(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+UPDATE bounties
+SET deactivated_by_admin = true
+WHERE
+ public_id = ?
+AND
+ approved
+AND NOT (deactivated_by_seller OR deactivated_by_admin)
+;",
+            public_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn num_pending(db: &mut Connection<Db>) -> Result<u32, sqlx::Error> {
+        let num_bounties = sqlx::query!(
+            "
+select
+ COUNT(bounties.id) as num_pending_bounties
+from
+ bounties
+WHERE
+ bounties.submitted
+AND
+ NOT bounties.viewed
+;",
+        )
+        .fetch_one(&mut **db)
+        .map_ok(|r| r.num_pending_bounties as u32)
+        .await?;
+
+        Ok(num_bounties)
+    }
+
+    pub async fn delete(bounty_id: i32, db: &mut Connection<Db>) -> Result<usize, String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        let delete_result = sqlx::query!(
+            "
+DELETE FROM bounties
+WHERE
+ id = ?
+;",
+            bounty_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to delete bounty.")?;
+
+        sqlx::query!(
+            "
+DELETE from bountyimages
+WHERE
+ bounty_id = ?
+;",
+            bounty_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to delete images for bounty.")?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(delete_result.rows_affected() as _)
+        //Ok(())
+    }
+}
+
+impl BountyImage {
+    /// Returns the number of affected rows: 1.
+    pub async fn insert(
+        bountyimage: BountyImage,
+        db: &mut Connection<Db>,
+    ) -> Result<usize, sqlx::Error> {
+        let insert_result = sqlx::query!(
+            "INSERT INTO bountyimages (public_id, bounty_id, image_data, is_primary) VALUES (?, ?, ?, ?)",
+            bountyimage.public_id,
+            bountyimage.bounty_id,
+            bountyimage.image_data,
+            bountyimage.is_primary,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(insert_result.rows_affected() as _)
+    }
+
+    pub async fn all_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Vec<BountyImage>, sqlx::Error> {
+        let bounty_images = sqlx::query!(
+            "select * from bountyimages WHERE bounty_id = ? ORDER BY bountyimages.is_primary DESC;",
+            bounty_id
+        )
+        .fetch(&mut **db)
+        .map_ok(|r| BountyImage {
+            id: r.id.map(|n| n.try_into().unwrap()),
+            public_id: r.public_id,
+            bounty_id: r.bounty_id.try_into().unwrap(),
+            image_data: r.image_data,
+            is_primary: r.is_primary,
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(bounty_images)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<BountyImage, sqlx::Error> {
+        let bounty_image = sqlx::query!(
+            "select * from bountyimages WHERE public_id = ?;",
+            public_id
+        )
+        .fetch_one(&mut **db)
+        .map_ok(|r| BountyImage {
+            id: Some(r.id.try_into().unwrap()),
+            public_id: r.public_id,
+            bounty_id: r.bounty_id.try_into().unwrap(),
+            image_data: r.image_data,
+            is_primary: r.is_primary,
+        })
+        .await?;
+
+        Ok(bounty_image)
+    }
+
+    pub async fn mark_image_as_primary_by_public_id(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+        image_id: &str,
+    ) -> Result<usize, sqlx::Error> {
+        // Set all images for bounty_id to not primary.
+        let update_primary_result = sqlx::query!(
+            "
+UPDATE
+ bountyimages
+SET
+ is_primary = (public_id = ?)
+WHERE
+ bounty_id = ?
+;",
+            image_id,
+            bounty_id
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(update_primary_result.rows_affected() as _)
+    }
+
+    /// Returns the number of affected rows: 1.
+    pub async fn delete_with_public_id(
+        public_id: &str,
+        db: &mut Connection<Db>,
+    ) -> Result<usize, sqlx::Error> {
+        let delete_result =
+            sqlx::query!("DELETE FROM bountyimages WHERE public_id = ?", public_id)
+                .execute(&mut **db)
+                .await?;
+
+        Ok(delete_result.rows_affected() as _)
+    }
+}
+
+impl RocketAuthUser {
+    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<RocketAuthUser, sqlx::Error> {
+        let rocket_auth_user = sqlx::query!("select id, email from users WHERE id = ?;", id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| RocketAuthUser {
+                id: Some(r.id as i32),
+                username: r.email.unwrap(),
+            })
+            .await?;
+
+        Ok(rocket_auth_user)
+    }
+
+    pub async fn single_by_username(
+        db: &mut Connection<Db>,
+        username: String,
+    ) -> Result<RocketAuthUser, sqlx::Error> {
+        let rocket_auth_user =
+            sqlx::query!("select id, email from users WHERE email = ?;", username)
+                .fetch_one(&mut **db)
+                .map_ok(|r| RocketAuthUser {
+                    id: Some(r.id.unwrap() as i32),
+                    username: r.email.unwrap(),
+                })
+                .await?;
+
+        Ok(rocket_auth_user)
+    }
+
+    /// The argon2 hash of the password of the user, as stored by rocket_auth.
+    pub async fn password_hash(db: &mut Connection<Db>, id: i32) -> Result<String, sqlx::Error> {
+        let password_hash = sqlx::query("select password from users WHERE id = ?;")
+            .bind(id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| r.try_get("password").unwrap())
+            .await?;
+
+        Ok(password_hash)
+    }
+
+    pub async fn all_admins(db: &mut Connection<Db>) -> Result<Vec<RocketAuthUser>, sqlx::Error> {
+        let rocket_auth_users =
+            sqlx::query("select id, email from users WHERE is_admin ORDER BY email ASC;")
+                .fetch(&mut **db)
+                .map_ok(|r| RocketAuthUser {
+                    id: Some(r.try_get("id").unwrap()),
+                    username: r.try_get("email").unwrap(),
+                })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(rocket_auth_users)
+    }
+}
+
+impl BountyDisplay {
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<BountyDisplay, sqlx::Error> {
+        let bounty = Bounty::single_by_public_id(&mut *db, public_id).await?;
+        let images = BountyImage::all_for_bounty(&mut *db, bounty.id.unwrap()).await?;
+        let image_displays = images
+            .iter()
+            .map(|img| BountyImageDisplay {
+                id: img.id,
+                public_id: img.clone().public_id,
+                bounty_id: img.bounty_id,
+                image_data_base64: util::to_base64(&img.image_data),
+                is_primary: img.is_primary,
+            })
+            .collect::<Vec<_>>();
+        let rocket_auth_user = RocketAuthUser::single(&mut *db, bounty.user_id).await.ok();
+
+        let bounty_display = BountyDisplay {
+            bounty,
+            images: image_displays,
+            user: rocket_auth_user,
+        };
+
+        Ok(bounty_display)
+    }
+}
+
+impl BountyCard {
+    pub async fn all_active(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
+        // Other example query: https://stackoverflow.com/a/13698334/1639564
+        // TODO: change WHERE condition to use dynamically calculated remaining quantity
+        // based on number of awarded cases.
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+INNER JOIN
+ useraccounts
+ON
+ bounties.user_id = useraccounts.user_id
+AND
+ NOT useraccounts.disabled
+WHERE
+ bounties.approved
+AND
+ not (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+		            deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_deactivated(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ bounties.approved
+AND
+ (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+		            approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_pending(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
+        // Other example query: https://stackoverflow.com/a/13698334/1639564
+        // TODO: change WHERE condition to use dynamically calculated remaining quantity
+        // based on number of awarded cases.
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ bounties.submitted
+AND
+ NOT bounties.viewed
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_unsubmitted_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
+        // Other example query: https://stackoverflow.com/a/13698334/1639564
+        // TODO: change WHERE condition to use dynamically calculated remaining quantity
+        // based on number of awarded cases.
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ not bounties.submitted
+AND
+ users.id = ?
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", user_id, limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_pending_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
+        // Other example query: https://stackoverflow.com/a/13698334/1639564
+        // TODO: change WHERE condition to use dynamically calculated remaining quantity
+        // based on number of awarded cases. 
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ bounties.submitted
+AND
+ NOT bounties.viewed
+AND
+ users.id = ?
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", user_id, limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_rejected_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
+        // Other example query: https://stackoverflow.com/a/13698334/1639564
+        // TODO: change WHERE condition to use dynamically calculated remaining quantity
+        // based on number of awarded cases.
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ not bounties.approved
+AND
+ NOT bounties.viewed
+AND
+ users.id = ?
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", user_id, limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_admin.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_deactivated_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ bounties.approved
+AND
+ (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+AND
+ users.id = ?
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", user_id, limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_admin.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_active_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
+        // Other example query: https://stackoverflow.com/a/13698334/1639564
+        // TODO: change WHERE condition to use dynamically calculated remaining quantity
+        // based on number of awarded cases.
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ bounties.approved
+AND
+ bounties.viewed
+AND
+ NOT (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+AND
+ users.id = ?
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", user_id, limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    pub async fn all_active_for_search_text(
+        db: &mut Connection<Db>,
+        search_text: &str,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
+        // Other example query: https://stackoverflow.com/a/13698334/1639564
+        // TODO: change WHERE condition to use dynamically calculated remaining quantity
+        // based on number of awarded cases.
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        // let uppercase_search_term = search_text.to_owned().to_ascii_uppercase();
+        let wildcard_search_term = format!("%{}%", search_text.to_ascii_uppercase());
+        let bounty_cards =
+            sqlx::query!("
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+INNER JOIN
+ useraccounts
+ON
+ bounties.user_id = useraccounts.user_id
+AND
+ NOT useraccounts.disabled
+WHERE
+ bounties.approved
+AND
+ bounties.viewed
+AND
+ NOT (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+AND
+ (UPPER(bounties.title) like ? OR UPPER(bounties.description) like ?)
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", wildcard_search_term, wildcard_search_term, limit, offset)
+                .fetch(&mut **db)
+            .map_ok(|r| {
+                let l = Bounty {
+                    id: Some(r.id.unwrap().try_into().unwrap()),
+                    public_id: r.public_id.unwrap(),
+                    user_id: r.user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                };
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                BountyCard {
+                    bounty: l,
+                    image: i,
+                    user: u.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(bounty_cards)
+    }
+
+    /// Same as the search, but only for posters with an average rating of at least the minimum.
+    pub async fn all_active_for_search_text_and_min_rating(
+        db: &mut Connection<Db>,
+        search_text: &str,
+        min_rating: u32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let wildcard_search_term = format!("%{}%", search_text.to_ascii_uppercase());
+        let bounty_cards = sqlx::query(
+            "
+select
+ bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ bounties
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+INNER JOIN
+ users
+ON
+ bounties.user_id = users.id
+INNER JOIN
+ useraccounts
+ON
+ bounties.user_id = useraccounts.user_id
+AND
+ NOT useraccounts.disabled
+WHERE
+ bounties.approved
+AND
+ bounties.viewed
+AND
+ NOT (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+AND
+ (UPPER(bounties.title) like ? OR UPPER(bounties.description) like ?)
+AND
+ (SELECT AVG(caseratings.score) FROM caseratings WHERE caseratings.ratee_user_id = bounties.user_id) >= ?
+GROUP BY
+ bounties.id
+ORDER BY bounties.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;",
+        )
+        .bind(&wildcard_search_term)
+        .bind(&wildcard_search_term)
+        .bind(min_rating)
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| {
+            let bounty = Bounty {
+                id: Some(r.try_get("id").unwrap()),
+                public_id: r.try_get("public_id").unwrap(),
+                user_id: r.try_get("user_id").unwrap(),
+                title: r.try_get("title").unwrap(),
+                description: r.try_get("description").unwrap(),
+                price_sat: {
+                    let price_sat_i64: i64 = r.try_get("price_sat").unwrap();
+                    price_sat_i64 as u64
+                },
+                fee_rate_basis_points: r.try_get("fee_rate_basis_points").unwrap(),
+                submitted: r.try_get("submitted").unwrap(),
+                viewed: r.try_get("viewed").unwrap(),
+                approved: r.try_get("approved").unwrap(),
+                deactivated_by_seller: r.try_get("deactivated_by_seller").unwrap(),
+                deactivated_by_admin: r.try_get("deactivated_by_admin").unwrap(),
+                created_time_ms: {
+                    let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                    time_ms_i64 as u64
+                },
+            };
+            let maybe_image_id: Option<i32> = r.try_get("image_id").unwrap();
+            let image = maybe_image_id.map(|image_id| BountyImage {
+                id: Some(image_id),
+                public_id: r.try_get("image_public_id").unwrap(),
+                bounty_id: bounty.id.unwrap(),
+                image_data: r.try_get("image_data").unwrap(),
+                is_primary: r.try_get("is_primary").unwrap(),
+            });
+            let user = RocketAuthUser {
+                id: Some(r.try_get("rocket_auth_user_id").unwrap()),
+                username: r.try_get("rocket_auth_user_username").unwrap(),
+            };
+            BountyCard {
+                bounty,
+                image,
+                user,
+            }
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(bounty_cards)
+    }
+}
+
+impl BountyCardDisplay {
+    fn bounty_card_to_display(card: &BountyCard) -> BountyCardDisplay {
+        BountyCardDisplay {
+            bounty: card.bounty.clone(),
+            image: card.image.clone().map(|image| BountyImageDisplay {
+                id: image.id,
+                public_id: image.public_id,
+                bounty_id: image.bounty_id,
+                image_data_base64: util::to_base64(&image.image_data),
+                is_primary: image.is_primary,
+            }),
+            user: card.clone().user,
+            poster_reputation: None,
+        }
+    }
+
+    /// Adds the reputation of each poster, for the lists that hunters browse.
+    async fn with_poster_reputations(
+        db: &mut Connection<Db>,
+        mut bounty_card_displays: Vec<BountyCardDisplay>,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        for display in bounty_card_displays.iter_mut() {
+            let poster_user_id = display.bounty.user_id;
+            display.poster_reputation =
+                Some(Reputation::single_for_user(db, poster_user_id).await?);
+        }
+
+        Ok(bounty_card_displays)
+    }
+
+    pub async fn all_active(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards = BountyCard::all_active(db, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        BountyCardDisplay::with_poster_reputations(db, bounty_card_displays).await
+    }
+
+    pub async fn all_deactivated(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards = BountyCard::all_deactivated(db, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        Ok(bounty_card_displays)
+    }
+
+    pub async fn all_pending(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards = BountyCard::all_pending(db, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        Ok(bounty_card_displays)
+    }
+
+    pub async fn all_unsubmitted_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards =
+            BountyCard::all_unsubmitted_for_user(db, user_id, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        Ok(bounty_card_displays)
+    }
+
+    pub async fn all_pending_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards =
+            BountyCard::all_pending_for_user(db, user_id, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        Ok(bounty_card_displays)
+    }
+
+    pub async fn all_rejected_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards =
+            BountyCard::all_rejected_for_user(db, user_id, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        Ok(bounty_card_displays)
+    }
+
+    pub async fn all_deactivated_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards =
+            BountyCard::all_deactivated_for_user(db, user_id, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        Ok(bounty_card_displays)
+    }
+
+    pub async fn all_active_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards =
+            BountyCard::all_active_for_user(db, user_id, page_size, page_num).await?;
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        BountyCardDisplay::with_poster_reputations(db, bounty_card_displays).await
+    }
+
+    pub async fn all_active_for_search_text(
+        db: &mut Connection<Db>,
+        search_text: &str,
+        maybe_min_rating: Option<u32>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
+        let bounty_cards = match maybe_min_rating {
+            Some(min_rating) => {
+                BountyCard::all_active_for_search_text_and_min_rating(
+                    db,
+                    search_text,
+                    min_rating,
+                    page_size,
+                    page_num,
+                )
+                .await?
+            }
+            None => {
+                BountyCard::all_active_for_search_text(db, search_text, page_size, page_num).await?
+            }
+        };
+        let bounty_card_displays = bounty_cards
+            .iter()
+            .map(BountyCardDisplay::bounty_card_to_display)
+            .collect::<Vec<_>>();
+
+        BountyCardDisplay::with_poster_reputations(db, bounty_card_displays).await
+    }
+}
+
+impl AdminSettings {
+    pub async fn single(db: &mut Connection<Db>) -> Result<AdminSettings, sqlx::Error> {
+        let maybe_admin_settings = sqlx::query!("select * from adminsettings;")
+            .fetch_optional(&mut **db)
+            .map_ok(|maybe_r| {
+                maybe_r.map(|r| AdminSettings {
+                    id: Some(r.id.try_into().unwrap()),
+                    market_name: r.market_name,
+                    market_info: markdown::to_html(&r.market_info),
+                    market_info_raw: r.market_info,
+                    fee_rate_basis_points: r.fee_rate_basis_points.try_into().unwrap(),
+                    user_bond_price_sat: r.user_bond_price_sat.try_into().unwrap(),
+                    user_bond_price_sat_text: util::short_num_format(r.user_bond_price_sat.try_into().unwrap()),
+                    pgp_key: r.pgp_key,
+                    max_allowed_users: r.max_allowed_users.try_into().unwrap(),
+                })
+            })
+            .await?;
+
+        let admin_settings = maybe_admin_settings.unwrap_or_default();
+
+        Ok(admin_settings)
+    }
+
+    async fn insert_if_doesnt_exist(db: &mut Connection<Db>) -> Result<(), sqlx::Error> {
+        let admin_settings = AdminSettings::default();
+        let user_bond_price_sat_i64: i64 = admin_settings.user_bond_price_sat.try_into().unwrap();
+        let max_allowed_users_i64: i64 = admin_settings.max_allowed_users.try_into().unwrap();
+        sqlx::query!(
+            "
+INSERT INTO
+ adminsettings (market_name, market_info, fee_rate_basis_points, user_bond_price_sat, pgp_key, max_allowed_users)
+SELECT ?, ?, ?, ?, ?, ?
+WHERE NOT EXISTS(SELECT 1 FROM adminsettings)
+;",
+            admin_settings.market_name,
+            admin_settings.market_info,
+            admin_settings.fee_rate_basis_points,
+            user_bond_price_sat_i64,
+            admin_settings.pgp_key,
+            max_allowed_users_i64,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_market_name(
+        db: &mut Connection<Db>,
+        new_market_name: &str,
+    ) -> Result<(), sqlx::Error> {
+        AdminSettings::insert_if_doesnt_exist(db).await?;
+
+        sqlx::query!("UPDATE adminsettings SET market_name = ?", new_market_name)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_market_info(
+        db: &mut Connection<Db>,
+        new_market_info: &str,
+    ) -> Result<(), sqlx::Error> {
+        AdminSettings::insert_if_doesnt_exist(db).await?;
+
+        sqlx::query!("UPDATE adminsettings SET market_info = ?", new_market_info)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_fee_rate(
+        db: &mut Connection<Db>,
+        new_fee_rate_basis_points: i32,
+    ) -> Result<(), sqlx::Error> {
+        AdminSettings::insert_if_doesnt_exist(db).await?;
+
+        sqlx::query!(
+            "UPDATE adminsettings SET fee_rate_basis_points = ?",
+            new_fee_rate_basis_points,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_user_bond_price(
+        db: &mut Connection<Db>,
+        new_user_bond_price_sat: u64,
+    ) -> Result<(), sqlx::Error> {
+        let user_bond_price_sat_i64: i64 = new_user_bond_price_sat.try_into().unwrap();
+
+        AdminSettings::insert_if_doesnt_exist(db).await?;
+
+        sqlx::query!(
+            "UPDATE adminsettings SET user_bond_price_sat = ?",
+            user_bond_price_sat_i64,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_pgp_key(
+        db: &mut Connection<Db>,
+        new_pgp_key: &str,
+    ) -> Result<(), sqlx::Error> {
+        AdminSettings::insert_if_doesnt_exist(db).await?;
+
+        sqlx::query!("UPDATE adminsettings SET pgp_key = ?", new_pgp_key,)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_max_allowed_users(
+        db: &mut Connection<Db>,
+        new_max_allowed_users: u64,
+    ) -> Result<(), sqlx::Error> {
+        let max_allowed_users_i64: i64 = new_max_allowed_users.try_into().unwrap();
+
+        AdminSettings::insert_if_doesnt_exist(db).await?;
+
+        sqlx::query!(
+            "UPDATE adminsettings SET max_allowed_users = ?",
+            max_allowed_users_i64,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl UserSettings {
+    pub async fn single(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<UserSettings, sqlx::Error> {
+        let maybe_user_settings =
+            sqlx::query!("select * from usersettings WHERE user_id = ?;", user_id,)
+                .fetch_optional(&mut **db)
+                .map_ok(|maybe_r| {
+                    maybe_r.map(|r| UserSettings {
+                        id: Some(r.id.try_into().unwrap()),
+                        pgp_key: r.pgp_key,
+                    })
+                })
+                .await?;
+        let user_settings = maybe_user_settings.unwrap_or_default();
+
+        Ok(user_settings)
+    }
+
+    /// Returns the number of affected rows: 1.
+    async fn insert_if_doesnt_exist(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        let user_settings = UserSettings::default();
+        sqlx::query!(
+            "
+INSERT INTO
+ usersettings (user_id, pgp_key)
+SELECT ?, ?
+WHERE NOT EXISTS(SELECT 1 FROM usersettings WHERE user_id = ?)
+;",
+            user_id,
+            user_settings.pgp_key,
+            user_id,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_pgp_key(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        new_pgp_key: &str,
+    ) -> Result<(), sqlx::Error> {
+        UserSettings::insert_if_doesnt_exist(db, user_id).await?;
+
+        sqlx::query!(
+            "UPDATE usersettings SET pgp_key = ? WHERE user_id = ?;",
+            new_pgp_key,
+            user_id,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl Case {
+    /// Returns the id of the inserted row.
+    /// Inserts the case with its team, linked to its milestone if any. Returns the id of
+    /// the inserted row.
+    pub async fn insert(
+        case: Case,
+        maybe_milestone_id: Option<i32>,
+        team_members: Vec<CaseTeamMember>,
+        max_unpaid_cases: u32,
+        db: &mut Connection<Db>,
+    ) -> Result<i32, String> {
+        let amount_owed_sat: i64 = case.amount_owed_sat.try_into().unwrap();
+        let seller_credit_sat: i64 = case.seller_credit_sat.try_into().unwrap();
+        let created_time_ms: i64 = case.created_time_ms.try_into().unwrap();
+        let payment_time_ms: i64 = case.payment_time_ms.try_into().unwrap();
+
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        let insert_result = sqlx::query!(
+            "INSERT INTO cases (public_id, buyer_user_id, seller_user_id, quantity, bounty_id, case_details, amount_owed_sat, seller_credit_sat, paid, awarded, canceled_by_seller, canceled_by_buyer, invoice_hash, invoice_payment_request, created_time_ms, payment_time_ms ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            case.public_id,
+            case.buyer_user_id,
+            case.seller_user_id,
+            case.quantity,
+            case.bounty_id,
+            case.case_details,
+            amount_owed_sat,
+            seller_credit_sat,
+            case.paid,
+            case.awarded,
+            case.canceled_by_seller,
+            case.canceled_by_buyer,
+            case.invoice_hash,
+            case.invoice_payment_request,
+            created_time_ms,
+            payment_time_ms,
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to insert case.")?;
+
+        let num_unpaid_cases = sqlx::query!(
+            "
+select
+ COUNT(cases.id) as num_unpaid_cases
+from
+ cases
+WHERE
+ cases.buyer_user_id = ?
+AND
+ NOT cases.paid
+;",
+            case.buyer_user_id,
+        )
+        .fetch_one(&mut *tx)
+        .map_ok(|r| r.num_unpaid_cases as u32)
+        .await
+        .map_err(|_| "failed to get count of unpaid cases for buyer.")?;
+
+        if num_unpaid_cases > max_unpaid_cases {
+            return Err(format!(
+                "more than {:?} unpaid cases not allowed.",
+                max_unpaid_cases,
+            ));
+        }
+
+        let case_id = insert_result.last_insert_rowid() as i32;
+        if let Some(milestone_id) = maybe_milestone_id {
+            BountyMilestone::add_case_for_connection(&mut *tx, milestone_id, case_id)
+                .await
+                .map_err(|_| "failed to add case to milestone.")?;
+        }
+        for member in team_members {
+            let member = CaseTeamMember { case_id, ..member };
+            CaseTeamMember::insert_for_connection(&mut *tx, member)
+                .await
+                .map_err(|_| "failed to add co-author to case.")?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(case_id)
+    }
+
+    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<Case, sqlx::Error> {
+        let case = sqlx::query!("select * from cases WHERE id = ?;", id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| Case {
+                id: Some(r.id.try_into().unwrap()),
+                public_id: r.public_id,
+                quantity: r.quantity.try_into().unwrap(),
+                buyer_user_id: r.buyer_user_id.try_into().unwrap(),
+                seller_user_id: r.seller_user_id.try_into().unwrap(),
+                bounty_id: r.bounty_id.try_into().unwrap(),
+                case_details: markdown::to_html(&r.case_details),
+                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+                seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
+                paid: r.paid,
+                awarded: r.awarded,
+                canceled_by_seller: r.canceled_by_seller,
+                canceled_by_buyer: r.canceled_by_buyer,
+                invoice_hash: r.invoice_hash,
+                invoice_payment_request: r.invoice_payment_request,
+                created_time_ms: r.created_time_ms.try_into().unwrap(),
+                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+            })
+            .await?;
+
+        Ok(case)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<Case, sqlx::Error> {
+        let case = sqlx::query!("select * from cases WHERE public_id = ?;", public_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| Case {
+                id: Some(r.id.try_into().unwrap()),
+                public_id: r.public_id,
+                quantity: r.quantity.try_into().unwrap(),
+                buyer_user_id: r.buyer_user_id.try_into().unwrap(),
+                seller_user_id: r.seller_user_id.try_into().unwrap(),
+                bounty_id: r.bounty_id.try_into().unwrap(),
+                case_details: markdown::to_html(&r.case_details),
+                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+                seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
+                paid: r.paid,
+                awarded: r.awarded,
+                canceled_by_seller: r.canceled_by_seller,
+                canceled_by_buyer: r.canceled_by_buyer,
+                invoice_hash: r.invoice_hash,
+                invoice_payment_request: r.invoice_payment_request,
+                created_time_ms: r.created_time_ms.try_into().unwrap(),
+                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+            })
+            .await?;
+
+        Ok(case)
+    }
+
+    pub async fn single_by_invoice_hash(
+        db: &mut PoolConnection<Sqlite>,
+        invoice_hash: &str,
+    ) -> Result<Case, sqlx::Error> {
+        let case = sqlx::query!("select * from cases WHERE invoice_hash = ?;", invoice_hash)
+            .fetch_one(&mut **db)
+            .map_ok(|r| Case {
+                id: Some(r.id.try_into().unwrap()),
+                public_id: r.public_id,
+                quantity: r.quantity.try_into().unwrap(),
+                buyer_user_id: r.buyer_user_id.try_into().unwrap(),
+                seller_user_id: r.seller_user_id.try_into().unwrap(),
+                bounty_id: r.bounty_id.try_into().unwrap(),
+                case_details: markdown::to_html(&r.case_details),
+                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+                seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
+                paid: r.paid,
+                awarded: r.awarded,
+                canceled_by_seller: r.canceled_by_seller,
+                canceled_by_buyer: r.canceled_by_buyer,
+                invoice_hash: r.invoice_hash,
+                invoice_payment_request: r.invoice_payment_request,
+                created_time_ms: r.created_time_ms.try_into().unwrap(),
+                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+            })
+            .await?;
+
+        Ok(case)
+    }
+
+    pub async fn all_older_than(
+        db: &mut PoolConnection<Sqlite>,
+        created_time_ms: u64,
+    ) -> Result<Vec<Case>, sqlx::Error> {
+        let created_time_ms_i64: i64 = created_time_ms.try_into().unwrap();
+
+        let cases = sqlx::query!(
+            "
+select *
+from
+ cases
+WHERE
+ created_time_ms < ?
+AND
+ NOT paid
+;",
+            created_time_ms_i64,
+        )
+        .fetch(&mut **db)
+        .map_ok(|r| Case {
+            id: Some(r.id.try_into().unwrap()),
+            public_id: r.public_id,
+            quantity: r.quantity.try_into().unwrap(),
+            buyer_user_id: r.buyer_user_id.try_into().unwrap(),
+            seller_user_id: r.seller_user_id.try_into().unwrap(),
+            bounty_id: r.bounty_id.try_into().unwrap(),
+            case_details: markdown::to_html(&r.case_details),
+            amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+            seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
+            paid: r.paid,
+            awarded: r.awarded,
+            canceled_by_seller: r.canceled_by_seller,
+            canceled_by_buyer: r.canceled_by_buyer,
+            invoice_hash: r.invoice_hash,
+            invoice_payment_request: r.invoice_payment_request,
+            created_time_ms: r.created_time_ms.try_into().unwrap(),
+            payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(cases)
+    }
+
+    pub async fn all_pending_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Vec<Case>, sqlx::Error> {
+        let cases = sqlx::query(
+            "
+select *
+from
+ cases
+WHERE
+ cases.bounty_id = ?
+AND
+ cases.paid
+AND
+ not (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+ORDER BY cases.payment_time_ms ASC
+;",
+        )
+        .bind(bounty_id)
+        .fetch(&mut **db)
+        .map_ok(|r| Case::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(cases)
+    }
+
+    pub async fn all_pending_for_milestone(
+        db: &mut Connection<Db>,
+        milestone_id: i32,
+    ) -> Result<Vec<Case>, sqlx::Error> {
+        let cases = sqlx::query(
+            "
+select cases.*
+from
+ cases
+JOIN
+ casemilestones
+ON
+ cases.id = casemilestones.case_id
+WHERE
+ casemilestones.milestone_id = ?
+AND
+ cases.paid
+AND
+ not (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+ORDER BY cases.payment_time_ms ASC
+;",
+        )
+        .bind(milestone_id)
+        .fetch(&mut **db)
+        .map_ok(|r| Case::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(cases)
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> Case {
+        Case {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            quantity: r.try_get("quantity").unwrap(),
+            buyer_user_id: r.try_get("buyer_user_id").unwrap(),
+            seller_user_id: r.try_get("seller_user_id").unwrap(),
+            bounty_id: r.try_get("bounty_id").unwrap(),
+            case_details: r.try_get("case_details").unwrap(),
+            amount_owed_sat: {
+                let amount_sat_i64: i64 = r.try_get("amount_owed_sat").unwrap();
+                amount_sat_i64 as u64
+            },
+            seller_credit_sat: {
+                let amount_sat_i64: i64 = r.try_get("seller_credit_sat").unwrap();
+                amount_sat_i64 as u64
+            },
+            paid: r.try_get("paid").unwrap(),
+            awarded: r.try_get("awarded").unwrap(),
+            canceled_by_seller: r.try_get("canceled_by_seller").unwrap(),
+            canceled_by_buyer: r.try_get("canceled_by_buyer").unwrap(),
+            invoice_hash: r.try_get("invoice_hash").unwrap(),
+            invoice_payment_request: r.try_get("invoice_payment_request").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            payment_time_ms: {
+                let time_ms_i64: i64 = r.try_get("payment_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+
+    pub async fn mark_as_paid(
+        db: &mut PoolConnection<Sqlite>,
+        case_id: i32,
+        time_now_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let time_now_ms_i64: i64 = time_now_ms.try_into().unwrap();
+
+        sqlx::query!(
+            "UPDATE cases SET paid = true, payment_time_ms = ? WHERE id = ?",
+            time_now_ms_i64,
+            case_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        let case = Case::single_for_connection(&mut **db, case_id).await?;
+        Notification::insert_for_case(
+            &mut **db,
+            case_id,
+            case.buyer_user_id,
+            "case_paid",
+            "Case paid",
+        )
+        .await?;
+        Notification::insert_for_case(
+            &mut **db,
+            case_id,
+            case.seller_user_id,
+            "case_received",
+            "Case received",
+        )
+        .await?;
+        WebhookDelivery::enqueue_for_case(&mut **db, case_id, "case_paid", case.amount_owed_sat)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn single_for_connection(
+        conn: &mut SqliteConnection,
+        case_id: i32,
+    ) -> Result<Case, sqlx::Error> {
+        let case = sqlx::query("select * from cases WHERE id = ?;")
+            .bind(case_id)
+            .fetch_one(conn)
+            .map_ok(|r| Case::from_row(&r))
+            .await?;
+
+        Ok(case)
+    }
+
+    pub async fn mark_as_canceled_by_seller_for_connection(
+        conn: &mut SqliteConnection,
+        case_id: i32,
+        reason: &str,
+    ) -> Result<(), sqlx::Error> {
+        let update_result = sqlx::query!(
+            "
+UPDATE
+ cases
+SET
+ canceled_by_seller = true, case_details= ''
+WHERE
+ id = ?
+AND
+ not (awarded OR canceled_by_seller OR canceled_by_buyer)
+;",
+            case_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+        if update_result.rows_affected() == 1 {
+            let case = Case::single_for_connection(&mut *conn, case_id).await?;
+            Notification::insert_for_case(
+                &mut *conn,
+                case_id,
+                case.buyer_user_id,
+                "case_canceled",
+                "Case canceled by poster",
+            )
+            .await?;
+            CaseAuditEvent::insert_for_connection(
+                &mut *conn,
+                case_id,
+                Some(case.seller_user_id),
+                "canceled_by_poster",
+                reason,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn mark_as_canceled_by_buyer(
+        db: &mut PoolConnection<Sqlite>,
+        case_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        let update_result = sqlx::query!(
+            "
+UPDATE
+ cases
+SET
+ canceled_by_buyer = true, case_details= ''
+WHERE
+ id = ?
+AND
+ not (awarded OR canceled_by_seller OR canceled_by_buyer)
+;",
+            case_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        if update_result.rows_affected() == 1 {
+            let case = Case::single_for_connection(&mut **db, case_id).await?;
+            Notification::insert_for_case(
+                &mut **db,
+                case_id,
+                case.seller_user_id,
+                "case_canceled",
+                "Case canceled by submitter",
+            )
+            .await?;
+            CaseAuditEvent::insert_for_connection(
+                &mut **db,
+                case_id,
+                Some(case.buyer_user_id),
+                "canceled_by_submitter",
+                "",
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_expired_case(
+        db: &mut PoolConnection<Sqlite>,
+        case_id: i32,
+        cancel_case_invoice_future: impl Future<
+            Output = Result<tonic_openssl_lnd::invoicesrpc::CancelInvoiceResp, String>,
+        >,
+    ) -> Result<(), String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        sqlx::query!(
+            "
+DELETE FROM cases
+WHERE
+ id = ?
+AND
+ NOT paid
+;",
+            case_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to delete case from database.")?;
+
+        cancel_case_invoice_future
+            .await
+            .map_err(|e| format!("failed to cancel case invoice: {:?}", e))?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        Ok(())
+    }
+
+    pub async fn seller_info_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<SellerInfo, sqlx::Error> {
+        let total_amount_sold_sat = sqlx::query(
+            "
+            select
+             SUM(cases.amount_owed_sat) as total_amount_sold_sat
+            FROM
+             cases
+            WHERE
+             cases.awarded
+            AND
+             cases.seller_user_id = ?
+            GROUP BY
+             cases.seller_user_id
+            ;",
+        )
+        .bind(user_id)
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| {
+            maybe_r.map(|r| {
+                let amount_sat_i64: i64 = r.try_get("total_amount_sold_sat").unwrap();
+                let amount_sat_u64: u64 = amount_sat_i64.try_into().unwrap();
+                amount_sat_u64
+            })
+        })
+        .await?;
+
+        let seller_info = SellerInfo {
+            username: "".to_string(),
+            total_amount_sold_sat: total_amount_sold_sat.unwrap_or(0),
+        };
+
+        // TODO: remove option from return type.
+        Ok(seller_info)
+    }
+
+    pub async fn seller_info_for_all_users(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<SellerInfo>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let seller_infos = sqlx::query(
+                    "
+        SELECT total_amount_sold_sat, users.email
+        FROM
+         users
+        LEFT JOIN
+            (select
+             SUM(cases.amount_owed_sat) as total_amount_sold_sat, cases.seller_user_id as awarded_seller_user_id
+            FROM
+             cases
+            WHERE
+             awarded 
+            GROUP BY
+             cases.seller_user_id) as seller_infos
+        ON
+         users.id = seller_infos.awarded_seller_user_id
+        WHERE
+         total_amount_sold_sat > 0
+        ORDER BY
+         total_amount_sold_sat DESC
+        LIMIT ?
+        OFFSET ?
+            ;")
+            .bind(limit)
+            .bind(offset)
+            .fetch(&mut **db)
+            .map_ok(|r| {
+                SellerInfo {
+                    username: r.try_get("email").unwrap(),
+                    total_amount_sold_sat: {
+                        let amount_sat_i64: i64 = r.try_get("total_amount_sold_sat").unwrap();
+                        let amount_sat_u64: u64 = amount_sat_i64.try_into().unwrap();
+                        amount_sat_u64
+                    },
+                }})
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(seller_infos)
+    }
+
+    // TODO: implement this.
+    pub async fn most_recent_paid_case(
+        db: &mut PoolConnection<Sqlite>,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let latest_paid_case_invoice_hash = sqlx::query!(
+            "
+SELECT
+ invoice_hash
+FROM
+(SELECT invoice_hash, payment_time_ms FROM useraccounts
+ UNION ALL
+SELECT invoice_hash, payment_time_ms FROM cases)
+WHERE
+ payment_time_ms = (SELECT MAX(payment_time_ms) FROM
+(SELECT invoice_hash, payment_time_ms FROM useraccounts
+ UNION ALL
+SELECT invoice_hash, payment_time_ms FROM cases))
+LIMIT 1
+;"
+        )
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| maybe_r.map(|r| r.invoice_hash))
+        .await?;
+
+        Ok(latest_paid_case_invoice_hash)
+    }
+
+    pub async fn num_processing_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<u32, sqlx::Error> {
+        let num_cases = sqlx::query!(
+            "
+select
+ COUNT(cases.id) as num_processing_cases
+from
+ cases
+WHERE
+ cases.paid
+AND
+ not (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+AND
+ cases.seller_user_id = ?
+;",
+            user_id,
+        )
+        .fetch_one(&mut **db)
+        .map_ok(|r| r.num_processing_cases as u32)
+        .await?;
+
+        Ok(num_cases)
+    }
+}
+
+impl BountyMilestone {
+    pub async fn insert_for_connection(
+        conn: &mut SqliteConnection,
+        milestone: BountyMilestone,
+    ) -> Result<i32, sqlx::Error> {
+        let reward_sat: i64 = milestone.reward_sat.try_into().unwrap();
+        let created_time_ms: i64 = milestone.created_time_ms.try_into().unwrap();
+        let insert_result = sqlx::query(
+            "INSERT INTO bountymilestones (public_id, bounty_id, position, description, reward_sat, awarded, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(milestone.public_id)
+        .bind(milestone.bounty_id)
+        .bind(milestone.position)
+        .bind(milestone.description)
+        .bind(reward_sat)
+        .bind(milestone.awarded)
+        .bind(created_time_ms)
+        .execute(conn)
+        .await?;
+
+        Ok(insert_result.last_insert_rowid() as _)
+    }
+
+    pub async fn all_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Vec<BountyMilestone>, sqlx::Error> {
+        let milestones = sqlx::query(
+            "
+select *
+from
+ bountymilestones
+WHERE
+ bountymilestones.bounty_id = ?
+ORDER BY bountymilestones.position ASC
+;",
+        )
+        .bind(bounty_id)
+        .fetch(&mut **db)
+        .map_ok(|r| BountyMilestone::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(milestones)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<BountyMilestone, sqlx::Error> {
+        let milestone = sqlx::query("select * from bountymilestones WHERE public_id = ?;")
+            .bind(public_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| BountyMilestone::from_row(&r))
+            .await?;
+
+        Ok(milestone)
+    }
+
+    pub async fn single_for_case(
+        db: &mut Connection<Db>,
+        case_id: i32,
+    ) -> Result<Option<BountyMilestone>, sqlx::Error> {
+        let maybe_milestone = sqlx::query(
+            "
+select bountymilestones.*
+from
+ casemilestones
+JOIN
+ bountymilestones
+ON
+ casemilestones.milestone_id = bountymilestones.id
+WHERE
+ casemilestones.case_id = ?
+;",
+        )
+        .bind(case_id)
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| maybe_r.map(|r| BountyMilestone::from_row(&r)))
+        .await?;
+
+        Ok(maybe_milestone)
+    }
+
+    pub async fn add_case_for_connection(
+        conn: &mut SqliteConnection,
+        milestone_id: i32,
+        case_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO casemilestones (case_id, milestone_id) VALUES (?, ?)")
+            .bind(case_id)
+            .bind(milestone_id)
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> BountyMilestone {
+        BountyMilestone {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            bounty_id: r.try_get("bounty_id").unwrap(),
+            position: r.try_get("position").unwrap(),
+            description: r.try_get("description").unwrap(),
+            reward_sat: {
+                let reward_sat_i64: i64 = r.try_get("reward_sat").unwrap();
+                reward_sat_i64 as u64
+            },
+            awarded: r.try_get("awarded").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl BountyClaimSettings {
+    pub async fn single_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Option<BountyClaimSettings>, sqlx::Error> {
+        let maybe_settings = sqlx::query("select * from bountyclaimsettings WHERE bounty_id = ?;")
+            .bind(bounty_id)
+            .fetch_optional(&mut **db)
+            .map_ok(|maybe_r| {
+                maybe_r.map(|r| BountyClaimSettings {
+                    bounty_id: r.try_get("bounty_id").unwrap(),
+                    claim_window_ms: {
+                        let window_ms_i64: i64 = r.try_get("claim_window_ms").unwrap();
+                        window_ms_i64 as u64
+                    },
+                    stake_sat: {
+                        let stake_sat_i64: i64 = r.try_get("stake_sat").unwrap();
+                        stake_sat_i64 as u64
+                    },
+                })
+            })
+            .await?;
+
+        Ok(maybe_settings)
+    }
+
+    pub async fn set_for_bounty(
+        settings: BountyClaimSettings,
+        db: &mut Connection<Db>,
+    ) -> Result<(), sqlx::Error> {
+        let claim_window_ms: i64 = settings.claim_window_ms.try_into().unwrap();
+        let stake_sat: i64 = settings.stake_sat.try_into().unwrap();
+        sqlx::query(
+            "
+INSERT INTO bountyclaimsettings (bounty_id, claim_window_ms, stake_sat)
+VALUES (?, ?, ?)
+ON CONFLICT(bounty_id) DO UPDATE SET claim_window_ms = excluded.claim_window_ms, stake_sat = excluded.stake_sat
+;",
+        )
+        .bind(settings.bounty_id)
+        .bind(claim_window_ms)
+        .bind(stake_sat)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM bountyclaimsettings WHERE bounty_id = ?")
+            .bind(bounty_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl BountyClaim {
+    /// Reserves a bounty for a hunter. The stake is held from their account balance
+    /// until the claim is released, so the balance must cover it.
+    pub async fn insert(claim: BountyClaim, db: &mut Connection<Db>) -> Result<(), String> {
+        BountyClaim::insert_for_connection(claim, &mut **db).await
+    }
+
+    pub async fn insert_for_connection(
+        claim: BountyClaim,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), String> {
+        let stake_sat: i64 = claim.stake_sat.try_into().unwrap();
+        let created_time_ms: i64 = claim.created_time_ms.try_into().unwrap();
+        let expires_time_ms: i64 = claim.expires_time_ms.try_into().unwrap();
+        let mut tx = conn
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        // The unique index on active claims rejects a second claim on the same bounty.
+        sqlx::query(
+            "INSERT INTO bountyclaims (public_id, bounty_id, user_id, stake_sat, released, release_reason, created_time_ms, expires_time_ms, released_time_ms) VALUES (?, ?, ?, ?, false, '', ?, ?, 0)",
+        )
+        .bind(&claim.public_id)
+        .bind(claim.bounty_id)
+        .bind(claim.user_id)
+        .bind(stake_sat)
+        .bind(created_time_ms)
+        .bind(expires_time_ms)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "Bounty is already claimed.")?;
+
+        let account_balance_sat =
+            AccountInfo::total_account_balance_for_connection(&mut *tx, claim.user_id)
+                .await
+                .map_err(|_| "failed to get account balance.")?;
+        if account_balance_sat < 0 {
+            return Err("Insufficient funds for the claim stake.".to_string());
+        }
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
+    }
+
+    /// Gets the claim on a bounty that has not been released, even if it is past its expiry.
+    pub async fn active_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Option<BountyClaim>, sqlx::Error> {
+        let maybe_claim = sqlx::query(
+            "
+select bountyclaims.*, bounties.public_id as bounty_public_id, bounties.title as bounty_title, users.email as username
+from
+ bountyclaims
+LEFT JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+LEFT JOIN
+ users
+ON
+ bountyclaims.user_id = users.id
+WHERE
+ bountyclaims.bounty_id = ?
+AND
+ NOT bountyclaims.released
+;",
+        )
+        .bind(bounty_id)
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| maybe_r.map(|r| BountyClaim::from_row(&r)))
+        .await?;
+
+        Ok(maybe_claim)
+    }
+
+    pub async fn all_expired(
+        conn: &mut SqliteConnection,
+        now: u64,
+    ) -> Result<Vec<BountyClaim>, sqlx::Error> {
+        let now_i64: i64 = now.try_into().unwrap();
+        let claims = sqlx::query(
+            "
+select bountyclaims.*, bounties.public_id as bounty_public_id, bounties.title as bounty_title, users.email as username
+from
+ bountyclaims
+LEFT JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+LEFT JOIN
+ users
+ON
+ bountyclaims.user_id = users.id
+WHERE
+ NOT bountyclaims.released
+AND
+ bountyclaims.expires_time_ms <= ?
+;",
+        )
+        .bind(now_i64)
+        .fetch(&mut *conn)
+        .map_ok(|r| BountyClaim::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(claims)
+    }
+
+    /// Releases a claim and its stake. Returns false if the claim was already released.
+    pub async fn release_for_connection(
+        conn: &mut SqliteConnection,
+        claim_id: i32,
+        release_reason: &str,
+        now: u64,
+    ) -> Result<bool, sqlx::Error> {
+        let now_i64: i64 = now.try_into().unwrap();
+        let update_result = sqlx::query(
+            "UPDATE bountyclaims SET released = true, release_reason = ?, released_time_ms = ? WHERE id = ? AND NOT released",
+        )
+        .bind(release_reason)
+        .bind(now_i64)
+        .bind(claim_id)
+        .execute(conn)
+        .await?;
+
+        Ok(update_result.rows_affected() == 1)
+    }
+
+    /// Releases the claim of a hunter on a bounty, if they hold one.
+    pub async fn release_for_user(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+        user_id: i32,
+        release_reason: &str,
+        now: u64,
+    ) -> Result<bool, sqlx::Error> {
+        let now_i64: i64 = now.try_into().unwrap();
+        let update_result = sqlx::query(
+            "UPDATE bountyclaims SET released = true, release_reason = ?, released_time_ms = ? WHERE bounty_id = ? AND user_id = ? AND NOT released",
+        )
+        .bind(release_reason)
+        .bind(now_i64)
+        .bind(bounty_id)
+        .bind(user_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(update_result.rows_affected() == 1)
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> BountyClaim {
+        BountyClaim {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            bounty_id: r.try_get("bounty_id").unwrap(),
+            bounty_public_id: r.try_get("bounty_public_id").unwrap(),
+            bounty_title: r.try_get("bounty_title").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            username: r.try_get("username").unwrap(),
+            stake_sat: {
+                let stake_sat_i64: i64 = r.try_get("stake_sat").unwrap();
+                stake_sat_i64 as u64
+            },
+            released: r.try_get("released").unwrap(),
+            release_reason: r.try_get("release_reason").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            expires_time_ms: {
+                let time_ms_i64: i64 = r.try_get("expires_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            released_time_ms: {
+                let time_ms_i64: i64 = r.try_get("released_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl BountyRevision {
+    /// Queues a proposed edit of an approved bounty for admin review.
+    ///
+    /// The first edit of a bounty also records the originally approved
+    /// version, so that the revision history starts from it.
+    pub async fn insert_pending(
+        revision: BountyRevision,
+        db: &mut Connection<Db>,
+    ) -> Result<i32, String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        let num_pending_revisions = sqlx::query(
+            "select COUNT(bountyrevisions.id) as num_pending_revisions from bountyrevisions WHERE bountyrevisions.bounty_id = ? AND NOT bountyrevisions.viewed;",
+        )
+        .bind(revision.bounty_id)
+        .fetch_one(&mut *tx)
+        .map_ok(|r| r.try_get::<i64, _>("num_pending_revisions").unwrap())
+        .await
+        .map_err(|_| "failed to get count of pending revisions.")?;
+        if num_pending_revisions > 0 {
+            return Err("An edit of this bounty is already waiting for review.".to_string());
+        }
+
+        let num_revisions = sqlx::query(
+            "select COUNT(bountyrevisions.id) as num_revisions from bountyrevisions WHERE bountyrevisions.bounty_id = ?;",
+        )
+        .bind(revision.bounty_id)
+        .fetch_one(&mut *tx)
+        .map_ok(|r| r.try_get::<i64, _>("num_revisions").unwrap())
+        .await
+        .map_err(|_| "failed to get count of revisions.")?;
+        if num_revisions == 0 {
+            sqlx::query(
+                "
+INSERT INTO bountyrevisions (public_id, bounty_id, title, description, criteria_changed, viewed, approved, created_time_ms, reviewed_time_ms)
+select ?, bounties.id, bounties.title, bounties.description, false, true, true, bounties.created_time_ms, bounties.created_time_ms
+from
+ bounties
+WHERE
+ bounties.id = ?
+;",
+            )
+            .bind(util::create_uuid())
+            .bind(revision.bounty_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to insert original revision.")?;
+        }
+
+        let created_time_ms: i64 = revision.created_time_ms.try_into().unwrap();
+        let reviewed_time_ms: i64 = revision.reviewed_time_ms.try_into().unwrap();
+        let insert_result = sqlx::query(
+            "INSERT INTO bountyrevisions (public_id, bounty_id, title, description, criteria_changed, viewed, approved, created_time_ms, reviewed_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(revision.public_id)
+        .bind(revision.bounty_id)
+        .bind(revision.title)
+        .bind(revision.description)
+        .bind(revision.criteria_changed)
+        .bind(revision.viewed)
+        .bind(revision.approved)
+        .bind(created_time_ms)
+        .bind(reviewed_time_ms)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to insert revision.")?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(insert_result.last_insert_rowid() as _)
+    }
+
+    /// Gets the title and unrendered description of the live version of a bounty.
+    pub async fn live_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<(String, String), sqlx::Error> {
+        let live_version = sqlx::query("select title, description from bounties WHERE id = ?;")
+            .bind(bounty_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| {
+                (
+                    r.try_get("title").unwrap(),
+                    r.try_get("description").unwrap(),
+                )
+            })
+            .await?;
+
+        Ok(live_version)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<BountyRevision, sqlx::Error> {
+        let revision = sqlx::query("select * from bountyrevisions WHERE public_id = ?;")
+            .bind(public_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| BountyRevision::from_row(&r))
+            .await?;
+
+        Ok(revision)
+    }
+
+    pub async fn pending_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Option<BountyRevision>, sqlx::Error> {
+        let maybe_revision = sqlx::query(
+            "select * from bountyrevisions WHERE bounty_id = ? AND NOT viewed;",
+        )
+        .bind(bounty_id)
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| maybe_r.map(|r| BountyRevision::from_row(&r)))
+        .await?;
+
+        Ok(maybe_revision)
+    }
+
+    pub async fn all_approved_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Vec<BountyRevision>, sqlx::Error> {
+        let revisions = sqlx::query(
+            "
+select *
+from
+ bountyrevisions
+WHERE
+ bountyrevisions.bounty_id = ?
+AND
+ bountyrevisions.approved
+ORDER BY bountyrevisions.reviewed_time_ms ASC, bountyrevisions.id ASC
+;",
+        )
+        .bind(bounty_id)
+        .fetch(&mut **db)
+        .map_ok(|r| BountyRevision::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(revisions)
+    }
+
+    /// Time of the most recent approved edit that changed the acceptance criteria.
+    pub async fn last_criteria_change_time_ms(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Option<u64>, sqlx::Error> {
+        let maybe_time_ms = sqlx::query(
+            "
+select MAX(bountyrevisions.reviewed_time_ms) as last_change_time_ms
+from
+ bountyrevisions
+WHERE
+ bountyrevisions.bounty_id = ?
+AND
+ bountyrevisions.approved
+AND
+ bountyrevisions.criteria_changed
+;",
+        )
+        .bind(bounty_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let time_ms_i64: Option<i64> = r.try_get("last_change_time_ms").unwrap();
+            time_ms_i64.map(|t| t as u64)
+        })
+        .await?;
+
+        Ok(maybe_time_ms)
+    }
+
+    /// Approves a pending revision and makes it the live version of the bounty.
+    pub async fn mark_as_approved(
+        db: &mut Connection<Db>,
+        revision_id: i32,
+        reviewed_time_ms: u64,
+    ) -> Result<(), String> {
+        let reviewed_time_ms: i64 = reviewed_time_ms.try_into().unwrap();
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        let update_result = sqlx::query(
+            "UPDATE bountyrevisions SET viewed = true, approved = true, reviewed_time_ms = ? WHERE id = ? AND NOT viewed",
+        )
+        .bind(reviewed_time_ms)
+        .bind(revision_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to mark revision as approved.")?;
+        if update_result.rows_affected() != 1 {
+            return Err("Revision has already been reviewed.".to_string());
+        }
+
+        sqlx::query(
+            "
+UPDATE bounties
+SET
+ title = (select bountyrevisions.title from bountyrevisions WHERE bountyrevisions.id = ?),
+ description = (select bountyrevisions.description from bountyrevisions WHERE bountyrevisions.id = ?)
+WHERE
+ bounties.id = (select bountyrevisions.bounty_id from bountyrevisions WHERE bountyrevisions.id = ?)
+;",
+        )
+        .bind(revision_id)
+        .bind(revision_id)
+        .bind(revision_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to update bounty.")?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
+    }
+
+    pub async fn mark_as_rejected(
+        db: &mut Connection<Db>,
+        revision_id: i32,
+        reviewed_time_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let reviewed_time_ms: i64 = reviewed_time_ms.try_into().unwrap();
+        sqlx::query(
+            "UPDATE bountyrevisions SET viewed = true, approved = false, reviewed_time_ms = ? WHERE id = ? AND NOT viewed",
+        )
+        .bind(reviewed_time_ms)
+        .bind(revision_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> BountyRevision {
+        BountyRevision {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            bounty_id: r.try_get("bounty_id").unwrap(),
+            title: r.try_get("title").unwrap(),
+            description: r.try_get("description").unwrap(),
+            criteria_changed: r.try_get("criteria_changed").unwrap(),
+            viewed: r.try_get("viewed").unwrap(),
+            approved: r.try_get("approved").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            reviewed_time_ms: {
+                let time_ms_i64: i64 = r.try_get("reviewed_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl BountyRevisionCard {
+    pub async fn all_pending(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<BountyRevisionCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let revision_cards = sqlx::query(
+            "
+select bountyrevisions.*, bounties.public_id as bounty_public_id, bounties.title as live_title, users.email as poster_username
+from
+ bountyrevisions
+JOIN
+ bounties
+ON
+ bountyrevisions.bounty_id = bounties.id
+LEFT JOIN
+ users
+ON
+ bounties.user_id = users.id
+WHERE
+ NOT bountyrevisions.viewed
+ORDER BY bountyrevisions.created_time_ms ASC
+LIMIT ?
+OFFSET ?
+;",
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| BountyRevisionCard {
+            revision: BountyRevision::from_row(&r),
+            bounty_public_id: r.try_get("bounty_public_id").unwrap(),
+            live_title: r.try_get("live_title").unwrap(),
+            poster_username: r.try_get("poster_username").unwrap(),
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(revision_cards)
+    }
+}
+
+impl BountyComment {
+    pub async fn insert(
+        comment: BountyComment,
+        db: &mut Connection<Db>,
+    ) -> Result<i32, sqlx::Error> {
+        let created_time_ms: i64 = comment.created_time_ms.try_into().unwrap();
+        let edited_time_ms: i64 = comment.edited_time_ms.try_into().unwrap();
+        let insert_result = sqlx::query(
+            "INSERT INTO bountycomments (public_id, bounty_id, user_id, parent_id, body, hidden_by_admin, created_time_ms, edited_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(comment.public_id)
+        .bind(comment.bounty_id)
+        .bind(comment.user_id)
+        .bind(comment.parent_id)
+        .bind(comment.body)
+        .bind(comment.hidden_by_admin)
+        .bind(created_time_ms)
+        .bind(edited_time_ms)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(insert_result.last_insert_rowid() as _)
+    }
+
+    pub async fn all_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
+    ) -> Result<Vec<BountyComment>, sqlx::Error> {
+        let comments = sqlx::query(
+            "
+select bountycomments.*, users.email as username
+from
+ bountycomments
+LEFT JOIN
+ users
+ON
+ bountycomments.user_id = users.id
+WHERE
+ bountycomments.bounty_id = ?
+ORDER BY bountycomments.created_time_ms ASC, bountycomments.id ASC
+;",
+        )
+        .bind(bounty_id)
+        .fetch(&mut **db)
+        .map_ok(|r| BountyComment::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(comments)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<BountyComment, sqlx::Error> {
+        let comment = sqlx::query(
+            "
+select bountycomments.*, users.email as username
+from
+ bountycomments
+LEFT JOIN
+ users
+ON
+ bountycomments.user_id = users.id
+WHERE
+ bountycomments.public_id = ?
+;",
+        )
+        .bind(public_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| BountyComment::from_row(&r))
+        .await?;
+
+        Ok(comment)
+    }
+
+    pub async fn update_body(
+        db: &mut Connection<Db>,
+        comment_id: i32,
+        body: &str,
+        edited_time_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let edited_time_ms: i64 = edited_time_ms.try_into().unwrap();
+        sqlx::query("UPDATE bountycomments SET body = ?, edited_time_ms = ? WHERE id = ?")
+            .bind(body)
+            .bind(edited_time_ms)
+            .bind(comment_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_hidden_by_admin(
+        db: &mut Connection<Db>,
+        comment_id: i32,
+        hidden: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE bountycomments SET hidden_by_admin = ? WHERE id = ?")
+            .bind(hidden)
+            .bind(comment_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> BountyComment {
+        let body: String = r.try_get("body").unwrap();
+        BountyComment {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            bounty_id: r.try_get("bounty_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            username: r.try_get("username").unwrap(),
+            parent_id: r.try_get("parent_id").unwrap(),
+            body_html: markdown::to_html(&body),
+            body,
+            hidden_by_admin: r.try_get("hidden_by_admin").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            edited_time_ms: {
+                let time_ms_i64: i64 = r.try_get("edited_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl CaseTeamMember {
+    pub async fn insert_for_connection(
+        conn: &mut SqliteConnection,
+        member: CaseTeamMember,
+    ) -> Result<i32, sqlx::Error> {
+        let created_time_ms: i64 = member.created_time_ms.try_into().unwrap();
+        let insert_result = sqlx::query(
+            "INSERT INTO caseteammembers (public_id, case_id, user_id, share_basis_points, accepted, created_time_ms) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(member.public_id)
+        .bind(member.case_id)
+        .bind(member.user_id)
+        .bind(member.share_basis_points)
+        .bind(member.accepted)
+        .bind(created_time_ms)
+        .execute(conn)
+        .await?;
+
+        Ok(insert_result.last_insert_rowid() as _)
+    }
+
+    pub async fn all_for_case(
+        db: &mut Connection<Db>,
+        case_id: i32,
+    ) -> Result<Vec<CaseTeamMember>, sqlx::Error> {
+        let members = sqlx::query(
+            "
+select caseteammembers.*, users.email as username
+from
+ caseteammembers
+LEFT JOIN
+ users
+ON
+ caseteammembers.user_id = users.id
+WHERE
+ caseteammembers.case_id = ?
+ORDER BY caseteammembers.id ASC
+;",
+        )
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseTeamMember::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        let num_unapproved_bounties = sqlx::query!(
+        Ok(members)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<CaseTeamMember, sqlx::Error> {
+        let member = sqlx::query(
             "
-select
- COUNT(bounties.id) as num_unapproved_bounties
+select caseteammembers.*, users.email as username
+from
+ caseteammembers
+LEFT JOIN
+ users
+ON
+ caseteammembers.user_id = users.id
+WHERE
+ caseteammembers.public_id = ?
+;",
+        )
+        .bind(public_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| CaseTeamMember::from_row(&r))
+        .await?;
+
+        Ok(member)
+    }
+
+    pub async fn mark_as_accepted(
+        db: &mut Connection<Db>,
+        member_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE caseteammembers SET accepted = true WHERE id = ?")
+            .bind(member_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(db: &mut Connection<Db>, member_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM caseteammembers WHERE id = ?")
+            .bind(member_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> CaseTeamMember {
+        CaseTeamMember {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            username: r.try_get("username").unwrap(),
+            share_basis_points: r.try_get("share_basis_points").unwrap(),
+            accepted: r.try_get("accepted").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl TeamCaseCard {
+    pub async fn all_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        accepted: bool,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<TeamCaseCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let team_case_cards = sqlx::query(
+            "
+select caseteammembers.*, users.email as username, cases.public_id as case_public_id, cases.awarded as case_awarded, bounties.public_id as bounty_public_id, bounties.title as bounty_title, submitters.email as submitter_username
 from
+ caseteammembers
+JOIN
+ cases
+ON
+ caseteammembers.case_id = cases.id
+LEFT JOIN
  bounties
+ON
+ cases.bounty_id = bounties.id
+LEFT JOIN
+ users
+ON
+ caseteammembers.user_id = users.id
+LEFT JOIN
+ users as submitters
+ON
+ cases.buyer_user_id = submitters.id
 WHERE
- bounties.user_id = ?
+ caseteammembers.user_id = ?
 AND
- NOT bounties.approved
+ caseteammembers.accepted = ?
+ORDER BY caseteammembers.created_time_ms DESC
+LIMIT ?
+OFFSET ?
 ;",
-            bounty.user_id,
         )
-        .fetch_one(&mut *tx)
-        .map_ok(|r| r.num_unapproved_bounties as u32)
-        .await
-        .map_err(|_| "failed to get count of unapproved bounties.")?;
+        .bind(user_id)
+        .bind(accepted)
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| TeamCaseCard {
+            member: CaseTeamMember::from_row(&r),
+            case_public_id: r.try_get("case_public_id").unwrap(),
+            case_awarded: r.try_get("case_awarded").unwrap(),
+            bounty_public_id: r.try_get("bounty_public_id").unwrap(),
+            bounty_title: r.try_get("bounty_title").unwrap(),
+            submitter_username: r.try_get("submitter_username").unwrap(),
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        if num_unapproved_bounties > max_unapproved_bounties {
-            return Err(format!(
-                "more than {:?} unapproved bounties not allowed.",
-                max_unapproved_bounties
-            ));
-        }
+        Ok(team_case_cards)
+    }
+}
 
-        tx.commit()
-            .await
-            .map_err(|_| "failed to commit transaction.")?;
+impl CaseMessage {
+    pub async fn insert(
+        message: CaseMessage,
+        db: &mut Connection<Db>,
+    ) -> Result<i32, sqlx::Error> {
+        let created_time_ms: i64 = message.created_time_ms.try_into().unwrap();
+        let insert_result = sqlx::query(
+            "INSERT INTO casemessages (public_id, case_id, sender_user_id, recipient_user_id, body, encrypted, read, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(message.public_id)
+        .bind(message.case_id)
+        .bind(message.sender_user_id)
+        .bind(message.recipient_user_id)
+        .bind(message.body)
+        .bind(message.encrypted)
+        .bind(message.read)
+        .bind(created_time_ms)
+        .execute(&mut **db)
+        .await?;
 
         Ok(insert_result.last_insert_rowid() as _)
     }
 
-    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<Bounty, sqlx::Error> {
-        let bounty = sqlx::query!("select * from bounties WHERE id = ?;", id)
-            .fetch_one(&mut **db)
-            .map_ok(|r| Bounty {
-                id: Some(r.id.try_into().unwrap()),
-                public_id: r.public_id,
-                user_id: r.user_id.try_into().unwrap(),
-                title: r.title,
-                description: markdown::to_html(&r.description),
-                price_sat: r.price_sat.try_into().unwrap(),
-                fee_rate_basis_points: r.fee_rate_basis_points.try_into().unwrap(),
-                submitted: r.submitted,
-                viewed: r.viewed,
-		        approved: r.approved,
-                deactivated_by_seller: r.deactivated_by_seller,
-                deactivated_by_admin: r.deactivated_by_admin,
-                created_time_ms: r.created_time_ms.try_into().unwrap(),
-            })
-            .await?;
+    pub async fn all_for_case(
+        db: &mut Connection<Db>,
+        case_id: i32,
+    ) -> Result<Vec<CaseMessage>, sqlx::Error> {
+        let messages = sqlx::query(
+            "
+select casemessages.*, users.email as sender_username
+from
+ casemessages
+LEFT JOIN
+ users
+ON
+ casemessages.sender_user_id = users.id
+WHERE
+ casemessages.case_id = ?
+ORDER BY casemessages.created_time_ms ASC, casemessages.id ASC
+;",
+        )
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseMessage::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(bounty)
+        Ok(messages)
     }
 
-    pub async fn single_by_public_id(
+    pub async fn mark_all_as_read(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<Bounty, sqlx::Error> {
-        let bounty = sqlx::query!("select * from bounties WHERE public_id = ?;", public_id)
-            .fetch_one(&mut **db)
-            .map_ok(|r| Bounty {
-                id: Some(r.id.try_into().unwrap()),
-                public_id: r.public_id,
-                user_id: r.user_id.try_into().unwrap(),
-                title: r.title,
-                description: markdown::to_html(&r.description),
-                price_sat: r.price_sat.try_into().unwrap(),
-                fee_rate_basis_points: r.fee_rate_basis_points.try_into().unwrap(),
-                submitted: r.submitted,
-                viewed: r.viewed,
-		        approved: r.approved,
-                deactivated_by_seller: r.deactivated_by_seller,
-                deactivated_by_admin: r.deactivated_by_admin,
-                created_time_ms: r.created_time_ms.try_into().unwrap(),
-            })
-            .await?;
+        case_id: i32,
+        recipient_user_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE casemessages SET read = true WHERE case_id = ? AND recipient_user_id = ? AND NOT read",
+        )
+        .bind(case_id)
+        .bind(recipient_user_id)
+        .execute(&mut **db)
+        .await?;
 
-        Ok(bounty)
+        Ok(())
+    }
+
+    pub async fn num_unread_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<u32, sqlx::Error> {
+        let num_unread_messages = sqlx::query(
+            "select COUNT(casemessages.id) as num_unread_messages from casemessages WHERE casemessages.recipient_user_id = ? AND NOT casemessages.read;",
+        )
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_unread_messages_i64: i64 = r.try_get("num_unread_messages").unwrap();
+            num_unread_messages_i64 as u32
+        })
+        .await?;
+
+        Ok(num_unread_messages)
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> CaseMessage {
+        CaseMessage {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            sender_user_id: r.try_get("sender_user_id").unwrap(),
+            sender_username: r.try_get("sender_username").unwrap(),
+            recipient_user_id: r.try_get("recipient_user_id").unwrap(),
+            body: r.try_get("body").unwrap(),
+            encrypted: r.try_get("encrypted").unwrap(),
+            read: r.try_get("read").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl CaseMessageCard {
+    pub async fn all_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<CaseMessageCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let case_message_cards = sqlx::query(
+            "
+select
+ cases.public_id as case_public_id,
+ bounties.title as bounty_title,
+ COUNT(casemessages.id) as num_messages,
+ SUM(casemessages.recipient_user_id = ? AND NOT casemessages.read) as num_unread_messages,
+ MAX(casemessages.created_time_ms) as last_message_time_ms
+from
+ casemessages
+JOIN
+ cases
+ON
+ casemessages.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+WHERE
+ casemessages.sender_user_id = ?
+OR
+ casemessages.recipient_user_id = ?
+GROUP BY cases.id
+ORDER BY last_message_time_ms DESC
+LIMIT ?
+OFFSET ?
+;",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseMessageCard {
+            case_public_id: r.try_get("case_public_id").unwrap(),
+            bounty_title: r.try_get("bounty_title").unwrap(),
+            num_messages: {
+                let num_messages_i64: i64 = r.try_get("num_messages").unwrap();
+                num_messages_i64 as u32
+            },
+            num_unread_messages: {
+                let num_unread_messages_i64: i64 = r.try_get("num_unread_messages").unwrap();
+                num_unread_messages_i64 as u32
+            },
+            last_message_time_ms: {
+                let time_ms_i64: i64 = r.try_get("last_message_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(case_message_cards)
+    }
+}
+
+impl CaseAward {
+    /// Inserts the award of a single winner of a case.
+    pub async fn insert_for_connection(
+        conn: &mut SqliteConnection,
+        case_award: CaseAward,
+    ) -> Result<(), sqlx::Error> {
+        let amount_sat: i64 = case_award.amount_sat.try_into().unwrap();
+        let fee_sat: i64 = case_award.fee_sat.try_into().unwrap();
+        let credit_sat: i64 = case_award.credit_sat.try_into().unwrap();
+        let created_time_ms: i64 = case_award.created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO caseawards (public_id, case_id, bounty_id, poster_user_id, winner_user_id, milestone_id, amount_sat, fee_sat, credit_sat, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(case_award.public_id)
+        .bind(case_award.case_id)
+        .bind(case_award.bounty_id)
+        .bind(case_award.poster_user_id)
+        .bind(case_award.winner_user_id)
+        .bind(case_award.milestone_id)
+        .bind(amount_sat)
+        .bind(fee_sat)
+        .bind(credit_sat)
+        .bind(created_time_ms)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Awards several cases of the same bounty (or bounty milestone) at once.
+    /// The reward is paid from the poster's account balance, so the whole
+    /// award is rolled back if the poster cannot cover it.
+    pub async fn do_split_award(
+        db: &mut Connection<Db>,
+        poster_user_id: i32,
+        maybe_milestone_id: Option<i32>,
+        case_awards: Vec<CaseAward>,
+    ) -> Result<(), String> {
+        CaseAward::do_split_award_for_connection(
+            &mut **db,
+            poster_user_id,
+            maybe_milestone_id,
+            case_awards,
+        )
+        .await
+    }
+
+    pub async fn do_split_award_for_connection(
+        conn: &mut SqliteConnection,
+        poster_user_id: i32,
+        maybe_milestone_id: Option<i32>,
+        case_awards: Vec<CaseAward>,
+    ) -> Result<(), String> {
+        let mut tx = conn
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        let mut case_ids: Vec<i32> = case_awards.iter().map(|a| a.case_id).collect();
+        case_ids.sort_unstable();
+        case_ids.dedup();
+        for case_id in case_ids {
+            let update_result = sqlx::query(
+                "
+UPDATE
+ cases
+SET
+ awarded = true, case_details= ''
+WHERE
+ id = ?
+AND
+ paid
+AND
+ not (awarded OR canceled_by_seller OR canceled_by_buyer)
+AND
+ id NOT IN (SELECT casedisputes.case_id FROM casedisputes WHERE NOT casedisputes.resolved)
+;",
+            )
+            .bind(case_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to mark case as awarded.")?;
+            if update_result.rows_affected() != 1 {
+                return Err("Case is no longer pending, or is disputed.".to_string());
+            }
+            CaseAuditEvent::insert_for_connection(
+                &mut *tx,
+                case_id,
+                Some(poster_user_id),
+                "awarded",
+                "",
+            )
+            .await
+            .map_err(|_| "failed to insert case audit event.")?;
+        }
+
+        CaseAward::insert_all_for_connection(&mut *tx, maybe_milestone_id, &case_awards).await?;
+        for case_award in case_awards {
+            Notification::insert_for_case(
+                &mut *tx,
+                case_award.case_id,
+                case_award.winner_user_id,
+                "case_awarded",
+                &format!("Case awarded {} sats", case_award.credit_sat),
+            )
+            .await
+            .map_err(|_| "failed to insert notification.")?;
+        }
+
+        let poster_balance_sat =
+            AccountInfo::total_account_balance_for_connection(&mut *tx, poster_user_id)
+                .await
+                .map_err(|_| "failed to get account balance.")?;
+        if poster_balance_sat < 0 {
+            return Err("Insufficient funds to pay the reward.".to_string());
+        }
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
     }
 
-    pub async fn mark_as_submitted(
-        db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "UPDATE bounties SET submitted = true WHERE public_id = ?",
-            public_id,
-        )
-        .execute(&mut **db)
-        .await?;
+    /// Inserts the awards for a reward, and marks the reward as paid: the milestone
+    /// if there is one, otherwise the whole bounty. Must run in the same transaction
+    /// as the case updates, so that a reward cannot be paid twice.
+    pub async fn insert_all_for_connection(
+        conn: &mut SqliteConnection,
+        maybe_milestone_id: Option<i32>,
+        case_awards: &[CaseAward],
+    ) -> Result<(), String> {
+        let bounty_id = match case_awards.first() {
+            Some(case_award) => case_award.bounty_id,
+            None => return Ok(()),
+        };
+        match maybe_milestone_id {
+            Some(milestone_id) => {
+                let update_result = sqlx::query(
+                    "UPDATE bountymilestones SET awarded = true WHERE id = ? AND NOT awarded",
+                )
+                .bind(milestone_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| "failed to mark milestone as awarded.")?;
+                if update_result.rows_affected() != 1 {
+                    return Err("Milestone has already been awarded.".to_string());
+                }
+            }
+            None => {
+                let num_case_awards =
+                    CaseAward::num_for_bounty_for_connection(&mut *conn, bounty_id)
+                        .await
+                        .map_err(|_| "failed to get case awards.")?;
+                if num_case_awards > 0 {
+                    return Err("Bounty has already been awarded.".to_string());
+                }
+            }
+        }
+
+        for case_award in case_awards {
+            CaseAward::insert_for_connection(&mut *conn, case_award.clone())
+                .await
+                .map_err(|_| "failed to insert case award.")?;
+            WebhookDelivery::enqueue_for_case(
+                &mut *conn,
+                case_award.case_id,
+                "case_awarded",
+                case_award.credit_sat,
+            )
+            .await
+            .map_err(|_| "failed to queue webhook delivery.")?;
+        }
+
         Ok(())
     }
 
-    pub async fn mark_as_approved(
+    pub async fn all_for_case(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "UPDATE bounties SET viewed = true, approved = true WHERE public_id = ?",
-            public_id,
+        case_id: i32,
+    ) -> Result<Vec<CaseAward>, sqlx::Error> {
+        let case_awards = sqlx::query(
+            "
+select *
+from
+ caseawards
+WHERE
+ caseawards.case_id = ?
+ORDER BY caseawards.created_time_ms ASC
+;",
         )
-        .execute(&mut **db)
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseAward {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            bounty_id: r.try_get("bounty_id").unwrap(),
+            poster_user_id: r.try_get("poster_user_id").unwrap(),
+            winner_user_id: r.try_get("winner_user_id").unwrap(),
+            milestone_id: r.try_get("milestone_id").unwrap(),
+            amount_sat: {
+                let amount_sat_i64: i64 = r.try_get("amount_sat").unwrap();
+                amount_sat_i64 as u64
+            },
+            fee_sat: {
+                let fee_sat_i64: i64 = r.try_get("fee_sat").unwrap();
+                fee_sat_i64 as u64
+            },
+            credit_sat: {
+                let credit_sat_i64: i64 = r.try_get("credit_sat").unwrap();
+                credit_sat_i64 as u64
+            },
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
         .await?;
-        Ok(())
+
+        Ok(case_awards)
     }
 
-    pub async fn mark_as_rejected(
+    pub async fn num_for_bounty(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "UPDATE bounties SET viewed = true, approved = false WHERE public_id = ?",
-            public_id,
+        bounty_id: i32,
+    ) -> Result<u32, sqlx::Error> {
+        CaseAward::num_for_bounty_for_connection(&mut **db, bounty_id).await
+    }
+
+    pub async fn num_for_bounty_for_connection(
+        conn: &mut SqliteConnection,
+        bounty_id: i32,
+    ) -> Result<u32, sqlx::Error> {
+        let num_case_awards = sqlx::query(
+            "
+select
+ COUNT(caseawards.id) as num_case_awards
+from
+ caseawards
+WHERE
+ caseawards.bounty_id = ?
+;",
         )
-        .execute(&mut **db)
+        .bind(bounty_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let num_i64: i64 = r.try_get("num_case_awards").unwrap();
+            num_i64 as u32
+        })
         .await?;
-        Ok(())
+
+        Ok(num_case_awards)
     }
+}
 
-    pub async fn mark_as_deactivated_by_seller(
+impl CaseCard {
+    pub async fn all_unpaid_for_user(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<CaseCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let cases = sqlx::query!(
             "
-UPDATE bounties
-SET deactivated_by_seller = true
+select
+ cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ cases
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+LEFT JOIN
+ users
+ON
+ bounties.user_id = users.id
 WHERE
- public_id = ?
+ not cases.paid
 AND
- approved
-AND NOT (deactivated_by_seller OR deactivated_by_admin)
+ case_buyer_user_id = ?
+GROUP BY
+ cases.id
+ORDER BY cases.created_time_ms DESC
+LIMIT ?
+OFFSET ?
 ;",
-            public_id,
+            user_id,
+            limit,
+            offset,
         )
-        .execute(&mut **db)
-        .await?;
-        Ok(())
+            .fetch(&mut **db)
+            .map_ok(|r| {
+                let o = Case {
+                    id: Some(r.case_id.unwrap().try_into().unwrap()),
+                    public_id: r.case_public_id.unwrap(),
+                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
+                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
+                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
+                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
+                    case_details: r.case_details.unwrap(),
+                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
+                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
+                    paid: r.paid.unwrap(),
+                    awarded: r.awarded.unwrap(),
+                    canceled_by_seller: r.canceled_by_seller.unwrap(),
+                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
+                    invoice_hash: r.invoice_hash.unwrap(),
+                    invoice_payment_request: r.invoice_payment_request.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
+                };
+                let l = r.id.map(|bounty_id| Bounty {
+                    id: Some(bounty_id.try_into().unwrap()),
+                    public_id: r.bounty_public_id.unwrap(),
+                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
+                });
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                CaseCard {
+                    case: o,
+                    bounty: l,
+                    image: i,
+                    user: u,
+                    poster_reputation: None,
+                }
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        CaseCard::with_poster_reputations(db, cases).await
     }
 
-    pub async fn mark_as_deactivated_by_admin(
+    pub async fn all_paid_for_user(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<CaseCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let cases = sqlx::query!(
             "
-UPDATE bounties
-SET deactivated_by_admin = true
+select
+ cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ cases
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+LEFT JOIN
+ users
+ON
+ bounties.user_id = users.id
 WHERE
- public_id = ?
+ cases.paid
 AND
- approved
-AND NOT (deactivated_by_seller OR deactivated_by_admin)
+ case_buyer_user_id = ?
+GROUP BY
+ cases.id
+ORDER BY cases.payment_time_ms DESC
+LIMIT ?
+OFFSET ?
 ;",
-            public_id,
+            user_id,
+            limit,
+            offset,
         )
-        .execute(&mut **db)
-        .await?;
-        Ok(())
+            .fetch(&mut **db)
+            .map_ok(|r| {
+                let o = Case {
+                    id: Some(r.case_id.unwrap().try_into().unwrap()),
+                    public_id: r.case_public_id.unwrap(),
+                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
+                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
+                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
+                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
+                    case_details: r.case_details.unwrap(),
+                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
+                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
+                    paid: r.paid.unwrap(),
+                    awarded: r.awarded.unwrap(),
+                    canceled_by_seller: r.canceled_by_seller.unwrap(),
+                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
+                    invoice_hash: r.invoice_hash.unwrap(),
+                    invoice_payment_request: r.invoice_payment_request.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
+                };
+                let l = r.id.map(|bounty_id| Bounty {
+                    id: Some(bounty_id.try_into().unwrap()),
+                    public_id: r.bounty_public_id.unwrap(),
+                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
+                });
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                CaseCard {
+                    case: o,
+                    bounty: l,
+                    image: i,
+                    user: u,
+                    poster_reputation: None,
+                }
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        CaseCard::with_poster_reputations(db, cases).await
     }
 
-    pub async fn num_pending(db: &mut Connection<Db>) -> Result<u32, sqlx::Error> {
-        let num_bounties = sqlx::query!(
+    pub async fn all_received_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<CaseCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let cases = sqlx::query!(
             "
 select
- COUNT(bounties.id) as num_pending_bounties
+ cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
 from
+ cases
+LEFT JOIN
  bounties
+ON
+ cases.bounty_id = bounties.id
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+LEFT JOIN
+ users
+ON
+ bounties.user_id = users.id
 WHERE
- bounties.submitted
+ cases.awarded
 AND
- NOT bounties.viewed
+ case_seller_user_id = ?
+GROUP BY
+ cases.id
+ORDER BY cases.payment_time_ms DESC
+LIMIT ?
+OFFSET ?
 ;",
+            user_id,
+            limit,
+            offset,
         )
-        .fetch_one(&mut **db)
-        .map_ok(|r| r.num_pending_bounties as u32)
-        .await?;
+            .fetch(&mut **db)
+            .map_ok(|r| {
+                let o = Case {
+                    id: Some(r.case_id.unwrap().try_into().unwrap()),
+                    public_id: r.case_public_id.unwrap(),
+                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
+                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
+                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
+                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
+                    case_details: r.case_details.unwrap(),
+                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
+                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
+                    paid: r.paid.unwrap(),
+                    awarded: r.awarded.unwrap(),
+                    canceled_by_seller: r.canceled_by_seller.unwrap(),
+                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
+                    invoice_hash: r.invoice_hash.unwrap(),
+                    invoice_payment_request: r.invoice_payment_request.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
+                };
+                let l = r.id.map(|bounty_id| Bounty {
+                    id: Some(bounty_id.try_into().unwrap()),
+                    public_id: r.bounty_public_id.unwrap(),
+                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
+                });
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                CaseCard {
+                    case: o,
+                    bounty: l,
+                    image: i,
+                    user: u,
+                    poster_reputation: None,
+                }
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
 
-        Ok(num_bounties)
+        CaseCard::with_poster_reputations(db, cases).await
     }
 
-    pub async fn delete(bounty_id: i32, db: &mut Connection<Db>) -> Result<usize, String> {
-        let mut tx = db
-            .begin()
-            .await
-            .map_err(|_| "failed to begin transaction.")?;
-
-        let delete_result = sqlx::query!(
-            "
-DELETE FROM bounties
-WHERE
- id = ?
-;",
-            bounty_id
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|_| "failed to delete bounty.")?;
-
-        sqlx::query!(
+    pub async fn all_processing_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<CaseCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let cases = sqlx::query!(
             "
-DELETE from bountyimages
+select
+ cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+from
+ cases
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+LEFT JOIN
+ bountyimages
+ON
+ bounties.id = bountyimages.bounty_id
+AND
+ bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+LEFT JOIN
+ users
+ON
+ bounties.user_id = users.id
 WHERE
- bounty_id = ?
+ cases.paid
+AND
+ not (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+AND
+ cases.seller_user_id = ?
+GROUP BY
+ cases.id
+ORDER BY cases.payment_time_ms DESC
+LIMIT ?
+OFFSET ?
 ;",
-            bounty_id
+            user_id,
+            limit,
+            offset,
         )
-        .execute(&mut *tx)
-        .await
-        .map_err(|_| "failed to delete images for bounty.")?;
-
-        tx.commit()
-            .await
-            .map_err(|_| "failed to commit transaction.")?;
+            .fetch(&mut **db)
+            .map_ok(|r| {
 
-        Ok(delete_result.rows_affected() as _)
-        //Ok(())
-    }
-}
+                let o = Case {
+                    id: Some(r.case_id.unwrap().try_into().unwrap()),
+                    public_id: r.case_public_id.unwrap(),
+                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
+                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
+                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
+                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
+                    case_details: r.case_details.unwrap(),
+                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
+                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
+                    paid: r.paid.unwrap(),
+                    awarded: r.awarded.unwrap(),
+                    canceled_by_seller: r.canceled_by_seller.unwrap(),
+                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
+                    invoice_hash: r.invoice_hash.unwrap(),
+                    invoice_payment_request: r.invoice_payment_request.unwrap(),
+                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
+                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
+                };
+                let l = r.id.map(|bounty_id| Bounty {
+                    id: Some(bounty_id.try_into().unwrap()),
+                    public_id: r.bounty_public_id.unwrap(),
+                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
+                    title: r.title.unwrap(),
+                    description: r.description.unwrap(),
+                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
+                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
+                    submitted: r.submitted.unwrap(),
+                    viewed: r.viewed.unwrap(),
+                    approved: r.approved.unwrap(),
+                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
+                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
+                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
+                });
+                let i = r.image_id.map(|image_id| BountyImage {
+                    id: Some(image_id.try_into().unwrap()),
+                    public_id: r.image_public_id.unwrap(),
+                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
+                    image_data: r.image_data.unwrap(),
+                    is_primary: r.is_primary.unwrap(),
+                });
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                CaseCard {
+                    case: o,
+                    bounty: l,
+                    image: i,
+                    user: u,
+                    poster_reputation: None,
+                }
 
-impl BountyImage {
-    /// Returns the number of affected rows: 1.
-    pub async fn insert(
-        bountyimage: BountyImage,
-        db: &mut Connection<Db>,
-    ) -> Result<usize, sqlx::Error> {
-        let insert_result = sqlx::query!(
-            "INSERT INTO bountyimages (public_id, bounty_id, image_data, is_primary) VALUES (?, ?, ?, ?)",
-            bountyimage.public_id,
-            bountyimage.bounty_id,
-            bountyimage.image_data,
-            bountyimage.is_primary,
-        )
-        .execute(&mut **db)
-        .await?;
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
 
-        Ok(insert_result.rows_affected() as _)
+        CaseCard::with_poster_reputations(db, cases).await
     }
 
-    pub async fn all_for_bounty(
+    /// Adds the reputation of the poster of each case.
+    async fn with_poster_reputations(
         db: &mut Connection<Db>,
-        bounty_id: i32,
-    ) -> Result<Vec<BountyImage>, sqlx::Error> {
-        let bounty_images = sqlx::query!(
-            "select * from bountyimages WHERE bounty_id = ? ORDER BY bountyimages.is_primary DESC;",
-            bounty_id
-        )
-        .fetch(&mut **db)
-        .map_ok(|r| BountyImage {
-            id: r.id.map(|n| n.try_into().unwrap()),
-            public_id: r.public_id,
-            bounty_id: r.bounty_id.try_into().unwrap(),
-            image_data: r.image_data,
-            is_primary: r.is_primary,
-        })
-        .try_collect::<Vec<_>>()
-        .await?;
+        mut case_cards: Vec<CaseCard>,
+    ) -> Result<Vec<CaseCard>, sqlx::Error> {
+        for case_card in case_cards.iter_mut() {
+            let poster_user_id = case_card.case.seller_user_id;
+            case_card.poster_reputation =
+                Some(Reputation::single_for_user(db, poster_user_id).await?);
+        }
 
-        Ok(bounty_images)
+        Ok(case_cards)
     }
+}
 
-    pub async fn single_by_public_id(
+impl AccountInfo {
+    pub async fn account_info_for_user(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<BountyImage, sqlx::Error> {
-        let bounty_image = sqlx::query!(
-            "select * from bountyimages WHERE public_id = ?;",
-            public_id
-        )
-        .fetch_one(&mut **db)
-        .map_ok(|r| BountyImage {
-            id: Some(r.id.try_into().unwrap()),
-            public_id: r.public_id,
-            bounty_id: r.bounty_id.try_into().unwrap(),
-            image_data: r.image_data,
-            is_primary: r.is_primary,
+        user_id: i32,
+    ) -> Result<AccountInfo, sqlx::Error> {
+        let account_balance_sat = AccountInfo::total_account_balance_for_user(db, user_id).await?;
+        let account_balance_sat_text = util::short_num_format(account_balance_sat.try_into().unwrap());
+        let num_unawarded_cases = Case::num_processing_for_user(db, user_id).await?;
+        Ok(AccountInfo {
+            account_balance_sat,
+            account_balance_sat_text,
+            num_unawarded_cases,
         })
-        .await?;
-
-        Ok(bounty_image)
     }
 
-    pub async fn mark_image_as_primary_by_public_id(
+    /// Counts the bounties, cases and claims of the user that are still open.
+    pub async fn num_open_items_for_user(
         db: &mut Connection<Db>,
-        bounty_id: i32,
-        image_id: &str,
-    ) -> Result<usize, sqlx::Error> {
-        // Set all images for bounty_id to not primary.
-        let update_primary_result = sqlx::query!(
+        user_id: i32,
+    ) -> Result<u32, sqlx::Error> {
+        let num_open_items = sqlx::query(
             "
-UPDATE
- bountyimages
-SET
- is_primary = (public_id = ?)
-WHERE
- bounty_id = ?
+select
+ (select COUNT(bounties.id) from bounties WHERE bounties.user_id = ? AND NOT (bounties.deactivated_by_seller OR bounties.deactivated_by_admin))
+ + (select COUNT(cases.id) from cases WHERE (cases.buyer_user_id = ? OR cases.seller_user_id = ?) AND cases.paid AND NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer))
+ + (select COUNT(bountyclaims.id) from bountyclaims WHERE bountyclaims.user_id = ? AND NOT bountyclaims.released)
+ as num_open_items
 ;",
-            image_id,
-            bounty_id
         )
-        .execute(&mut **db)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_open_items_i64: i64 = r.try_get("num_open_items").unwrap();
+            num_open_items_i64 as u32
+        })
         .await?;
 
-        Ok(update_primary_result.rows_affected() as _)
+        Ok(num_open_items)
     }
 
-    /// Returns the number of affected rows: 1.
-    pub async fn delete_with_public_id(
-        public_id: &str,
+    pub async fn all_account_balance_changes_for_user(
         db: &mut Connection<Db>,
-    ) -> Result<usize, sqlx::Error> {
-        let delete_result =
-            sqlx::query!("DELETE FROM bountyimages WHERE public_id = ?", public_id)
-                .execute(&mut **db)
-                .await?;
-
-        Ok(delete_result.rows_affected() as _)
-    }
-}
-
-impl RocketAuthUser {
-    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<RocketAuthUser, sqlx::Error> {
-        let rocket_auth_user = sqlx::query!("select id, email from users WHERE id = ?;", id)
-            .fetch_one(&mut **db)
-            .map_ok(|r| RocketAuthUser {
-                id: Some(r.id as i32),
-                username: r.email.unwrap(),
+        user_id: i32,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<AccountBalanceChange>, sqlx::Error> {
+        // TODO: Case by event time in SQL query. When this is fixed: https://github.com/launchbadge/sqlx/issues/1350
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let account_balance_changes = sqlx::query("
+SELECT * FROM
+(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ cases.awarded
+AND
+ cases.seller_user_id = ?
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ (cases.canceled_by_seller OR cases.canceled_by_buyer)
+AND
+ cases.buyer_user_id = ?
+UNION ALL
+select caseawards.winner_user_id as user_id, caseawards.credit_sat as amount_change_sat, 'awarded_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+WHERE
+ caseawards.winner_user_id = ?
+UNION ALL
+select caseawards.poster_user_id as user_id, (0 - caseawards.amount_sat) as amount_change_sat, 'paid_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+WHERE
+ caseawards.poster_user_id = ?
+UNION ALL
+select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
+from
+ withdrawals
+WHERE
+ withdrawals.user_id = ?
+UNION ALL
+select bountyclaims.user_id as user_id, (0 - bountyclaims.stake_sat) as amount_change_sat, 'claim_stake' as event_type, bounties.public_id as event_id, bountyclaims.created_time_ms as event_time_ms
+from
+ bountyclaims
+JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+WHERE
+ NOT bountyclaims.released
+AND
+ bountyclaims.user_id = ?
+UNION ALL
+select cases.seller_user_id as user_id, (0 - casedisputes.reserved_sat) as amount_change_sat, 'dispute_reserve' as event_type, cases.public_id as event_id, casedisputes.created_time_ms as event_time_ms
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+WHERE
+ NOT casedisputes.resolved
+AND
+ cases.seller_user_id = ?)
+ORDER BY event_time_ms DESC
+LIMIT ?
+OFFSET ?
+;")
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch(&mut **db)
+            .map_ok(|r| AccountBalanceChange {
+                amount_change_sat: r.try_get("amount_change_sat").unwrap(),
+                event_type: r.try_get("event_type").unwrap(),
+                event_id: r.try_get("event_id").unwrap(),
+                event_time_ms: {
+                    let time_ms_i64: i64 = r.try_get("event_time_ms").unwrap();
+                    time_ms_i64 as u64
+                },
             })
+            .try_collect::<Vec<_>>()
             .await?;
 
-        Ok(rocket_auth_user)
-    }
-
-    pub async fn single_by_username(
-        db: &mut Connection<Db>,
-        username: String,
-    ) -> Result<RocketAuthUser, sqlx::Error> {
-        let rocket_auth_user =
-            sqlx::query!("select id, email from users WHERE email = ?;", username)
-                .fetch_one(&mut **db)
-                .map_ok(|r| RocketAuthUser {
-                    id: Some(r.id.unwrap() as i32),
-                    username: r.email.unwrap(),
-                })
-                .await?;
-
-        Ok(rocket_auth_user)
+        Ok(account_balance_changes)
     }
-}
 
-impl BountyDisplay {
-    pub async fn single_by_public_id(
+    pub async fn total_account_balance_for_user(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<BountyDisplay, sqlx::Error> {
-        let bounty = Bounty::single_by_public_id(&mut *db, public_id).await?;
-        let images = BountyImage::all_for_bounty(&mut *db, bounty.id.unwrap()).await?;
-        let image_displays = images
-            .iter()
-            .map(|img| BountyImageDisplay {
-                id: img.id,
-                public_id: img.clone().public_id,
-                bounty_id: img.bounty_id,
-                image_data_base64: util::to_base64(&img.image_data),
-                is_primary: img.is_primary,
-            })
-            .collect::<Vec<_>>();
-        let rocket_auth_user = RocketAuthUser::single(&mut *db, bounty.user_id).await.ok();
-
-        let bounty_display = BountyDisplay {
-            bounty,
-            images: image_displays,
-            user: rocket_auth_user,
-        };
-
-        Ok(bounty_display)
+        user_id: i32,
+    ) -> Result<i64, sqlx::Error> {
+        AccountInfo::total_account_balance_for_connection(&mut **db, user_id).await
     }
-}
 
-impl BountyCard {
-    pub async fn all_active(
-        db: &mut Connection<Db>,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
-        // Other example query: https://stackoverflow.com/a/13698334/1639564
-        // TODO: change WHERE condition to use dynamically calculated remaining quantity
-        // based on number of awarded cases.
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+    pub async fn total_account_balance_for_connection(
+        conn: &mut SqliteConnection,
+        user_id: i32,
+    ) -> Result<i64, sqlx::Error> {
+        let account_balance_sat = sqlx::query("
+SELECT SUM(amount_change_sat) as total_account_balance_sat FROM
+(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ cases.awarded
+AND
+ cases.seller_user_id = ?
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
 from
- bounties
-LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
+ cases
+WHERE
+ cases.paid
 AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
- users
+ (cases.canceled_by_seller OR cases.canceled_by_buyer)
+AND
+ cases.buyer_user_id = ?
+UNION ALL
+select caseawards.winner_user_id as user_id, caseawards.credit_sat as amount_change_sat, 'awarded_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
 ON
- bounties.user_id = users.id
-INNER JOIN
- useraccounts
+ caseawards.case_id = cases.id
+WHERE
+ caseawards.winner_user_id = ?
+UNION ALL
+select caseawards.poster_user_id as user_id, (0 - caseawards.amount_sat) as amount_change_sat, 'paid_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
 ON
- bounties.user_id = useraccounts.user_id
+ caseawards.case_id = cases.id
+WHERE
+ caseawards.poster_user_id = ?
+UNION ALL
+select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
+from
+ withdrawals
+WHERE
+ withdrawals.user_id = ?
+UNION ALL
+select bountyclaims.user_id as user_id, (0 - bountyclaims.stake_sat) as amount_change_sat, 'claim_stake' as event_type, bounties.public_id as event_id, bountyclaims.created_time_ms as event_time_ms
+from
+ bountyclaims
+JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+WHERE
+ NOT bountyclaims.released
 AND
- NOT useraccounts.disabled
+ bountyclaims.user_id = ?
+UNION ALL
+select cases.seller_user_id as user_id, (0 - casedisputes.reserved_sat) as amount_change_sat, 'dispute_reserve' as event_type, cases.public_id as event_id, casedisputes.created_time_ms as event_time_ms
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
 WHERE
- bounties.approved
+ NOT casedisputes.resolved
 AND
- not (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-		            deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
+ cases.seller_user_id = ?)
+;")
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .map_ok(|r|  {
+                let balance_sat_i64: i64 = r.try_get("total_account_balance_sat").unwrap();
+                balance_sat_i64
             })
-                .try_collect::<Vec<_>>()
-                .await?;
+            .await?;
 
-        Ok(bounty_cards)
+        Ok(account_balance_sat)
     }
 
-    pub async fn all_deactivated(
+    pub async fn all_account_balance_changes(
         db: &mut Connection<Db>,
         page_size: u32,
         page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
+    ) -> Result<Vec<AccountBalanceChange>, sqlx::Error> {
+        // TODO: Case by event time in SQL query. When this is fixed: https://github.com/launchbadge/sqlx/issues/1350
         let offset = (page_num - 1) * page_size;
         let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+        let account_balance_changes = sqlx::query("
+SELECT * FROM
+(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ cases.awarded
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ (cases.canceled_by_seller OR cases.canceled_by_buyer)
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'processing_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+UNION ALL
+select caseawards.winner_user_id as user_id, caseawards.credit_sat as amount_change_sat, 'awarded_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+UNION ALL
+select caseawards.poster_user_id as user_id, (0 - caseawards.amount_sat) as amount_change_sat, 'paid_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+UNION ALL
+select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
+from
+ withdrawals
+UNION ALL
+select useraccounts.user_id as user_id, useraccounts.amount_owed_sat as amount_change_sat, 'user_activation' as event_type, useraccounts.public_id as event_id, useraccounts.created_time_ms as event_time_ms
+from
+ useraccounts
+WHERE
+ useraccounts.paid
+UNION ALL
+select bountyclaims.user_id as user_id, (0 - bountyclaims.stake_sat) as amount_change_sat, 'claim_stake' as event_type, bounties.public_id as event_id, bountyclaims.created_time_ms as event_time_ms
 from
+ bountyclaims
+JOIN
  bounties
-LEFT JOIN
- bountyimages
 ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
- users
+ bountyclaims.bounty_id = bounties.id
+WHERE
+ NOT bountyclaims.released
+UNION ALL
+select bountyclaims.user_id as user_id, bountyclaims.stake_sat as amount_change_sat, 'held_claim_stake' as event_type, bounties.public_id as event_id, bountyclaims.created_time_ms as event_time_ms
+from
+ bountyclaims
+JOIN
+ bounties
 ON
- bounties.user_id = users.id
+ bountyclaims.bounty_id = bounties.id
 WHERE
- bounties.approved
-AND
- (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
+ NOT bountyclaims.released)
+ORDER BY event_time_ms DESC
 LIMIT ?
 OFFSET ?
-;", limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-		            approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
+;")
+            .bind(limit)
+            .bind(offset)
+            .fetch(&mut **db)
+            .map_ok(|r| AccountBalanceChange {
+                amount_change_sat: r.try_get("amount_change_sat").unwrap(),
+                event_type: r.try_get("event_type").unwrap(),
+                event_id: r.try_get("event_id").unwrap(),
+                event_time_ms: {
+                    let time_ms_i64: i64 = r.try_get("event_time_ms").unwrap();
+                    time_ms_i64 as u64
+                },
             })
-                .try_collect::<Vec<_>>()
-                .await?;
+            .try_collect::<Vec<_>>()
+            .await?;
 
-        Ok(bounty_cards)
+        Ok(account_balance_changes)
     }
 
-    pub async fn all_pending(
-        db: &mut Connection<Db>,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
-        // Other example query: https://stackoverflow.com/a/13698334/1639564
-        // TODO: change WHERE condition to use dynamically calculated remaining quantity
-        // based on number of awarded cases.
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+    pub async fn total_market_liabilities_sat(db: &mut Connection<Db>) -> Result<i64, sqlx::Error> {
+        AccountInfo::total_market_liabilities_sat_for_connection(&mut **db).await
+    }
+
+    pub async fn total_market_liabilities_sat_for_connection(
+        conn: &mut SqliteConnection,
+    ) -> Result<i64, sqlx::Error> {
+        let market_liabilities_sat = sqlx::query("
+SELECT SUM(amount_change_sat) as total_market_liabilities_sat FROM
+(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ cases.awarded
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ (cases.canceled_by_seller OR cases.canceled_by_buyer)
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'processing_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+UNION ALL
+select caseawards.winner_user_id as user_id, caseawards.credit_sat as amount_change_sat, 'awarded_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+UNION ALL
+select caseawards.poster_user_id as user_id, (0 - caseawards.amount_sat) as amount_change_sat, 'paid_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+UNION ALL
+select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
+from
+ withdrawals
+UNION ALL
+select useraccounts.user_id as user_id, useraccounts.amount_owed_sat as amount_change_sat, 'user_activation' as event_type, useraccounts.public_id as event_id, useraccounts.created_time_ms as event_time_ms
 from
+ useraccounts
+WHERE
+ useraccounts.paid
+UNION ALL
+select bountyclaims.user_id as user_id, (0 - bountyclaims.stake_sat) as amount_change_sat, 'claim_stake' as event_type, bounties.public_id as event_id, bountyclaims.created_time_ms as event_time_ms
+from
+ bountyclaims
+JOIN
  bounties
-LEFT JOIN
- bountyimages
 ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
- users
+ bountyclaims.bounty_id = bounties.id
+WHERE
+ NOT bountyclaims.released
+UNION ALL
+select bountyclaims.user_id as user_id, bountyclaims.stake_sat as amount_change_sat, 'held_claim_stake' as event_type, bounties.public_id as event_id, bountyclaims.created_time_ms as event_time_ms
+from
+ bountyclaims
+JOIN
+ bounties
 ON
- bounties.user_id = users.id
+ bountyclaims.bounty_id = bounties.id
 WHERE
- bounties.submitted
-AND
- NOT bounties.viewed
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
+ NOT bountyclaims.released)
+;")
+            .fetch_one(&mut *conn)
+            .map_ok(|r|  {
+                let balance_sat_i64: i64 = r.try_get("total_market_liabilities_sat").unwrap();
+                balance_sat_i64
             })
-                .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(market_liabilities_sat)
+    }
+
+    /// Sats paid for cases that are still waiting on a decision.
+    pub async fn total_held_escrow_sat_for_connection(
+        conn: &mut SqliteConnection,
+    ) -> Result<i64, sqlx::Error> {
+        let held_escrow_sat = sqlx::query(
+            "
+SELECT SUM(cases.amount_owed_sat) as total_held_escrow_sat
+FROM
+ cases
+WHERE
+ cases.paid
+AND
+ NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+;",
+        )
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let amount_sat_i64: i64 = r.try_get("total_held_escrow_sat").unwrap();
+            amount_sat_i64
+        })
+        .await?;
+
+        Ok(held_escrow_sat)
+    }
+
+    /// Sats staked on bounty claims that are not released yet. The stakes are taken out
+    /// of the balances of the hunters, but are still owed back to them.
+    pub async fn total_held_claim_stakes_sat_for_connection(
+        conn: &mut SqliteConnection,
+    ) -> Result<i64, sqlx::Error> {
+        let held_claim_stakes_sat = sqlx::query(
+            "
+SELECT COALESCE(SUM(bountyclaims.stake_sat), 0) as total_held_claim_stakes_sat
+FROM
+ bountyclaims
+WHERE
+ NOT bountyclaims.released
+;",
+        )
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let amount_sat_i64: i64 = r.try_get("total_held_claim_stakes_sat").unwrap();
+            amount_sat_i64
+        })
+        .await?;
+
+        Ok(held_claim_stakes_sat)
+    }
+
+    // TODO: Use when sqlx is fixed.
+    //     pub async fn account_balance(
+    //         db: &mut Connection<Db>,
+    //         user_id: i32,
+    //     ) -> Result<u64, sqlx::Error> {
+    //         let account_balance_result = sqlx::query!("
+    // SELECT SUM(data.amount_change_sat) as account_balance, data.user_id as user_id
+    // FROM
+    // (select bounties.user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+    // from
+    //  cases
+    // LEFT JOIN
+    //  bounties
+    // ON
+    //  cases.bounty_id = bounties.id
+    // WHERE
+    //  cases.paid
+    // AND
+    //  cases.awarded
+    // AND
+    //  bounties.user_id = ?
+    // UNION ALL
+    // select cases.user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+    // from
+    //  cases
+    // WHERE
+    //  cases.paid
+    // AND
+    //  not cases.awarded
+    // AND
+    //  cases.user_id = ?
+    // ) data
+    // GROUP BY user_id
+    // ;",
+    //         user_id, user_id)
+    //         .fetch_optional(&mut **db)
+    //         .await?;
+
+    //         let account_balance = match account_balance_result {
+    //             Some(r) => r.account_balance,
+    //             None => 0,
+    //         };
+
+    //         Ok(account_balance)
+    //     }
+}
+
+impl Notification {
+    /// Notifies a user of an event, unless they turned off notifications of that type.
+    pub async fn insert_for_connection(
+        conn: &mut SqliteConnection,
+        user_id: i32,
+        event_type: &str,
+        message: &str,
+        link: &str,
+    ) -> Result<(), sqlx::Error> {
+        let created_time_ms: i64 = util::current_time_millis().try_into().unwrap();
+        sqlx::query(
+            "
+INSERT INTO notifications (public_id, user_id, event_type, message, link, read, created_time_ms)
+select ?, ?, ?, ?, ?, false, ?
+WHERE NOT EXISTS
+ (select 1 from disablednotifications WHERE disablednotifications.user_id = ? AND disablednotifications.event_type = ?)
+;",
+        )
+        .bind(util::create_uuid())
+        .bind(user_id)
+        .bind(event_type)
+        .bind(message)
+        .bind(link)
+        .bind(created_time_ms)
+        .bind(user_id)
+        .bind(event_type)
+        .execute(&mut *conn)
+        .await?;
+        if EMAIL_EVENT_TYPES.contains(&event_type) {
+            Email::enqueue_for_connection(conn, user_id, event_type, message, link).await?;
+        }
+        if NOSTR_EVENT_TYPES.contains(&event_type) {
+            NostrEvent::enqueue_direct_message_for_connection(conn, user_id, message, link)
                 .await?;
+        }
 
-        Ok(bounty_cards)
+        Ok(())
     }
 
-    pub async fn all_unsubmitted_for_user(
-        db: &mut Connection<Db>,
+    /// Notifies a user of an event on a case, with a link to the case.
+    pub async fn insert_for_case(
+        conn: &mut SqliteConnection,
+        case_id: i32,
         user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
-        // Other example query: https://stackoverflow.com/a/13698334/1639564
-        // TODO: change WHERE condition to use dynamically calculated remaining quantity
-        // based on number of awarded cases.
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+        event_type: &str,
+        message: &str,
+    ) -> Result<(), sqlx::Error> {
+        let (case_public_id, maybe_bounty_title): (String, Option<String>) = sqlx::query(
+            "
+select cases.public_id as case_public_id, bounties.title as bounty_title
 from
- bounties
+ cases
 LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
- users
+ bounties
 ON
- bounties.user_id = users.id
-WHERE
- not bounties.submitted
-AND
- users.id = ?
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", user_id, limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
-            })
-                .try_collect::<Vec<_>>()
-                .await?;
-
-        Ok(bounty_cards)
+ cases.bounty_id = bounties.id
+WHERE
+ cases.id = ?
+;",
+        )
+        .bind(case_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            (
+                r.try_get("case_public_id").unwrap(),
+                r.try_get("bounty_title").unwrap(),
+            )
+        })
+        .await?;
+        let message = match maybe_bounty_title {
+            Some(bounty_title) => format!("{}: {}", message, bounty_title),
+            None => message.to_string(),
+        };
+        Notification::insert_for_connection(
+            conn,
+            user_id,
+            event_type,
+            &message,
+            &format!("/case/{}", case_public_id),
+        )
+        .await
     }
 
-    pub async fn all_pending_for_user(
+    pub async fn all_for_user(
         db: &mut Connection<Db>,
         user_id: i32,
         page_size: u32,
         page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
-        // Other example query: https://stackoverflow.com/a/13698334/1639564
-        // TODO: change WHERE condition to use dynamically calculated remaining quantity
-        // based on number of awarded cases. 
+    ) -> Result<Vec<Notification>, sqlx::Error> {
         let offset = (page_num - 1) * page_size;
         let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+        let notifications = sqlx::query(
+            "
+select *
 from
- bounties
+ notifications
+WHERE
+ notifications.user_id = ?
+ORDER BY notifications.created_time_ms DESC, notifications.id DESC
+LIMIT ?
+OFFSET ?
+;",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| Notification::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(notifications)
+    }
+
+    pub async fn num_unread_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<u32, sqlx::Error> {
+        let num_unread_notifications = sqlx::query(
+            "select COUNT(notifications.id) as num_unread_notifications from notifications WHERE notifications.user_id = ? AND NOT notifications.read;",
+        )
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_unread_notifications_i64: i64 = r.try_get("num_unread_notifications").unwrap();
+            num_unread_notifications_i64 as u32
+        })
+        .await?;
+
+        Ok(num_unread_notifications)
+    }
+
+    pub async fn mark_as_read(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        public_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE notifications SET read = true WHERE user_id = ? AND public_id = ?")
+            .bind(user_id)
+            .bind(public_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_all_as_read(db: &mut Connection<Db>, user_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE notifications SET read = true WHERE user_id = ? AND NOT read")
+            .bind(user_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn disabled_event_types_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let event_types = sqlx::query(
+            "select event_type from disablednotifications WHERE user_id = ? ORDER BY event_type ASC;",
+        )
+        .bind(user_id)
+        .fetch(&mut **db)
+        .map_ok(|r| r.try_get("event_type").unwrap())
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(event_types)
+    }
+
+    pub async fn set_disabled_event_types(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        event_types: Vec<&str>,
+    ) -> Result<(), String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        sqlx::query("DELETE FROM disablednotifications WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to delete notification preferences.")?;
+        for event_type in event_types {
+            sqlx::query("INSERT INTO disablednotifications (user_id, event_type) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(event_type)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "failed to insert notification preference.")?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> Notification {
+        Notification {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            event_type: r.try_get("event_type").unwrap(),
+            message: r.try_get("message").unwrap(),
+            link: r.try_get("link").unwrap(),
+            read: r.try_get("read").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+/// Events that are also sent by email to users who opted in.
+const EMAIL_EVENT_TYPES: [&str; 5] = [
+    "bounty_approved",
+    "bounty_rejected",
+    "case_received",
+    "case_awarded",
+    "withdrawal_sent",
+];
+
+impl Email {
+    /// Queues an email for a user, if they opted in to email notifications.
+    pub async fn enqueue_for_connection(
+        conn: &mut SqliteConnection,
+        user_id: i32,
+        event_type: &str,
+        message: &str,
+        link: &str,
+    ) -> Result<(), sqlx::Error> {
+        let now: i64 = util::current_time_millis().try_into().unwrap();
+        sqlx::query(
+            "
+INSERT INTO emails (public_id, user_id, event_type, message, link, sent, num_attempts, last_error, next_attempt_time_ms, created_time_ms)
+select ?, ?, ?, ?, ?, false, 0, '', ?, ?
+WHERE EXISTS
+ (select 1 from emailsettings WHERE emailsettings.user_id = ? AND emailsettings.enabled)
+;",
+        )
+        .bind(util::create_uuid())
+        .bind(user_id)
+        .bind(event_type)
+        .bind(message)
+        .bind(link)
+        .bind(now)
+        .bind(now)
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the unsent emails that are due for another delivery attempt.
+    pub async fn all_due(
+        db: &mut PoolConnection<Sqlite>,
+        time_now_ms: u64,
+        max_attempts: u32,
+    ) -> Result<Vec<Email>, sqlx::Error> {
+        let time_now_ms: i64 = time_now_ms.try_into().unwrap();
+        let emails = sqlx::query(
+            "
+select emails.*, users.email as to_address
+from
+ emails
 LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
  users
 ON
- bounties.user_id = users.id
+ emails.user_id = users.id
 WHERE
- bounties.submitted
+ NOT emails.sent
 AND
- NOT bounties.viewed
+ emails.num_attempts < ?
 AND
- users.id = ?
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", user_id, limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
-            })
-                .try_collect::<Vec<_>>()
-                .await?;
+ emails.next_attempt_time_ms <= ?
+ORDER BY emails.next_attempt_time_ms ASC
+;",
+        )
+        .bind(max_attempts)
+        .bind(time_now_ms)
+        .fetch(&mut **db)
+        .map_ok(|r| Email {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            to_address: r.try_get("to_address").unwrap(),
+            event_type: r.try_get("event_type").unwrap(),
+            message: r.try_get("message").unwrap(),
+            link: r.try_get("link").unwrap(),
+            sent: r.try_get("sent").unwrap(),
+            num_attempts: r.try_get("num_attempts").unwrap(),
+            last_error: r.try_get("last_error").unwrap(),
+            next_attempt_time_ms: {
+                let time_ms_i64: i64 = r.try_get("next_attempt_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(bounty_cards)
+        Ok(emails)
     }
 
-    pub async fn all_rejected_for_user(
+    /// Marks an email as sent. The link of a password reset holds the reset token, so it
+    /// is not kept after the email is sent.
+    pub async fn mark_as_sent(
+        db: &mut PoolConnection<Sqlite>,
+        email_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE emails SET sent = true, num_attempts = num_attempts + 1, link = CASE WHEN event_type = 'password_reset' THEN '' ELSE link END WHERE id = ?",
+        )
+        .bind(email_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Drops the links of the password reset emails of a user that were not sent, once
+    /// their tokens can no longer be used.
+    pub async fn clear_password_reset_links_for_user(
         db: &mut Connection<Db>,
         user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
-        // Other example query: https://stackoverflow.com/a/13698334/1639564
-        // TODO: change WHERE condition to use dynamically calculated remaining quantity
-        // based on number of awarded cases.
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE emails SET link = '' WHERE user_id = ? AND event_type = 'password_reset'",
+        )
+        .bind(user_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_as_failed(
+        db: &mut PoolConnection<Sqlite>,
+        email_id: i32,
+        error: &str,
+        next_attempt_time_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let next_attempt_time_ms: i64 = next_attempt_time_ms.try_into().unwrap();
+        sqlx::query(
+            "UPDATE emails SET num_attempts = num_attempts + 1, last_error = ?, next_attempt_time_ms = ? WHERE id = ?",
+        )
+        .bind(error)
+        .bind(next_attempt_time_ms)
+        .bind(email_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn enabled_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let maybe_enabled = sqlx::query("select enabled from emailsettings WHERE user_id = ?;")
+            .bind(user_id)
+            .fetch_optional(&mut **db)
+            .map_ok(|maybe_r| maybe_r.map(|r| r.try_get("enabled").unwrap()))
+            .await?;
+
+        Ok(maybe_enabled.unwrap_or(false))
+    }
+
+    pub async fn set_enabled_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        enabled: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO emailsettings (user_id, enabled) VALUES (?, ?) ON CONFLICT(user_id) DO UPDATE SET enabled = excluded.enabled",
+        )
+        .bind(user_id)
+        .bind(enabled)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Events that are also sent as Nostr direct messages to users who linked an npub.
+const NOSTR_EVENT_TYPES: [&str; 4] = [
+    "case_received",
+    "case_paid",
+    "case_awarded",
+    "case_canceled",
+];
+
+impl NostrEvent {
+    /// Queues a public note from the market key.
+    pub async fn enqueue_note(
+        db: &mut Connection<Db>,
+        message: &str,
+        link: &str,
+    ) -> Result<(), sqlx::Error> {
+        NostrEvent::enqueue_note_for_connection(&mut **db, message, link).await
+    }
+
+    pub async fn enqueue_note_for_connection(
+        conn: &mut SqliteConnection,
+        message: &str,
+        link: &str,
+    ) -> Result<(), sqlx::Error> {
+        let now: i64 = util::current_time_millis().try_into().unwrap();
+        sqlx::query(
+            "
+INSERT INTO nostrevents (public_id, recipient_user_id, message, link, sent, num_attempts, last_error, next_attempt_time_ms, created_time_ms)
+VALUES (?, NULL, ?, ?, false, 0, '', ?, ?)
+;",
+        )
+        .bind(util::create_uuid())
+        .bind(message)
+        .bind(link)
+        .bind(now)
+        .bind(now)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Queues a direct message for a user, if they linked a Nostr public key.
+    pub async fn enqueue_direct_message_for_connection(
+        conn: &mut SqliteConnection,
+        user_id: i32,
+        message: &str,
+        link: &str,
+    ) -> Result<(), sqlx::Error> {
+        let now: i64 = util::current_time_millis().try_into().unwrap();
+        sqlx::query(
+            "
+INSERT INTO nostrevents (public_id, recipient_user_id, message, link, sent, num_attempts, last_error, next_attempt_time_ms, created_time_ms)
+select ?, ?, ?, ?, false, 0, '', ?, ?
+WHERE EXISTS
+ (select 1 from usernostrkeys WHERE usernostrkeys.user_id = ?)
+;",
+        )
+        .bind(util::create_uuid())
+        .bind(user_id)
+        .bind(message)
+        .bind(link)
+        .bind(now)
+        .bind(now)
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the unsent events that are due for another publish attempt.
+    pub async fn all_due(
+        db: &mut PoolConnection<Sqlite>,
+        time_now_ms: u64,
+        max_attempts: u32,
+    ) -> Result<Vec<NostrEvent>, sqlx::Error> {
+        let time_now_ms: i64 = time_now_ms.try_into().unwrap();
+        let nostr_events = sqlx::query(
+            "
+select nostrevents.*, usernostrkeys.public_key as recipient_public_key
 from
- bounties
+ nostrevents
 LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
- users
+ usernostrkeys
 ON
- bounties.user_id = users.id
+ nostrevents.recipient_user_id = usernostrkeys.user_id
 WHERE
- not bounties.approved
+ NOT nostrevents.sent
 AND
- NOT bounties.viewed
+ nostrevents.num_attempts < ?
 AND
- users.id = ?
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", user_id, limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_admin.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
-            })
-                .try_collect::<Vec<_>>()
+ nostrevents.next_attempt_time_ms <= ?
+ORDER BY nostrevents.next_attempt_time_ms ASC
+;",
+        )
+        .bind(max_attempts)
+        .bind(time_now_ms)
+        .fetch(&mut **db)
+        .map_ok(|r| NostrEvent {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            recipient_user_id: r.try_get("recipient_user_id").unwrap(),
+            recipient_public_key: r.try_get("recipient_public_key").unwrap(),
+            message: r.try_get("message").unwrap(),
+            link: r.try_get("link").unwrap(),
+            sent: r.try_get("sent").unwrap(),
+            num_attempts: r.try_get("num_attempts").unwrap(),
+            last_error: r.try_get("last_error").unwrap(),
+            next_attempt_time_ms: {
+                let time_ms_i64: i64 = r.try_get("next_attempt_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(nostr_events)
+    }
+
+    pub async fn mark_as_sent(
+        db: &mut PoolConnection<Sqlite>,
+        nostr_event_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE nostrevents SET sent = true, num_attempts = num_attempts + 1 WHERE id = ?",
+        )
+        .bind(nostr_event_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_as_failed(
+        db: &mut PoolConnection<Sqlite>,
+        nostr_event_id: i32,
+        error: &str,
+        next_attempt_time_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let next_attempt_time_ms: i64 = next_attempt_time_ms.try_into().unwrap();
+        sqlx::query(
+            "UPDATE nostrevents SET num_attempts = num_attempts + 1, last_error = ?, next_attempt_time_ms = ? WHERE id = ?",
+        )
+        .bind(error)
+        .bind(next_attempt_time_ms)
+        .bind(nostr_event_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the Nostr public key (hex) that a user linked, if any.
+    pub async fn public_key_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let maybe_public_key =
+            sqlx::query("select public_key from usernostrkeys WHERE user_id = ?;")
+                .bind(user_id)
+                .fetch_optional(&mut **db)
+                .map_ok(|maybe_r| maybe_r.map(|r| r.try_get("public_key").unwrap()))
                 .await?;
 
-        Ok(bounty_cards)
+        Ok(maybe_public_key)
     }
 
-    pub async fn all_deactivated_for_user(
+    pub async fn set_public_key_for_user(
         db: &mut Connection<Db>,
         user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+        public_key: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO usernostrkeys (user_id, public_key) VALUES (?, ?) ON CONFLICT(user_id) DO UPDATE SET public_key = excluded.public_key",
+        )
+        .bind(user_id)
+        .bind(public_key)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_public_key_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM usernostrkeys WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl Webhook {
+    pub async fn insert(webhook: Webhook, db: &mut Connection<Db>) -> Result<i32, sqlx::Error> {
+        let created_time_ms: i64 = webhook.created_time_ms.try_into().unwrap();
+        let insert_result = sqlx::query(
+            "INSERT INTO webhooks (public_id, user_id, url, secret, all_events, created_time_ms) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(webhook.public_id)
+        .bind(webhook.user_id)
+        .bind(webhook.url)
+        .bind(webhook.secret)
+        .bind(webhook.all_events)
+        .bind(created_time_ms)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(insert_result.last_insert_rowid() as _)
+    }
+
+    pub async fn all_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<Vec<Webhook>, sqlx::Error> {
+        let webhooks = sqlx::query(
+            "select * from webhooks WHERE user_id = ? ORDER BY created_time_ms ASC;",
+        )
+        .bind(user_id)
+        .fetch(&mut **db)
+        .map_ok(|r| Webhook::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(webhooks)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<Webhook, sqlx::Error> {
+        let webhook = sqlx::query("select * from webhooks WHERE public_id = ?;")
+            .bind(public_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| Webhook::from_row(&r))
+            .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn num_for_user(db: &mut Connection<Db>, user_id: i32) -> Result<u32, sqlx::Error> {
+        let num_webhooks = sqlx::query(
+            "select COUNT(webhooks.id) as num_webhooks from webhooks WHERE webhooks.user_id = ?;",
+        )
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_webhooks_i64: i64 = r.try_get("num_webhooks").unwrap();
+            num_webhooks_i64 as u32
+        })
+        .await?;
+
+        Ok(num_webhooks)
+    }
+
+    /// Stops the webhooks of a user from receiving the events of every user, when the
+    /// user is no longer an admin.
+    pub async fn disable_all_events_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE webhooks SET all_events = false WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut **db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes a webhook, along with its delivery log.
+    pub async fn delete(db: &mut Connection<Db>, webhook_id: i32) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+
+        sqlx::query("DELETE FROM webhookdeliveries WHERE webhook_id = ?")
+            .bind(webhook_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM webhooks WHERE id = ?")
+            .bind(webhook_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> Webhook {
+        Webhook {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            url: r.try_get("url").unwrap(),
+            secret: r.try_get("secret").unwrap(),
+            all_events: r.try_get("all_events").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl WebhookDelivery {
+    /// Queues a delivery of an event to the webhooks of the given users, and to every
+    /// webhook of an admin that receives all market events.
+    pub async fn enqueue_for_connection(
+        conn: &mut SqliteConnection,
+        user_ids: &[i32],
+        event_type: &str,
+        payload: serde_json::Value,
+    ) -> Result<(), sqlx::Error> {
+        let now: i64 = util::current_time_millis().try_into().unwrap();
+        let webhooks = sqlx::query(
+            "select webhooks.*, users.is_admin from webhooks LEFT JOIN users ON webhooks.user_id = users.id;",
+        )
+        .fetch(&mut *conn)
+        .map_ok(|r| {
+            let is_admin: Option<bool> = r.try_get("is_admin").unwrap();
+            (Webhook::from_row(&r), is_admin.unwrap_or(false))
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+        let payload = payload.to_string();
+
+        for webhook in webhooks
+            .iter()
+            .filter(|(w, is_admin)| (w.all_events && *is_admin) || user_ids.contains(&w.user_id))
+            .map(|(w, _)| w)
+        {
+            sqlx::query(
+                "INSERT INTO webhookdeliveries (public_id, webhook_id, event_type, payload, delivered, num_attempts, last_status_code, last_error, next_attempt_time_ms, created_time_ms) VALUES (?, ?, ?, ?, false, 0, 0, '', ?, ?)",
+            )
+            .bind(util::create_uuid())
+            .bind(webhook.id.unwrap())
+            .bind(event_type)
+            .bind(&payload)
+            .bind(now)
+            .bind(now)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Queues a delivery of an event on a case, to the bounty poster and the case submitter.
+    pub async fn enqueue_for_case(
+        conn: &mut SqliteConnection,
+        case_id: i32,
+        event_type: &str,
+        amount_sat: u64,
+    ) -> Result<(), sqlx::Error> {
+        let case = Case::single_for_connection(&mut *conn, case_id).await?;
+        let bounty_public_id: String =
+            sqlx::query("select public_id from bounties WHERE id = ?;")
+                .bind(case.bounty_id)
+                .fetch_one(&mut *conn)
+                .map_ok(|r| r.try_get("public_id").unwrap())
+                .await?;
+        let payload = serde_json::json!({
+            "case_id": case.public_id,
+            "bounty_id": bounty_public_id,
+            "amount_sat": amount_sat,
+            "link": format!("/case/{}", case.public_id),
+        });
+        WebhookDelivery::enqueue_for_connection(
+            conn,
+            &[case.seller_user_id, case.buyer_user_id],
+            event_type,
+            payload,
+        )
+        .await
+    }
+
+    /// Gets the undelivered events that are due for another attempt, with the webhook to send them to.
+    pub async fn all_due(
+        db: &mut PoolConnection<Sqlite>,
+        time_now_ms: u64,
+        max_attempts: u32,
+    ) -> Result<Vec<(WebhookDelivery, Webhook)>, sqlx::Error> {
+        let time_now_ms: i64 = time_now_ms.try_into().unwrap();
+        let deliveries = sqlx::query(
+            "
+select webhookdeliveries.*, webhooks.public_id as webhook_public_id, webhooks.user_id, webhooks.url, webhooks.secret, webhooks.all_events, webhooks.created_time_ms as webhook_created_time_ms
 from
- bounties
-LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+ webhookdeliveries
 INNER JOIN
- users
+ webhooks
 ON
- bounties.user_id = users.id
+ webhookdeliveries.webhook_id = webhooks.id
 WHERE
- bounties.approved
-AND
- (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+ NOT webhookdeliveries.delivered
 AND
- users.id = ?
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", user_id, limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_admin.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
-            })
-                .try_collect::<Vec<_>>()
-                .await?;
+ webhookdeliveries.num_attempts < ?
+AND
+ webhookdeliveries.next_attempt_time_ms <= ?
+ORDER BY webhookdeliveries.next_attempt_time_ms ASC
+;",
+        )
+        .bind(max_attempts)
+        .bind(time_now_ms)
+        .fetch(&mut **db)
+        .map_ok(|r| {
+            let webhook = Webhook {
+                id: Some(r.try_get("webhook_id").unwrap()),
+                public_id: r.try_get("webhook_public_id").unwrap(),
+                user_id: r.try_get("user_id").unwrap(),
+                url: r.try_get("url").unwrap(),
+                secret: r.try_get("secret").unwrap(),
+                all_events: r.try_get("all_events").unwrap(),
+                created_time_ms: {
+                    let time_ms_i64: i64 = r.try_get("webhook_created_time_ms").unwrap();
+                    time_ms_i64 as u64
+                },
+            };
+            (WebhookDelivery::from_row(&r), webhook)
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(bounty_cards)
+        Ok(deliveries)
     }
 
-    pub async fn all_active_for_user(
+    pub async fn all_for_webhook(
         db: &mut Connection<Db>,
-        user_id: i32,
+        webhook_id: i32,
         page_size: u32,
         page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
-        // Other example query: https://stackoverflow.com/a/13698334/1639564
-        // TODO: change WHERE condition to use dynamically calculated remaining quantity
-        // based on number of awarded cases.
+    ) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
         let offset = (page_num - 1) * page_size;
         let limit = page_size;
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+        let deliveries = sqlx::query(
+            "
+select *
 from
- bounties
-LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
- users
-ON
- bounties.user_id = users.id
+ webhookdeliveries
 WHERE
- bounties.approved
-AND
- bounties.viewed
-AND
- NOT (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
-AND
- users.id = ?
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
+ webhookdeliveries.webhook_id = ?
+ORDER BY webhookdeliveries.created_time_ms DESC, webhookdeliveries.id DESC
 LIMIT ?
 OFFSET ?
-;", user_id, limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
-            })
-                .try_collect::<Vec<_>>()
-                .await?;
+;",
+        )
+        .bind(webhook_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| WebhookDelivery::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(bounty_cards)
+        Ok(deliveries)
     }
 
-    pub async fn all_active_for_search_text(
+    pub async fn mark_as_delivered(
+        db: &mut PoolConnection<Sqlite>,
+        delivery_id: i32,
+        status_code: u16,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE webhookdeliveries SET delivered = true, num_attempts = num_attempts + 1, last_status_code = ?, last_error = '' WHERE id = ?",
+        )
+        .bind(status_code)
+        .bind(delivery_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_as_failed(
+        db: &mut PoolConnection<Sqlite>,
+        delivery_id: i32,
+        status_code: u16,
+        error: &str,
+        next_attempt_time_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let next_attempt_time_ms: i64 = next_attempt_time_ms.try_into().unwrap();
+        sqlx::query(
+            "UPDATE webhookdeliveries SET num_attempts = num_attempts + 1, last_status_code = ?, last_error = ?, next_attempt_time_ms = ? WHERE id = ?",
+        )
+        .bind(status_code)
+        .bind(error)
+        .bind(next_attempt_time_ms)
+        .bind(delivery_id)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> WebhookDelivery {
+        WebhookDelivery {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            webhook_id: r.try_get("webhook_id").unwrap(),
+            event_type: r.try_get("event_type").unwrap(),
+            payload: r.try_get("payload").unwrap(),
+            delivered: r.try_get("delivered").unwrap(),
+            num_attempts: r.try_get("num_attempts").unwrap(),
+            last_status_code: r.try_get("last_status_code").unwrap(),
+            last_error: r.try_get("last_error").unwrap(),
+            next_attempt_time_ms: {
+                let time_ms_i64: i64 = r.try_get("next_attempt_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl CaseDispute {
+    /// Opens a dispute on a case, and tells the bounty poster about it.
+    pub async fn insert(
+        dispute: CaseDispute,
+        poster_user_id: i32,
+        submitter_user_id: i32,
         db: &mut Connection<Db>,
-        search_text: &str,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCard>, sqlx::Error> {
-        // Example query for this kind of join/group by: https://stackoverflow.com/a/63037790/1639564
-        // Other example query: https://stackoverflow.com/a/13698334/1639564
-        // TODO: change WHERE condition to use dynamically calculated remaining quantity
-        // based on number of awarded cases.
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        // let uppercase_search_term = search_text.to_owned().to_ascii_uppercase();
-        let wildcard_search_term = format!("%{}%", search_text.to_ascii_uppercase());
-        let bounty_cards =
-            sqlx::query!("
-select
- bounties.id, bounties.public_id, bounties.user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+
+        CaseDispute::insert_for_connection(&mut *tx, &dispute).await?;
+        CaseAuditEvent::insert_for_connection(
+            &mut *tx,
+            dispute.case_id,
+            Some(submitter_user_id),
+            "dispute_opened",
+            &dispute.reason,
+        )
+        .await?;
+        Notification::insert_for_case(
+            &mut *tx,
+            dispute.case_id,
+            poster_user_id,
+            "dispute_opened",
+            "Dispute opened",
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Inserts the dispute, and reserves the disputed reward from the balance of the
+    /// poster, up to what the poster has left.
+    pub async fn insert_for_connection(
+        conn: &mut SqliteConnection,
+        dispute: &CaseDispute,
+    ) -> Result<(), sqlx::Error> {
+        let reserved_sat =
+            CaseDispute::reserve_sat_for_connection(&mut *conn, dispute.case_id).await?;
+
+        let created_time_ms: i64 = dispute.created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO casedisputes (public_id, case_id, reason, resolved, resolution, submitter_share_basis_points, resolution_note, resolved_by_user_id, reserved_sat, created_time_ms, resolved_time_ms) VALUES (?, ?, ?, false, '', 0, '', NULL, ?, ?, 0)",
+        )
+        .bind(&dispute.public_id)
+        .bind(dispute.case_id)
+        .bind(&dispute.reason)
+        .bind(reserved_sat)
+        .bind(created_time_ms)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the amount that a dispute on the case would reserve: the disputed reward, up
+    /// to what the poster has left.
+    pub async fn reserve_sat_for_connection(
+        conn: &mut SqliteConnection,
+        case_id: i32,
+    ) -> Result<i64, sqlx::Error> {
+        let (poster_user_id, reward_sat) = sqlx::query(
+            "
+select cases.seller_user_id, COALESCE(bountymilestones.reward_sat, bounties.price_sat) as reward_sat
 from
+ cases
+JOIN
  bounties
-LEFT JOIN
- bountyimages
 ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-INNER JOIN
- users
+ cases.bounty_id = bounties.id
+LEFT JOIN
+ casemilestones
 ON
- bounties.user_id = users.id
-INNER JOIN
- useraccounts
+ casemilestones.case_id = cases.id
+LEFT JOIN
+ bountymilestones
 ON
- bounties.user_id = useraccounts.user_id
-AND
- NOT useraccounts.disabled
+ casemilestones.milestone_id = bountymilestones.id
 WHERE
- bounties.approved
-AND
- bounties.viewed
-AND
- NOT (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
-AND
- (UPPER(bounties.title) like ? OR UPPER(bounties.description) like ?)
-GROUP BY
- bounties.id
-ORDER BY bounties.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", wildcard_search_term, wildcard_search_term, limit, offset)
-                .fetch(&mut **db)
-            .map_ok(|r| {
-                let l = Bounty {
-                    id: Some(r.id.unwrap().try_into().unwrap()),
-                    public_id: r.public_id.unwrap(),
-                    user_id: r.user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                };
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                BountyCard {
-                    bounty: l,
-                    image: i,
-                    user: u.unwrap(),
-                }
-            })
-                .try_collect::<Vec<_>>()
-                .await?;
-
-        Ok(bounty_cards)
-    }
-}
+ cases.id = ?
+;",
+        )
+        .bind(case_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let poster_user_id: i32 = r.try_get("seller_user_id").unwrap();
+            let reward_sat_i64: i64 = r.try_get("reward_sat").unwrap();
+            (poster_user_id, reward_sat_i64)
+        })
+        .await?;
+        let poster_balance_sat =
+            AccountInfo::total_account_balance_for_connection(&mut *conn, poster_user_id).await?;
 
-impl BountyCardDisplay {
-    fn bounty_card_to_display(card: &BountyCard) -> BountyCardDisplay {
-        BountyCardDisplay {
-            bounty: card.bounty.clone(),
-            image: card.image.clone().map(|image| BountyImageDisplay {
-                id: image.id,
-                public_id: image.public_id,
-                bounty_id: image.bounty_id,
-                image_data_base64: util::to_base64(&image.image_data),
-                is_primary: image.is_primary,
-            }),
-            user: card.clone().user,
-        }
+        Ok(reward_sat.min(poster_balance_sat.max(0)))
     }
 
-    pub async fn all_active(
+    pub async fn single_for_case(
         db: &mut Connection<Db>,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards = BountyCard::all_active(db, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+        case_id: i32,
+    ) -> Result<Option<CaseDispute>, sqlx::Error> {
+        let maybe_dispute = sqlx::query(
+            "
+select casedisputes.*, cases.public_id as case_public_id
+from
+ casedisputes
+LEFT JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+WHERE
+ casedisputes.case_id = ?
+;",
+        )
+        .bind(case_id)
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| maybe_r.map(|r| CaseDispute::from_row(&r)))
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(maybe_dispute)
     }
 
-    pub async fn all_deactivated(
+    pub async fn all_open(
         db: &mut Connection<Db>,
         page_size: u32,
         page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards = BountyCard::all_deactivated(db, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+    ) -> Result<Vec<CaseDispute>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let disputes = sqlx::query(
+            "
+select casedisputes.*, cases.public_id as case_public_id
+from
+ casedisputes
+LEFT JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+WHERE
+ NOT casedisputes.resolved
+ORDER BY casedisputes.created_time_ms ASC
+LIMIT ?
+OFFSET ?
+;",
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseDispute::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(disputes)
     }
 
-    pub async fn all_pending(
-        db: &mut Connection<Db>,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards = BountyCard::all_pending(db, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+    pub async fn num_open(db: &mut Connection<Db>) -> Result<u32, sqlx::Error> {
+        let num_open_disputes = sqlx::query(
+            "select COUNT(casedisputes.id) as num_open_disputes from casedisputes WHERE NOT casedisputes.resolved;",
+        )
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_open_disputes_i64: i64 = r.try_get("num_open_disputes").unwrap();
+            num_open_disputes_i64 as u32
+        })
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(num_open_disputes)
     }
 
-    pub async fn all_unsubmitted_for_user(
+    /// Records the decision of an admin on a dispute, and pays out the given awards from
+    /// the reserved reward. The dispute holds the resolution fields set by the admin.
+    pub async fn resolve(
         db: &mut Connection<Db>,
-        user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards =
-            BountyCard::all_unsubmitted_for_user(db, user_id, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+        dispute: CaseDispute,
+        case: &Case,
+        maybe_milestone_id: Option<i32>,
+        case_awards: Vec<CaseAward>,
+    ) -> Result<(), String> {
+        CaseDispute::resolve_for_connection(
+            &mut **db,
+            dispute,
+            case,
+            maybe_milestone_id,
+            case_awards,
+        )
+        .await
+    }
 
-        Ok(bounty_card_displays)
+    pub async fn resolve_for_connection(
+        conn: &mut SqliteConnection,
+        dispute: CaseDispute,
+        case: &Case,
+        maybe_milestone_id: Option<i32>,
+        case_awards: Vec<CaseAward>,
+    ) -> Result<(), String> {
+        let resolved_time_ms: i64 = dispute.resolved_time_ms.try_into().unwrap();
+        let admin_user_id = dispute.resolved_by_user_id.unwrap();
+        let resolution = dispute.resolution.as_str();
+        let resolution_note = dispute.resolution_note.as_str();
+        let mut tx = conn
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        let update_result = sqlx::query(
+            "UPDATE casedisputes SET resolved = true, resolution = ?, submitter_share_basis_points = ?, resolution_note = ?, resolved_by_user_id = ?, resolved_time_ms = ? WHERE id = ? AND NOT resolved",
+        )
+        .bind(resolution)
+        .bind(dispute.submitter_share_basis_points)
+        .bind(resolution_note)
+        .bind(admin_user_id)
+        .bind(resolved_time_ms)
+        .bind(dispute.id.unwrap())
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to update dispute.")?;
+        if update_result.rows_affected() != 1 {
+            return Err("Dispute is already resolved.".to_string());
+        }
+
+        // The awards are paid out of the reserve, so there must be one to pay from.
+        if dispute.submitter_share_basis_points > 0 && dispute.reserved_sat == 0 {
+            return Err(
+                "Nothing was reserved for this dispute, so it can only be refunded.".to_string(),
+            );
+        }
+        CaseAward::insert_all_for_connection(&mut *tx, maybe_milestone_id, &case_awards).await?;
+
+        // A case that the poster rejected stays canceled, so the refunded case fee is never
+        // taken back from the submitter. Only a case that is still pending, after an
+        // escalated review deadline, gets closed here.
+        if !(case.awarded || case.canceled_by_seller || case.canceled_by_buyer) {
+            CaseRevision::insert_original_for_connection(&mut *tx, case.id.unwrap())
+                .await
+                .map_err(|_| "failed to record case details.")?;
+            let case_update_query = if dispute.submitter_share_basis_points > 0 {
+                "UPDATE cases SET awarded = true, case_details = '' WHERE id = ? AND paid AND NOT (awarded OR canceled_by_seller OR canceled_by_buyer)"
+            } else {
+                "UPDATE cases SET canceled_by_seller = true, case_details = '' WHERE id = ? AND paid AND NOT (awarded OR canceled_by_seller OR canceled_by_buyer)"
+            };
+            let update_result = sqlx::query(case_update_query)
+                .bind(case.id.unwrap())
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "failed to close case.")?;
+            if update_result.rows_affected() != 1 {
+                return Err("Case is already closed.".to_string());
+            }
+        }
+
+        let details = if resolution_note.is_empty() {
+            resolution.to_string()
+        } else {
+            format!("{}: {}", resolution, resolution_note)
+        };
+        CaseAuditEvent::insert_for_connection(
+            &mut *tx,
+            case.id.unwrap(),
+            Some(admin_user_id),
+            "dispute_resolved",
+            &details,
+        )
+        .await
+        .map_err(|_| "failed to insert audit event.")?;
+        for user_id in [case.buyer_user_id, case.seller_user_id] {
+            Notification::insert_for_case(
+                &mut *tx,
+                case.id.unwrap(),
+                user_id,
+                "dispute_resolved",
+                &format!("Dispute resolved ({})", resolution),
+            )
+            .await
+            .map_err(|_| "failed to insert notification.")?;
+        }
+
+        let poster_balance_sat =
+            AccountInfo::total_account_balance_for_connection(&mut *tx, case.seller_user_id)
+                .await
+                .map_err(|_| "failed to get account balance.")?;
+        if poster_balance_sat < 0 {
+            return Err("Insufficient funds to pay the reward.".to_string());
+        }
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
     }
 
-    pub async fn all_pending_for_user(
+    /// Counts the resolved disputes that a user won, lost or split, either as the
+    /// case submitter or as the bounty poster.
+    pub async fn stats_for_user(
         db: &mut Connection<Db>,
         user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards =
-            BountyCard::all_pending_for_user(db, user_id, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+    ) -> Result<DisputeStats, sqlx::Error> {
+        let dispute_stats = sqlx::query(
+            "
+select
+ COUNT(CASE WHEN (cases.buyer_user_id = ? AND casedisputes.resolution = 'award') OR (cases.seller_user_id = ? AND casedisputes.resolution = 'refund') THEN 1 END) as num_won,
+ COUNT(CASE WHEN (cases.buyer_user_id = ? AND casedisputes.resolution = 'refund') OR (cases.seller_user_id = ? AND casedisputes.resolution = 'award') THEN 1 END) as num_lost,
+ COUNT(CASE WHEN casedisputes.resolution = 'split' THEN 1 END) as num_split
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+WHERE
+ casedisputes.resolved
+AND
+ (cases.buyer_user_id = ? OR cases.seller_user_id = ?)
+;",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_won_i64: i64 = r.try_get("num_won").unwrap();
+            let num_lost_i64: i64 = r.try_get("num_lost").unwrap();
+            let num_split_i64: i64 = r.try_get("num_split").unwrap();
+            DisputeStats {
+                num_won: num_won_i64 as u32,
+                num_lost: num_lost_i64 as u32,
+                num_split: num_split_i64 as u32,
+            }
+        })
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(dispute_stats)
     }
 
-    pub async fn all_rejected_for_user(
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> CaseDispute {
+        CaseDispute {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            case_public_id: r.try_get("case_public_id").unwrap(),
+            reason: r.try_get("reason").unwrap(),
+            resolved: r.try_get("resolved").unwrap(),
+            resolution: r.try_get("resolution").unwrap(),
+            submitter_share_basis_points: r.try_get("submitter_share_basis_points").unwrap(),
+            resolution_note: r.try_get("resolution_note").unwrap(),
+            resolved_by_user_id: r.try_get("resolved_by_user_id").unwrap(),
+            reserved_sat: {
+                let reserved_sat_i64: i64 = r.try_get("reserved_sat").unwrap();
+                reserved_sat_i64 as u64
+            },
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+            resolved_time_ms: {
+                let time_ms_i64: i64 = r.try_get("resolved_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl DisputeEvidence {
+    pub async fn insert(
+        evidence: DisputeEvidence,
         db: &mut Connection<Db>,
-        user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards =
-            BountyCard::all_rejected_for_user(db, user_id, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+    ) -> Result<(), sqlx::Error> {
+        let created_time_ms: i64 = evidence.created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO disputeevidence (public_id, dispute_id, user_id, body, created_time_ms) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(evidence.public_id)
+        .bind(evidence.dispute_id)
+        .bind(evidence.user_id)
+        .bind(evidence.body)
+        .bind(created_time_ms)
+        .execute(&mut **db)
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(())
     }
 
-    pub async fn all_deactivated_for_user(
+    pub async fn all_for_dispute(
         db: &mut Connection<Db>,
-        user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards =
-            BountyCard::all_deactivated_for_user(db, user_id, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+        dispute_id: i32,
+    ) -> Result<Vec<DisputeEvidence>, sqlx::Error> {
+        let evidence = sqlx::query(
+            "
+select disputeevidence.*, users.email as username
+from
+ disputeevidence
+LEFT JOIN
+ users
+ON
+ disputeevidence.user_id = users.id
+WHERE
+ disputeevidence.dispute_id = ?
+ORDER BY disputeevidence.created_time_ms ASC, disputeevidence.id ASC
+;",
+        )
+        .bind(dispute_id)
+        .fetch(&mut **db)
+        .map_ok(|r| DisputeEvidence {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            dispute_id: r.try_get("dispute_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            username: r.try_get("username").unwrap(),
+            body: r.try_get("body").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(evidence)
     }
+}
 
-    pub async fn all_active_for_user(
+impl CaseAuditEvent {
+    /// Records a change of state of a case. The user is whoever made the change.
+    pub async fn insert_for_connection(
+        conn: &mut SqliteConnection,
+        case_id: i32,
+        user_id: Option<i32>,
+        event_type: &str,
+        details: &str,
+    ) -> Result<(), sqlx::Error> {
+        let created_time_ms: i64 = util::current_time_millis().try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO caseauditevents (case_id, user_id, event_type, details, created_time_ms) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(case_id)
+        .bind(user_id)
+        .bind(event_type)
+        .bind(details)
+        .bind(created_time_ms)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn all_for_case(
         db: &mut Connection<Db>,
-        user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards =
-            BountyCard::all_active_for_user(db, user_id, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+        case_id: i32,
+    ) -> Result<Vec<CaseAuditEvent>, sqlx::Error> {
+        let audit_events = sqlx::query(
+            "
+select caseauditevents.*, users.email as username
+from
+ caseauditevents
+LEFT JOIN
+ users
+ON
+ caseauditevents.user_id = users.id
+WHERE
+ caseauditevents.case_id = ?
+ORDER BY caseauditevents.created_time_ms ASC, caseauditevents.id ASC
+;",
+        )
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseAuditEvent {
+            id: Some(r.try_get("id").unwrap()),
+            case_id: r.try_get("case_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            username: r.try_get("username").unwrap(),
+            event_type: r.try_get("event_type").unwrap(),
+            details: r.try_get("details").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(audit_events)
     }
 
-    pub async fn all_active_for_search_text(
+    /// Gets the time of the latest event of a type on a case.
+    pub async fn last_time_for_case(
         db: &mut Connection<Db>,
-        search_text: &str,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<BountyCardDisplay>, sqlx::Error> {
-        let bounty_cards =
-            BountyCard::all_active_for_search_text(db, search_text, page_size, page_num).await?;
-        let bounty_card_displays = bounty_cards
-            .iter()
-            .map(BountyCardDisplay::bounty_card_to_display)
-            .collect::<Vec<_>>();
+        case_id: i32,
+        event_type: &str,
+    ) -> Result<Option<u64>, sqlx::Error> {
+        let maybe_time_ms = sqlx::query(
+            "select MAX(created_time_ms) as last_time_ms from caseauditevents WHERE case_id = ? AND event_type = ?;",
+        )
+        .bind(case_id)
+        .bind(event_type)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let maybe_time_ms_i64: Option<i64> = r.try_get("last_time_ms").unwrap();
+            maybe_time_ms_i64.map(|time_ms_i64| time_ms_i64 as u64)
+        })
+        .await?;
 
-        Ok(bounty_card_displays)
+        Ok(maybe_time_ms)
     }
 }
 
-impl AdminSettings {
-    pub async fn single(db: &mut Connection<Db>) -> Result<AdminSettings, sqlx::Error> {
-        let maybe_admin_settings = sqlx::query!("select * from adminsettings;")
-            .fetch_optional(&mut **db)
-            .map_ok(|maybe_r| {
-                maybe_r.map(|r| AdminSettings {
-                    id: Some(r.id.try_into().unwrap()),
-                    market_name: r.market_name,
-                    market_info: markdown::to_html(&r.market_info),
-                    market_info_raw: r.market_info,
-                    fee_rate_basis_points: r.fee_rate_basis_points.try_into().unwrap(),
-                    user_bond_price_sat: r.user_bond_price_sat.try_into().unwrap(),
-                    user_bond_price_sat_text: util::short_num_format(r.user_bond_price_sat.try_into().unwrap()),
-                    pgp_key: r.pgp_key,
-                    max_allowed_users: r.max_allowed_users.try_into().unwrap(),
-                })
-            })
-            .await?;
+impl CaseRevision {
+    /// Records the details the case was submitted with, unless the case already has revisions.
+    pub async fn insert_original_for_connection(
+        conn: &mut SqliteConnection,
+        case_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "
+INSERT INTO caserevisions (public_id, case_id, case_details, created_time_ms)
+SELECT ?, cases.id, cases.case_details, cases.created_time_ms
+FROM
+ cases
+WHERE
+ cases.id = ?
+AND
+ NOT EXISTS (SELECT 1 FROM caserevisions WHERE caserevisions.case_id = cases.id)
+;",
+        )
+        .bind(util::create_uuid())
+        .bind(case_id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the details of a pending case with a revision from the submitter, and
+    /// marks the open requests for changes as addressed. No new fee is charged.
+    pub async fn resubmit(
+        revision: CaseRevision,
+        poster_user_id: i32,
+        submitter_user_id: i32,
+        db: &mut Connection<Db>,
+    ) -> Result<(), String> {
+        let created_time_ms: i64 = revision.created_time_ms.try_into().unwrap();
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        CaseRevision::insert_original_for_connection(&mut *tx, revision.case_id)
+            .await
+            .map_err(|_| "failed to record original case details.")?;
+        let update_result = sqlx::query(
+            "
+UPDATE
+ cases
+SET
+ case_details = ?
+WHERE
+ id = ?
+AND
+ paid
+AND
+ not (awarded OR canceled_by_seller OR canceled_by_buyer)
+;",
+        )
+        .bind(&revision.case_details)
+        .bind(revision.case_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to update case details.")?;
+        if update_result.rows_affected() != 1 {
+            return Err("Case can no longer be revised.".to_string());
+        }
+        sqlx::query(
+            "INSERT INTO caserevisions (public_id, case_id, case_details, created_time_ms) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&revision.public_id)
+        .bind(revision.case_id)
+        .bind(&revision.case_details)
+        .bind(created_time_ms)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to insert case revision.")?;
+        sqlx::query(
+            "UPDATE caseresponses SET addressed = true WHERE case_id = ? AND response_type = 'changes_requested' AND NOT addressed",
+        )
+        .bind(revision.case_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to mark requests for changes as addressed.")?;
+        CaseAuditEvent::insert_for_connection(
+            &mut *tx,
+            revision.case_id,
+            Some(submitter_user_id),
+            "resubmitted",
+            "",
+        )
+        .await
+        .map_err(|_| "failed to insert audit event.")?;
+        Notification::insert_for_case(
+            &mut *tx,
+            revision.case_id,
+            poster_user_id,
+            "case_resubmitted",
+            "Case revised",
+        )
+        .await
+        .map_err(|_| "failed to insert notification.")?;
 
-        let admin_settings = maybe_admin_settings.unwrap_or_default();
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
 
-        Ok(admin_settings)
+        Ok(())
     }
 
-    async fn insert_if_doesnt_exist(db: &mut Connection<Db>) -> Result<(), sqlx::Error> {
-        let admin_settings = AdminSettings::default();
-        let user_bond_price_sat_i64: i64 = admin_settings.user_bond_price_sat.try_into().unwrap();
-        let max_allowed_users_i64: i64 = admin_settings.max_allowed_users.try_into().unwrap();
-        sqlx::query!(
+    pub async fn all_for_case(
+        db: &mut Connection<Db>,
+        case_id: i32,
+    ) -> Result<Vec<CaseRevision>, sqlx::Error> {
+        let revisions = sqlx::query(
             "
-INSERT INTO
- adminsettings (market_name, market_info, fee_rate_basis_points, user_bond_price_sat, pgp_key, max_allowed_users)
-SELECT ?, ?, ?, ?, ?, ?
-WHERE NOT EXISTS(SELECT 1 FROM adminsettings)
+select *
+from
+ caserevisions
+WHERE
+ case_id = ?
+ORDER BY created_time_ms ASC, id ASC
 ;",
-            admin_settings.market_name,
-            admin_settings.market_info,
-            admin_settings.fee_rate_basis_points,
-            user_bond_price_sat_i64,
-            admin_settings.pgp_key,
-            max_allowed_users_i64,
         )
-        .execute(&mut **db)
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseRevision {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            case_details: r.try_get("case_details").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
         .await?;
 
-        Ok(())
+        Ok(revisions)
     }
+}
 
-    pub async fn set_market_name(
+impl CaseAttachment {
+    pub async fn insert(
+        attachment: CaseAttachment,
+        data: Vec<u8>,
         db: &mut Connection<Db>,
-        new_market_name: &str,
     ) -> Result<(), sqlx::Error> {
-        AdminSettings::insert_if_doesnt_exist(db).await?;
-
-        sqlx::query!("UPDATE adminsettings SET market_name = ?", new_market_name)
-            .execute(&mut **db)
-            .await?;
+        let size_bytes: i64 = attachment.size_bytes.try_into().unwrap();
+        let created_time_ms: i64 = attachment.created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO caseattachments (public_id, case_id, user_id, filename, content_type, encrypted, size_bytes, data, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(attachment.public_id)
+        .bind(attachment.case_id)
+        .bind(attachment.user_id)
+        .bind(attachment.filename)
+        .bind(attachment.content_type)
+        .bind(attachment.encrypted)
+        .bind(size_bytes)
+        .bind(data)
+        .bind(created_time_ms)
+        .execute(&mut **db)
+        .await?;
 
         Ok(())
     }
 
-    pub async fn set_market_info(
+    pub async fn all_for_case(
         db: &mut Connection<Db>,
-        new_market_info: &str,
-    ) -> Result<(), sqlx::Error> {
-        AdminSettings::insert_if_doesnt_exist(db).await?;
-
-        sqlx::query!("UPDATE adminsettings SET market_info = ?", new_market_info)
-            .execute(&mut **db)
-            .await?;
+        case_id: i32,
+    ) -> Result<Vec<CaseAttachment>, sqlx::Error> {
+        let attachments = sqlx::query(
+            "
+select id, public_id, case_id, user_id, filename, content_type, encrypted, size_bytes, created_time_ms
+from
+ caseattachments
+WHERE
+ case_id = ?
+ORDER BY created_time_ms ASC, id ASC
+;",
+        )
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseAttachment::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(())
+        Ok(attachments)
     }
 
-    pub async fn set_fee_rate(
+    pub async fn single_by_public_id(
         db: &mut Connection<Db>,
-        new_fee_rate_basis_points: i32,
-    ) -> Result<(), sqlx::Error> {
-        AdminSettings::insert_if_doesnt_exist(db).await?;
-
-        sqlx::query!(
-            "UPDATE adminsettings SET fee_rate_basis_points = ?",
-            new_fee_rate_basis_points,
+        public_id: &str,
+    ) -> Result<CaseAttachment, sqlx::Error> {
+        let attachment = sqlx::query(
+            "
+select id, public_id, case_id, user_id, filename, content_type, encrypted, size_bytes, created_time_ms
+from
+ caseattachments
+WHERE
+ public_id = ?
+;",
         )
-        .execute(&mut **db)
+        .bind(public_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| CaseAttachment::from_row(&r))
         .await?;
 
-        Ok(())
+        Ok(attachment)
     }
 
-    pub async fn set_user_bond_price(
+    pub async fn data_by_public_id(
         db: &mut Connection<Db>,
-        new_user_bond_price_sat: u64,
-    ) -> Result<(), sqlx::Error> {
-        let user_bond_price_sat_i64: i64 = new_user_bond_price_sat.try_into().unwrap();
+        public_id: &str,
+    ) -> Result<Vec<u8>, sqlx::Error> {
+        let data = sqlx::query("select data from caseattachments WHERE public_id = ?;")
+            .bind(public_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| r.try_get("data").unwrap())
+            .await?;
 
-        AdminSettings::insert_if_doesnt_exist(db).await?;
+        Ok(data)
+    }
 
-        sqlx::query!(
-            "UPDATE adminsettings SET user_bond_price_sat = ?",
-            user_bond_price_sat_i64,
+    pub async fn total_size_for_case(
+        db: &mut Connection<Db>,
+        case_id: i32,
+    ) -> Result<u64, sqlx::Error> {
+        let total_size_bytes = sqlx::query(
+            "select SUM(size_bytes) as total_size_bytes from caseattachments WHERE case_id = ?;",
         )
-        .execute(&mut **db)
+        .bind(case_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let total_size_bytes_i64: Option<i64> = r.try_get("total_size_bytes").unwrap();
+            total_size_bytes_i64.unwrap_or(0) as u64
+        })
         .await?;
 
-        Ok(())
+        Ok(total_size_bytes)
     }
 
-    pub async fn set_pgp_key(
+    pub async fn delete_with_public_id(
+        public_id: &str,
         db: &mut Connection<Db>,
-        new_pgp_key: &str,
     ) -> Result<(), sqlx::Error> {
-        AdminSettings::insert_if_doesnt_exist(db).await?;
-
-        sqlx::query!("UPDATE adminsettings SET pgp_key = ?", new_pgp_key,)
+        sqlx::query("DELETE FROM caseattachments WHERE public_id = ?")
+            .bind(public_id)
             .execute(&mut **db)
             .await?;
 
         Ok(())
     }
 
-    pub async fn set_max_allowed_users(
-        db: &mut Connection<Db>,
-        new_max_allowed_users: u64,
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> CaseAttachment {
+        CaseAttachment {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            filename: r.try_get("filename").unwrap(),
+            content_type: r.try_get("content_type").unwrap(),
+            encrypted: r.try_get("encrypted").unwrap(),
+            size_bytes: {
+                let size_bytes_i64: i64 = r.try_get("size_bytes").unwrap();
+                size_bytes_i64 as u64
+            },
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl CaseResponse {
+    pub async fn insert_for_connection(
+        conn: &mut SqliteConnection,
+        response: &CaseResponse,
     ) -> Result<(), sqlx::Error> {
-        let max_allowed_users_i64: i64 = new_max_allowed_users.try_into().unwrap();
+        let created_time_ms: i64 = response.created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO caseresponses (public_id, case_id, response_type, reason, note, addressed, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&response.public_id)
+        .bind(response.case_id)
+        .bind(&response.response_type)
+        .bind(&response.reason)
+        .bind(&response.note)
+        .bind(response.addressed)
+        .bind(created_time_ms)
+        .execute(conn)
+        .await?;
 
-        AdminSettings::insert_if_doesnt_exist(db).await?;
+        Ok(())
+    }
 
-        sqlx::query!(
-            "UPDATE adminsettings SET max_allowed_users = ?",
-            max_allowed_users_i64,
+    /// Rejects a pending case for the given reason. The case details are kept in the
+    /// revision history before canceling clears them.
+    pub async fn reject(
+        response: CaseResponse,
+        db: &mut Connection<Db>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+
+        CaseRevision::insert_original_for_connection(&mut *tx, response.case_id).await?;
+        Case::mark_as_canceled_by_seller_for_connection(
+            &mut *tx,
+            response.case_id,
+            &response.reason,
         )
-        .execute(&mut **db)
         .await?;
+        CaseResponse::insert_for_connection(&mut *tx, &response).await?;
+
+        tx.commit().await?;
 
         Ok(())
     }
-}
 
-impl UserSettings {
-    pub async fn single(
+    /// Asks the submitter to revise a pending case, and tells them about it.
+    pub async fn request_changes(
+        response: CaseResponse,
+        poster_user_id: i32,
+        submitter_user_id: i32,
         db: &mut Connection<Db>,
-        user_id: i32,
-    ) -> Result<UserSettings, sqlx::Error> {
-        let maybe_user_settings =
-            sqlx::query!("select * from usersettings WHERE user_id = ?;", user_id,)
-                .fetch_optional(&mut **db)
-                .map_ok(|maybe_r| {
-                    maybe_r.map(|r| UserSettings {
-                        id: Some(r.id.try_into().unwrap()),
-                        pgp_key: r.pgp_key,
-                    })
-                })
-                .await?;
-        let user_settings = maybe_user_settings.unwrap_or_default();
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+
+        CaseResponse::insert_for_connection(&mut *tx, &response).await?;
+        CaseAuditEvent::insert_for_connection(
+            &mut *tx,
+            response.case_id,
+            Some(poster_user_id),
+            "changes_requested",
+            &response.note,
+        )
+        .await?;
+        Notification::insert_for_case(
+            &mut *tx,
+            response.case_id,
+            submitter_user_id,
+            "case_changes_requested",
+            "Changes requested on case",
+        )
+        .await?;
 
-        Ok(user_settings)
+        tx.commit().await?;
+
+        Ok(())
     }
 
-    /// Returns the number of affected rows: 1.
-    async fn insert_if_doesnt_exist(
+    pub async fn all_for_case(
         db: &mut Connection<Db>,
-        user_id: i32,
-    ) -> Result<(), sqlx::Error> {
-        let user_settings = UserSettings::default();
-        sqlx::query!(
+        case_id: i32,
+    ) -> Result<Vec<CaseResponse>, sqlx::Error> {
+        let responses = sqlx::query(
             "
-INSERT INTO
- usersettings (user_id, pgp_key)
-SELECT ?, ?
-WHERE NOT EXISTS(SELECT 1 FROM usersettings WHERE user_id = ?)
+select *
+from
+ caseresponses
+WHERE
+ case_id = ?
+ORDER BY created_time_ms ASC, id ASC
 ;",
-            user_id,
-            user_settings.pgp_key,
-            user_id,
         )
-        .execute(&mut **db)
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseResponse::from_row(&r))
+        .try_collect::<Vec<_>>()
         .await?;
 
-        Ok(())
+        Ok(responses)
     }
 
-    pub async fn set_pgp_key(
+    /// Gets the request for changes that the submitter has not answered yet, if any.
+    pub async fn open_changes_request_for_case(
         db: &mut Connection<Db>,
-        user_id: i32,
-        new_pgp_key: &str,
-    ) -> Result<(), sqlx::Error> {
-        UserSettings::insert_if_doesnt_exist(db, user_id).await?;
-
-        sqlx::query!(
-            "UPDATE usersettings SET pgp_key = ? WHERE user_id = ?;",
-            new_pgp_key,
-            user_id,
+        case_id: i32,
+    ) -> Result<Option<CaseResponse>, sqlx::Error> {
+        let maybe_response = sqlx::query(
+            "
+select *
+from
+ caseresponses
+WHERE
+ case_id = ?
+AND
+ response_type = 'changes_requested'
+AND
+ NOT addressed
+ORDER BY created_time_ms DESC, id DESC
+LIMIT 1
+;",
         )
-        .execute(&mut **db)
+        .bind(case_id)
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| maybe_r.map(|r| CaseResponse::from_row(&r)))
         .await?;
 
-        Ok(())
+        Ok(maybe_response)
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> CaseResponse {
+        CaseResponse {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            response_type: r.try_get("response_type").unwrap(),
+            reason: r.try_get("reason").unwrap(),
+            note: r.try_get("note").unwrap(),
+            addressed: r.try_get("addressed").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
     }
 }
 
-impl Case {
-    /// Returns the id of the inserted row.
-    pub async fn insert(
-        case: Case,
-        max_unpaid_cases: u32,
+impl Withdrawal {
+    pub async fn do_withdrawal(
+        withdrawal: Withdrawal,
         db: &mut Connection<Db>,
+        send_withdrawal_funds_future: impl Future<
+            Output = Result<tonic_openssl_lnd::lnrpc::SendResponse, String>,
+        >,
+        max_withdrawals_per_interval: u32,
+        interval_start_time_ms: u64,
     ) -> Result<i32, String> {
-        let amount_owed_sat: i64 = case.amount_owed_sat.try_into().unwrap();
-        let seller_credit_sat: i64 = case.seller_credit_sat.try_into().unwrap();
-        let created_time_ms: i64 = case.created_time_ms.try_into().unwrap();
-        let payment_time_ms: i64 = case.payment_time_ms.try_into().unwrap();
-
         let mut tx = db
             .begin()
             .await
             .map_err(|_| "failed to begin transaction.")?;
 
+        // Insert the new withdrawal.
+        let amount_sat: i64 = withdrawal.amount_sat.try_into().unwrap();
+        let created_time_ms: i64 = withdrawal.created_time_ms.try_into().unwrap();
         let insert_result = sqlx::query!(
-            "INSERT INTO cases (public_id, buyer_user_id, seller_user_id, quantity, bounty_id, case_details, amount_owed_sat, seller_credit_sat, paid, awarded, canceled_by_seller, canceled_by_buyer, invoice_hash, invoice_payment_request, created_time_ms, payment_time_ms ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            case.public_id,
-            case.buyer_user_id,
-            case.seller_user_id,
-            case.quantity,
-            case.bounty_id,
-            case.case_details,
-            amount_owed_sat,
-            seller_credit_sat,
-            case.paid,
-            case.awarded,
-            case.canceled_by_seller,
-            case.canceled_by_buyer,
-            case.invoice_hash,
-            case.invoice_payment_request,
+            "INSERT INTO withdrawals (public_id, user_id, amount_sat, invoice_hash, invoice_payment_request, created_time_ms) VALUES (?, ?, ?, ?, ?, ?)",
+            withdrawal.public_id,
+            withdrawal.user_id,
+            amount_sat,
+            withdrawal.invoice_hash,
+            withdrawal.invoice_payment_request,
             created_time_ms,
-            payment_time_ms,
         )
             .execute(&mut *tx)
             .await
-            .map_err(|_| "failed to insert case.")?;
+            .map_err(|_| "failed to insert new withdrawal.")?;
+        let new_withdrawal_id = insert_result.last_insert_rowid();
 
-        let num_unpaid_cases = sqlx::query!(
+        // Check if any constraints are violated.
+        let user_id = withdrawal.user_id;
+
+        let start_time_ms_i64: i64 = interval_start_time_ms.try_into().unwrap();
+        let withdrawal_count = sqlx::query!(
             "
-select
- COUNT(cases.id) as num_unpaid_cases
-from
- cases
+select count(id) as withdrawal_count from withdrawals
 WHERE
- cases.buyer_user_id = ?
+ user_id = ?
 AND
- NOT cases.paid
-;",
-            case.buyer_user_id,
+ created_time_ms > ?
+ORDER BY withdrawals.created_time_ms ASC;",
+            user_id,
+            start_time_ms_i64,
         )
         .fetch_one(&mut *tx)
-        .map_ok(|r| r.num_unpaid_cases as u32)
+        .map_ok(|r| r.withdrawal_count as u32)
         .await
-        .map_err(|_| "failed to get count of unpaid cases for buyer.")?;
+        .map_err(|_| "failed to get withdrawal count.")?;
 
-        if num_unpaid_cases > max_unpaid_cases {
+        if withdrawal_count > max_withdrawals_per_interval {
             return Err(format!(
-                "more than {:?} unpaid cases not allowed.",
-                max_unpaid_cases,
+                "More than {:?} withdrawals in a single day not allowed.",
+                max_withdrawals_per_interval,
             ));
         }
 
-        tx.commit()
+        let account_balance_sat = sqlx::query("
+SELECT SUM(amount_change_sat) as total_account_balance_sat FROM
+(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ cases.awarded
+AND
+ cases.seller_user_id = ?
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+from
+ cases
+WHERE
+ cases.paid
+AND
+ (cases.canceled_by_seller OR cases.canceled_by_buyer)
+AND
+ cases.buyer_user_id = ?
+UNION ALL
+select caseawards.winner_user_id as user_id, caseawards.credit_sat as amount_change_sat, 'awarded_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+WHERE
+ caseawards.winner_user_id = ?
+UNION ALL
+select caseawards.poster_user_id as user_id, (0 - caseawards.amount_sat) as amount_change_sat, 'paid_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+WHERE
+ caseawards.poster_user_id = ?
+UNION ALL
+select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
+from
+ withdrawals
+WHERE
+ withdrawals.user_id = ?
+UNION ALL
+select bountyclaims.user_id as user_id, (0 - bountyclaims.stake_sat) as amount_change_sat, 'claim_stake' as event_type, bounties.public_id as event_id, bountyclaims.created_time_ms as event_time_ms
+from
+ bountyclaims
+JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+WHERE
+ NOT bountyclaims.released
+AND
+ bountyclaims.user_id = ?
+UNION ALL
+select cases.seller_user_id as user_id, (0 - casedisputes.reserved_sat) as amount_change_sat, 'dispute_reserve' as event_type, cases.public_id as event_id, casedisputes.created_time_ms as event_time_ms
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+WHERE
+ NOT casedisputes.resolved
+AND
+ cases.seller_user_id = ?)
+;")
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .map_ok(|r|  {
+                let balance_sat_i64: i64 = r.try_get("total_account_balance_sat").unwrap();
+                balance_sat_i64
+            })
             .await
-            .map_err(|_| "failed to commit transaction.")?;
+            .map_err(|_| "failed to insert get account balance changes.")?;
 
-        Ok(insert_result.last_insert_rowid() as _)
-    }
+        if account_balance_sat < 0 {
+            return Err("Insufficient funds for withdrawal.".to_string());
+        }
 
-    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<Case, sqlx::Error> {
-        let case = sqlx::query!("select * from cases WHERE id = ?;", id)
-            .fetch_one(&mut **db)
-            .map_ok(|r| Case {
-                id: Some(r.id.try_into().unwrap()),
-                public_id: r.public_id,
-                quantity: r.quantity.try_into().unwrap(),
-                buyer_user_id: r.buyer_user_id.try_into().unwrap(),
-                seller_user_id: r.seller_user_id.try_into().unwrap(),
-                bounty_id: r.bounty_id.try_into().unwrap(),
-                case_details: markdown::to_html(&r.case_details),
-                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-                seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
-                paid: r.paid,
-                awarded: r.awarded,
-                canceled_by_seller: r.canceled_by_seller,
-                canceled_by_buyer: r.canceled_by_buyer,
-                invoice_hash: r.invoice_hash,
-                invoice_payment_request: r.invoice_payment_request,
-                created_time_ms: r.created_time_ms.try_into().unwrap(),
-                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
-            })
-            .await?;
+        let send_response = match send_withdrawal_funds_future.await {
+            Ok(send_response) => send_response,
+            Err(e) => {
+                // Roll back the withdrawal before telling the user it failed.
+                tx.rollback()
+                    .await
+                    .map_err(|_| "failed to roll back transaction.")?;
+                Notification::insert_for_connection(
+                    &mut **db,
+                    user_id,
+                    "withdrawal_failed",
+                    &format!("Withdrawal of {} sats failed", withdrawal.amount_sat),
+                    "/withdraw",
+                )
+                .await
+                .map_err(|_| "failed to insert notification.")?;
+                return Err(format!("failed to send withdrawal payment: {:?}", e));
+            }
+        };
 
-        Ok(case)
+        // Update the withdrawal row with the payment invoice hash.
+        let payment_hash_hex = util::to_hex(&send_response.payment_hash);
+        sqlx::query!(
+            "UPDATE withdrawals SET invoice_hash = ? WHERE id = ?",
+            payment_hash_hex,
+            new_withdrawal_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to update new withdrawal payment hash.")?;
+        Notification::insert_for_connection(
+            &mut *tx,
+            user_id,
+            "withdrawal_sent",
+            &format!("Withdrawal of {} sats sent", withdrawal.amount_sat),
+            &format!("/withdrawal/{}", withdrawal.public_id),
+        )
+        .await
+        .map_err(|_| "failed to insert notification.")?;
+        WebhookDelivery::enqueue_for_connection(
+            &mut *tx,
+            &[user_id],
+            "withdrawal_sent",
+            serde_json::json!({
+                "withdrawal_id": withdrawal.public_id,
+                "amount_sat": withdrawal.amount_sat,
+                "link": format!("/withdrawal/{}", withdrawal.public_id),
+            }),
+        )
+        .await
+        .map_err(|_| "failed to queue webhook delivery.")?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        Ok(new_withdrawal_id as _)
     }
 
     pub async fn single_by_public_id(
         db: &mut Connection<Db>,
         public_id: &str,
-    ) -> Result<Case, sqlx::Error> {
-        let case = sqlx::query!("select * from cases WHERE public_id = ?;", public_id)
-            .fetch_one(&mut **db)
-            .map_ok(|r| Case {
-                id: Some(r.id.try_into().unwrap()),
-                public_id: r.public_id,
-                quantity: r.quantity.try_into().unwrap(),
-                buyer_user_id: r.buyer_user_id.try_into().unwrap(),
-                seller_user_id: r.seller_user_id.try_into().unwrap(),
-                bounty_id: r.bounty_id.try_into().unwrap(),
-                case_details: markdown::to_html(&r.case_details),
-                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-                seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
-                paid: r.paid,
-                awarded: r.awarded,
-                canceled_by_seller: r.canceled_by_seller,
-                canceled_by_buyer: r.canceled_by_buyer,
-                invoice_hash: r.invoice_hash,
-                invoice_payment_request: r.invoice_payment_request,
-                created_time_ms: r.created_time_ms.try_into().unwrap(),
-                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
-            })
-            .await?;
-
-        Ok(case)
-    }
-
-    pub async fn single_by_invoice_hash(
-        db: &mut PoolConnection<Sqlite>,
-        invoice_hash: &str,
-    ) -> Result<Case, sqlx::Error> {
-        let case = sqlx::query!("select * from cases WHERE invoice_hash = ?;", invoice_hash)
+    ) -> Result<Withdrawal, sqlx::Error> {
+        let withdrawal = sqlx::query!("select * from withdrawals WHERE public_id = ?;", public_id)
             .fetch_one(&mut **db)
-            .map_ok(|r| Case {
+            .map_ok(|r| Withdrawal {
                 id: Some(r.id.try_into().unwrap()),
                 public_id: r.public_id,
-                quantity: r.quantity.try_into().unwrap(),
-                buyer_user_id: r.buyer_user_id.try_into().unwrap(),
-                seller_user_id: r.seller_user_id.try_into().unwrap(),
-                bounty_id: r.bounty_id.try_into().unwrap(),
-                case_details: markdown::to_html(&r.case_details),
-                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-                seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
-                paid: r.paid,
-                awarded: r.awarded,
-                canceled_by_seller: r.canceled_by_seller,
-                canceled_by_buyer: r.canceled_by_buyer,
+                user_id: r.user_id.try_into().unwrap(),
+                amount_sat: r.amount_sat.try_into().unwrap(),
                 invoice_hash: r.invoice_hash,
                 invoice_payment_request: r.invoice_payment_request,
                 created_time_ms: r.created_time_ms.try_into().unwrap(),
-                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
             })
             .await?;
 
-        Ok(case)
+        Ok(withdrawal)
     }
 
-    pub async fn all_older_than(
-        db: &mut PoolConnection<Sqlite>,
-        created_time_ms: u64,
-    ) -> Result<Vec<Case>, sqlx::Error> {
-        let created_time_ms_i64: i64 = created_time_ms.try_into().unwrap();
-
-        let cases = sqlx::query!(
-            "
-select *
-from
- cases
-WHERE
- created_time_ms < ?
-AND
- NOT paid
-;",
-            created_time_ms_i64,
-        )
-        .fetch(&mut **db)
-        .map_ok(|r| Case {
-            id: Some(r.id.try_into().unwrap()),
-            public_id: r.public_id,
-            quantity: r.quantity.try_into().unwrap(),
-            buyer_user_id: r.buyer_user_id.try_into().unwrap(),
-            seller_user_id: r.seller_user_id.try_into().unwrap(),
-            bounty_id: r.bounty_id.try_into().unwrap(),
-            case_details: markdown::to_html(&r.case_details),
-            amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-            seller_credit_sat: r.seller_credit_sat.try_into().unwrap(),
-            paid: r.paid,
-            awarded: r.awarded,
-            canceled_by_seller: r.canceled_by_seller,
-            canceled_by_buyer: r.canceled_by_buyer,
-            invoice_hash: r.invoice_hash,
-            invoice_payment_request: r.invoice_payment_request,
-            created_time_ms: r.created_time_ms.try_into().unwrap(),
-            payment_time_ms: r.payment_time_ms.try_into().unwrap(),
-        })
-        .try_collect::<Vec<_>>()
-        .await?;
-
-        Ok(cases)
-    }
+    //     pub async fn count_for_user_since_time_ms(
+    //         db: &mut Connection<Db>,
+    //         user_id: i32,
+    //         start_time_ms: u64,
+    //     ) -> Result<u32, sqlx::Error> {
+    //         let start_time_ms_i64: i64 = start_time_ms.try_into().unwrap();
 
-    pub async fn mark_as_paid(
-        db: &mut PoolConnection<Sqlite>,
-        case_id: i32,
-        time_now_ms: u64,
-    ) -> Result<(), sqlx::Error> {
-        let time_now_ms_i64: i64 = time_now_ms.try_into().unwrap();
+    //         let withdrawal_count = sqlx::query!(
+    //             "
+    // select count(id) as withdrawal_count from withdrawals
+    // WHERE
+    //  user_id = ?
+    // AND
+    //  created_time_ms > ?
+    // ORDER BY withdrawals.created_time_ms ASC;",
+    //             user_id,
+    //             start_time_ms_i64,
+    //         )
+    //         .fetch_one(&mut **db)
+    //         .map_ok(|r| r.withdrawal_count)
+    //         .await?;
 
-        sqlx::query!(
-            "UPDATE cases SET paid = true, payment_time_ms = ? WHERE id = ?",
-            time_now_ms_i64,
-            case_id,
-        )
-        .execute(&mut **db)
-        .await?;
+    //         Ok(withdrawal_count.try_into().unwrap())
+    //     }
+}
 
-        Ok(())
+impl FeeRevenueInfo {
+    pub async fn fee_revenue_info(db: &mut Connection<Db>) -> Result<FeeRevenueInfo, sqlx::Error> {
+        FeeRevenueInfo::fee_revenue_info_for_connection(&mut **db).await
     }
 
-    pub async fn mark_as_awarded(
-        db: &mut PoolConnection<Sqlite>,
-        case_id: i32,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
+    /// Fees are never credited to a user, so they are tracked separately
+    /// from the user liabilities.
+    async fn fee_revenue_info_for_connection(
+        conn: &mut SqliteConnection,
+    ) -> Result<FeeRevenueInfo, sqlx::Error> {
+        let total_fee_revenue_sat = sqlx::query(
             "
-UPDATE
+SELECT SUM(fee_sat) as total_fee_revenue_sat FROM
+(select (cases.amount_owed_sat - cases.seller_credit_sat) as fee_sat
+from
  cases
-SET
- awarded = true, case_details= ''
 WHERE
- id = ?
-AND
- paid
+ cases.paid
 AND
- not (awarded OR canceled_by_seller OR canceled_by_buyer)
+ cases.awarded
+UNION ALL
+select caseawards.fee_sat as fee_sat
+from
+ caseawards)
 ;",
-            case_id,
         )
-        .execute(&mut **db)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let amount_sat_i64: i64 = r.try_get("total_fee_revenue_sat").unwrap();
+            amount_sat_i64
+        })
         .await?;
 
-        Ok(())
+        let total_fee_withdrawals_sat = sqlx::query(
+            "
+SELECT SUM(feewithdrawals.amount_sat) as total_fee_withdrawals_sat
+FROM
+ feewithdrawals
+;",
+        )
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let amount_sat_i64: i64 = r.try_get("total_fee_withdrawals_sat").unwrap();
+            amount_sat_i64
+        })
+        .await?;
+
+        Ok(FeeRevenueInfo {
+            total_fee_revenue_sat,
+            total_fee_withdrawals_sat,
+            available_fee_revenue_sat: total_fee_revenue_sat - total_fee_withdrawals_sat,
+        })
     }
+}
 
-    pub async fn mark_as_canceled_by_seller(
-        db: &mut PoolConnection<Sqlite>,
-        case_id: i32,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
+impl FeeRevenuePeriod {
+    pub async fn all_by_day(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<FeeRevenuePeriod>, sqlx::Error> {
+        FeeRevenuePeriod::all_by_period(db, "%Y-%m-%d", page_size, page_num).await
+    }
+
+    pub async fn all_by_month(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<FeeRevenuePeriod>, sqlx::Error> {
+        FeeRevenuePeriod::all_by_period(db, "%Y-%m", page_size, page_num).await
+    }
+
+    async fn all_by_period(
+        db: &mut Connection<Db>,
+        period_format: &str,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<FeeRevenuePeriod>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let fee_revenue_periods = sqlx::query(
             "
-UPDATE
+SELECT strftime(?, event_time_ms / 1000, 'unixepoch') as period, SUM(fee_sat) as total_fee_sat, COUNT(*) as num_cases FROM
+(select (cases.amount_owed_sat - cases.seller_credit_sat) as fee_sat, cases.created_time_ms as event_time_ms
+from
  cases
-SET
- canceled_by_seller = true, case_details= ''
 WHERE
- id = ?
+ cases.paid
 AND
- not (awarded OR canceled_by_seller OR canceled_by_buyer)
+ cases.awarded
+UNION ALL
+select caseawards.fee_sat as fee_sat, caseawards.created_time_ms as event_time_ms
+from
+ caseawards)
+GROUP BY
+ period
+ORDER BY period DESC
+LIMIT ?
+OFFSET ?
 ;",
-            case_id,
         )
-        .execute(&mut **db)
+        .bind(period_format)
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| FeeRevenuePeriod {
+            period: r.try_get("period").unwrap(),
+            total_fee_sat: r.try_get("total_fee_sat").unwrap(),
+            num_cases: {
+                let num_i64: i64 = r.try_get("num_cases").unwrap();
+                num_i64 as u32
+            },
+        })
+        .try_collect::<Vec<_>>()
         .await?;
 
-        Ok(())
+        Ok(fee_revenue_periods)
     }
+}
 
-    pub async fn mark_as_canceled_by_buyer(
-        db: &mut PoolConnection<Sqlite>,
-        case_id: i32,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
+impl FeeRevenueBounty {
+    pub async fn all(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<FeeRevenueBounty>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let fee_revenue_bounties = sqlx::query(
             "
-UPDATE
+SELECT COALESCE(bounties.public_id, '') as bounty_public_id, COALESCE(bounties.title, '') as bounty_title, SUM(fee_sat) as total_fee_sat, COUNT(*) as num_cases FROM
+(select cases.bounty_id as bounty_id, (cases.amount_owed_sat - cases.seller_credit_sat) as fee_sat
+from
  cases
-SET
- canceled_by_buyer = true, case_details= ''
 WHERE
- id = ?
+ cases.paid
 AND
- not (awarded OR canceled_by_seller OR canceled_by_buyer)
+ cases.awarded
+UNION ALL
+select caseawards.bounty_id as bounty_id, caseawards.fee_sat as fee_sat
+from
+ caseawards) as fees
+LEFT JOIN
+ bounties
+ON
+ fees.bounty_id = bounties.id
+GROUP BY
+ fees.bounty_id
+ORDER BY total_fee_sat DESC
+LIMIT ?
+OFFSET ?
 ;",
-            case_id,
         )
-        .execute(&mut **db)
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| FeeRevenueBounty {
+            bounty_public_id: r.try_get("bounty_public_id").unwrap(),
+            bounty_title: r.try_get("bounty_title").unwrap(),
+            total_fee_sat: r.try_get("total_fee_sat").unwrap(),
+            num_cases: {
+                let num_i64: i64 = r.try_get("num_cases").unwrap();
+                num_i64 as u32
+            },
+        })
+        .try_collect::<Vec<_>>()
         .await?;
 
-        Ok(())
+        Ok(fee_revenue_bounties)
     }
+}
 
-    pub async fn delete_expired_case(
-        db: &mut PoolConnection<Sqlite>,
-        case_id: i32,
-        cancel_case_invoice_future: impl Future<
-            Output = Result<tonic_openssl_lnd::invoicesrpc::CancelInvoiceResp, String>,
+impl FeeWithdrawal {
+    pub async fn do_withdrawal(
+        fee_withdrawal: FeeWithdrawal,
+        db: &mut Connection<Db>,
+        send_withdrawal_funds_future: impl Future<
+            Output = Result<tonic_openssl_lnd::lnrpc::SendResponse, String>,
         >,
-    ) -> Result<(), String> {
+    ) -> Result<i32, String> {
         let mut tx = db
             .begin()
             .await
             .map_err(|_| "failed to begin transaction.")?;
 
-        sqlx::query!(
-            "
-DELETE FROM cases
-WHERE
- id = ?
-AND
- NOT paid
-;",
-            case_id,
+        // Insert the new fee withdrawal.
+        let amount_sat: i64 = fee_withdrawal.amount_sat.try_into().unwrap();
+        let created_time_ms: i64 = fee_withdrawal.created_time_ms.try_into().unwrap();
+        let insert_result = sqlx::query(
+            "INSERT INTO feewithdrawals (public_id, user_id, amount_sat, invoice_hash, invoice_payment_request, created_time_ms) VALUES (?, ?, ?, ?, ?, ?)",
         )
+        .bind(fee_withdrawal.public_id)
+        .bind(fee_withdrawal.user_id)
+        .bind(amount_sat)
+        .bind(fee_withdrawal.invoice_hash)
+        .bind(fee_withdrawal.invoice_payment_request)
+        .bind(created_time_ms)
         .execute(&mut *tx)
         .await
-        .map_err(|_| "failed to delete case from database.")?;
+        .map_err(|_| "failed to insert new fee withdrawal.")?;
+        let new_fee_withdrawal_id = insert_result.last_insert_rowid();
 
-        cancel_case_invoice_future
+        // Only fees that were actually earned may be withdrawn.
+        let fee_revenue_info = FeeRevenueInfo::fee_revenue_info_for_connection(&mut *tx)
             .await
-            .map_err(|e| format!("failed to cancel case invoice: {:?}", e))?;
+            .map_err(|_| "failed to get fee revenue info.")?;
 
-        tx.commit()
-            .await
-            .map_err(|_| "failed to begin transaction.")?;
+        if fee_revenue_info.available_fee_revenue_sat < 0 {
+            return Err("Insufficient fee revenue for withdrawal.".to_string());
+        }
 
-        Ok(())
-    }
+        let send_response = send_withdrawal_funds_future
+            .await
+            .map_err(|e| format!("failed to send fee withdrawal payment: {:?}", e))?;
 
-    pub async fn seller_info_for_user(
-        db: &mut Connection<Db>,
-        user_id: i32,
-    ) -> Result<SellerInfo, sqlx::Error> {
-        let total_amount_sold_sat = sqlx::query(
-            "
-            select
-             SUM(cases.amount_owed_sat) as total_amount_sold_sat
-            FROM
-             cases
-            WHERE
-             cases.awarded
-            AND
-             cases.seller_user_id = ?
-            GROUP BY
-             cases.seller_user_id
-            ;",
-        )
-        .bind(user_id)
-        .fetch_optional(&mut **db)
-        .map_ok(|maybe_r| {
-            maybe_r.map(|r| {
-                let amount_sat_i64: i64 = r.try_get("total_amount_sold_sat").unwrap();
-                let amount_sat_u64: u64 = amount_sat_i64.try_into().unwrap();
-                amount_sat_u64
-            })
-        })
-        .await?;
+        // Update the fee withdrawal row with the payment invoice hash.
+        let payment_hash_hex = util::to_hex(&send_response.payment_hash);
+        sqlx::query("UPDATE feewithdrawals SET invoice_hash = ? WHERE id = ?")
+            .bind(payment_hash_hex)
+            .bind(new_fee_withdrawal_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to update new fee withdrawal payment hash.")?;
 
-        let seller_info = SellerInfo {
-            username: "".to_string(),
-            total_amount_sold_sat: total_amount_sold_sat.unwrap_or(0),
-        };
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
 
-        // TODO: remove option from return type.
-        Ok(seller_info)
+        Ok(new_fee_withdrawal_id as _)
     }
 
-    pub async fn seller_info_for_all_users(
+    pub async fn all(
         db: &mut Connection<Db>,
         page_size: u32,
         page_num: u32,
-    ) -> Result<Vec<SellerInfo>, sqlx::Error> {
+    ) -> Result<Vec<FeeWithdrawal>, sqlx::Error> {
         let offset = (page_num - 1) * page_size;
         let limit = page_size;
-        let seller_infos = sqlx::query(
-                    "
-        SELECT total_amount_sold_sat, users.email
-        FROM
-         users
-        LEFT JOIN
-            (select
-             SUM(cases.amount_owed_sat) as total_amount_sold_sat, cases.seller_user_id as awarded_seller_user_id
-            FROM
-             cases
-            WHERE
-             awarded 
-            GROUP BY
-             cases.seller_user_id) as seller_infos
-        ON
-         users.id = seller_infos.awarded_seller_user_id
-        WHERE
-         total_amount_sold_sat > 0
-        ORDER BY
-         total_amount_sold_sat DESC
-        LIMIT ?
-        OFFSET ?
-            ;")
-            .bind(limit)
-            .bind(offset)
-            .fetch(&mut **db)
-            .map_ok(|r| {
-                SellerInfo {
-                    username: r.try_get("email").unwrap(),
-                    total_amount_sold_sat: {
-                        let amount_sat_i64: i64 = r.try_get("total_amount_sold_sat").unwrap();
-                        let amount_sat_u64: u64 = amount_sat_i64.try_into().unwrap();
-                        amount_sat_u64
-                    },
-                }})
-            .try_collect::<Vec<_>>()
-            .await?;
-
-        Ok(seller_infos)
-    }
-
-    // TODO: implement this.
-    pub async fn most_recent_paid_case(
-        db: &mut PoolConnection<Sqlite>,
-    ) -> Result<Option<String>, sqlx::Error> {
-        let latest_paid_case_invoice_hash = sqlx::query!(
+        let fee_withdrawals = sqlx::query(
             "
-SELECT
- invoice_hash
-FROM
-(SELECT invoice_hash, payment_time_ms FROM useraccounts
- UNION ALL
-SELECT invoice_hash, payment_time_ms FROM cases)
-WHERE
- payment_time_ms = (SELECT MAX(payment_time_ms) FROM
-(SELECT invoice_hash, payment_time_ms FROM useraccounts
- UNION ALL
-SELECT invoice_hash, payment_time_ms FROM cases))
-LIMIT 1
-;"
+select *
+from
+ feewithdrawals
+ORDER BY feewithdrawals.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;",
         )
-        .fetch_optional(&mut **db)
-        .map_ok(|maybe_r| maybe_r.map(|r| r.invoice_hash))
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| FeeWithdrawal {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            amount_sat: {
+                let amount_sat_i64: i64 = r.try_get("amount_sat").unwrap();
+                amount_sat_i64.try_into().unwrap()
+            },
+            invoice_hash: r.try_get("invoice_hash").unwrap(),
+            invoice_payment_request: r.try_get("invoice_payment_request").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
         .await?;
 
-        Ok(latest_paid_case_invoice_hash)
+        Ok(fee_withdrawals)
     }
+}
 
-    pub async fn num_processing_for_user(
+impl LedgerEntry {
+    pub async fn all_for_user(
         db: &mut Connection<Db>,
         user_id: i32,
-    ) -> Result<u32, sqlx::Error> {
-        let num_cases = sqlx::query!(
-            "
-select
- COUNT(cases.id) as num_processing_cases
+        start_time_ms: i64,
+        end_time_ms: i64,
+        maybe_event_type: Option<String>,
+    ) -> Result<Vec<LedgerEntry>, sqlx::Error> {
+        let ledger_entries = sqlx::query("
+SELECT ledger.*, users.email as username FROM
+(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
 from
  cases
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
 WHERE
  cases.paid
 AND
- not (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+ cases.awarded
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ cases
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+WHERE
+ cases.paid
 AND
- cases.seller_user_id = ?
-;",
-            user_id,
-        )
-        .fetch_one(&mut **db)
-        .map_ok(|r| r.num_processing_cases as u32)
-        .await?;
+ (cases.canceled_by_seller OR cases.canceled_by_buyer)
+UNION ALL
+select caseawards.winner_user_id as user_id, caseawards.credit_sat as amount_change_sat, 'awarded_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ caseawards.bounty_id = bounties.id
+UNION ALL
+select caseawards.poster_user_id as user_id, (0 - caseawards.amount_sat) as amount_change_sat, 'paid_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ caseawards.bounty_id = bounties.id
+UNION ALL
+select bountyclaims.user_id as user_id, (0 - bountyclaims.stake_sat) as amount_change_sat, 'claim_stake' as event_type, bountyclaims.public_id as event_id, bountyclaims.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, NULL as case_public_id
+from
+ bountyclaims
+LEFT JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+UNION ALL
+select bountyclaims.user_id as user_id, bountyclaims.stake_sat as amount_change_sat, 'claim_stake_returned' as event_type, bountyclaims.public_id as event_id, bountyclaims.released_time_ms as event_time_ms, bounties.public_id as bounty_public_id, NULL as case_public_id
+from
+ bountyclaims
+LEFT JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+WHERE
+ bountyclaims.released
+UNION ALL
+select cases.seller_user_id as user_id, (0 - casedisputes.reserved_sat) as amount_change_sat, 'dispute_reserve' as event_type, casedisputes.public_id as event_id, casedisputes.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+UNION ALL
+select cases.seller_user_id as user_id, casedisputes.reserved_sat as amount_change_sat, 'dispute_reserve_released' as event_type, casedisputes.public_id as event_id, casedisputes.resolved_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+WHERE
+ casedisputes.resolved
+UNION ALL
+select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms, NULL as bounty_public_id, NULL as case_public_id
+from
+ withdrawals) as ledger
+LEFT JOIN
+ users
+ON
+ ledger.user_id = users.id
+WHERE
+ ledger.user_id = ?
+AND
+ ledger.event_time_ms >= ?
+AND
+ ledger.event_time_ms < ?
+AND
+ (? IS NULL OR ledger.event_type = ?)
+ORDER BY ledger.event_time_ms ASC
+;")
+            .bind(user_id)
+            .bind(start_time_ms)
+            .bind(end_time_ms)
+            .bind(maybe_event_type.clone())
+            .bind(maybe_event_type)
+            .fetch(&mut **db)
+            .map_ok(|r| LedgerEntry::from_row(&r))
+            .try_collect::<Vec<_>>()
+            .await?;
 
-        Ok(num_cases)
+        Ok(ledger_entries)
     }
-}
 
-impl CaseCard {
-    pub async fn all_unpaid_for_user(
+    pub async fn all(
         db: &mut Connection<Db>,
-        user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<CaseCard>, sqlx::Error> {
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let cases = sqlx::query!(
-            "
-select
- cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+        start_time_ms: i64,
+        end_time_ms: i64,
+        maybe_event_type: Option<String>,
+    ) -> Result<Vec<LedgerEntry>, sqlx::Error> {
+        let ledger_entries = sqlx::query("
+SELECT ledger.*, users.email as username FROM
+(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
 from
  cases
 LEFT JOIN
  bounties
 ON
  cases.bounty_id = bounties.id
+WHERE
+ cases.paid
+AND
+ cases.awarded
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ cases
 LEFT JOIN
- bountyimages
+ bounties
 ON
- bounties.id = bountyimages.bounty_id
+ cases.bounty_id = bounties.id
+WHERE
+ cases.paid
 AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
+ (cases.canceled_by_seller OR cases.canceled_by_buyer)
+UNION ALL
+select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'processing_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ cases
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+WHERE
+ cases.paid
+AND
+ NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+UNION ALL
+select caseawards.winner_user_id as user_id, caseawards.credit_sat as amount_change_sat, 'awarded_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ caseawards.bounty_id = bounties.id
+UNION ALL
+select caseawards.poster_user_id as user_id, (0 - caseawards.amount_sat) as amount_change_sat, 'paid_reward' as event_type, cases.public_id as event_id, caseawards.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ caseawards
+JOIN
+ cases
+ON
+ caseawards.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ caseawards.bounty_id = bounties.id
+UNION ALL
+select bountyclaims.user_id as user_id, (0 - bountyclaims.stake_sat) as amount_change_sat, 'claim_stake' as event_type, bountyclaims.public_id as event_id, bountyclaims.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, NULL as case_public_id
+from
+ bountyclaims
+LEFT JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+UNION ALL
+select bountyclaims.user_id as user_id, bountyclaims.stake_sat as amount_change_sat, 'claim_stake_returned' as event_type, bountyclaims.public_id as event_id, bountyclaims.released_time_ms as event_time_ms, bounties.public_id as bounty_public_id, NULL as case_public_id
+from
+ bountyclaims
+LEFT JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
+WHERE
+ bountyclaims.released
+UNION ALL
+select cases.seller_user_id as user_id, (0 - casedisputes.reserved_sat) as amount_change_sat, 'dispute_reserve' as event_type, casedisputes.public_id as event_id, casedisputes.created_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+UNION ALL
+select cases.seller_user_id as user_id, casedisputes.reserved_sat as amount_change_sat, 'dispute_reserve_released' as event_type, casedisputes.public_id as event_id, casedisputes.resolved_time_ms as event_time_ms, bounties.public_id as bounty_public_id, cases.public_id as case_public_id
+from
+ casedisputes
+JOIN
+ cases
+ON
+ casedisputes.case_id = cases.id
+LEFT JOIN
+ bounties
+ON
+ cases.bounty_id = bounties.id
+WHERE
+ casedisputes.resolved
+UNION ALL
+select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms, NULL as bounty_public_id, NULL as case_public_id
+from
+ withdrawals
+UNION ALL
+select useraccounts.user_id as user_id, useraccounts.amount_owed_sat as amount_change_sat, 'user_activation' as event_type, useraccounts.public_id as event_id, useraccounts.created_time_ms as event_time_ms, NULL as bounty_public_id, NULL as case_public_id
+from
+ useraccounts
+WHERE
+ useraccounts.paid) as ledger
 LEFT JOIN
  users
 ON
- bounties.user_id = users.id
+ ledger.user_id = users.id
 WHERE
- not cases.paid
+ ledger.event_time_ms >= ?
 AND
- case_buyer_user_id = ?
-GROUP BY
- cases.id
-ORDER BY cases.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;",
-            user_id,
-            limit,
-            offset,
-        )
+ ledger.event_time_ms < ?
+AND
+ (? IS NULL OR ledger.event_type = ?)
+ORDER BY ledger.event_time_ms ASC
+;")
+            .bind(start_time_ms)
+            .bind(end_time_ms)
+            .bind(maybe_event_type.clone())
+            .bind(maybe_event_type)
             .fetch(&mut **db)
-            .map_ok(|r| {
-                let o = Case {
-                    id: Some(r.case_id.unwrap().try_into().unwrap()),
-                    public_id: r.case_public_id.unwrap(),
-                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
-                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
-                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
-                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
-                    case_details: r.case_details.unwrap(),
-                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
-                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
-                    paid: r.paid.unwrap(),
-                    awarded: r.awarded.unwrap(),
-                    canceled_by_seller: r.canceled_by_seller.unwrap(),
-                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
-                    invoice_hash: r.invoice_hash.unwrap(),
-                    invoice_payment_request: r.invoice_payment_request.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
-                };
-                let l = r.id.map(|bounty_id| Bounty {
-                    id: Some(bounty_id.try_into().unwrap()),
-                    public_id: r.bounty_public_id.unwrap(),
-                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
-                });
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                CaseCard {
-                    case: o,
-                    bounty: l,
-                    image: i,
-                    user: u,
-                }
-            })
+            .map_ok(|r| LedgerEntry::from_row(&r))
             .try_collect::<Vec<_>>()
             .await?;
 
-        Ok(cases)
+        Ok(ledger_entries)
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> LedgerEntry {
+        let time_ms_i64: i64 = r.try_get("event_time_ms").unwrap();
+        LedgerEntry {
+            event_time_ms: time_ms_i64 as u64,
+            event_date: util::date_string_from_millis(time_ms_i64 as u64),
+            event_type: r.try_get("event_type").unwrap(),
+            event_id: r.try_get("event_id").unwrap(),
+            user_id: r.try_get("user_id").unwrap(),
+            username: r.try_get("username").unwrap(),
+            amount_change_sat: r.try_get("amount_change_sat").unwrap(),
+            bounty_public_id: r.try_get("bounty_public_id").unwrap(),
+            case_public_id: r.try_get("case_public_id").unwrap(),
+        }
+    }
+}
+
+impl SolvencySnapshot {
+    /// Returns the id of the inserted row.
+    pub async fn insert(
+        snapshot: SolvencySnapshot,
+        db: &mut PoolConnection<Sqlite>,
+    ) -> Result<i32, sqlx::Error> {
+        let created_time_ms: i64 = snapshot.created_time_ms.try_into().unwrap();
+
+        let insert_result = sqlx::query(
+            "INSERT INTO solvencysnapshots (public_id, channel_balance_sat, onchain_balance_sat, user_liabilities_sat, held_escrow_sat, coverage_basis_points, below_threshold, message, signature, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(snapshot.public_id)
+        .bind(snapshot.channel_balance_sat)
+        .bind(snapshot.onchain_balance_sat)
+        .bind(snapshot.user_liabilities_sat)
+        .bind(snapshot.held_escrow_sat)
+        .bind(snapshot.coverage_basis_points)
+        .bind(snapshot.below_threshold)
+        .bind(snapshot.message)
+        .bind(snapshot.signature)
+        .bind(created_time_ms)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(insert_result.last_insert_rowid() as _)
     }
 
-    pub async fn all_paid_for_user(
+    pub async fn most_recent(
+        db: &mut Connection<Db>,
+    ) -> Result<Option<SolvencySnapshot>, sqlx::Error> {
+        let maybe_snapshot = sqlx::query(
+            "
+select *
+from
+ solvencysnapshots
+ORDER BY solvencysnapshots.created_time_ms DESC
+LIMIT 1
+;",
+        )
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| maybe_r.map(|r| SolvencySnapshot::from_row(&r)))
+        .await?;
+
+        Ok(maybe_snapshot)
+    }
+
+    pub async fn all(
         db: &mut Connection<Db>,
-        user_id: i32,
         page_size: u32,
         page_num: u32,
-    ) -> Result<Vec<CaseCard>, sqlx::Error> {
+    ) -> Result<Vec<SolvencySnapshot>, sqlx::Error> {
         let offset = (page_num - 1) * page_size;
         let limit = page_size;
-        let cases = sqlx::query!(
+        let snapshots = sqlx::query(
             "
-select
- cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+select *
 from
- cases
-LEFT JOIN
- bounties
-ON
- cases.bounty_id = bounties.id
-LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-LEFT JOIN
- users
-ON
- bounties.user_id = users.id
-WHERE
- cases.paid
-AND
- case_buyer_user_id = ?
-GROUP BY
- cases.id
-ORDER BY cases.payment_time_ms DESC
+ solvencysnapshots
+ORDER BY solvencysnapshots.created_time_ms DESC
 LIMIT ?
 OFFSET ?
 ;",
-            user_id,
-            limit,
-            offset,
         )
-            .fetch(&mut **db)
-            .map_ok(|r| {
-                let o = Case {
-                    id: Some(r.case_id.unwrap().try_into().unwrap()),
-                    public_id: r.case_public_id.unwrap(),
-                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
-                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
-                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
-                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
-                    case_details: r.case_details.unwrap(),
-                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
-                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
-                    paid: r.paid.unwrap(),
-                    awarded: r.awarded.unwrap(),
-                    canceled_by_seller: r.canceled_by_seller.unwrap(),
-                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
-                    invoice_hash: r.invoice_hash.unwrap(),
-                    invoice_payment_request: r.invoice_payment_request.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
-                };
-                let l = r.id.map(|bounty_id| Bounty {
-                    id: Some(bounty_id.try_into().unwrap()),
-                    public_id: r.bounty_public_id.unwrap(),
-                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
-                });
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                CaseCard {
-                    case: o,
-                    bounty: l,
-                    image: i,
-                    user: u,
-                }
+        .bind(limit)
+        .bind(offset)
+        .fetch(&mut **db)
+        .map_ok(|r| SolvencySnapshot::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(snapshots)
+    }
+
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> SolvencySnapshot {
+        SolvencySnapshot {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            channel_balance_sat: r.try_get("channel_balance_sat").unwrap(),
+            onchain_balance_sat: r.try_get("onchain_balance_sat").unwrap(),
+            user_liabilities_sat: r.try_get("user_liabilities_sat").unwrap(),
+            held_escrow_sat: r.try_get("held_escrow_sat").unwrap(),
+            coverage_basis_points: r.try_get("coverage_basis_points").unwrap(),
+            below_threshold: r.try_get("below_threshold").unwrap(),
+            message: r.try_get("message").unwrap(),
+            signature: r.try_get("signature").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
+    }
+}
+
+impl AdminInfo {
+    pub async fn admin_info(db: &mut Connection<Db>) -> Result<AdminInfo, sqlx::Error> {
+        let num_pending_bounties = Bounty::num_pending(db).await?;
+        let solvency_alert = SolvencySnapshot::most_recent(db)
+            .await?
+            .map(|snapshot| snapshot.below_threshold)
+            .unwrap_or(false);
+        Ok(AdminInfo {
+            num_pending_bounties,
+            solvency_alert,
+        })
+    }
+}
+
+impl UserAccount {
+    /// Returns the id of the inserted row.
+    pub async fn insert(
+        user_account: UserAccount,
+        db: &mut Connection<Db>,
+    ) -> Result<i32, sqlx::Error> {
+        let amount_owed_sat: i64 = user_account.amount_owed_sat.try_into().unwrap();
+        let created_time_ms: i64 = user_account.created_time_ms.try_into().unwrap();
+        let payment_time_ms: i64 = user_account.payment_time_ms.try_into().unwrap();
+
+        let insert_result = sqlx::query!(
+            "INSERT INTO useraccounts (public_id, user_id, amount_owed_sat, paid, disabled, invoice_payment_request, invoice_hash, created_time_ms, payment_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            user_account.public_id,
+            user_account.user_id,
+            amount_owed_sat,
+            user_account.paid,
+            user_account.disabled,
+            user_account.invoice_payment_request,
+            user_account.invoice_hash,
+            created_time_ms,
+            payment_time_ms,
+        )
+            .execute(&mut **db)
+            .await?;
+
+        Ok(insert_result.last_insert_rowid() as _)
+    }
+
+    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<UserAccount, sqlx::Error> {
+        let user_account = sqlx::query!("select * from useraccounts WHERE user_id = ?;", id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| UserAccount {
+                id: Some(r.id.try_into().unwrap()),
+                public_id: r.public_id,
+                user_id: r.user_id.try_into().unwrap(),
+                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+                paid: r.paid,
+                disabled: r.disabled,
+                invoice_payment_request: r.invoice_payment_request,
+                invoice_hash: r.invoice_hash,
+                created_time_ms: r.created_time_ms.try_into().unwrap(),
+                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
             })
-            .try_collect::<Vec<_>>()
             .await?;
 
-        Ok(cases)
+        Ok(user_account)
+    }
+
+    pub async fn single_by_public_id(
+        db: &mut Connection<Db>,
+        public_id: &str,
+    ) -> Result<UserAccount, sqlx::Error> {
+        let user_account =
+            sqlx::query!("select * from useraccounts WHERE public_id = ?;", public_id)
+                .fetch_one(&mut **db)
+                .map_ok(|r| UserAccount {
+                    id: Some(r.id.try_into().unwrap()),
+                    public_id: r.public_id,
+                    user_id: r.user_id.try_into().unwrap(),
+                    amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+                    paid: r.paid,
+                    disabled: r.disabled,
+                    invoice_payment_request: r.invoice_payment_request,
+                    invoice_hash: r.invoice_hash,
+                    created_time_ms: r.created_time_ms.try_into().unwrap(),
+                    payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+                })
+                .await?;
+
+        Ok(user_account)
+    }
+
+    pub async fn single_by_invoice_hash(
+        db: &mut PoolConnection<Sqlite>,
+        invoice_hash: &str,
+    ) -> Result<UserAccount, sqlx::Error> {
+        let user_account = sqlx::query!(
+            "select * from useraccounts WHERE invoice_hash = ?;",
+            invoice_hash
+        )
+        .fetch_one(&mut **db)
+        .map_ok(|r| UserAccount {
+            id: Some(r.id.try_into().unwrap()),
+            public_id: r.public_id,
+            user_id: r.user_id.try_into().unwrap(),
+            amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+            paid: r.paid,
+            disabled: r.disabled,
+            invoice_payment_request: r.invoice_payment_request,
+            invoice_hash: r.invoice_hash,
+            created_time_ms: r.created_time_ms.try_into().unwrap(),
+            payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+        })
+        .await?;
+
+        Ok(user_account)
+    }
+
+    pub async fn mark_as_paid(
+        db: &mut PoolConnection<Sqlite>,
+        user_account_id: i32,
+        time_now_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let time_now_ms_i64: i64 = time_now_ms.try_into().unwrap();
+
+        sqlx::query!(
+            "UPDATE useraccounts SET paid = true, payment_time_ms = ? WHERE id = ?",
+            time_now_ms_i64,
+            user_account_id,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_as_disabled(
+        db: &mut PoolConnection<Sqlite>,
+        user_id: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE useraccounts SET disabled = true WHERE user_id = ?",
+            user_id,
+        )
+        .execute(&mut **db)
+        .await?;
+        let username: String = sqlx::query("select email from users WHERE id = ?;")
+            .bind(user_id)
+            .fetch_one(&mut **db)
+            .map_ok(|r| r.try_get("email").unwrap())
+            .await?;
+        // Only webhooks that receive all market events are told about disabled users.
+        WebhookDelivery::enqueue_for_connection(
+            &mut **db,
+            &[],
+            "user_disabled",
+            serde_json::json!({
+                "username": username,
+                "link": format!("/user/{}", username),
+            }),
+        )
+        .await?;
+
+        Ok(())
     }
 
-    pub async fn all_received_for_user(
-        db: &mut Connection<Db>,
+    pub async fn mark_as_enabled(
+        db: &mut PoolConnection<Sqlite>,
         user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<CaseCard>, sqlx::Error> {
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let cases = sqlx::query!(
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE useraccounts SET disabled = false WHERE user_id = ?",
+            user_id,
+        )
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn number_of_users(db: &mut Connection<Db>) -> Result<u64, sqlx::Error> {
+        let num_users = sqlx::query!(
             "
 select
- cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+ COUNT(users.id) as num_users
 from
- cases
-LEFT JOIN
- bounties
-ON
- cases.bounty_id = bounties.id
-LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-LEFT JOIN
  users
-ON
- bounties.user_id = users.id
 WHERE
- cases.awarded
-AND
- case_seller_user_id = ?
-GROUP BY
- cases.id
-ORDER BY cases.payment_time_ms DESC
-LIMIT ?
-OFFSET ?
+ NOT users.is_admin
 ;",
-            user_id,
-            limit,
-            offset,
         )
-            .fetch(&mut **db)
-            .map_ok(|r| {
-                let o = Case {
-                    id: Some(r.case_id.unwrap().try_into().unwrap()),
-                    public_id: r.case_public_id.unwrap(),
-                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
-                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
-                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
-                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
-                    case_details: r.case_details.unwrap(),
-                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
-                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
-                    paid: r.paid.unwrap(),
-                    awarded: r.awarded.unwrap(),
-                    canceled_by_seller: r.canceled_by_seller.unwrap(),
-                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
-                    invoice_hash: r.invoice_hash.unwrap(),
-                    invoice_payment_request: r.invoice_payment_request.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
-                };
-                let l = r.id.map(|bounty_id| Bounty {
-                    id: Some(bounty_id.try_into().unwrap()),
-                    public_id: r.bounty_public_id.unwrap(),
-                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
-                });
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                CaseCard {
-                    case: o,
-                    bounty: l,
-                    image: i,
-                    user: u,
-                }
-            })
-            .try_collect::<Vec<_>>()
-            .await?;
+        .fetch_one(&mut **db)
+        .map_ok(|r| r.num_users as u64)
+        .await?;
 
-        Ok(cases)
+        Ok(num_users)
     }
 
-    pub async fn all_processing_for_user(
-        db: &mut Connection<Db>,
-        user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<CaseCard>, sqlx::Error> {
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let cases = sqlx::query!(
+    pub async fn all_older_than(
+        db: &mut PoolConnection<Sqlite>,
+        created_time_ms: u64,
+    ) -> Result<Vec<UserAccount>, sqlx::Error> {
+        let created_time_ms_i64: i64 = created_time_ms.try_into().unwrap();
+
+        let user_accounts = sqlx::query!(
             "
-select
- cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id as case_buyer_user_id, cases.seller_user_id as case_seller_user_id, cases.quantity as case_quantity, cases.bounty_id as case_bounty_id, cases.case_details, cases.amount_owed_sat, cases.seller_credit_sat, cases.paid, cases.awarded, cases.canceled_by_seller, cases.canceled_by_buyer, cases.invoice_hash, cases.invoice_payment_request, cases.created_time_ms, cases.payment_time_ms, bounties.id, bounties.public_id as bounty_public_id, bounties.user_id as bounty_user_id, bounties.title, bounties.description, bounties.price_sat, bounties.fee_rate_basis_points, bounties.submitted, bounties.viewed, bounties.approved, bounties.deactivated_by_seller, bounties.deactivated_by_admin, bounties.created_time_ms as bounty_created_time_ms, bountyimages.id as image_id, bountyimages.public_id as image_public_id, bountyimages.bounty_id, bountyimages.image_data, bountyimages.is_primary, users.id as rocket_auth_user_id, users.email as rocket_auth_user_username
+select *
 from
- cases
-LEFT JOIN
- bounties
-ON
- cases.bounty_id = bounties.id
-LEFT JOIN
- bountyimages
-ON
- bounties.id = bountyimages.bounty_id
-AND
- bountyimages.is_primary = (SELECT MAX(is_primary) FROM bountyimages WHERE bounty_id = bounties.id)
-LEFT JOIN
- users
-ON
- bounties.user_id = users.id
+ useraccounts
 WHERE
- cases.paid
-AND
- not (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+ created_time_ms < ?
 AND
- cases.seller_user_id = ?
-GROUP BY
- cases.id
-ORDER BY cases.payment_time_ms DESC
-LIMIT ?
-OFFSET ?
+ NOT paid
 ;",
-            user_id,
-            limit,
-            offset,
+            created_time_ms_i64,
         )
-            .fetch(&mut **db)
-            .map_ok(|r| {
+        .fetch(&mut **db)
+        .map_ok(|r| UserAccount {
+            id: Some(r.id.try_into().unwrap()),
+            public_id: r.public_id,
+            user_id: r.user_id.try_into().unwrap(),
+            amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
+            paid: r.paid,
+            disabled: r.disabled,
+            invoice_payment_request: r.invoice_payment_request,
+            invoice_hash: r.invoice_hash,
+            created_time_ms: r.created_time_ms.try_into().unwrap(),
+            payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
 
-                let o = Case {
-                    id: Some(r.case_id.unwrap().try_into().unwrap()),
-                    public_id: r.case_public_id.unwrap(),
-                    quantity: r.case_quantity.unwrap().try_into().unwrap(),
-                    buyer_user_id: r.case_buyer_user_id.unwrap().try_into().unwrap(),
-                    seller_user_id: r.case_seller_user_id.unwrap().try_into().unwrap(),
-                    bounty_id: r.case_bounty_id.unwrap().try_into().unwrap(),
-                    case_details: r.case_details.unwrap(),
-                    amount_owed_sat: r.amount_owed_sat.unwrap().try_into().unwrap(),
-                    seller_credit_sat: r.seller_credit_sat.unwrap().try_into().unwrap(),
-                    paid: r.paid.unwrap(),
-                    awarded: r.awarded.unwrap(),
-                    canceled_by_seller: r.canceled_by_seller.unwrap(),
-                    canceled_by_buyer: r.canceled_by_buyer.unwrap(),
-                    invoice_hash: r.invoice_hash.unwrap(),
-                    invoice_payment_request: r.invoice_payment_request.unwrap(),
-                    created_time_ms: r.created_time_ms.unwrap().try_into().unwrap(),
-                    payment_time_ms: r.payment_time_ms.unwrap().try_into().unwrap(),
-                };
-                let l = r.id.map(|bounty_id| Bounty {
-                    id: Some(bounty_id.try_into().unwrap()),
-                    public_id: r.bounty_public_id.unwrap(),
-                    user_id: r.bounty_user_id.unwrap().try_into().unwrap(),
-                    title: r.title.unwrap(),
-                    description: r.description.unwrap(),
-                    price_sat: r.price_sat.unwrap().try_into().unwrap(),
-                    fee_rate_basis_points: r.fee_rate_basis_points.unwrap().try_into().unwrap(),
-                    submitted: r.submitted.unwrap(),
-                    viewed: r.viewed.unwrap(),
-                    approved: r.approved.unwrap(),
-                    deactivated_by_seller: r.deactivated_by_seller.unwrap(),
-                    deactivated_by_admin: r.deactivated_by_admin.unwrap(),
-                    created_time_ms: r.bounty_created_time_ms.unwrap().try_into().unwrap(),
-                });
-                let i = r.image_id.map(|image_id| BountyImage {
-                    id: Some(image_id.try_into().unwrap()),
-                    public_id: r.image_public_id.unwrap(),
-                    bounty_id: r.bounty_id.unwrap().try_into().unwrap(),
-                    image_data: r.image_data.unwrap(),
-                    is_primary: r.is_primary.unwrap(),
-                });
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                CaseCard {
-                    case: o,
-                    bounty: l,
-                    image: i,
-                    user: u,
-                }
+        Ok(user_accounts)
+    }
+
+    pub async fn delete_expired_user_account(
+        db: &mut PoolConnection<Sqlite>,
+        user_account_id: i32,
+        cancel_user_account_invoice_future: impl Future<
+            Output = Result<tonic_openssl_lnd::invoicesrpc::CancelInvoiceResp, String>,
+        >,
+    ) -> Result<(), String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        sqlx::query!(
+            "
+DELETE FROM useraccounts
+WHERE
+ user_id = ?
+AND
+ NOT paid
+;",
+            user_account_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to delete user account from database.")?;
 
-            })
-            .try_collect::<Vec<_>>()
-            .await?;
+        sqlx::query!(
+            "
+DELETE FROM users
+WHERE
+ id = ?
+;",
+            user_account_id,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to delete user from database.")?;
 
-        Ok(cases)
-    }
-}
+        cancel_user_account_invoice_future
+            .await
+            .map_err(|e| format!("failed to cancel user account invoice: {:?}", e))?;
 
-impl AccountInfo {
-    pub async fn account_info_for_user(
-        db: &mut Connection<Db>,
-        user_id: i32,
-    ) -> Result<AccountInfo, sqlx::Error> {
-        let account_balance_sat = AccountInfo::total_account_balance_for_user(db, user_id).await?;
-        let account_balance_sat_text = util::short_num_format(account_balance_sat.try_into().unwrap());
-        let num_unawarded_cases = Case::num_processing_for_user(db, user_id).await?;
-        Ok(AccountInfo {
-            account_balance_sat,
-            account_balance_sat_text,
-            num_unawarded_cases,
-        })
+        tx.commit()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        Ok(())
     }
 
-    pub async fn all_account_balance_changes_for_user(
-        db: &mut Connection<Db>,
-        user_id: i32,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<AccountBalanceChange>, sqlx::Error> {
-        // TODO: Case by event time in SQL query. When this is fixed: https://github.com/launchbadge/sqlx/issues/1350
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let account_balance_changes = sqlx::query("
-SELECT * FROM
-(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-from
- cases
+    pub async fn delete_users_with_no_account(
+        db: &mut PoolConnection<Sqlite>,
+    ) -> Result<(), String> {
+        // Deactivated users have no account, but are kept.
+        sqlx::query(
+            "
+DELETE FROM users
 WHERE
- cases.paid
-AND
- cases.awarded
+ NOT is_admin
 AND
- cases.seller_user_id = ?
-UNION ALL
-select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-from
- cases
+ id IN
+(SELECT users.id FROM users
+LEFT JOIN
+ useraccounts
+ON
+ users.id=useraccounts.user_id
 WHERE
- cases.paid
-AND
- (cases.canceled_by_seller OR cases.canceled_by_buyer)
+ useraccounts.user_id IS NULL)
 AND
- cases.buyer_user_id = ?
-UNION ALL
-select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
-from
- withdrawals
-WHERE
- withdrawals.user_id = ?)
-ORDER BY event_time_ms DESC
-LIMIT ?
-OFFSET ?
-;")
-            .bind(user_id)
-            .bind(user_id)
-            .bind(user_id)
-            .bind(limit)
-            .bind(offset)
-            .fetch(&mut **db)
-            .map_ok(|r| AccountBalanceChange {
-                amount_change_sat: r.try_get("amount_change_sat").unwrap(),
-                event_type: r.try_get("event_type").unwrap(),
-                event_id: r.try_get("event_id").unwrap(),
-                event_time_ms: {
-                    let time_ms_i64: i64 = r.try_get("event_time_ms").unwrap();
-                    time_ms_i64 as u64
-                },
-            })
-            .try_collect::<Vec<_>>()
-            .await?;
+ id NOT IN
+(SELECT deactivatedaccounts.user_id FROM deactivatedaccounts);
+;",
+        )
+        .execute(&mut **db)
+        .await
+        .map_err(|_| "failed to delete user account from database.")?;
 
-        Ok(account_balance_changes)
+        Ok(())
     }
 
-    pub async fn total_account_balance_for_user(
+    pub async fn deactivation_info(
+        db: &mut Connection<Db>,
+        user_account: &UserAccount,
+    ) -> Result<DeactivationInfo, sqlx::Error> {
+        UserAccount::deactivation_info_for_connection(&mut **db, user_account).await
+    }
+
+    pub async fn is_deactivated(
         db: &mut Connection<Db>,
         user_id: i32,
-    ) -> Result<i64, sqlx::Error> {
-        let account_balance_sat = sqlx::query("
-SELECT SUM(amount_change_sat) as total_account_balance_sat FROM
-(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-from
- cases
-WHERE
- cases.paid
-AND
- cases.awarded
-AND
- cases.seller_user_id = ?
-UNION ALL
-select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-from
- cases
-WHERE
- cases.paid
-AND
- (cases.canceled_by_seller OR cases.canceled_by_buyer)
-AND
- cases.buyer_user_id = ?
-UNION ALL
-select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
-from
- withdrawals
-WHERE
- withdrawals.user_id = ?)
-;")
-            .bind(user_id)
-            .bind(user_id)
-            .bind(user_id)
-            .fetch_one(&mut **db)
-            .map_ok(|r|  {
-                let balance_sat_i64: i64 = r.try_get("total_account_balance_sat").unwrap();
-                balance_sat_i64
-            })
-            .await?;
+    ) -> Result<bool, sqlx::Error> {
+        let num_deactivated = sqlx::query(
+            "select COUNT(user_id) as num_deactivated from deactivatedaccounts WHERE user_id = ?;",
+        )
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_i64: i64 = r.try_get("num_deactivated").unwrap();
+            num_i64
+        })
+        .await?;
 
-        Ok(account_balance_sat)
+        Ok(num_deactivated > 0)
     }
 
-    pub async fn all_account_balance_changes(
-        db: &mut Connection<Db>,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<AccountBalanceChange>, sqlx::Error> {
-        // TODO: Case by event time in SQL query. When this is fixed: https://github.com/launchbadge/sqlx/issues/1350
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let account_balance_changes = sqlx::query("
-SELECT * FROM
-(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+    /// Computes the exact refund owed to a user when they deactivate,
+    /// along with any outstanding obligations that block deactivation.
+    async fn deactivation_info_for_connection(
+        conn: &mut SqliteConnection,
+        user_account: &UserAccount,
+    ) -> Result<DeactivationInfo, sqlx::Error> {
+        let user_id = user_account.user_id;
+
+        let account_balance_sat =
+            AccountInfo::total_account_balance_for_connection(&mut *conn, user_id).await?;
+
+        let num_unpaid_cases = sqlx::query(
+            "
+select
+ COUNT(cases.id) as num_unpaid_cases
 from
  cases
 WHERE
- cases.paid
+ NOT cases.paid
 AND
- cases.awarded
-UNION ALL
-select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+ (cases.buyer_user_id = ? OR cases.seller_user_id = ?)
+;",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let num_i64: i64 = r.try_get("num_unpaid_cases").unwrap();
+            num_i64 as u32
+        })
+        .await?;
+
+        let num_processing_cases = sqlx::query(
+            "
+select
+ COUNT(cases.id) as num_processing_cases
 from
  cases
 WHERE
  cases.paid
 AND
- (cases.canceled_by_seller OR cases.canceled_by_buyer)
-UNION ALL
-select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'processing_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-from
- cases
-WHERE
- cases.paid
+ not (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
 AND
- NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
-UNION ALL
-select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
-from
- withdrawals
-UNION ALL
-select useraccounts.user_id as user_id, useraccounts.amount_owed_sat as amount_change_sat, 'user_activation' as event_type, useraccounts.public_id as event_id, useraccounts.created_time_ms as event_time_ms
-from
- useraccounts
-WHERE
- useraccounts.paid)
-ORDER BY event_time_ms DESC
-LIMIT ?
-OFFSET ?
-;")
-            .bind(limit)
-            .bind(offset)
-            .fetch(&mut **db)
-            .map_ok(|r| AccountBalanceChange {
-                amount_change_sat: r.try_get("amount_change_sat").unwrap(),
-                event_type: r.try_get("event_type").unwrap(),
-                event_id: r.try_get("event_id").unwrap(),
-                event_time_ms: {
-                    let time_ms_i64: i64 = r.try_get("event_time_ms").unwrap();
-                    time_ms_i64 as u64
-                },
-            })
-            .try_collect::<Vec<_>>()
-            .await?;
-
-        Ok(account_balance_changes)
-    }
+ (cases.buyer_user_id = ? OR cases.seller_user_id = ?)
+;",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let num_i64: i64 = r.try_get("num_processing_cases").unwrap();
+            num_i64 as u32
+        })
+        .await?;
 
-    pub async fn total_market_liabilities_sat(db: &mut Connection<Db>) -> Result<i64, sqlx::Error> {
-        let market_liabilities_sat = sqlx::query("
-SELECT SUM(amount_change_sat) as total_market_liabilities_sat FROM
-(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-from
- cases
-WHERE
- cases.paid
-AND
- cases.awarded
-UNION ALL
-select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+        let num_active_bounties = sqlx::query(
+            "
+select
+ COUNT(bounties.id) as num_active_bounties
 from
- cases
+ bounties
 WHERE
- cases.paid
+ bounties.approved
 AND
- (cases.canceled_by_seller OR cases.canceled_by_buyer)
-UNION ALL
-select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'processing_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+ NOT (bounties.deactivated_by_seller OR bounties.deactivated_by_admin)
+AND
+ bounties.user_id = ?
+;",
+        )
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let num_i64: i64 = r.try_get("num_active_bounties").unwrap();
+            num_i64 as u32
+        })
+        .await?;
+
+        let num_open_disputes = sqlx::query(
+            "
+select
+ COUNT(casedisputes.id) as num_open_disputes
 from
+ casedisputes
+JOIN
  cases
+ON
+ casedisputes.case_id = cases.id
 WHERE
- cases.paid
+ NOT casedisputes.resolved
 AND
- NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
-UNION ALL
-select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
-from
- withdrawals
-UNION ALL
-select useraccounts.user_id as user_id, useraccounts.amount_owed_sat as amount_change_sat, 'user_activation' as event_type, useraccounts.public_id as event_id, useraccounts.created_time_ms as event_time_ms
+ (cases.buyer_user_id = ? OR cases.seller_user_id = ?)
+;",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let num_i64: i64 = r.try_get("num_open_disputes").unwrap();
+            num_i64 as u32
+        })
+        .await?;
+
+        let num_active_claims = sqlx::query(
+            "
+select
+ COUNT(bountyclaims.id) as num_active_claims
 from
- useraccounts
+ bountyclaims
+JOIN
+ bounties
+ON
+ bountyclaims.bounty_id = bounties.id
 WHERE
- useraccounts.paid)
-;")
-            .fetch_one(&mut **db)
-            .map_ok(|r|  {
-                let balance_sat_i64: i64 = r.try_get("total_market_liabilities_sat").unwrap();
-                balance_sat_i64
-            })
-            .await?;
-
-        Ok(market_liabilities_sat)
-    }
-
-    // TODO: Use when sqlx is fixed.
-    //     pub async fn account_balance(
-    //         db: &mut Connection<Db>,
-    //         user_id: i32,
-    //     ) -> Result<u64, sqlx::Error> {
-    //         let account_balance_result = sqlx::query!("
-    // SELECT SUM(data.amount_change_sat) as account_balance, data.user_id as user_id
-    // FROM
-    // (select bounties.user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-    // from
-    //  cases
-    // LEFT JOIN
-    //  bounties
-    // ON
-    //  cases.bounty_id = bounties.id
-    // WHERE
-    //  cases.paid
-    // AND
-    //  cases.awarded
-    // AND
-    //  bounties.user_id = ?
-    // UNION ALL
-    // select cases.user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
-    // from
-    //  cases
-    // WHERE
-    //  cases.paid
-    // AND
-    //  not cases.awarded
-    // AND
-    //  cases.user_id = ?
-    // ) data
-    // GROUP BY user_id
-    // ;",
-    //         user_id, user_id)
-    //         .fetch_optional(&mut **db)
-    //         .await?;
+ NOT bountyclaims.released
+AND
+ (bountyclaims.user_id = ? OR bounties.user_id = ?)
+;",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .map_ok(|r| {
+            let num_i64: i64 = r.try_get("num_active_claims").unwrap();
+            num_i64 as u32
+        })
+        .await?;
 
-    //         let account_balance = match account_balance_result {
-    //             Some(r) => r.account_balance,
-    //             None => 0,
-    //         };
+        let bond_sat_i64: i64 = user_account.amount_owed_sat.try_into().unwrap();
 
-    //         Ok(account_balance)
-    //     }
-}
+        Ok(DeactivationInfo {
+            bond_sat: user_account.amount_owed_sat,
+            account_balance_sat,
+            refund_sat: bond_sat_i64 + account_balance_sat,
+            num_unpaid_cases,
+            num_processing_cases,
+            num_active_bounties,
+            num_open_disputes,
+            num_active_claims,
+        })
+    }
 
-impl Withdrawal {
-    pub async fn do_withdrawal(
-        withdrawal: Withdrawal,
+    pub async fn do_deactivation(
+        amount_sat: u64,
+        invoice_payment_request: String,
+        user_account: UserAccount,
         db: &mut Connection<Db>,
-        send_withdrawal_funds_future: impl Future<
+        send_deactivation_funds_future: impl Future<
             Output = Result<tonic_openssl_lnd::lnrpc::SendResponse, String>,
         >,
-        max_withdrawals_per_interval: u32,
-        interval_start_time_ms: u64,
-    ) -> Result<i32, String> {
+        created_time_ms: u64,
+    ) -> Result<(), String> {
         let mut tx = db
             .begin()
             .await
             .map_err(|_| "failed to begin transaction.")?;
 
-        // Insert the new withdrawal.
-        let amount_sat: i64 = withdrawal.amount_sat.try_into().unwrap();
-        let created_time_ms: i64 = withdrawal.created_time_ms.try_into().unwrap();
-        let insert_result = sqlx::query!(
-            "INSERT INTO withdrawals (public_id, user_id, amount_sat, invoice_hash, invoice_payment_request, created_time_ms) VALUES (?, ?, ?, ?, ?, ?)",
-            withdrawal.public_id,
-            withdrawal.user_id,
-            amount_sat,
-            withdrawal.invoice_hash,
-            withdrawal.invoice_payment_request,
-            created_time_ms,
-        )
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| "failed to insert new withdrawal.")?;
-        let new_withdrawal_id = insert_result.last_insert_rowid();
+        // Compute the refund inside the transaction so it cannot go stale.
+        let deactivation_info =
+            UserAccount::deactivation_info_for_connection(&mut *tx, &user_account)
+                .await
+                .map_err(|_| "failed to get deactivation info.")?;
 
-        // Check if any constraints are violated.
-        let user_id = withdrawal.user_id;
+        if deactivation_info.num_unpaid_cases > 0 {
+            return Err("Cannot deactivate while unpaid cases are outstanding.".to_string());
+        }
+        if deactivation_info.num_processing_cases > 0 {
+            return Err("Cannot deactivate while cases are held in escrow.".to_string());
+        }
+        if deactivation_info.num_active_bounties > 0 {
+            return Err("Cannot deactivate while bounties are still active.".to_string());
+        }
+        if deactivation_info.num_open_disputes > 0 {
+            return Err("Cannot deactivate while disputes are open.".to_string());
+        }
+        if deactivation_info.num_active_claims > 0 {
+            return Err("Cannot deactivate while bounty claims are held.".to_string());
+        }
+        if deactivation_info.refund_sat <= 0 {
+            return Err("No funds available to refund.".to_string());
+        }
+        let amount_sat: i64 = amount_sat.try_into().unwrap();
+        if amount_sat != deactivation_info.refund_sat {
+            return Err(format!(
+                "Invoice amount must be exactly {:?} sats.",
+                deactivation_info.refund_sat
+            ));
+        }
 
-        let start_time_ms_i64: i64 = interval_start_time_ms.try_into().unwrap();
-        let withdrawal_count = sqlx::query!(
+        // Record the account balance part of the refund as a withdrawal, so
+        // that the ledger and the market liabilities stay balanced.
+        let maybe_withdrawal_id = if deactivation_info.account_balance_sat > 0 {
+            let created_time_ms: i64 = created_time_ms.try_into().unwrap();
+            let insert_result = sqlx::query!(
+                "INSERT INTO withdrawals (public_id, user_id, amount_sat, invoice_hash, invoice_payment_request, created_time_ms) VALUES (?, ?, ?, ?, ?, ?)",
+                util::create_uuid(),
+                user_account.user_id,
+                deactivation_info.account_balance_sat,
+                "",
+                invoice_payment_request,
+                created_time_ms,
+            )
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "failed to insert deactivation withdrawal.")?;
+            Some(insert_result.last_insert_rowid())
+        } else {
+            None
+        };
+
+        let delete_user_account_result = sqlx::query!(
             "
-select count(id) as withdrawal_count from withdrawals
+DELETE FROM useraccounts
 WHERE
  user_id = ?
 AND
- created_time_ms > ?
-ORDER BY withdrawals.created_time_ms ASC;",
-            user_id,
-            start_time_ms_i64,
+ paid = true
+;",
+            user_account.user_id,
         )
-        .fetch_one(&mut *tx)
-        .map_ok(|r| r.withdrawal_count as u32)
+        .execute(&mut *tx)
         .await
-        .map_err(|_| "failed to get withdrawal count.")?;
+        .map_err(|_| "failed to delete user account.")?;
 
-        if withdrawal_count > max_withdrawals_per_interval {
-            return Err(format!(
-                "More than {:?} withdrawals in a single day not allowed.",
-                max_withdrawals_per_interval,
-            ));
+        // Validate that at least one paid user account was deleted
+        if delete_user_account_result.rows_affected() < 1 {
+            return Err("No user bond found.".to_string());
         }
 
-        let account_balance_sat = sqlx::query("
-SELECT SUM(amount_change_sat) as total_account_balance_sat FROM
-(select cases.seller_user_id as user_id, cases.seller_credit_sat as amount_change_sat, 'received_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+        // Keep the user and their bounties, so that their cases still point at them.
+        let created_time_ms_i64: i64 = created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO deactivatedaccounts (user_id, bond_sat, refund_sat, created_time_ms) VALUES (?, ?, ?, ?)",
+        )
+        .bind(user_account.user_id)
+        .bind(deactivation_info.bond_sat as i64)
+        .bind(deactivation_info.refund_sat)
+        .bind(created_time_ms_i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to mark user as deactivated.")?;
+
+        sqlx::query(
+            "
+UPDATE bounties
+SET deactivated_by_seller = true
+WHERE user_id = ?
+;",
+        )
+        .bind(user_account.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "failed to deactivate user bounties.")?;
+
+        let send_response = send_deactivation_funds_future
+            .await
+            .map_err(|e| format!("failed to send deactivation payment: {:?}", e))?;
+
+        if let Some(withdrawal_id) = maybe_withdrawal_id {
+            let payment_hash_hex = util::to_hex(&send_response.payment_hash);
+            sqlx::query!(
+                "UPDATE withdrawals SET invoice_hash = ? WHERE id = ?",
+                payment_hash_hex,
+                withdrawal_id,
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to update deactivation withdrawal payment hash.")?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
+    }
+}
+
+impl UserCard {
+    pub async fn all_active(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<UserCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let user_cards =
+            sqlx::query!("
+select
+ users.id as rocket_auth_user_id, users.email as rocket_auth_user_username, useraccounts.id as useraccounts_id, useraccounts.public_id as useraccounts_public_id, useraccounts.user_id as useraccounts_user_id, useraccounts.amount_owed_sat as useraccounts_amount_owed_sat, useraccounts.paid as useraccounts_paid, useraccounts.disabled as useraccounts_disabled, useraccounts.invoice_payment_request as useraccounts_invoice_payment_request, useraccounts.invoice_hash as useraccounts_invoice_hash, useraccounts.created_time_ms as useraccounts_created_time_ms, useraccounts.payment_time_ms as useraccounts_payment_time_ms
 from
- cases
+ users
+INNER JOIN
+ useraccounts
+ON
+ users.id = useraccounts.user_id
 WHERE
- cases.paid
-AND
- cases.awarded
+ useraccounts.paid
 AND
- cases.seller_user_id = ?
-UNION ALL
-select cases.buyer_user_id as user_id, cases.amount_owed_sat as amount_change_sat, 'refunded_case' as event_type, cases.public_id as event_id, cases.created_time_ms as event_time_ms
+ NOT useraccounts.disabled
+GROUP BY
+ users.id
+ORDER BY useraccounts.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", limit, offset)
+            .fetch(&mut **db)
+            .map_ok(|r| {
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                let ua = r.useraccounts_id.map(|user_account_id| UserAccount {
+                    id: Some(user_account_id.try_into().unwrap()),
+                    public_id: r.useraccounts_public_id.unwrap(),
+                    user_id: r.useraccounts_user_id.unwrap().try_into().unwrap(),
+                    amount_owed_sat: r.useraccounts_amount_owed_sat.unwrap().try_into().unwrap(),
+                    paid: r.useraccounts_paid.unwrap(),
+                    disabled: r.useraccounts_disabled.unwrap(),
+                    invoice_payment_request: r.useraccounts_invoice_payment_request.unwrap(),
+                    invoice_hash: r.useraccounts_invoice_hash.unwrap(),
+                    created_time_ms: r.useraccounts_created_time_ms.unwrap().try_into().unwrap(),
+                    payment_time_ms: r.useraccounts_payment_time_ms.unwrap().try_into().unwrap(),
+                });
+                UserCard {
+                    user: u.unwrap(),
+                    user_account: ua.unwrap(),
+                }
+            })
+                .try_collect::<Vec<_>>()
+                .await?;
+
+        Ok(user_cards)
+    }
+
+    pub async fn all_disabled(
+        db: &mut Connection<Db>,
+        page_size: u32,
+        page_num: u32,
+    ) -> Result<Vec<UserCard>, sqlx::Error> {
+        let offset = (page_num - 1) * page_size;
+        let limit = page_size;
+        let user_cards =
+            sqlx::query!("
+select
+ users.id as rocket_auth_user_id, users.email as rocket_auth_user_username, useraccounts.id as useraccounts_id, useraccounts.public_id as useraccounts_public_id, useraccounts.user_id as useraccounts_user_id, useraccounts.amount_owed_sat as useraccounts_amount_owed_sat, useraccounts.paid as useraccounts_paid, useraccounts.disabled as useraccounts_disabled, useraccounts.invoice_payment_request as useraccounts_invoice_payment_request, useraccounts.invoice_hash as useraccounts_invoice_hash, useraccounts.created_time_ms as useraccounts_created_time_ms, useraccounts.payment_time_ms as useraccounts_payment_time_ms
 from
- cases
+ users
+INNER JOIN
+ useraccounts
+ON
+ users.id = useraccounts.user_id
 WHERE
- cases.paid
-AND
- (cases.canceled_by_seller OR cases.canceled_by_buyer)
+ useraccounts.paid
 AND
- cases.buyer_user_id = ?
-UNION ALL
-select withdrawals.user_id as user_id, (0 - withdrawals.amount_sat) as amount_change_sat, 'withdrawal' as event_type, withdrawals.public_id as event_id, withdrawals.created_time_ms as event_time_ms
-from
- withdrawals
-WHERE
- withdrawals.user_id = ?)
-;")
-            .bind(user_id)
-            .bind(user_id)
-            .bind(user_id)
-            .fetch_one(&mut *tx)
-            .map_ok(|r|  {
-                let balance_sat_i64: i64 = r.try_get("total_account_balance_sat").unwrap();
-                balance_sat_i64
+ useraccounts.disabled
+GROUP BY
+ users.id
+ORDER BY useraccounts.created_time_ms DESC
+LIMIT ?
+OFFSET ?
+;", limit, offset)
+            .fetch(&mut **db)
+            .map_ok(|r| {
+                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
+                    id: Some(rocket_auth_user_id.try_into().unwrap()),
+                    username: r.rocket_auth_user_username.unwrap(),
+                });
+                let ua = r.useraccounts_id.map(|user_account_id| UserAccount {
+                    id: Some(user_account_id.try_into().unwrap()),
+                    public_id: r.useraccounts_public_id.unwrap(),
+                    user_id: r.useraccounts_user_id.unwrap().try_into().unwrap(),
+                    amount_owed_sat: r.useraccounts_amount_owed_sat.unwrap().try_into().unwrap(),
+                    paid: r.useraccounts_paid.unwrap(),
+                    disabled: r.useraccounts_disabled.unwrap(),
+                    invoice_payment_request: r.useraccounts_invoice_payment_request.unwrap(),
+                    invoice_hash: r.useraccounts_invoice_hash.unwrap(),
+                    created_time_ms: r.useraccounts_created_time_ms.unwrap().try_into().unwrap(),
+                    payment_time_ms: r.useraccounts_payment_time_ms.unwrap().try_into().unwrap(),
+                });
+                UserCard {
+                    user: u.unwrap(),
+                    user_account: ua.unwrap(),
+                }
             })
-            .await
-            .map_err(|_| "failed to insert get account balance changes.")?;
+                .try_collect::<Vec<_>>()
+                .await?;
 
-        if account_balance_sat < 0 {
-            return Err("Insufficient funds for withdrawal.".to_string());
-        }
+        Ok(user_cards)
+    }
+}
 
-        let send_response = send_withdrawal_funds_future
-            .await
-            .map_err(|e| format!("failed to send withdrawal payment: {:?}", e))?;
+impl CaseRating {
+    /// Saves a rating, and tells the user who was rated.
+    pub async fn insert(rating: CaseRating, db: &mut Connection<Db>) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
 
-        // Update the withdrawal row with the payment invoice hash.
-        let payment_hash_hex = util::to_hex(&send_response.payment_hash);
-        sqlx::query!(
-            "UPDATE withdrawals SET invoice_hash = ? WHERE id = ?",
-            payment_hash_hex,
-            new_withdrawal_id,
+        let score: i64 = rating.score.into();
+        let created_time_ms: i64 = rating.created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO caseratings (public_id, case_id, rater_user_id, ratee_user_id, rater_role, score, review, created_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
         )
+        .bind(&rating.public_id)
+        .bind(rating.case_id)
+        .bind(rating.rater_user_id)
+        .bind(rating.ratee_user_id)
+        .bind(&rating.rater_role)
+        .bind(score)
+        .bind(&rating.review)
+        .bind(created_time_ms)
         .execute(&mut *tx)
-        .await
-        .map_err(|_| "failed to update new withdrawal payment hash.")?;
+        .await?;
+        Notification::insert_for_case(
+            &mut *tx,
+            rating.case_id,
+            rating.ratee_user_id,
+            "case_rated",
+            "You were rated on case",
+        )
+        .await?;
 
-        tx.commit()
-            .await
-            .map_err(|_| "failed to begin transaction.")?;
+        tx.commit().await?;
 
-        Ok(new_withdrawal_id as _)
+        Ok(())
     }
 
-    pub async fn single_by_public_id(
+    pub async fn all_for_case(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<Withdrawal, sqlx::Error> {
-        let withdrawal = sqlx::query!("select * from withdrawals WHERE public_id = ?;", public_id)
-            .fetch_one(&mut **db)
-            .map_ok(|r| Withdrawal {
-                id: Some(r.id.try_into().unwrap()),
-                public_id: r.public_id,
-                user_id: r.user_id.try_into().unwrap(),
-                amount_sat: r.amount_sat.try_into().unwrap(),
-                invoice_hash: r.invoice_hash,
-                invoice_payment_request: r.invoice_payment_request,
-                created_time_ms: r.created_time_ms.try_into().unwrap(),
-            })
-            .await?;
+        case_id: i32,
+    ) -> Result<Vec<CaseRating>, sqlx::Error> {
+        let ratings = sqlx::query(
+            "
+select caseratings.*, cases.public_id as case_public_id, users.email as rater_username
+from
+ caseratings
+LEFT JOIN
+ cases
+ON
+ caseratings.case_id = cases.id
+LEFT JOIN
+ users
+ON
+ caseratings.rater_user_id = users.id
+WHERE
+ caseratings.case_id = ?
+ORDER BY caseratings.created_time_ms ASC, caseratings.id ASC
+;",
+        )
+        .bind(case_id)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseRating::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
 
-        Ok(withdrawal)
+        Ok(ratings)
     }
 
-    //     pub async fn count_for_user_since_time_ms(
-    //         db: &mut Connection<Db>,
-    //         user_id: i32,
-    //         start_time_ms: u64,
-    //     ) -> Result<u32, sqlx::Error> {
-    //         let start_time_ms_i64: i64 = start_time_ms.try_into().unwrap();
-
-    //         let withdrawal_count = sqlx::query!(
-    //             "
-    // select count(id) as withdrawal_count from withdrawals
-    // WHERE
-    //  user_id = ?
-    // AND
-    //  created_time_ms > ?
-    // ORDER BY withdrawals.created_time_ms ASC;",
-    //             user_id,
-    //             start_time_ms_i64,
-    //         )
-    //         .fetch_one(&mut **db)
-    //         .map_ok(|r| r.withdrawal_count)
-    //         .await?;
+    /// Gets the most recent ratings received by a user.
+    pub async fn all_for_ratee(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        limit: u32,
+    ) -> Result<Vec<CaseRating>, sqlx::Error> {
+        let ratings = sqlx::query(
+            "
+select caseratings.*, cases.public_id as case_public_id, users.email as rater_username
+from
+ caseratings
+LEFT JOIN
+ cases
+ON
+ caseratings.case_id = cases.id
+LEFT JOIN
+ users
+ON
+ caseratings.rater_user_id = users.id
+WHERE
+ caseratings.ratee_user_id = ?
+ORDER BY caseratings.created_time_ms DESC, caseratings.id DESC
+LIMIT ?
+;",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch(&mut **db)
+        .map_ok(|r| CaseRating::from_row(&r))
+        .try_collect::<Vec<_>>()
+        .await?;
 
-    //         Ok(withdrawal_count.try_into().unwrap())
-    //     }
-}
+        Ok(ratings)
+    }
 
-impl AdminInfo {
-    pub async fn admin_info(db: &mut Connection<Db>) -> Result<AdminInfo, sqlx::Error> {
-        let num_pending_bounties = Bounty::num_pending(db).await?;
-        Ok(AdminInfo {
-            num_pending_bounties,
-        })
+    fn from_row(r: &sqlx::sqlite::SqliteRow) -> CaseRating {
+        CaseRating {
+            id: Some(r.try_get("id").unwrap()),
+            public_id: r.try_get("public_id").unwrap(),
+            case_id: r.try_get("case_id").unwrap(),
+            case_public_id: r.try_get("case_public_id").unwrap(),
+            rater_user_id: r.try_get("rater_user_id").unwrap(),
+            rater_username: r.try_get("rater_username").unwrap(),
+            ratee_user_id: r.try_get("ratee_user_id").unwrap(),
+            rater_role: r.try_get("rater_role").unwrap(),
+            score: r.try_get("score").unwrap(),
+            review: r.try_get("review").unwrap(),
+            created_time_ms: {
+                let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                time_ms_i64 as u64
+            },
+        }
     }
 }
 
-impl UserAccount {
-    /// Returns the id of the inserted row.
-    pub async fn insert(
-        user_account: UserAccount,
+impl Reputation {
+    pub async fn single_for_user(
         db: &mut Connection<Db>,
-    ) -> Result<i32, sqlx::Error> {
-        let amount_owed_sat: i64 = user_account.amount_owed_sat.try_into().unwrap();
-        let created_time_ms: i64 = user_account.created_time_ms.try_into().unwrap();
-        let payment_time_ms: i64 = user_account.payment_time_ms.try_into().unwrap();
-
-        let insert_result = sqlx::query!(
-            "INSERT INTO useraccounts (public_id, user_id, amount_owed_sat, paid, disabled, invoice_payment_request, invoice_hash, created_time_ms, payment_time_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            user_account.public_id,
-            user_account.user_id,
-            amount_owed_sat,
-            user_account.paid,
-            user_account.disabled,
-            user_account.invoice_payment_request,
-            user_account.invoice_hash,
-            created_time_ms,
-            payment_time_ms,
+        user_id: i32,
+    ) -> Result<Reputation, sqlx::Error> {
+        let reputation = sqlx::query(
+            "
+select
+ (SELECT COUNT(id) FROM caseratings WHERE ratee_user_id = ?) as num_ratings,
+ (SELECT COALESCE(AVG(score), 0.0) FROM caseratings WHERE ratee_user_id = ?) as average_score,
+ (SELECT COUNT(id) FROM cases WHERE seller_user_id = ? AND paid AND (awarded OR canceled_by_seller)) as num_decided_cases,
+ (SELECT COUNT(id) FROM cases WHERE seller_user_id = ? AND paid AND canceled_by_seller) as num_canceled_cases
+;",
         )
-            .execute(&mut **db)
-            .await?;
-
-        Ok(insert_result.last_insert_rowid() as _)
-    }
-
-    pub async fn single(db: &mut Connection<Db>, id: i32) -> Result<UserAccount, sqlx::Error> {
-        let user_account = sqlx::query!("select * from useraccounts WHERE user_id = ?;", id)
-            .fetch_one(&mut **db)
-            .map_ok(|r| UserAccount {
-                id: Some(r.id.try_into().unwrap()),
-                public_id: r.public_id,
-                user_id: r.user_id.try_into().unwrap(),
-                amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-                paid: r.paid,
-                disabled: r.disabled,
-                invoice_payment_request: r.invoice_payment_request,
-                invoice_hash: r.invoice_hash,
-                created_time_ms: r.created_time_ms.try_into().unwrap(),
-                payment_time_ms: r.payment_time_ms.try_into().unwrap(),
-            })
-            .await?;
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| {
+            let num_ratings: u32 = r.try_get("num_ratings").unwrap();
+            let average_score: f64 = r.try_get("average_score").unwrap();
+            let num_decided_cases: u32 = r.try_get("num_decided_cases").unwrap();
+            let num_canceled_cases: u32 = r.try_get("num_canceled_cases").unwrap();
+            let cancels_every_case = num_decided_cases >= MIN_DECIDED_CASES_FOR_CANCEL_FLAG
+                && num_canceled_cases == num_decided_cases;
+            Reputation {
+                num_ratings,
+                average_score,
+                average_score_text: format!("{:.1}", average_score),
+                num_decided_cases,
+                num_canceled_cases,
+                cancels_every_case,
+            }
+        })
+        .await?;
 
-        Ok(user_account)
+        Ok(reputation)
     }
+}
 
-    pub async fn single_by_public_id(
+impl BountyReviewDeadline {
+    pub async fn single_for_bounty(
         db: &mut Connection<Db>,
-        public_id: &str,
-    ) -> Result<UserAccount, sqlx::Error> {
-        let user_account =
-            sqlx::query!("select * from useraccounts WHERE public_id = ?;", public_id)
-                .fetch_one(&mut **db)
-                .map_ok(|r| UserAccount {
-                    id: Some(r.id.try_into().unwrap()),
-                    public_id: r.public_id,
-                    user_id: r.user_id.try_into().unwrap(),
-                    amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-                    paid: r.paid,
-                    disabled: r.disabled,
-                    invoice_payment_request: r.invoice_payment_request,
-                    invoice_hash: r.invoice_hash,
-                    created_time_ms: r.created_time_ms.try_into().unwrap(),
-                    payment_time_ms: r.payment_time_ms.try_into().unwrap(),
+        bounty_id: i32,
+    ) -> Result<Option<BountyReviewDeadline>, sqlx::Error> {
+        let maybe_deadline =
+            sqlx::query("select * from bountyreviewdeadlines WHERE bounty_id = ?;")
+                .bind(bounty_id)
+                .fetch_optional(&mut **db)
+                .map_ok(|maybe_r| {
+                    maybe_r.map(|r| BountyReviewDeadline {
+                        bounty_id: r.try_get("bounty_id").unwrap(),
+                        review_deadline_ms: {
+                            let deadline_ms_i64: i64 = r.try_get("review_deadline_ms").unwrap();
+                            deadline_ms_i64 as u64
+                        },
+                    })
                 })
                 .await?;
 
-        Ok(user_account)
+        Ok(maybe_deadline)
     }
 
-    pub async fn single_by_invoice_hash(
-        db: &mut PoolConnection<Sqlite>,
-        invoice_hash: &str,
-    ) -> Result<UserAccount, sqlx::Error> {
-        let user_account = sqlx::query!(
-            "select * from useraccounts WHERE invoice_hash = ?;",
-            invoice_hash
+    pub async fn set_for_bounty(
+        deadline: BountyReviewDeadline,
+        db: &mut Connection<Db>,
+    ) -> Result<(), sqlx::Error> {
+        let review_deadline_ms: i64 = deadline.review_deadline_ms.try_into().unwrap();
+        sqlx::query(
+            "
+INSERT INTO bountyreviewdeadlines (bounty_id, review_deadline_ms)
+VALUES (?, ?)
+ON CONFLICT(bounty_id) DO UPDATE SET review_deadline_ms = excluded.review_deadline_ms
+;",
         )
-        .fetch_one(&mut **db)
-        .map_ok(|r| UserAccount {
-            id: Some(r.id.try_into().unwrap()),
-            public_id: r.public_id,
-            user_id: r.user_id.try_into().unwrap(),
-            amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-            paid: r.paid,
-            disabled: r.disabled,
-            invoice_payment_request: r.invoice_payment_request,
-            invoice_hash: r.invoice_hash,
-            created_time_ms: r.created_time_ms.try_into().unwrap(),
-            payment_time_ms: r.payment_time_ms.try_into().unwrap(),
-        })
+        .bind(deadline.bounty_id)
+        .bind(review_deadline_ms)
+        .execute(&mut **db)
         .await?;
 
-        Ok(user_account)
+        Ok(())
     }
 
-    pub async fn mark_as_paid(
-        db: &mut PoolConnection<Sqlite>,
-        user_account_id: i32,
-        time_now_ms: u64,
+    pub async fn remove_for_bounty(
+        db: &mut Connection<Db>,
+        bounty_id: i32,
     ) -> Result<(), sqlx::Error> {
-        let time_now_ms_i64: i64 = time_now_ms.try_into().unwrap();
+        sqlx::query("DELETE FROM bountyreviewdeadlines WHERE bounty_id = ?")
+            .bind(bounty_id)
+            .execute(&mut **db)
+            .await?;
 
-        sqlx::query!(
-            "UPDATE useraccounts SET paid = true, payment_time_ms = ? WHERE id = ?",
-            time_now_ms_i64,
-            user_account_id,
+        Ok(())
+    }
+}
+
+impl CaseReviewDeadline {
+    /// Gets the pending cases whose review deadline is before the given time.
+    /// The clock starts at payment or at the last resubmission, and is stopped while
+    /// the poster waits for requested changes or the case is in a dispute.
+    pub async fn all_before(
+        conn: &mut SqliteConnection,
+        default_deadline_ms: u64,
+        before_ms: u64,
+    ) -> Result<Vec<CaseReviewDeadline>, sqlx::Error> {
+        CaseReviewDeadline::all_before_for_case(conn, default_deadline_ms, before_ms, None).await
+    }
+
+    /// Gets the review deadline of a case, if the case is still waiting for the poster.
+    pub async fn single_for_case(
+        db: &mut Connection<Db>,
+        case_id: i32,
+        default_deadline_ms: u64,
+    ) -> Result<Option<CaseReviewDeadline>, sqlx::Error> {
+        let deadlines = CaseReviewDeadline::all_before_for_case(
+            &mut **db,
+            default_deadline_ms,
+            i64::MAX as u64,
+            Some(case_id),
         )
-        .execute(&mut **db)
         .await?;
 
-        Ok(())
+        Ok(deadlines.into_iter().next())
     }
 
-    pub async fn mark_as_disabled(
-        db: &mut PoolConnection<Sqlite>,
-        user_id: i32,
+    async fn all_before_for_case(
+        conn: &mut SqliteConnection,
+        default_deadline_ms: u64,
+        before_ms: u64,
+        maybe_case_id: Option<i32>,
+    ) -> Result<Vec<CaseReviewDeadline>, sqlx::Error> {
+        let default_deadline_ms_i64: i64 = default_deadline_ms.try_into().unwrap();
+        let before_ms_i64: i64 = before_ms.try_into().unwrap();
+        let deadlines = sqlx::query(
+            "
+select * from
+(select
+ cases.id as case_id, cases.public_id as case_public_id, cases.buyer_user_id, cases.seller_user_id,
+ MAX(cases.payment_time_ms, COALESCE((SELECT MAX(caserevisions.created_time_ms) FROM caserevisions WHERE caserevisions.case_id = cases.id), 0)) + COALESCE(bountyreviewdeadlines.review_deadline_ms, ?) as deadline_ms
+from
+ cases
+LEFT JOIN
+ bountyreviewdeadlines
+ON
+ cases.bounty_id = bountyreviewdeadlines.bounty_id
+WHERE
+ cases.paid
+AND
+ NOT (cases.awarded OR cases.canceled_by_seller OR cases.canceled_by_buyer)
+AND
+ NOT EXISTS (SELECT 1 FROM casedisputes WHERE casedisputes.case_id = cases.id)
+AND
+ NOT EXISTS (SELECT 1 FROM caseresponses WHERE caseresponses.case_id = cases.id AND caseresponses.response_type = 'changes_requested' AND NOT caseresponses.addressed)
+AND
+ (? IS NULL OR cases.id = ?))
+WHERE
+ deadline_ms < ?
+ORDER BY deadline_ms ASC
+;",
+        )
+        .bind(default_deadline_ms_i64)
+        .bind(maybe_case_id)
+        .bind(maybe_case_id)
+        .bind(before_ms_i64)
+        .fetch(&mut *conn)
+        .map_ok(|r| CaseReviewDeadline {
+            case_id: r.try_get("case_id").unwrap(),
+            case_public_id: r.try_get("case_public_id").unwrap(),
+            buyer_user_id: r.try_get("buyer_user_id").unwrap(),
+            seller_user_id: r.try_get("seller_user_id").unwrap(),
+            deadline_ms: {
+                let deadline_ms_i64: i64 = r.try_get("deadline_ms").unwrap();
+                deadline_ms_i64 as u64
+            },
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+        Ok(deadlines)
+    }
+
+    /// Sends the poster a reminder about the deadline, unless one was already sent for it.
+    pub async fn remind_for_connection(
+        conn: &mut SqliteConnection,
+        deadline: &CaseReviewDeadline,
+        now: u64,
     ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "UPDATE useraccounts SET disabled = true WHERE user_id = ?",
-            user_id,
+        let deadline_ms: i64 = deadline.deadline_ms.try_into().unwrap();
+        let created_time_ms: i64 = now.try_into().unwrap();
+        let mut tx = conn.begin().await?;
+
+        let insert_result = sqlx::query(
+            "INSERT OR IGNORE INTO casedeadlinereminders (case_id, deadline_ms, created_time_ms) VALUES (?, ?, ?)",
         )
-        .execute(&mut **db)
+        .bind(deadline.case_id)
+        .bind(deadline_ms)
+        .bind(created_time_ms)
+        .execute(&mut *tx)
         .await?;
+        if insert_result.rows_affected() == 1 {
+            Notification::insert_for_case(
+                &mut *tx,
+                deadline.case_id,
+                deadline.seller_user_id,
+                "review_deadline_approaching",
+                "Review deadline approaching for case",
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
 
         Ok(())
     }
 
-    pub async fn mark_as_enabled(
-        db: &mut PoolConnection<Sqlite>,
-        user_id: i32,
+    /// Handles a case that the poster did not review in time. The case is either refunded
+    /// to the submitter, or escalated to an admin as a dispute.
+    pub async fn expire_for_connection(
+        conn: &mut SqliteConnection,
+        deadline: &CaseReviewDeadline,
+        refund: bool,
+        now: u64,
     ) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "UPDATE useraccounts SET disabled = false WHERE user_id = ?",
-            user_id,
+        let mut tx = conn.begin().await?;
+
+        // A dispute with nothing reserved could only end in a refund, so refund right away.
+        let refund = refund
+            || CaseDispute::reserve_sat_for_connection(&mut *tx, deadline.case_id).await? == 0;
+        let (policy, message) = if refund {
+            ("refund", "Review deadline passed, case refunded")
+        } else {
+            (
+                "escalate",
+                "Review deadline passed, case escalated to an admin",
+            )
+        };
+        CaseAuditEvent::insert_for_connection(
+            &mut *tx,
+            deadline.case_id,
+            None,
+            "review_deadline_passed",
+            policy,
         )
-        .execute(&mut **db)
         .await?;
+        if refund {
+            // Canceling clears the case details, so keep them in the revision history first.
+            CaseRevision::insert_original_for_connection(&mut *tx, deadline.case_id).await?;
+            Case::mark_as_canceled_by_seller_for_connection(
+                &mut *tx,
+                deadline.case_id,
+                "review_deadline_passed",
+            )
+            .await?;
+        } else {
+            let dispute = CaseDispute {
+                id: None,
+                public_id: util::create_uuid(),
+                case_id: deadline.case_id,
+                case_public_id: None,
+                reason: "The poster did not review the case before the review deadline."
+                    .to_string(),
+                resolved: false,
+                resolution: "".to_string(),
+                submitter_share_basis_points: 0,
+                resolution_note: "".to_string(),
+                resolved_by_user_id: None,
+                reserved_sat: 0,
+                created_time_ms: now,
+                resolved_time_ms: 0,
+            };
+            CaseDispute::insert_for_connection(&mut *tx, &dispute).await?;
+            CaseAuditEvent::insert_for_connection(
+                &mut *tx,
+                deadline.case_id,
+                None,
+                "dispute_opened",
+                &dispute.reason,
+            )
+            .await?;
+        }
+        for user_id in [deadline.buyer_user_id, deadline.seller_user_id] {
+            Notification::insert_for_case(
+                &mut *tx,
+                deadline.case_id,
+                user_id,
+                "review_deadline_passed",
+                message,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
 
         Ok(())
     }
+}
 
-    pub async fn number_of_users(db: &mut Connection<Db>) -> Result<u64, sqlx::Error> {
-        let num_users = sqlx::query!(
+impl ResponseTimeStats {
+    /// How quickly a poster reviews the paid cases on their bounties. Awarding, rejecting
+    /// and requesting changes all count as a response, but a deadline passing does not.
+    pub async fn single_for_seller(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<ResponseTimeStats, sqlx::Error> {
+        let response_time_stats = sqlx::query(
             "
 select
- COUNT(users.id) as num_users
+ COUNT(response_time_ms) as num_reviewed_cases,
+ AVG(response_time_ms) as average_response_time_ms,
+ (SELECT COUNT(caseauditevents.id) FROM caseauditevents JOIN cases ON caseauditevents.case_id = cases.id WHERE cases.seller_user_id = ? AND caseauditevents.event_type = 'review_deadline_passed') as num_missed_deadlines
 from
- users
+(select
+ (SELECT MIN(caseauditevents.created_time_ms) FROM caseauditevents WHERE caseauditevents.case_id = cases.id AND caseauditevents.user_id = cases.seller_user_id AND caseauditevents.event_type IN ('awarded', 'canceled_by_poster', 'changes_requested') AND caseauditevents.details != 'review_deadline_passed') - cases.payment_time_ms as response_time_ms
+from
+ cases
 WHERE
- NOT users.is_admin
+ cases.seller_user_id = ?
+AND
+ cases.paid)
 ;",
         )
+        .bind(user_id)
+        .bind(user_id)
         .fetch_one(&mut **db)
-        .map_ok(|r| r.num_users as u64)
+        .map_ok(|r| {
+            let maybe_average_response_time_ms: Option<f64> =
+                r.try_get("average_response_time_ms").unwrap();
+            ResponseTimeStats {
+                num_reviewed_cases: r.try_get("num_reviewed_cases").unwrap(),
+                average_response_time_hours: maybe_average_response_time_ms
+                    .map(|time_ms| (time_ms / (60.0 * 60.0 * 1000.0)).round() as u64),
+                num_missed_deadlines: r.try_get("num_missed_deadlines").unwrap(),
+            }
+        })
         .await?;
 
-        Ok(num_users)
+        Ok(response_time_stats)
     }
+}
 
-    pub async fn all_older_than(
-        db: &mut PoolConnection<Sqlite>,
-        created_time_ms: u64,
-    ) -> Result<Vec<UserAccount>, sqlx::Error> {
-        let created_time_ms_i64: i64 = created_time_ms.try_into().unwrap();
+impl TwoFactorSettings {
+    pub async fn single_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<Option<TwoFactorSettings>, sqlx::Error> {
+        let maybe_settings = sqlx::query("select * from twofactorsettings WHERE user_id = ?;")
+            .bind(user_id)
+            .fetch_optional(&mut **db)
+            .map_ok(|maybe_r| {
+                maybe_r.map(|r| TwoFactorSettings {
+                    user_id: r.try_get("user_id").unwrap(),
+                    secret: r.try_get("secret").unwrap(),
+                    enabled: r.try_get("enabled").unwrap(),
+                    last_used_step: {
+                        let step_i64: i64 = r.try_get("last_used_step").unwrap();
+                        step_i64 as u64
+                    },
+                    created_time_ms: {
+                        let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                        time_ms_i64 as u64
+                    },
+                })
+            })
+            .await?;
 
-        let user_accounts = sqlx::query!(
-            "
-select *
-from
- useraccounts
-WHERE
- created_time_ms < ?
-AND
- NOT paid
-;",
-            created_time_ms_i64,
+        Ok(maybe_settings)
+    }
+
+    pub async fn enabled_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let maybe_settings = TwoFactorSettings::single_for_user(db, user_id).await?;
+
+        Ok(maybe_settings
+            .map(|settings| settings.enabled)
+            .unwrap_or(false))
+    }
+
+    /// Saves a new secret that is not used for logins until it is confirmed.
+    pub async fn start_enrollment(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        secret: &str,
+        created_time_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let created_time_ms: i64 = created_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO twofactorsettings (user_id, secret, enabled, last_used_step, created_time_ms) VALUES (?, ?, false, 0, ?) ON CONFLICT(user_id) DO UPDATE SET secret = excluded.secret, last_used_step = 0, created_time_ms = excluded.created_time_ms WHERE NOT enabled",
         )
-        .fetch(&mut **db)
-        .map_ok(|r| UserAccount {
-            id: Some(r.id.try_into().unwrap()),
-            public_id: r.public_id,
-            user_id: r.user_id.try_into().unwrap(),
-            amount_owed_sat: r.amount_owed_sat.try_into().unwrap(),
-            paid: r.paid,
-            disabled: r.disabled,
-            invoice_payment_request: r.invoice_payment_request,
-            invoice_hash: r.invoice_hash,
-            created_time_ms: r.created_time_ms.try_into().unwrap(),
-            payment_time_ms: r.payment_time_ms.try_into().unwrap(),
-        })
-        .try_collect::<Vec<_>>()
+        .bind(user_id)
+        .bind(secret)
+        .bind(created_time_ms)
+        .execute(&mut **db)
         .await?;
 
-        Ok(user_accounts)
+        Ok(())
     }
 
-    pub async fn delete_expired_user_account(
-        db: &mut PoolConnection<Sqlite>,
-        user_account_id: i32,
-        cancel_user_account_invoice_future: impl Future<
-            Output = Result<tonic_openssl_lnd::invoicesrpc::CancelInvoiceResp, String>,
-        >,
+    /// Turns on two-factor logins with the confirmed secret, and replaces the recovery codes.
+    pub async fn enable(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        confirmed_step: u64,
+        recovery_code_hashes: &[String],
+        now: u64,
     ) -> Result<(), String> {
+        let confirmed_step: i64 = confirmed_step.try_into().unwrap();
         let mut tx = db
             .begin()
             .await
             .map_err(|_| "failed to begin transaction.")?;
 
-        sqlx::query!(
-            "
-DELETE FROM useraccounts
-WHERE
- user_id = ?
-AND
- NOT paid
-;",
-            user_account_id,
+        sqlx::query(
+            "UPDATE twofactorsettings SET enabled = true, last_used_step = ? WHERE user_id = ?",
         )
+        .bind(confirmed_step)
+        .bind(user_id)
         .execute(&mut *tx)
         .await
-        .map_err(|_| "failed to delete user account from database.")?;
+        .map_err(|_| "failed to enable two-factor authentication.")?;
 
-        sqlx::query!(
-            "
-DELETE FROM users
-WHERE
- id = ?
-;",
-            user_account_id,
+        TwoFactorSettings::replace_recovery_codes_for_connection(
+            &mut tx,
+            user_id,
+            recovery_code_hashes,
+            now,
         )
-        .execute(&mut *tx)
-        .await
-        .map_err(|_| "failed to delete user from database.")?;
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
+    }
+
+    /// Marks a time step as used. Returns false if it or a later step was already used.
+    pub async fn use_step(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        step: u64,
+    ) -> Result<bool, sqlx::Error> {
+        let step: i64 = step.try_into().unwrap();
+        let update_result = sqlx::query(
+            "UPDATE twofactorsettings SET last_used_step = ? WHERE user_id = ? AND enabled AND last_used_step < ?",
+        )
+        .bind(step)
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(update_result.rows_affected() == 1)
+    }
+
+    pub async fn disable(db: &mut Connection<Db>, user_id: i32) -> Result<(), String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        sqlx::query("DELETE FROM twofactorsettings WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to delete two-factor settings.")?;
+
+        sqlx::query("DELETE FROM twofactorrecoverycodes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to delete recovery codes.")?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
+    }
+
+    pub async fn replace_recovery_codes(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        code_hashes: &[String],
+        now: u64,
+    ) -> Result<(), String> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|_| "failed to begin transaction.")?;
+
+        TwoFactorSettings::replace_recovery_codes_for_connection(
+            &mut tx,
+            user_id,
+            code_hashes,
+            now,
+        )
+        .await?;
+
+        tx.commit()
+            .await
+            .map_err(|_| "failed to commit transaction.")?;
+
+        Ok(())
+    }
 
-        cancel_user_account_invoice_future
+    async fn replace_recovery_codes_for_connection(
+        conn: &mut SqliteConnection,
+        user_id: i32,
+        code_hashes: &[String],
+        now: u64,
+    ) -> Result<(), String> {
+        let created_time_ms: i64 = now.try_into().unwrap();
+
+        sqlx::query("DELETE FROM twofactorrecoverycodes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *conn)
             .await
-            .map_err(|e| format!("failed to cancel user account invoice: {:?}", e))?;
+            .map_err(|_| "failed to delete old recovery codes.")?;
 
-        tx.commit()
+        for code_hash in code_hashes {
+            sqlx::query(
+                "INSERT INTO twofactorrecoverycodes (user_id, code_hash, used, created_time_ms) VALUES (?, ?, false, ?)",
+            )
+            .bind(user_id)
+            .bind(code_hash)
+            .bind(created_time_ms)
+            .execute(&mut *conn)
             .await
-            .map_err(|_| "failed to begin transaction.")?;
+            .map_err(|_| "failed to insert recovery code.")?;
+        }
 
         Ok(())
     }
 
-    pub async fn delete_users_with_no_account(
-        db: &mut PoolConnection<Sqlite>,
-    ) -> Result<(), String> {
-        sqlx::query!(
-            "
-DELETE FROM users
-WHERE
- NOT is_admin
-AND
- id IN
-(SELECT users.id FROM users
-LEFT JOIN
- useraccounts
-ON
- users.id=useraccounts.user_id
-WHERE
- useraccounts.user_id IS NULL);
-;"
+    /// Uses up a recovery code. Returns false if the code does not exist or was already used.
+    pub async fn use_recovery_code(
+        db: &mut Connection<Db>,
+        user_id: i32,
+        code_hash: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let update_result = sqlx::query(
+            "UPDATE twofactorrecoverycodes SET used = true WHERE user_id = ? AND code_hash = ? AND NOT used",
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(update_result.rows_affected() >= 1)
+    }
+
+    pub async fn num_unused_recovery_codes(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<u32, sqlx::Error> {
+        let num_unused = sqlx::query(
+            "select count(id) as num_unused from twofactorrecoverycodes WHERE user_id = ? AND NOT used;",
+        )
+        .bind(user_id)
+        .fetch_one(&mut **db)
+        .map_ok(|r| r.try_get("num_unused").unwrap())
+        .await?;
+
+        Ok(num_unused)
+    }
+}
+
+impl TwoFactorPolicy {
+    pub async fn single(db: &mut Connection<Db>) -> Result<TwoFactorPolicy, sqlx::Error> {
+        let maybe_policy = sqlx::query("select * from twofactorpolicy WHERE id = 1;")
+            .fetch_optional(&mut **db)
+            .map_ok(|maybe_r| {
+                maybe_r.map(|r| TwoFactorPolicy {
+                    require_for_withdrawals: r.try_get("require_for_withdrawals").unwrap(),
+                    require_for_admins: r.try_get("require_for_admins").unwrap(),
+                })
+            })
+            .await?;
+
+        Ok(maybe_policy.unwrap_or_default())
+    }
+
+    pub async fn set(db: &mut Connection<Db>, policy: TwoFactorPolicy) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO twofactorpolicy (id, require_for_withdrawals, require_for_admins) VALUES (1, ?, ?) ON CONFLICT(id) DO UPDATE SET require_for_withdrawals = excluded.require_for_withdrawals, require_for_admins = excluded.require_for_admins",
         )
+        .bind(policy.require_for_withdrawals)
+        .bind(policy.require_for_admins)
         .execute(&mut **db)
-        .await
-        .map_err(|_| "failed to delete user account from database.")?;
+        .await?;
 
         Ok(())
     }
+}
 
-    pub async fn do_deactivation(
-        amount_sat: u64,
-        user_account: UserAccount,
+impl PasswordResetToken {
+    /// Saves a new token, and drops the earlier ones of the user so only the latest works.
+    pub async fn insert(
+        password_reset_token: PasswordResetToken,
         db: &mut Connection<Db>,
-        send_deactivation_funds_future: impl Future<
-            Output = Result<tonic_openssl_lnd::lnrpc::SendResponse, String>,
-        >,
     ) -> Result<(), String> {
+        let expiry_time_ms: i64 = password_reset_token.expiry_time_ms.try_into().unwrap();
+        let created_time_ms: i64 = password_reset_token.created_time_ms.try_into().unwrap();
         let mut tx = db
             .begin()
             .await
             .map_err(|_| "failed to begin transaction.")?;
 
-        // Insert the new withdrawal.
-        let activation_bond_amount_sat: i64 = user_account.amount_owed_sat.try_into().unwrap();
-        let amount_sat: i64 = amount_sat.try_into().unwrap();
-        let delete_user_account_result = sqlx::query!(
-            "
-DELETE FROM useraccounts
-WHERE
- user_id = ?
-AND
- paid = true
-;",
-            user_account.user_id,
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|_| "failed to delete user account.")?;
-
-        // Validate that at least one paid user account was deleted
-        if delete_user_account_result.rows_affected() < 1 {
-            return Err("No user bond found.".to_string());
-        }
-
-        sqlx::query!(
-            "
-DELETE FROM users
-WHERE id = ?
-;",
-            user_account.user_id,
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|_| "failed to delete user.")?;
-
-        sqlx::query!(
-            "
-DELETE FROM bountyimages
-WHERE
- bounty_id IN
-(SELECT bounties.id FROM bounties
-WHERE user_id = ?);
-;",
-            user_account.user_id,
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|_| "failed to delete user bounty images.")?;
+        sqlx::query("DELETE FROM passwordresettokens WHERE user_id = ?")
+            .bind(password_reset_token.user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "failed to delete old password reset tokens.")?;
 
-        sqlx::query!(
-            "
-DELETE FROM bounties
-WHERE user_id = ?
-;",
-            user_account.user_id,
+        sqlx::query(
+            "INSERT INTO passwordresettokens (user_id, token_hash, method, used, expiry_time_ms, created_time_ms) VALUES (?, ?, ?, ?, ?, ?)",
         )
+        .bind(password_reset_token.user_id)
+        .bind(password_reset_token.token_hash)
+        .bind(password_reset_token.method)
+        .bind(password_reset_token.used)
+        .bind(expiry_time_ms)
+        .bind(created_time_ms)
         .execute(&mut *tx)
         .await
-        .map_err(|_| "failed to delete user bounties.")?;
-
-        if activation_bond_amount_sat - amount_sat < 0 {
-            return Err("Insufficient funds for deactivation.".to_string());
-        }
-
-        send_deactivation_funds_future
-            .await
-            .map_err(|e| format!("failed to send deactivation payment: {:?}", e))?;
+        .map_err(|_| "failed to insert password reset token.")?;
 
         tx.commit()
             .await
@@ -3467,118 +9852,93 @@ WHERE user_id = ?
 
         Ok(())
     }
-}
 
-impl UserCard {
-    pub async fn all_active(
+    pub async fn single_unused_by_hash(
         db: &mut Connection<Db>,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<UserCard>, sqlx::Error> {
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let user_cards =
-            sqlx::query!("
-select
- users.id as rocket_auth_user_id, users.email as rocket_auth_user_username, useraccounts.id as useraccounts_id, useraccounts.public_id as useraccounts_public_id, useraccounts.user_id as useraccounts_user_id, useraccounts.amount_owed_sat as useraccounts_amount_owed_sat, useraccounts.paid as useraccounts_paid, useraccounts.disabled as useraccounts_disabled, useraccounts.invoice_payment_request as useraccounts_invoice_payment_request, useraccounts.invoice_hash as useraccounts_invoice_hash, useraccounts.created_time_ms as useraccounts_created_time_ms, useraccounts.payment_time_ms as useraccounts_payment_time_ms
-from
- users
-INNER JOIN
- useraccounts
-ON
- users.id = useraccounts.user_id
-WHERE
- useraccounts.paid
-AND
- NOT useraccounts.disabled
-GROUP BY
- users.id
-ORDER BY useraccounts.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", limit, offset)
-            .fetch(&mut **db)
-            .map_ok(|r| {
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                let ua = r.useraccounts_id.map(|user_account_id| UserAccount {
-                    id: Some(user_account_id.try_into().unwrap()),
-                    public_id: r.useraccounts_public_id.unwrap(),
-                    user_id: r.useraccounts_user_id.unwrap().try_into().unwrap(),
-                    amount_owed_sat: r.useraccounts_amount_owed_sat.unwrap().try_into().unwrap(),
-                    paid: r.useraccounts_paid.unwrap(),
-                    disabled: r.useraccounts_disabled.unwrap(),
-                    invoice_payment_request: r.useraccounts_invoice_payment_request.unwrap(),
-                    invoice_hash: r.useraccounts_invoice_hash.unwrap(),
-                    created_time_ms: r.useraccounts_created_time_ms.unwrap().try_into().unwrap(),
-                    payment_time_ms: r.useraccounts_payment_time_ms.unwrap().try_into().unwrap(),
-                });
-                UserCard {
-                    user: u.unwrap(),
-                    user_account: ua.unwrap(),
-                }
+        token_hash: &str,
+        time_now_ms: u64,
+    ) -> Result<Option<PasswordResetToken>, sqlx::Error> {
+        let time_now_ms: i64 = time_now_ms.try_into().unwrap();
+        let maybe_token = sqlx::query(
+            "select * from passwordresettokens WHERE token_hash = ? AND NOT used AND expiry_time_ms > ?;",
+        )
+        .bind(token_hash)
+        .bind(time_now_ms)
+        .fetch_optional(&mut **db)
+        .map_ok(|maybe_r| {
+            maybe_r.map(|r| PasswordResetToken {
+                id: Some(r.try_get("id").unwrap()),
+                user_id: r.try_get("user_id").unwrap(),
+                token_hash: r.try_get("token_hash").unwrap(),
+                method: r.try_get("method").unwrap(),
+                used: r.try_get("used").unwrap(),
+                expiry_time_ms: {
+                    let time_ms_i64: i64 = r.try_get("expiry_time_ms").unwrap();
+                    time_ms_i64 as u64
+                },
+                created_time_ms: {
+                    let time_ms_i64: i64 = r.try_get("created_time_ms").unwrap();
+                    time_ms_i64 as u64
+                },
             })
-                .try_collect::<Vec<_>>()
+        })
+        .await?;
+
+        Ok(maybe_token)
+    }
+
+    /// Uses up a token. Returns false if it was already used.
+    pub async fn mark_as_used(
+        db: &mut Connection<Db>,
+        password_reset_token_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let update_result =
+            sqlx::query("UPDATE passwordresettokens SET used = true WHERE id = ? AND NOT used")
+                .bind(password_reset_token_id)
+                .execute(&mut **db)
                 .await?;
 
-        Ok(user_cards)
+        Ok(update_result.rows_affected() == 1)
     }
+}
 
-    pub async fn all_disabled(
+impl SessionReset {
+    /// Ends the sessions of a user that were logged in before the given time.
+    pub async fn upsert(
         db: &mut Connection<Db>,
-        page_size: u32,
-        page_num: u32,
-    ) -> Result<Vec<UserCard>, sqlx::Error> {
-        let offset = (page_num - 1) * page_size;
-        let limit = page_size;
-        let user_cards =
-            sqlx::query!("
-select
- users.id as rocket_auth_user_id, users.email as rocket_auth_user_username, useraccounts.id as useraccounts_id, useraccounts.public_id as useraccounts_public_id, useraccounts.user_id as useraccounts_user_id, useraccounts.amount_owed_sat as useraccounts_amount_owed_sat, useraccounts.paid as useraccounts_paid, useraccounts.disabled as useraccounts_disabled, useraccounts.invoice_payment_request as useraccounts_invoice_payment_request, useraccounts.invoice_hash as useraccounts_invoice_hash, useraccounts.created_time_ms as useraccounts_created_time_ms, useraccounts.payment_time_ms as useraccounts_payment_time_ms
-from
- users
-INNER JOIN
- useraccounts
-ON
- users.id = useraccounts.user_id
-WHERE
- useraccounts.paid
-AND
- useraccounts.disabled
-GROUP BY
- users.id
-ORDER BY useraccounts.created_time_ms DESC
-LIMIT ?
-OFFSET ?
-;", limit, offset)
-            .fetch(&mut **db)
-            .map_ok(|r| {
-                let u = r.rocket_auth_user_id.map(|rocket_auth_user_id| RocketAuthUser {
-                    id: Some(rocket_auth_user_id.try_into().unwrap()),
-                    username: r.rocket_auth_user_username.unwrap(),
-                });
-                let ua = r.useraccounts_id.map(|user_account_id| UserAccount {
-                    id: Some(user_account_id.try_into().unwrap()),
-                    public_id: r.useraccounts_public_id.unwrap(),
-                    user_id: r.useraccounts_user_id.unwrap().try_into().unwrap(),
-                    amount_owed_sat: r.useraccounts_amount_owed_sat.unwrap().try_into().unwrap(),
-                    paid: r.useraccounts_paid.unwrap(),
-                    disabled: r.useraccounts_disabled.unwrap(),
-                    invoice_payment_request: r.useraccounts_invoice_payment_request.unwrap(),
-                    invoice_hash: r.useraccounts_invoice_hash.unwrap(),
-                    created_time_ms: r.useraccounts_created_time_ms.unwrap().try_into().unwrap(),
-                    payment_time_ms: r.useraccounts_payment_time_ms.unwrap().try_into().unwrap(),
-                });
-                UserCard {
-                    user: u.unwrap(),
-                    user_account: ua.unwrap(),
-                }
+        user_id: i32,
+        reset_time_ms: u64,
+    ) -> Result<(), sqlx::Error> {
+        let reset_time_ms: i64 = reset_time_ms.try_into().unwrap();
+        sqlx::query(
+            "INSERT INTO sessionresets (user_id, reset_time_ms) VALUES (?, ?) ON CONFLICT(user_id) DO UPDATE SET reset_time_ms = excluded.reset_time_ms",
+        )
+        .bind(user_id)
+        .bind(reset_time_ms)
+        .execute(&mut **db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn single_for_user(
+        db: &mut Connection<Db>,
+        user_id: i32,
+    ) -> Result<Option<SessionReset>, sqlx::Error> {
+        let maybe_session_reset = sqlx::query("select * from sessionresets WHERE user_id = ?;")
+            .bind(user_id)
+            .fetch_optional(&mut **db)
+            .map_ok(|maybe_r| {
+                maybe_r.map(|r| SessionReset {
+                    user_id: r.try_get("user_id").unwrap(),
+                    reset_time_ms: {
+                        let time_ms_i64: i64 = r.try_get("reset_time_ms").unwrap();
+                        time_ms_i64 as u64
+                    },
+                })
             })
-                .try_collect::<Vec<_>>()
-                .await?;
+            .await?;
 
-        Ok(user_cards)
+        Ok(maybe_session_reset)
     }
 }
@@ -1,6 +1,7 @@
 use crate::base::BaseContext;
 use crate::db::Db;
-use crate::models::{AccountBalanceChange, AccountInfo};
+use crate::ledger_export::{self, LedgerExport, LedgerExportParams};
+use crate::models::{AccountBalanceChange, AccountInfo, LedgerEntry};
 use crate::user_account::ActiveUser;
 use rocket::fairing::AdHoc;
 use rocket::request::FlashMessage;
@@ -19,6 +20,7 @@ struct Context {
     account_balance_sat: i64,
     account_balance_changes: Vec<AccountBalanceChange>,
     page_num: u32,
+    event_types: Vec<String>,
 }
 
 impl Context {
@@ -48,6 +50,10 @@ impl Context {
             account_balance_sat,
             account_balance_changes,
             page_num,
+            event_types: ledger_export::USER_LEDGER_EVENT_TYPES
+                .iter()
+                .map(|event_type| event_type.to_string())
+                .collect(),
         })
     }
 }
@@ -67,9 +73,29 @@ async fn index(
     Ok(Template::render("myaccountbalance", context))
 }
 
+#[get("/export?<params..>")]
+async fn export(
+    mut db: Connection<Db>,
+    params: LedgerExportParams,
+    active_user: ActiveUser,
+) -> Result<LedgerExport, String> {
+    let (start_time_ms, end_time_ms) = params.time_range_ms();
+    let maybe_event_type = params.event_type(ledger_export::USER_LEDGER_EVENT_TYPES)?;
+    let ledger_entries = LedgerEntry::all_for_user(
+        &mut db,
+        active_user.user.id,
+        start_time_ms,
+        end_time_ms,
+        maybe_event_type,
+    )
+    .await
+    .map_err(|_| "failed to get ledger entries.")?;
+    ledger_export::render(ledger_entries, params.format, "ledger")
+}
+
 pub fn my_account_balance_stage() -> AdHoc {
     AdHoc::on_ignite("My Account Balance Stage", |rocket| async {
-        rocket.mount("/my_account_balance", routes![index])
+        rocket.mount("/my_account_balance", routes![index, export])
         // .mount("/bounty", routes![new])
     })
 }
@@ -0,0 +1,157 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Case, CaseTeamMember, TeamCaseCard};
+use crate::user_account::ActiveUser;
+use rocket::fairing::AdHoc;
+use rocket::request::FlashMessage;
+use rocket::response::Flash;
+use rocket::response::Redirect;
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+const PAGE_SIZE: u32 = 10;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    invitations: Vec<TeamCaseCard>,
+    team_case_cards: Vec<TeamCaseCard>,
+    page_num: u32,
+}
+
+impl Context {
+    pub async fn raw(
+        flash: Option<(String, String)>,
+        mut db: Connection<Db>,
+        maybe_page_num: Option<u32>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let page_num = maybe_page_num.unwrap_or(1);
+        let invitations = TeamCaseCard::all_for_user(&mut db, user.id, false, PAGE_SIZE, 1)
+            .await
+            .map_err(|_| "failed to get team invitations.")?;
+        let team_case_cards =
+            TeamCaseCard::all_for_user(&mut db, user.id, true, PAGE_SIZE, page_num)
+                .await
+                .map_err(|_| "failed to get team cases.")?;
+        Ok(Context {
+            base_context,
+            flash,
+            invitations,
+            team_case_cards,
+            page_num,
+        })
+    }
+}
+
+#[put("/<id>/accept")]
+async fn accept(
+    id: &str,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match respond_to_invitation(&mut db, id, active_user.user, true).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to("/my_team_cases"),
+            "Invitation accepted.",
+        )),
+        Err(e) => {
+            error_!("Accept invitation({}) error: {}", id, e);
+            Err(Flash::error(Redirect::to("/my_team_cases"), e))
+        }
+    }
+}
+
+#[put("/<id>/decline")]
+async fn decline(
+    id: &str,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    match respond_to_invitation(&mut db, id, active_user.user, false).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to("/my_team_cases"),
+            "Invitation declined.",
+        )),
+        Err(e) => {
+            error_!("Decline invitation({}) error: {}", id, e);
+            Err(Flash::error(Redirect::to("/my_team_cases"), e))
+        }
+    }
+}
+
+async fn respond_to_invitation(
+    db: &mut Connection<Db>,
+    id: &str,
+    user: User,
+    accept: bool,
+) -> Result<(), String> {
+    let member = CaseTeamMember::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get invitation.")?;
+    if member.user_id != user.id() {
+        return Err("Invitation belongs to a different user.".to_string());
+    };
+    if member.accepted {
+        return Err("Invitation has already been accepted.".to_string());
+    };
+    let case = Case::single(db, member.case_id)
+        .await
+        .map_err(|_| "failed to get case.")?;
+    if case.awarded || case.canceled_by_seller || case.canceled_by_buyer {
+        return Err("Case is already closed.".to_string());
+    };
+
+    if accept {
+        CaseTeamMember::mark_as_accepted(db, member.id.unwrap())
+            .await
+            .map_err(|_| "failed to accept invitation.")?;
+    } else {
+        CaseTeamMember::delete(db, member.id.unwrap())
+            .await
+            .map_err(|_| "failed to decline invitation.")?;
+    }
+    Ok(())
+}
+
+#[get("/?<page_num>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    page_num: Option<u32>,
+    active_user: ActiveUser,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(
+        flash,
+        db,
+        page_num,
+        active_user.user,
+        admin_user,
+        csrf_token,
+    )
+    .await
+    .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("myteamcases", context))
+}
+
+pub fn my_team_cases_stage() -> AdHoc {
+    AdHoc::on_ignite("My Team Cases Stage", |rocket| async {
+        rocket.mount("/my_team_cases", routes![index, accept, decline])
+    })
+}
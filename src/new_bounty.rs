@@ -1,7 +1,8 @@
 use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::models::AdminSettings;
-use crate::models::{InitialBountyInfo, Bounty};
+use crate::models::{Bounty, BountyMilestone, InitialBountyInfo};
 use crate::user_account::ActiveUser;
 use crate::util;
 use rocket::fairing::AdHoc;
@@ -14,6 +15,7 @@ use rocket_db_pools::Connection;
 use rocket_dyn_templates::Template;
 
 const MAX_UNAPPROVED_BOUNTIES: u32 = 5;
+const MAX_MILESTONES: usize = 10;
 
 #[derive(Debug, Serialize)]
 #[serde(crate = "rocket::serde")]
@@ -29,10 +31,12 @@ impl Context {
         mut db: Connection<Db>,
         user: Option<User>,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, user.clone(), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let admin_settings = AdminSettings::single(&mut db)
             .await
             .map_err(|_| "failed to update market name.")?;
@@ -49,6 +53,7 @@ async fn new(
     bounty_form: Form<InitialBountyInfo>,
     mut db: Connection<Db>,
     active_user: ActiveUser,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     let bounty_info = bounty_form.into_inner();
 
@@ -95,6 +100,35 @@ async fn create_bounty(
         return Err("Admin user cannot create a bounty.".to_string());
     };
 
+    // Rows left blank in the form are not milestones.
+    let milestones: Vec<(String, u64)> = bounty_info
+        .milestones
+        .into_iter()
+        .filter(|m| !(m.description.is_empty() && m.reward_sat.unwrap_or(0) == 0))
+        .map(|m| (m.description, m.reward_sat.unwrap_or(0)))
+        .collect();
+    if milestones.len() > MAX_MILESTONES {
+        return Err(format!("No more than {} milestones allowed.", MAX_MILESTONES));
+    };
+    for (description, reward_sat) in milestones.iter() {
+        if description.is_empty() {
+            return Err("Milestone description cannot be empty.".to_string());
+        };
+        if description.len() > 1024 {
+            return Err("Milestone description length is too long.".to_string());
+        };
+        if *reward_sat == 0 {
+            return Err("Milestone reward must be a positive number.".to_string());
+        };
+    }
+    let total_reward_sat = milestones
+        .iter()
+        .try_fold(0u64, |total, (_, reward_sat)| total.checked_add(*reward_sat))
+        .ok_or("Milestone rewards must add up to the bounty reward.")?;
+    if !milestones.is_empty() && total_reward_sat != price_sat {
+        return Err("Milestone rewards must add up to the bounty reward.".to_string());
+    };
+
     let bounty = Bounty {
         id: None,
         public_id: util::create_uuid(),
@@ -110,7 +144,22 @@ async fn create_bounty(
         deactivated_by_admin: false,
         created_time_ms: now,
     };
-    match Bounty::insert(bounty, MAX_UNAPPROVED_BOUNTIES, db).await {
+    // The bounty id is filled in when the milestones are inserted with the bounty.
+    let milestones: Vec<BountyMilestone> = milestones
+        .into_iter()
+        .enumerate()
+        .map(|(position, (description, reward_sat))| BountyMilestone {
+            id: None,
+            public_id: util::create_uuid(),
+            bounty_id: 0,
+            position: position as u32,
+            description,
+            reward_sat,
+            awarded: false,
+            created_time_ms: now,
+        })
+        .collect();
+    match Bounty::insert(bounty, milestones, MAX_UNAPPROVED_BOUNTIES, db).await {
         Ok(bounty_id) => match Bounty::single(db, bounty_id).await {
             Ok(new_bounty) => Ok(new_bounty.public_id),
             Err(e) => {
@@ -131,9 +180,10 @@ async fn index(
     db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(flash, db, Some(active_user.user), admin_user)
+    let context = Context::raw(flash, db, Some(active_user.user), admin_user, csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("newbounty", context))
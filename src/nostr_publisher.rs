@@ -0,0 +1,156 @@
+use crate::config::Config;
+use crate::models::NostrEvent;
+use crate::util;
+use nostr::nips::nip19::{FromBech32, ToBech32};
+use nostr::secp256k1::{SecretKey, XOnlyPublicKey};
+use nostr::{Event, EventBuilder, Keys};
+use rocket::futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use sqlx::pool::PoolConnection;
+use sqlx::Sqlite;
+use std::str::FromStr;
+use tokio_tungstenite::tungstenite::Message;
+
+const MAX_NOSTR_ATTEMPTS: u32 = 5;
+const NOSTR_RETRY_BASE_DELAY_MS: u64 = 60 * 1000;
+const RELAY_TIMEOUT_S: u64 = 10;
+
+/// Publishing is turned off until relays and a market key are configured.
+pub fn nostr_enabled(config: &Config) -> bool {
+    !config.nostr_relays.is_empty() && !config.nostr_secret_key.is_empty()
+}
+
+/// Gets the market keys from the configured secret key, in hex or nsec form.
+pub fn market_keys(config: &Config) -> Result<Keys, String> {
+    let secret_key = SecretKey::from_bech32(&config.nostr_secret_key)
+        .or_else(|_| SecretKey::from_str(&config.nostr_secret_key))
+        .map_err(|_| "invalid nostr secret key.")?;
+    Ok(Keys::new(secret_key))
+}
+
+/// Gets the npub of the market key, to show users who their messages come from.
+pub fn market_npub(config: &Config) -> Result<String, String> {
+    market_keys(config)?
+        .public_key()
+        .to_bech32()
+        .map_err(|_| "failed to encode market public key.".to_string())
+}
+
+/// Parses a public key given as an npub or in hex.
+pub fn parse_public_key(public_key: &str) -> Result<XOnlyPublicKey, String> {
+    XOnlyPublicKey::from_bech32(public_key)
+        .or_else(|_| XOnlyPublicKey::from_str(public_key))
+        .map_err(|_| "Invalid Nostr public key.".to_string())
+}
+
+/// Publishes the queued events that are due, and schedules a retry for each one that fails.
+pub async fn send_pending_nostr_events(
+    config: Config,
+    mut conn: PoolConnection<Sqlite>,
+) -> Result<(), String> {
+    if !nostr_enabled(&config) {
+        return Ok(());
+    }
+    let keys = market_keys(&config)?;
+    let now = util::current_time_millis();
+    let nostr_events = NostrEvent::all_due(&mut conn, now, MAX_NOSTR_ATTEMPTS)
+        .await
+        .map_err(|_| "failed to get pending nostr events.")?;
+
+    for nostr_event in nostr_events {
+        let result = match build_event(&config, &keys, &nostr_event) {
+            Ok(event) => publish_event(&config.nostr_relays, &event).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => NostrEvent::mark_as_sent(&mut conn, nostr_event.id.unwrap())
+                .await
+                .map_err(|_| "failed to mark nostr event as sent.")?,
+            Err(e) => {
+                println!(
+                    "failed to publish nostr event {}: {:?}",
+                    nostr_event.public_id, e
+                );
+                let retry_delay_ms = NOSTR_RETRY_BASE_DELAY_MS << nostr_event.num_attempts;
+                NostrEvent::mark_as_failed(
+                    &mut conn,
+                    nostr_event.id.unwrap(),
+                    &e,
+                    now + retry_delay_ms,
+                )
+                .await
+                .map_err(|_| "failed to mark nostr event as failed.")?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Signs a queued event: a public note, or an encrypted direct message if it has a recipient.
+fn build_event(config: &Config, keys: &Keys, nostr_event: &NostrEvent) -> Result<Event, String> {
+    let content = format!(
+        "{}\n{}{}",
+        nostr_event.message, config.market_url, nostr_event.link
+    );
+    let builder = match (
+        nostr_event.recipient_user_id,
+        &nostr_event.recipient_public_key,
+    ) {
+        (None, _) => EventBuilder::new_text_note(content, &[]),
+        (Some(_), Some(recipient_public_key)) => {
+            let recipient_public_key = parse_public_key(recipient_public_key)?;
+            EventBuilder::new_encrypted_direct_msg(keys, recipient_public_key, content)
+                .map_err(|e| format!("failed to encrypt direct message: {:?}", e))?
+        }
+        // The user unlinked their key after the message was queued.
+        (Some(_), None) => return Err("recipient has no nostr public key.".to_string()),
+    };
+    builder
+        .to_event(keys)
+        .map_err(|e| format!("failed to sign nostr event: {:?}", e))
+}
+
+/// Sends an event to every relay. It counts as published if at least one relay accepts it.
+async fn publish_event(relays: &[String], event: &Event) -> Result<(), String> {
+    let client_message = json!(["EVENT", event]).to_string();
+    let mut errors = Vec::new();
+    for relay in relays {
+        match publish_to_relay(relay, &client_message).await {
+            Ok(_) => return Ok(()),
+            Err(e) => errors.push(format!("{}: {}", relay, e)),
+        }
+    }
+    Err(errors.join(", "))
+}
+
+async fn publish_to_relay(relay: &str, client_message: &str) -> Result<(), String> {
+    let timeout = rocket::tokio::time::Duration::from_secs(RELAY_TIMEOUT_S);
+    let (mut socket, _) =
+        rocket::tokio::time::timeout(timeout, tokio_tungstenite::connect_async(relay))
+            .await
+            .map_err(|_| "timed out connecting to relay.")?
+            .map_err(|e| format!("failed to connect to relay: {:?}", e))?;
+    socket
+        .send(Message::Text(client_message.to_string()))
+        .await
+        .map_err(|e| format!("failed to send event: {:?}", e))?;
+
+    // Relays answer with ["OK", <event id>, <accepted>, <message>]. Older relays do
+    // not answer at all, so a missing answer is not treated as a failure.
+    let result = match rocket::tokio::time::timeout(timeout, socket.next()).await {
+        Ok(Some(Ok(Message::Text(text)))) => match serde_json::from_str::<Vec<Value>>(&text) {
+            Ok(response) if response.first().and_then(|v| v.as_str()) == Some("OK") => {
+                if response.get(2).and_then(|v| v.as_bool()) == Some(false) {
+                    Err(format!("relay rejected event: {}", text))
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    };
+    socket.close(None).await.ok();
+    result
+}
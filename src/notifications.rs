@@ -0,0 +1,171 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Notification, NotificationPreferencesInput};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+const PAGE_SIZE: u32 = 10;
+
+pub const NOTIFICATION_EVENT_TYPES: [&str; 16] = [
+    "bounty_approved",
+    "bounty_rejected",
+    "case_received",
+    "case_paid",
+    "case_awarded",
+    "case_canceled",
+    "case_changes_requested",
+    "case_resubmitted",
+    "case_rated",
+    "dispute_opened",
+    "dispute_resolved",
+    "review_deadline_approaching",
+    "review_deadline_passed",
+    "claim_expired",
+    "withdrawal_sent",
+    "withdrawal_failed",
+];
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct NotificationPreference {
+    event_type: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    notifications: Vec<Notification>,
+    preferences: Vec<NotificationPreference>,
+    page_num: u32,
+}
+
+impl Context {
+    pub async fn raw(
+        flash: Option<(String, String)>,
+        mut db: Connection<Db>,
+        maybe_page_num: Option<u32>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let page_num = maybe_page_num.unwrap_or(1);
+        let notifications = Notification::all_for_user(&mut db, user.id(), PAGE_SIZE, page_num)
+            .await
+            .map_err(|_| "failed to get notifications.")?;
+        let disabled_event_types = Notification::disabled_event_types_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get notification preferences.")?;
+        let preferences = NOTIFICATION_EVENT_TYPES
+            .iter()
+            .map(|event_type| NotificationPreference {
+                event_type: event_type.to_string(),
+                enabled: !disabled_event_types.iter().any(|t| t == event_type),
+            })
+            .collect();
+        Ok(Context {
+            base_context,
+            flash,
+            notifications,
+            preferences,
+            page_num,
+        })
+    }
+}
+
+#[put("/<id>/read")]
+async fn read(id: &str, mut db: Connection<Db>, user: User, _csrf: CsrfChecked) -> Flash<Redirect> {
+    match Notification::mark_as_read(&mut db, user.id(), id).await {
+        Ok(_) => Flash::success(Redirect::to("/notifications"), "Marked as read."),
+        Err(e) => {
+            error_!("Mark notification read({}) error: {}", id, e);
+            Flash::error(
+                Redirect::to("/notifications"),
+                "failed to mark notification as read.",
+            )
+        }
+    }
+}
+
+#[put("/read_all")]
+async fn read_all(mut db: Connection<Db>, user: User, _csrf: CsrfChecked) -> Flash<Redirect> {
+    match Notification::mark_all_as_read(&mut db, user.id()).await {
+        Ok(_) => Flash::success(Redirect::to("/notifications"), "Marked all as read."),
+        Err(e) => {
+            error_!("Mark all notifications read error: {}", e);
+            Flash::error(
+                Redirect::to("/notifications"),
+                "failed to mark notifications as read.",
+            )
+        }
+    }
+}
+
+#[post("/preferences", data = "<preferences_form>")]
+async fn update_preferences(
+    preferences_form: Form<NotificationPreferencesInput>,
+    mut db: Connection<Db>,
+    user: User,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let preferences = preferences_form.into_inner();
+    let disabled_event_types = NOTIFICATION_EVENT_TYPES
+        .iter()
+        .filter(|event_type| {
+            !preferences
+                .enabled_event_types
+                .iter()
+                .any(|t| t == **event_type)
+        })
+        .copied()
+        .collect();
+
+    match Notification::set_disabled_event_types(&mut db, user.id(), disabled_event_types).await {
+        Ok(_) => Flash::success(
+            Redirect::to("/notifications"),
+            "Notification preferences updated.",
+        ),
+        Err(e) => {
+            error_!("Update notification preferences error: {}", e);
+            Flash::error(Redirect::to("/notifications"), e)
+        }
+    }
+}
+
+#[get("/?<page_num>")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    page_num: Option<u32>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(flash, db, page_num, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("notifications", context))
+}
+
+pub fn notifications_stage() -> AdHoc {
+    AdHoc::on_ignite("Notifications Stage", |rocket| async {
+        rocket.mount(
+            "/notifications",
+            routes![index, read, read_all, update_preferences],
+        )
+    })
+}
@@ -1,8 +1,12 @@
 use crate::base::BaseContext;
 use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::lightning;
-use crate::models::{Bounty, BountyDisplay, Case, CaseInfo,  UserSettings};
+use crate::models::{
+    Bounty, BountyClaim, BountyDisplay, BountyMilestone, Case, CaseInfo, CaseTeamMember,
+    RocketAuthUser, UserAccount, UserSettings,
+};
 use crate::user_account::ActiveUser;
 use crate::util;
 use pgp::composed::{Deserializable, Message};
@@ -19,6 +23,7 @@ use rocket_db_pools::Connection;
 use rocket_dyn_templates::Template;
 
 const MAX_UNPAID_ORDERS: u32 = 100;
+const MAX_CO_AUTHORS: usize = 5;
 
 #[derive(Debug, Serialize)]
 #[serde(crate = "rocket::serde")]
@@ -28,6 +33,7 @@ struct Context {
     bounty_display: Option<BountyDisplay>,
     quantity: i32,
     seller_user_settings: UserSettings,
+    open_milestones: Vec<BountyMilestone>,
 }
 
 impl Context {
@@ -38,22 +44,32 @@ impl Context {
         flash: Option<(String, String)>,
         user: User,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let bounty_display = BountyDisplay::single_by_public_id(&mut db, bounty_id)
             .await
             .map_err(|_| "failed to get admin settings.")?;
         let seller_user_settings = UserSettings::single(&mut db, bounty_display.bounty.user_id)
             .await
             .map_err(|_| "failed to get visited user settings.")?;
+        let open_milestones =
+            BountyMilestone::all_for_bounty(&mut db, bounty_display.bounty.id.unwrap())
+                .await
+                .map_err(|_| "failed to get bounty milestones.")?
+                .into_iter()
+                .filter(|milestone| !milestone.awarded)
+                .collect();
         Ok(Context {
             base_context,
             flash,
             bounty_display: Some(bounty_display),
             quantity,
             seller_user_settings,
+            open_milestones,
         })
     }
 }
@@ -66,6 +82,7 @@ async fn new(
     active_user: ActiveUser,
     _admin_user: Option<AdminUser>,
     config: &State<Config>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     let case_info = case_form.into_inner();
 
@@ -142,6 +159,81 @@ async fn create_case(
     if quantity == 0 {
         return Err("Quantity must be postive.".to_string());
     };
+    // While another hunter holds a claim on the bounty, nobody else can submit a case.
+    let maybe_active_claim = BountyClaim::active_for_bounty(db, bounty.id.unwrap())
+        .await
+        .map_err(|_| "failed to get bounty claim.")?
+        .filter(|claim| claim.expires_time_ms > now);
+    if let Some(claim) = maybe_active_claim {
+        if claim.user_id != user.id() {
+            return Err("Bounty is claimed by another hunter.".to_string());
+        };
+    };
+
+    let milestones = BountyMilestone::all_for_bounty(db, bounty.id.unwrap())
+        .await
+        .map_err(|_| "failed to get bounty milestones.")?;
+    let maybe_milestone = if milestones.is_empty() {
+        None
+    } else {
+        if milestones.iter().all(|milestone| milestone.awarded) {
+            return Err("Bounty is complete.".to_string());
+        };
+        let milestone_public_id = case_info.milestone_public_id.unwrap_or_default();
+        let milestone = milestones
+            .into_iter()
+            .find(|milestone| milestone.public_id == milestone_public_id)
+            .ok_or("Select a milestone for the case.")?;
+        if milestone.awarded {
+            return Err("Milestone has already been awarded.".to_string());
+        };
+        Some(milestone)
+    };
+
+    // Rows left blank in the form are not co-authors.
+    let co_authors: Vec<(String, u32)> = case_info
+        .co_authors
+        .into_iter()
+        .filter(|c| !c.username.is_empty())
+        .map(|c| (c.username, c.share_percent.unwrap_or(0)))
+        .collect();
+    if co_authors.len() > MAX_CO_AUTHORS {
+        return Err(format!("No more than {} co-authors allowed.", MAX_CO_AUTHORS));
+    };
+    let mut co_author_users: Vec<(i32, u32)> = vec![];
+    for (username, share_percent) in co_authors {
+        if !(1..=99).contains(&share_percent) {
+            return Err("Co-author share must be between 1 and 99 percent.".to_string());
+        };
+        let co_author_user = RocketAuthUser::single_by_username(db, username.clone())
+            .await
+            .map_err(|_| format!("User {} not found.", username))?;
+        let co_author_user_id = co_author_user.id.unwrap();
+        let co_author_is_active = UserAccount::single(db, co_author_user_id)
+            .await
+            .map(|user_account| user_account.paid && !user_account.disabled)
+            .unwrap_or(false);
+        if !co_author_is_active {
+            return Err(format!("User {} is not an active user.", username));
+        };
+        if co_author_user_id == user.id() || co_author_user_id == bounty.user_id {
+            return Err(format!("User {} cannot be a co-author.", username));
+        };
+        if co_author_users.iter().any(|(id, _)| *id == co_author_user_id) {
+            return Err(format!("User {} is listed more than once.", username));
+        };
+        co_author_users.push((co_author_user_id, share_percent));
+    }
+    // The submitter keeps whatever share is not given to co-authors.
+    let total_share_percent = co_author_users
+        .iter()
+        .try_fold(0u32, |total, (_, share_percent)| {
+            total.checked_add(*share_percent)
+        })
+        .unwrap_or(u32::MAX);
+    if total_share_percent >= 100 {
+        return Err("Co-author shares must add up to less than 100 percent.".to_string());
+    };
 
     let mut lightning_client = lightning::get_lnd_lightning_client(
         config.lnd_host.clone(),
@@ -180,14 +272,43 @@ async fn create_case(
         payment_time_ms: 0,
     };
 
-    match Case::insert(case, MAX_UNPAID_ORDERS, db).await {
-        Ok(case_id) => match Case::single(db, case_id).await {
-            Ok(new_case) => Ok(new_case.public_id),
-            Err(e) => {
-                error_!("DB insertion error: {}", e);
-                Err("New case could not be found after inserting.".to_string())
+    let maybe_milestone_id = maybe_milestone.and_then(|milestone| milestone.id);
+    // The case id is filled in when the team is inserted with the case.
+    let team_members: Vec<CaseTeamMember> = co_author_users
+        .into_iter()
+        .map(|(co_author_user_id, share_percent)| CaseTeamMember {
+            id: None,
+            public_id: util::create_uuid(),
+            case_id: 0,
+            user_id: co_author_user_id,
+            username: None,
+            share_basis_points: share_percent * 100,
+            accepted: false,
+            created_time_ms: now,
+        })
+        .collect();
+    match Case::insert(
+        case,
+        maybe_milestone_id,
+        team_members,
+        MAX_UNPAID_ORDERS,
+        db,
+    )
+    .await
+    {
+        Ok(case_id) => {
+            // Submitting a case ends the claim of the hunter and returns their stake.
+            BountyClaim::release_for_user(db, bounty.id.unwrap(), user.id(), "case_submitted", now)
+                .await
+                .map_err(|_| "failed to release bounty claim.")?;
+            match Case::single(db, case_id).await {
+                Ok(new_case) => Ok(new_case.public_id),
+                Err(e) => {
+                    error_!("DB insertion error: {}", e);
+                    Err("New case could not be found after inserting.".to_string())
+                }
             }
-        },
+        }
         Err(e) => {
             error_!("DB insertion error: {}", e);
             Err(e)
@@ -207,6 +328,7 @@ async fn index(
     db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
     let context = Context::raw(
@@ -216,6 +338,7 @@ async fn index(
         flash,
         active_user.user,
         admin_user,
+        csrf_token,
     )
     .await
     .map_err(|_| "failed to get template context.")?;
@@ -0,0 +1,296 @@
+use crate::base::BaseContext;
+use crate::config::Config;
+use crate::db::Db;
+use crate::util;
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{Method, Status};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest};
+use rocket::{Data, Request};
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+const RATE_LIMIT_WINDOW_MS: u64 = 60 * 1000;
+const MAX_TRACKED_KEYS: usize = 10000;
+
+/// The groups of routes that share a rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RouteGroup {
+    Auth,
+    CaseCreation,
+    Withdrawal,
+    ImageUpload,
+}
+
+impl RouteGroup {
+    fn for_request(method: Method, path: &str) -> Option<RouteGroup> {
+        if method != Method::Post {
+            return None;
+        };
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        match segments.as_slice() {
+            ["login"] | ["signup"] | ["two_factor", "verify"] => Some(RouteGroup::Auth),
+            ["reset_password"] | ["reset_password", "confirm"] => Some(RouteGroup::Auth),
+            ["prepare_case", _, "new"] => Some(RouteGroup::CaseCreation),
+            ["withdraw", "new"] | ["withdraw_fees", "new"] | ["deactivate_account", "new"] => {
+                Some(RouteGroup::Withdrawal)
+            }
+            ["update_bounty_images", _, "add_image"] | ["case_attachments", _] => {
+                Some(RouteGroup::ImageUpload)
+            }
+            _ => None,
+        }
+    }
+
+    /// The number of requests allowed per minute, or zero for no limit.
+    fn max_requests_per_minute(&self, config: &Config) -> u32 {
+        match self {
+            RouteGroup::Auth => config.auth_rate_limit_per_minute,
+            RouteGroup::CaseCreation => config.case_rate_limit_per_minute,
+            RouteGroup::Withdrawal => config.withdrawal_rate_limit_per_minute,
+            RouteGroup::ImageUpload => config.upload_rate_limit_per_minute,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RequestWindow {
+    start_time_ms: u64,
+    num_requests: u32,
+}
+
+#[derive(Debug)]
+struct FailedLogins {
+    num_failures: u32,
+    last_failure_time_ms: u64,
+    locked_until_ms: u64,
+}
+
+/// Counts requests by IP and by account, and failed logins by account and IP.
+/// The counts are kept in memory, so they start over when the market restarts.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    request_windows: Mutex<HashMap<(RouteGroup, String), RequestWindow>>,
+    failed_logins: Mutex<HashMap<String, FailedLogins>>,
+}
+
+impl RateLimiter {
+    /// Counts a request, and returns false if it goes over the limit of its group.
+    fn check(&self, group: RouteGroup, key: String, max_requests: u32, now: u64) -> bool {
+        let mut request_windows = self.request_windows.lock().unwrap();
+        if request_windows.len() > MAX_TRACKED_KEYS {
+            request_windows.retain(|_, window| window.start_time_ms + RATE_LIMIT_WINDOW_MS > now);
+        };
+        let window = request_windows
+            .entry((group, key))
+            .or_insert(RequestWindow {
+                start_time_ms: now,
+                num_requests: 0,
+            });
+        if window.start_time_ms + RATE_LIMIT_WINDOW_MS <= now {
+            window.start_time_ms = now;
+            window.num_requests = 0;
+        };
+        window.num_requests += 1;
+        window.num_requests <= max_requests
+    }
+
+    fn is_login_locked(&self, key: &str, now: u64) -> bool {
+        let failed_logins = self.failed_logins.lock().unwrap();
+        failed_logins
+            .get(key)
+            .map(|failures| failures.locked_until_ms > now)
+            .unwrap_or(false)
+    }
+
+    /// Locks the key for a while once it has too many failed logins in a row.
+    fn record_failed_login(&self, key: &str, max_failed_logins: u32, config: &Config, now: u64) {
+        let lockout_ms = config.login_lockout_minutes * 60 * 1000;
+        let mut failed_logins = self.failed_logins.lock().unwrap();
+        if failed_logins.len() > MAX_TRACKED_KEYS {
+            failed_logins.retain(|_, failures| {
+                failures.last_failure_time_ms + lockout_ms > now || failures.locked_until_ms > now
+            });
+        };
+        let failures = failed_logins
+            .entry(key.to_string())
+            .or_insert(FailedLogins {
+                num_failures: 0,
+                last_failure_time_ms: now,
+                locked_until_ms: 0,
+            });
+        // Failures that are older than the lockout no longer count.
+        if failures.last_failure_time_ms + lockout_ms <= now {
+            failures.num_failures = 0;
+        };
+        failures.num_failures += 1;
+        failures.last_failure_time_ms = now;
+        if max_failed_logins > 0 && failures.num_failures >= max_failed_logins {
+            failures.num_failures = 0;
+            failures.locked_until_ms = now + lockout_ms;
+        };
+    }
+
+    fn clear_failed_logins(&self, key: &str) {
+        let mut failed_logins = self.failed_logins.lock().unwrap();
+        failed_logins.remove(key);
+    }
+}
+
+/// The failed logins of the client that sent the request. Failures are counted by
+/// account and IP together, so that guessing from one address cannot lock the owner
+/// of the account out from another. They are also counted by account alone, with a
+/// higher limit, so that guessing spread over many addresses is still stopped.
+pub struct LoginLimiter<'r> {
+    rate_limiter: &'r RateLimiter,
+    maybe_client_ip: Option<IpAddr>,
+}
+
+impl<'r> LoginLimiter<'r> {
+    fn client_key(&self, email: &str) -> String {
+        match self.maybe_client_ip {
+            Some(ip) => format!("{}|{}", email, ip),
+            None => email.to_string(),
+        }
+    }
+
+    fn account_key(&self, email: &str) -> String {
+        format!("account:{}", email)
+    }
+
+    pub fn is_locked(&self, email: &str, now: u64) -> bool {
+        self.rate_limiter
+            .is_login_locked(&self.client_key(email), now)
+            || self
+                .rate_limiter
+                .is_login_locked(&self.account_key(email), now)
+    }
+
+    pub fn record_failure(&self, email: &str, config: &Config, now: u64) {
+        self.rate_limiter.record_failed_login(
+            &self.client_key(email),
+            config.max_failed_logins,
+            config,
+            now,
+        );
+        self.rate_limiter.record_failed_login(
+            &self.account_key(email),
+            config.max_failed_logins_per_account,
+            config,
+            now,
+        );
+    }
+
+    pub fn clear(&self, email: &str) {
+        self.rate_limiter
+            .clear_failed_logins(&self.client_key(email));
+        self.rate_limiter
+            .clear_failed_logins(&self.account_key(email));
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LoginLimiter<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<LoginLimiter<'r>, ()> {
+        match request.rocket().state::<RateLimiter>() {
+            Some(rate_limiter) => Outcome::Success(LoginLimiter {
+                rate_limiter,
+                maybe_client_ip: request.client_ip(),
+            }),
+            None => Outcome::Failure((Status::InternalServerError, ())),
+        }
+    }
+}
+
+/// Sends requests that go over the limit of their route group to the 429 page.
+pub struct RateLimit;
+
+#[rocket::async_trait]
+impl Fairing for RateLimit {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate limit",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let group = match RouteGroup::for_request(request.method(), request.uri().path().as_str()) {
+            Some(group) => group,
+            None => return,
+        };
+        let (rate_limiter, config) = match (
+            request.rocket().state::<RateLimiter>(),
+            request.rocket().state::<Config>(),
+        ) {
+            (Some(rate_limiter), Some(config)) => (rate_limiter, config),
+            _ => return,
+        };
+        let max_requests = group.max_requests_per_minute(config);
+        if max_requests == 0 {
+            return;
+        };
+        let now = util::current_time_millis();
+
+        let mut keys = vec![];
+        if let Some(ip) = request.client_ip() {
+            keys.push(format!("ip:{}", ip));
+        };
+        if let Outcome::Success(user) = request.guard::<User>().await {
+            keys.push(format!("user:{}", user.id()));
+        };
+        // Count the request against every key, even after one of them is over the limit.
+        let allowed = keys.into_iter().fold(true, |allowed, key| {
+            rate_limiter.check(group, key, max_requests, now) && allowed
+        });
+
+        if !allowed {
+            request.set_method(Method::Get);
+            request.set_uri(Origin::parse("/rate_limited").unwrap());
+        };
+    }
+}
+
+#[get("/rate_limited")]
+fn rate_limited() -> Status {
+    Status::TooManyRequests
+}
+
+#[catch(429)]
+async fn too_many_requests(request: &Request<'_>) -> Result<Template, String> {
+    let user = request.guard::<Option<User>>().await.succeeded().flatten();
+    let admin_user = request
+        .guard::<Option<AdminUser>>()
+        .await
+        .succeeded()
+        .flatten();
+    let mut db = match request.guard::<Connection<Db>>().await {
+        Outcome::Success(db) => db,
+        _ => return Err("Too many requests. Please wait and try again.".to_string()),
+    };
+    let base_context = BaseContext::raw(&mut db, user, admin_user)
+        .await
+        .map_err(|_| "failed to get base template.")?;
+    Ok(Template::render(
+        "ratelimited",
+        json!({ "base_context": base_context }),
+    ))
+}
+
+pub fn rate_limit_stage() -> AdHoc {
+    AdHoc::on_ignite("Rate Limit Stage", |rocket| async {
+        rocket
+            .manage(RateLimiter::default())
+            .attach(RateLimit)
+            .register("/", catchers![too_many_requests])
+            .mount("/", routes![rate_limited])
+    })
+}
@@ -0,0 +1,380 @@
+use crate::base::BaseContext;
+use crate::config::Config;
+use crate::csrf::CsrfToken;
+use crate::db::Db;
+use crate::models::{
+    Email, PasswordResetInput, PasswordResetRequestInput, PasswordResetToken, SessionReset,
+    UserSettings,
+};
+use crate::util;
+use pgp::composed::{Deserializable, Message, SignedPublicKey};
+use pgp::crypto::sym::SymmetricKeyAlgorithm;
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::http::{Cookie, CookieJar, Method};
+use rocket::outcome::Outcome;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket::{Data, Request, State};
+use rocket_auth::{User, Users};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+const PASSWORD_RESET_TOKEN_TTL_MS: u64 = 60 * 60 * 1000;
+// Private cookie with the time that the user logged in to this session.
+const LOGIN_TIME_COOKIE_NAME: &str = "login_time";
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    email_configured: bool,
+    maybe_encrypted_token: Option<String>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: Option<User>,
+        config: &Config,
+        maybe_encrypted_token: Option<String>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, user, None)
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        Ok(Context {
+            base_context,
+            flash,
+            email_configured: !config.smtp_host.is_empty(),
+            maybe_encrypted_token,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ConfirmContext {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    token: String,
+}
+
+#[derive(Responder)]
+enum ResetRequestResponse {
+    EncryptedToken(Template),
+    Redirect(Flash<Redirect>),
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    user: Option<User>,
+    config: &State<Config>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, user, config, None, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("resetpassword", context))
+}
+
+#[post("/", data = "<reset_request_form>")]
+async fn new(
+    reset_request_form: Form<PasswordResetRequestInput>,
+    mut db: Connection<Db>,
+    user: Option<User>,
+    users: &State<Users>,
+    config: &State<Config>,
+    csrf_token: CsrfToken,
+) -> ResetRequestResponse {
+    let reset_request = reset_request_form.into_inner();
+
+    match request_reset(reset_request, &mut db, users, config).await {
+        Ok(Some(encrypted_token)) => {
+            let flash = Some((
+                "success".to_string(),
+                "Decrypt the message below with your PGP key to get your reset token.".to_string(),
+            ));
+            match Context::raw(db, flash, user, config, Some(encrypted_token), csrf_token).await {
+                Ok(context) => {
+                    ResetRequestResponse::EncryptedToken(Template::render("resetpassword", context))
+                }
+                Err(e) => ResetRequestResponse::Redirect(Flash::error(
+                    Redirect::to(uri!("/reset_password", index())),
+                    e,
+                )),
+            }
+        }
+        Ok(None) => ResetRequestResponse::Redirect(Flash::success(
+            Redirect::to(uri!("/reset_password", confirm_index(_))),
+            "If the account has email turned on, a reset link was sent to its email address.",
+        )),
+        Err(e) => {
+            error_!("Password reset request error: {}", e);
+            ResetRequestResponse::Redirect(Flash::error(
+                Redirect::to(uri!("/reset_password", index())),
+                e,
+            ))
+        }
+    }
+}
+
+/// Creates a reset token, and returns it encrypted to the user's PGP key for the PGP method.
+/// The email method answers the same way whether or not the account exists, so it cannot
+/// be used to look up accounts.
+async fn request_reset(
+    reset_request: PasswordResetRequestInput,
+    db: &mut Connection<Db>,
+    users: &Users,
+    config: &Config,
+) -> Result<Option<String>, String> {
+    let now = util::current_time_millis();
+    let maybe_user = users
+        .get_by_email(&reset_request.username.trim().to_lowercase())
+        .await
+        .ok();
+    let token = util::create_uuid();
+
+    let (user, maybe_encrypted_token) = match reset_request.method.as_str() {
+        "pgp" => {
+            let user = maybe_user.ok_or("This account does not have a PGP key.")?;
+            let user_settings = UserSettings::single(db, user.id())
+                .await
+                .map_err(|_| "failed to get user settings.")?;
+            if user_settings.pgp_key.is_empty() {
+                return Err("This account does not have a PGP key.".to_string());
+            };
+            let encrypted_token = encrypt_token(&token, &user_settings.pgp_key)?;
+            (user, Some(encrypted_token))
+        }
+        "email" => {
+            if config.smtp_host.is_empty() {
+                return Err("This market does not send emails.".to_string());
+            };
+            let user = match maybe_user {
+                Some(user) => user,
+                None => return Ok(None),
+            };
+            let email_enabled = Email::enabled_for_user(db, user.id())
+                .await
+                .map_err(|_| "failed to get email settings.")?;
+            if !email_enabled {
+                return Ok(None);
+            };
+            (user, None)
+        }
+        _ => return Err("Unknown reset method.".to_string()),
+    };
+
+    let password_reset_token = PasswordResetToken {
+        id: None,
+        user_id: user.id(),
+        token_hash: util::to_sha256_hex(&token),
+        method: reset_request.method,
+        used: false,
+        expiry_time_ms: now + PASSWORD_RESET_TOKEN_TTL_MS,
+        created_time_ms: now,
+    };
+    PasswordResetToken::insert(password_reset_token, db).await?;
+
+    if maybe_encrypted_token.is_none() {
+        let link = format!("/reset_password/confirm?token={}", token);
+        Email::enqueue_for_connection(
+            &mut **db,
+            user.id(),
+            "password_reset",
+            "Reset your password",
+            &link,
+        )
+        .await
+        .map_err(|_| "failed to send reset email.")?;
+    };
+
+    Ok(maybe_encrypted_token)
+}
+
+fn encrypt_token(token: &str, pgp_key: &str) -> Result<String, String> {
+    let (public_key, _) =
+        SignedPublicKey::from_string(pgp_key).map_err(|_| "Invalid PGP key on account.")?;
+    let message = Message::new_literal("reset_token", token);
+    let mut rng = rand::thread_rng();
+    // Most keys encrypt with a subkey, and only sign with the primary key.
+    let encrypted_message = match public_key.public_subkeys.first() {
+        Some(subkey) => message.encrypt_to_keys(&mut rng, SymmetricKeyAlgorithm::AES128, &[subkey]),
+        None => message.encrypt_to_keys(
+            &mut rng,
+            SymmetricKeyAlgorithm::AES128,
+            &[&public_key.primary_key],
+        ),
+    }
+    .map_err(|_| "failed to encrypt reset token.")?;
+    encrypted_message
+        .to_armored_string(None)
+        .map_err(|_| "failed to armor reset token.".to_string())
+}
+
+#[get("/confirm?<token>")]
+async fn confirm_index(
+    token: Option<String>,
+    flash: Option<FlashMessage<'_>>,
+    mut db: Connection<Db>,
+    user: Option<User>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let base_context = BaseContext::raw(&mut db, user, None)
+        .await
+        .map_err(|_| "failed to get base template.")?
+        .with_csrf_token(csrf_token);
+    let context = ConfirmContext {
+        base_context,
+        flash,
+        token: token.unwrap_or_default(),
+    };
+    Ok(Template::render("resetpasswordconfirm", context))
+}
+
+#[post("/confirm", data = "<reset_form>")]
+async fn confirm(
+    reset_form: Form<PasswordResetInput>,
+    mut db: Connection<Db>,
+    users: &State<Users>,
+) -> Flash<Redirect> {
+    let reset_input = reset_form.into_inner();
+
+    match reset_password(reset_input, &mut db, users).await {
+        Ok(_) => Flash::success(
+            Redirect::to("/login"),
+            "Password successfully reset. Log in with your new password.",
+        ),
+        Err(e) => {
+            error_!("Password reset error: {}", e);
+            Flash::error(Redirect::to(uri!("/reset_password", confirm_index(_))), e)
+        }
+    }
+}
+
+async fn reset_password(
+    reset_input: PasswordResetInput,
+    db: &mut Connection<Db>,
+    users: &Users,
+) -> Result<(), String> {
+    let now = util::current_time_millis();
+
+    if reset_input.new_password != reset_input.confirm_password {
+        return Err("New passwords do not match.".to_string());
+    };
+    let token_hash = util::to_sha256_hex(reset_input.token.trim());
+    let password_reset_token = PasswordResetToken::single_unused_by_hash(db, &token_hash, now)
+        .await
+        .map_err(|_| "failed to get reset token.")?
+        .ok_or("Reset token is not valid or has expired.")?;
+    let mut user = users
+        .get_by_id(password_reset_token.user_id)
+        .await
+        .map_err(|_| "User not found.")?;
+    user.set_password(&reset_input.new_password)
+        .map_err(|e| format!("failed to set password: {}", e))?;
+
+    let used = PasswordResetToken::mark_as_used(db, password_reset_token.id.unwrap())
+        .await
+        .map_err(|_| "failed to use reset token.")?;
+    if !used {
+        return Err("Reset token was already used.".to_string());
+    };
+    users
+        .modify(&user)
+        .await
+        .map_err(|_| "failed to save new password.")?;
+    // Whoever had the old password may still be logged in.
+    SessionReset::upsert(db, user.id(), now)
+        .await
+        .map_err(|_| "failed to end existing sessions.")?;
+    Email::clear_password_reset_links_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to clear reset links.")?;
+
+    Ok(())
+}
+
+/// Keeps the time that the user logged in to the current session, so that the session can
+/// be ended after a password reset.
+pub fn remember_login_time(cookies: &CookieJar<'_>, now: u64) {
+    cookies.add_private(Cookie::new(LOGIN_TIME_COOKIE_NAME, now.to_string()));
+}
+
+/// Ends the sessions that were logged in before the last password reset of the user.
+pub struct SessionResetGate;
+
+#[rocket::async_trait]
+impl Fairing for SessionResetGate {
+    fn info(&self) -> Info {
+        Info {
+            name: "Session reset gate",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let path = request.uri().path().as_str().to_string();
+        if ["/login", "/logout", "/rate_limited"].contains(&path.as_str())
+            || path.starts_with("/css/")
+            || path.starts_with("/images/")
+        {
+            return;
+        };
+        let user = match request.guard::<User>().await {
+            Outcome::Success(user) => user,
+            _ => return,
+        };
+        // When the reset time cannot be read, the session is ended instead of let through.
+        match is_reset_session(request, &user).await {
+            Ok(false) => return,
+            Ok(true) => (),
+            Err(e) => error_!("Session reset gate error: {}", e),
+        };
+        request.set_method(Method::Get);
+        request.set_uri(Origin::parse("/logout").unwrap());
+    }
+}
+
+/// Whether the session was logged in before the last password reset of the user.
+async fn is_reset_session(request: &Request<'_>, user: &User) -> Result<bool, String> {
+    let mut db = match request.guard::<Connection<Db>>().await {
+        Outcome::Success(db) => db,
+        _ => return Err("failed to get database connection.".to_string()),
+    };
+    let maybe_session_reset = SessionReset::single_for_user(&mut db, user.id())
+        .await
+        .map_err(|_| "failed to get session reset.")?;
+    let session_reset = match maybe_session_reset {
+        Some(session_reset) => session_reset,
+        None => return Ok(false),
+    };
+    let maybe_login_time_ms = request
+        .cookies()
+        .get_private(LOGIN_TIME_COOKIE_NAME)
+        .and_then(|cookie| cookie.value().parse::<u64>().ok());
+
+    match maybe_login_time_ms {
+        Some(login_time_ms) => Ok(login_time_ms < session_reset.reset_time_ms),
+        None => Ok(true),
+    }
+}
+
+pub fn reset_password_stage() -> AdHoc {
+    AdHoc::on_ignite("Reset Password Stage", |rocket| async {
+        rocket.attach(SessionResetGate).mount(
+            "/reset_password",
+            routes![index, new, confirm_index, confirm],
+        )
+    })
+}
@@ -0,0 +1,35 @@
+use crate::config::Config;
+use crate::models::CaseReviewDeadline;
+use crate::util;
+use sqlx::pool::PoolConnection;
+use sqlx::Sqlite;
+
+/// Reminds posters about paid cases that are close to their review deadline, and
+/// refunds or escalates the cases whose deadline has passed.
+pub async fn handle_review_deadlines(
+    config: Config,
+    mut conn: PoolConnection<Sqlite>,
+) -> Result<(), String> {
+    let now = util::current_time_millis();
+    let default_deadline_ms = config.default_review_deadline_days * 24 * 60 * 60 * 1000;
+    let reminder_ms = config.review_deadline_reminder_hours * 60 * 60 * 1000;
+    let refund = config.review_deadline_policy == "refund";
+    let due_deadlines =
+        CaseReviewDeadline::all_before(&mut conn, default_deadline_ms, now + reminder_ms)
+            .await
+            .map_err(|_| "failed to get case review deadlines.")?;
+
+    for deadline in due_deadlines {
+        if deadline.deadline_ms < now {
+            CaseReviewDeadline::expire_for_connection(&mut conn, &deadline, refund, now)
+                .await
+                .map_err(|_| "failed to handle passed review deadline.")?;
+        } else {
+            CaseReviewDeadline::remind_for_connection(&mut conn, &deadline, now)
+                .await
+                .map_err(|_| "failed to send review deadline reminder.")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::csrf::CsrfChecked;
+use crate::db::Db;
+use crate::models::{Bounty, BountyReviewDeadline, BountyReviewDeadlineInfo};
+use crate::user_account::ActiveUser;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket_auth::User;
+use rocket_db_pools::Connection;
+
+const MAX_REVIEW_DEADLINE_DAYS: u64 = 90;
+
+#[put("/<id>", data = "<deadline_form>")]
+async fn update(
+    id: &str,
+    deadline_form: Form<BountyReviewDeadlineInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let deadline_info = deadline_form.into_inner();
+
+    match update_review_deadline(id, deadline_info, &mut db, active_user.user).await {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to(format!("/{}/{}", "bounty", id)),
+            "Review deadline updated.",
+        )),
+        Err(e) => {
+            error_!("Update review deadline({}) error: {}", id, e);
+            Err(Flash::error(
+                Redirect::to(format!("/{}/{}", "bounty", id)),
+                e,
+            ))
+        }
+    }
+}
+
+async fn update_review_deadline(
+    id: &str,
+    deadline_info: BountyReviewDeadlineInfo,
+    db: &mut Connection<Db>,
+    user: User,
+) -> Result<(), String> {
+    let bounty = Bounty::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get bounty")?;
+    let review_deadline_days = deadline_info.review_deadline_days.unwrap_or(0);
+
+    if bounty.user_id != user.id() {
+        return Err("Bounty belongs to a different user.".to_string());
+    };
+    // An empty deadline goes back to the market default.
+    if review_deadline_days == 0 {
+        BountyReviewDeadline::remove_for_bounty(db, bounty.id.unwrap())
+            .await
+            .map_err(|_| "failed to remove review deadline")?;
+        return Ok(());
+    };
+    if review_deadline_days > MAX_REVIEW_DEADLINE_DAYS {
+        return Err(format!(
+            "Review deadline cannot be longer than {} days.",
+            MAX_REVIEW_DEADLINE_DAYS
+        ));
+    };
+
+    let deadline = BountyReviewDeadline {
+        bounty_id: bounty.id.unwrap(),
+        review_deadline_ms: review_deadline_days * 24 * 60 * 60 * 1000,
+    };
+    BountyReviewDeadline::set_for_bounty(deadline, db)
+        .await
+        .map_err(|_| "failed to update review deadline")?;
+
+    Ok(())
+}
+
+pub fn review_deadlines_stage() -> AdHoc {
+    AdHoc::on_ignite("Review Deadlines Stage", |rocket| async {
+        rocket.mount("/review_deadlines", routes![update])
+    })
+}
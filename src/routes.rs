@@ -1,18 +1,29 @@
 use crate::config::Config;
 use crate::db::Db;
 use crate::case_expiry;
+use crate::claim_expiry;
+use crate::email;
+use crate::nostr_publisher;
 use crate::payment_processor;
+use crate::review_deadline_expiry;
+use crate::solvency;
 use crate::user_account_expiry;
+use crate::webhook_sender;
 use rocket::fairing::{self, AdHoc};
 use rocket::fs::{relative, FileServer};
 use rocket::{Build, Rocket};
-use rocket_auth::Error::SqlxError;
 use rocket_auth::Users;
 use rocket_db_pools::{sqlx, Database};
 use rocket_dyn_templates::Template;
 
 const PAYMENT_PROCESSOR_TASK_INTERVAL_S: u64 = 10;
 const ORDER_EXPIRY_TASK_INTERVAL_S: u64 = 600;
+const SOLVENCY_CHECK_TASK_INTERVAL_S: u64 = 600;
+const EMAIL_TASK_INTERVAL_S: u64 = 30;
+const NOSTR_TASK_INTERVAL_S: u64 = 30;
+const WEBHOOK_TASK_INTERVAL_S: u64 = 10;
+const CLAIM_EXPIRY_TASK_INTERVAL_S: u64 = 60;
+const REVIEW_DEADLINE_TASK_INTERVAL_S: u64 = 300;
 
 async fn run_migrations(rocket: Rocket<Build>) -> fairing::Result {
     match Db::fetch(&rocket) {
@@ -43,18 +54,41 @@ async fn create_users_table(rocket: Rocket<Build>) -> fairing::Result {
     }
 }
 
-async fn create_admin_user(rocket: Rocket<Build>, config: Config) -> fairing::Result {
+/// Creates the admin from the config, or resets its password if it already exists,
+/// so that changing admin_password in the config takes effect on the next start.
+/// An existing account that is not an admin is never taken over, so the market
+/// refuses to start instead. Admins are not demoted when they leave the config; that
+/// is done from the admin page.
+async fn sync_admin_user(rocket: Rocket<Build>, config: Config) -> fairing::Result {
     match Db::fetch(&rocket) {
         Some(db) => {
             let users: Users = db.0.clone().into();
-            // TODO: Delete all existing admins users here.
             let username = config.admin_username;
             let password = config.admin_password;
-            match users.create_user(&username, &password, true).await {
-                Ok(_) => Ok(rocket),
-                Err(e) => match e {
-                    SqlxError(_) => Ok(rocket),
-                    _ => {
+            match users.get_by_email(&username).await {
+                Ok(user) if !user.is_admin => {
+                    error!(
+                        "Admin username from config belongs to an account that is not an admin: {}. Refusing to start.",
+                        username
+                    );
+                    Err(rocket)
+                }
+                Ok(mut user) => {
+                    // A weak password is only accepted when the admin is first created.
+                    if let Err(e) = user.set_password(&password) {
+                        error!("Admin password from config was not applied: {}", e);
+                    };
+                    match users.modify(&user).await {
+                        Ok(_) => Ok(rocket),
+                        Err(e) => {
+                            error!("Failed to update admin user: {}", e);
+                            Err(rocket)
+                        }
+                    }
+                }
+                Err(_) => match users.create_user(&username, &password, true).await {
+                    Ok(_) => Ok(rocket),
+                    Err(e) => {
                         error!("Failed to create admin user: {}", e);
                         Err(rocket)
                     }
@@ -70,6 +104,11 @@ pub fn stage(config: Config) -> AdHoc {
     let config_clone_2 = config.clone();
     let config_clone_3 = config.clone();
     let config_clone_4 = config.clone();
+    let config_clone_5 = config.clone();
+    let config_clone_6 = config.clone();
+    let config_clone_7 = config.clone();
+    let config_clone_8 = config.clone();
+    let config_clone_9 = config.clone();
 
     AdHoc::on_ignite("SQLx Stage", |rocket| async {
         rocket
@@ -82,8 +121,8 @@ pub fn stage(config: Config) -> AdHoc {
                 "SQLx Create Users table",
                 create_users_table,
             ))
-            .attach(AdHoc::try_on_ignite("SQLx Create Admin User", |r| {
-                create_admin_user(r, config_clone_2)
+            .attach(AdHoc::try_on_ignite("SQLx Sync Admin User", |r| {
+                sync_admin_user(r, config_clone_2)
             }))
             .attach(AdHoc::on_liftoff("Process Payments", |rocket| {
                 // Copied from: https://stackoverflow.com/a/72457117/1639564
@@ -184,32 +223,222 @@ pub fn stage(config: Config) -> AdHoc {
                     })
                 },
             ))
+            .attach(AdHoc::on_liftoff("Check solvency", |rocket| {
+                Box::pin(async move {
+                    let pool = match Db::fetch(rocket) {
+                        Some(pool) => pool.0.clone(), // clone the wrapped pool
+                        None => panic!("failed to get db for background task."),
+                    };
+                    rocket::tokio::spawn(async move {
+                        let mut interval = rocket::tokio::time::interval(
+                            rocket::tokio::time::Duration::from_secs(
+                                SOLVENCY_CHECK_TASK_INTERVAL_S,
+                            ),
+                        );
+                        loop {
+                            if let Ok(conn) = pool.acquire().await {
+                                match solvency::check_solvency(config_clone_5.clone(), conn).await
+                                {
+                                    Ok(_) => (),
+                                    Err(e) => println!("solvency check task failed: {:?}", e),
+                                }
+                            }
+                            interval.tick().await;
+                        }
+                    });
+                })
+            }))
+            .attach(AdHoc::on_liftoff("Send emails", |rocket| {
+                Box::pin(async move {
+                    let pool = match Db::fetch(rocket) {
+                        Some(pool) => pool.0.clone(), // clone the wrapped pool
+                        None => panic!("failed to get db for background task."),
+                    };
+                    let templates = match email::email_templates() {
+                        Ok(templates) => templates,
+                        Err(e) => {
+                            println!("email task failed to start: {:?}", e);
+                            return;
+                        }
+                    };
+                    rocket::tokio::spawn(async move {
+                        let mut interval = rocket::tokio::time::interval(
+                            rocket::tokio::time::Duration::from_secs(EMAIL_TASK_INTERVAL_S),
+                        );
+                        loop {
+                            if let Ok(conn) = pool.acquire().await {
+                                match email::send_pending_emails(
+                                    config_clone_6.clone(),
+                                    &templates,
+                                    conn,
+                                )
+                                .await
+                                {
+                                    Ok(_) => (),
+                                    Err(e) => println!("email task failed: {:?}", e),
+                                }
+                            }
+                            interval.tick().await;
+                        }
+                    });
+                })
+            }))
+            .attach(AdHoc::on_liftoff("Publish nostr events", |rocket| {
+                Box::pin(async move {
+                    let pool = match Db::fetch(rocket) {
+                        Some(pool) => pool.0.clone(), // clone the wrapped pool
+                        None => panic!("failed to get db for background task."),
+                    };
+                    rocket::tokio::spawn(async move {
+                        let mut interval = rocket::tokio::time::interval(
+                            rocket::tokio::time::Duration::from_secs(NOSTR_TASK_INTERVAL_S),
+                        );
+                        loop {
+                            if let Ok(conn) = pool.acquire().await {
+                                match nostr_publisher::send_pending_nostr_events(
+                                    config_clone_7.clone(),
+                                    conn,
+                                )
+                                .await
+                                {
+                                    Ok(_) => (),
+                                    Err(e) => println!("nostr task failed: {:?}", e),
+                                }
+                            }
+                            interval.tick().await;
+                        }
+                    });
+                })
+            }))
+            .attach(AdHoc::on_liftoff("Deliver webhooks", |rocket| {
+                Box::pin(async move {
+                    let pool = match Db::fetch(rocket) {
+                        Some(pool) => pool.0.clone(), // clone the wrapped pool
+                        None => panic!("failed to get db for background task."),
+                    };
+                    rocket::tokio::spawn(async move {
+                        let mut interval = rocket::tokio::time::interval(
+                            rocket::tokio::time::Duration::from_secs(WEBHOOK_TASK_INTERVAL_S),
+                        );
+                        loop {
+                            if let Ok(conn) = pool.acquire().await {
+                                match webhook_sender::send_pending_webhook_deliveries(
+                                    config_clone_8.clone(),
+                                    conn,
+                                )
+                                .await
+                                {
+                                    Ok(_) => (),
+                                    Err(e) => println!("webhook task failed: {:?}", e),
+                                }
+                            }
+                            interval.tick().await;
+                        }
+                    });
+                })
+            }))
+            .attach(AdHoc::on_liftoff(
+                "Release expired bounty claims",
+                |rocket| {
+                    Box::pin(async move {
+                        let pool = match Db::fetch(rocket) {
+                            Some(pool) => pool.0.clone(), // clone the wrapped pool
+                            None => panic!("failed to get db for background task."),
+                        };
+                        rocket::tokio::spawn(async move {
+                            let mut interval = rocket::tokio::time::interval(
+                                rocket::tokio::time::Duration::from_secs(
+                                    CLAIM_EXPIRY_TASK_INTERVAL_S,
+                                ),
+                            );
+                            loop {
+                                if let Ok(conn) = pool.acquire().await {
+                                    match claim_expiry::release_expired_claims(conn).await {
+                                        Ok(_) => (),
+                                        Err(e) => println!("claim expiry task failed: {:?}", e),
+                                    }
+                                }
+                                interval.tick().await;
+                            }
+                        });
+                    })
+                },
+            ))
+            .attach(AdHoc::on_liftoff("Handle case review deadlines", |rocket| {
+                Box::pin(async move {
+                    let pool = match Db::fetch(rocket) {
+                        Some(pool) => pool.0.clone(), // clone the wrapped pool
+                        None => panic!("failed to get db for background task."),
+                    };
+                    rocket::tokio::spawn(async move {
+                        let mut interval = rocket::tokio::time::interval(
+                            rocket::tokio::time::Duration::from_secs(
+                                REVIEW_DEADLINE_TASK_INTERVAL_S,
+                            ),
+                        );
+                        loop {
+                            if let Ok(conn) = pool.acquire().await {
+                                match review_deadline_expiry::handle_review_deadlines(
+                                    config_clone_9.clone(),
+                                    conn,
+                                )
+                                .await
+                                {
+                                    Ok(_) => (),
+                                    Err(e) => println!("review deadline task failed: {:?}", e),
+                                }
+                            }
+                            interval.tick().await;
+                        }
+                    });
+                })
+            }))
             .attach(Template::fairing())
             .mount("/", FileServer::from(relative!("static")))
             .attach(crate::about::about_stage())
+            .attach(crate::rate_limit::rate_limit_stage())
+            .attach(crate::csrf::csrf_stage())
             .attach(crate::auth::auth_stage())
+            .attach(crate::two_factor::two_factor_stage())
+            .attach(crate::change_password::change_password_stage())
+            .attach(crate::reset_password::reset_password_stage())
             .attach(crate::admin::admin_stage())
+            .attach(crate::admins::admins_stage())
             .attach(crate::active_users::active_users_stage())
             .attach(crate::disabled_users::disabled_users_stage())
             .attach(crate::activate_account::activate_account_stage())
             .attach(crate::account_activation::account_activation_stage())
             .attach(crate::deactivate_account::deactivate_account_stage())
             .attach(crate::market_liabilities::market_liabilities_stage())
+            .attach(crate::fee_revenue::fee_revenue_stage())
+            .attach(crate::withdraw_fees::withdraw_fees_stage())
+            .attach(crate::solvency_report::solvency_report_stage())
             .attach(crate::bounties::bounties_stage())
             .attach(crate::deactivated_bounties::deactivated_bounties_stage())
             .attach(crate::bounty::bounty_stage())
+            .attach(crate::award_bounty::award_bounty_stage())
             .attach(crate::new_bounty::new_bounty_stage())
             .attach(crate::update_bounty_images::update_bounty_images_stage())
+            .attach(crate::edit_bounty::edit_bounty_stage())
+            .attach(crate::bounty_revisions::bounty_revisions_stage())
+            .attach(crate::bounty_comments::bounty_comments_stage())
+            .attach(crate::bounty_claims::bounty_claims_stage())
+            .attach(crate::review_deadlines::review_deadlines_stage())
             .attach(crate::user::user_stage())
             .attach(crate::user_profile::user_profile_stage())
             .attach(crate::update_market_name::update_market_name_stage())
+            .attach(crate::update_email_settings::update_email_settings_stage())
+            .attach(crate::update_nostr_settings::update_nostr_settings_stage())
+            .attach(crate::webhooks::webhooks_stage())
             .attach(crate::update_market_info::update_market_info_stage())
             .attach(crate::update_fee_rate::update_fee_rate_stage())
             .attach(crate::update_user_bond_price::update_user_bond_price_stage())
             .attach(crate::update_pgp_info::update_pgp_info_stage())
             .attach(crate::update_max_allowed_users::update_max_allowed_users_stage())
+            .attach(crate::update_two_factor_policy::update_two_factor_policy_stage())
             .attach(crate::update_user_pgp_info::update_user_pgp_info_stage())
             .attach(crate::view_pending_bounties::view_pending_bounties_stage())
+            .attach(crate::open_disputes::open_disputes_stage())
             .attach(crate::delete_bounty::delete_bounty_stage())
             .attach(crate::account::account_stage())
             .attach(crate::my_unsubmitted_bounties::my_unsubmitted_bounties_stage())
@@ -221,8 +450,16 @@ pub fn stage(config: Config) -> AdHoc {
             .attach(crate::my_paid_cases::my_paid_cases_stage())
             .attach(crate::my_account_balance::my_account_balance_stage())
             .attach(crate::my_processing_cases::my_processing_cases_stage())
+            .attach(crate::my_team_cases::my_team_cases_stage())
             .attach(crate::prepare_case::prepare_case_stage())
             .attach(crate::case::case_stage())
+            .attach(crate::case_messages::case_messages_stage())
+            .attach(crate::case_dispute::case_dispute_stage())
+            .attach(crate::case_revisions::case_revisions_stage())
+            .attach(crate::case_attachments::case_attachments_stage())
+            .attach(crate::case_ratings::case_ratings_stage())
+            .attach(crate::message_inbox::message_inbox_stage())
+            .attach(crate::notifications::notifications_stage())
             .attach(crate::withdraw::withdraw_stage())
             .attach(crate::withdrawal::withdrawal_stage())
             .attach(crate::seller_history::seller_history_stage())
@@ -10,6 +10,7 @@ use rocket_db_pools::Connection;
 use rocket_dyn_templates::Template;
 
 const PAGE_SIZE: u32 = 10;
+const MAX_RATING: u32 = 5;
 
 #[derive(Debug, Serialize)]
 #[serde(crate = "rocket::serde")]
@@ -17,6 +18,7 @@ struct Context {
     base_context: BaseContext,
     flash: Option<(String, String)>,
     search_text: String,
+    min_rating: Option<u32>,
     bounty_cards: Vec<BountyCardDisplay>,
     page_num: u32,
 }
@@ -25,6 +27,7 @@ impl Context {
     pub async fn raw(
         mut db: Connection<Db>,
         search_text: String,
+        maybe_min_rating: Option<u32>,
         flash: Option<(String, String)>,
         maybe_page_num: Option<u32>,
         user: Option<User>,
@@ -34,9 +37,13 @@ impl Context {
             .await
             .map_err(|_| "failed to get base template.")?;
         let page_num = maybe_page_num.unwrap_or(1);
+        let min_rating = maybe_min_rating
+            .filter(|r| *r > 0)
+            .map(|r| r.min(MAX_RATING));
         let bounty_cards = BountyCardDisplay::all_active_for_search_text(
             &mut db,
             &search_text,
+            min_rating,
             PAGE_SIZE,
             page_num,
         )
@@ -46,15 +53,17 @@ impl Context {
             base_context,
             flash,
             search_text,
+            min_rating,
             bounty_cards,
             page_num,
         })
     }
 }
 
-#[get("/?<search_text>&<page_num>")]
+#[get("/?<search_text>&<min_rating>&<page_num>")]
 async fn index(
     search_text: &str,
+    min_rating: Option<u32>,
     flash: Option<FlashMessage<'_>>,
     db: Connection<Db>,
     page_num: Option<u32>,
@@ -65,6 +74,7 @@ async fn index(
     let context = Context::raw(
         db,
         search_text.to_string(),
+        min_rating,
         flash,
         page_num,
         user,
@@ -0,0 +1,107 @@
+use crate::config::Config;
+use crate::lightning::get_lnd_lightning_client;
+use crate::models::{AccountInfo, ProofOfLiabilities, SolvencySnapshot};
+use crate::util;
+use sqlx::pool::PoolConnection;
+use sqlx::Sqlite;
+
+pub async fn check_solvency(
+    config: Config,
+    mut conn: PoolConnection<Sqlite>,
+) -> Result<(), String> {
+    let mut lightning_client = get_lnd_lightning_client(
+        config.lnd_host.clone(),
+        config.lnd_port,
+        config.lnd_tls_cert_path.clone(),
+        config.lnd_macaroon_path.clone(),
+    )
+    .await
+    .map_err(|e| format!("failed to get lightning client: {:?}", e))?;
+
+    let channel_balance_resp = lightning_client
+        .channel_balance(tonic_openssl_lnd::lnrpc::ChannelBalanceRequest {
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| format!("failed to get channel balance: {:?}", e))?
+        .into_inner();
+    let channel_balance_sat: i64 = channel_balance_resp
+        .local_balance
+        .map(|amount| amount.sat as i64)
+        .unwrap_or(0);
+    let wallet_balance_resp = lightning_client
+        .wallet_balance(tonic_openssl_lnd::lnrpc::WalletBalanceRequest {
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| format!("failed to get wallet balance: {:?}", e))?
+        .into_inner();
+    let onchain_balance_sat: i64 = wallet_balance_resp.confirmed_balance;
+
+    let total_liabilities_sat = AccountInfo::total_market_liabilities_sat_for_connection(&mut conn)
+        .await
+        .map_err(|_| "failed to get total market liabilities.")?;
+    let held_case_escrow_sat = AccountInfo::total_held_escrow_sat_for_connection(&mut conn)
+        .await
+        .map_err(|_| "failed to get total held escrow.")?;
+    // Claim stakes are held by the market until the claim is released, like case fees.
+    let held_claim_stakes_sat = AccountInfo::total_held_claim_stakes_sat_for_connection(&mut conn)
+        .await
+        .map_err(|_| "failed to get total held claim stakes.")?;
+    let held_escrow_sat = held_case_escrow_sat + held_claim_stakes_sat;
+    let user_liabilities_sat = total_liabilities_sat - held_escrow_sat;
+
+    let total_node_balance_sat = channel_balance_sat + onchain_balance_sat;
+    let coverage_basis_points = if total_liabilities_sat > 0 {
+        total_node_balance_sat * 10000 / total_liabilities_sat
+    } else {
+        // Nothing is owed, so the market is fully covered.
+        10000
+    };
+    let below_threshold = coverage_basis_points < config.min_solvency_coverage_basis_points;
+
+    let now = util::current_time_millis();
+    let proof = ProofOfLiabilities {
+        user_liabilities_sat,
+        held_escrow_sat,
+        channel_balance_sat,
+        onchain_balance_sat,
+        created_time_ms: now,
+    };
+    let message =
+        serde_json::to_string(&proof).map_err(|_| "failed to serialize proof of liabilities.")?;
+    let sign_message_resp = lightning_client
+        .sign_message(tonic_openssl_lnd::lnrpc::SignMessageRequest {
+            msg: message.clone().into_bytes(),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| format!("failed to sign proof of liabilities: {:?}", e))?
+        .into_inner();
+
+    if below_threshold {
+        warn!(
+            "solvency alert: node balance {} sats covers only {} basis points of {} sats in liabilities.",
+            total_node_balance_sat, coverage_basis_points, total_liabilities_sat,
+        );
+    }
+
+    let snapshot = SolvencySnapshot {
+        id: None,
+        public_id: util::create_uuid(),
+        channel_balance_sat,
+        onchain_balance_sat,
+        user_liabilities_sat,
+        held_escrow_sat,
+        coverage_basis_points,
+        below_threshold,
+        message,
+        signature: sign_message_resp.signature,
+        created_time_ms: now,
+    };
+    SolvencySnapshot::insert(snapshot, &mut conn)
+        .await
+        .map_err(|_| "failed to insert solvency snapshot.")?;
+
+    Ok(())
+}
@@ -1,10 +1,31 @@
+use crate::award_bounty::split_reward_sat;
 use crate::config::Config;
+use crate::email;
+use crate::models::{
+    AccountInfo, BountyClaim, Case, CaseAward, CaseDispute, CaseReviewDeadline, Email, NostrEvent,
+};
+use crate::nostr_publisher;
+use crate::util;
+use nostr::nips::nip04;
+use nostr::secp256k1::SecretKey;
+use nostr::{Event, Keys, Kind};
 use rocket::fairing::AdHoc;
+use rocket::futures::{SinkExt, StreamExt};
 use rocket::http::uri::fmt::{Query, UriDisplay};
-use rocket::http::ContentType;
+use rocket::http::{ContentType, Status};
 use rocket::local::blocking::Client;
 use rocket::serde::{Deserialize, Serialize};
+use rocket::tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 use rocket::{Build, Rocket};
+use rocket_auth::Users;
+use rocket_db_pools::sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use rocket_db_pools::sqlx::{self, Row, SqliteConnection};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, UriDisplayQuery)]
 #[serde(crate = "rocket::serde")]
@@ -16,6 +37,7 @@ struct LoginInfo {
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, UriDisplayQuery)]
 #[serde(crate = "rocket::serde")]
 struct UpdateMarketNameInfo {
+    csrf_token: String,
     market_name: String,
 }
 
@@ -34,6 +56,14 @@ fn rocket_build(config: Config) -> Rocket<Build> {
     rocket::custom(figment)
 }
 
+/// Reads the csrf token of the session from the first form of a page.
+fn csrf_token_from_page(page: &str) -> String {
+    let field = "name=\"csrf_token\" value=\"";
+    let value_start = page.find(field).expect("page has no csrf token") + field.len();
+    let value_end = value_start + page[value_start..].find('"').unwrap();
+    page[value_start..value_end].to_string()
+}
+
 fn test_admin_settings(_base: &str, stage: AdHoc, config: Config) {
     // NOTE: If we had more than one test running concurently that dispatches
     // DB-accessing requests, we'd need transactions or to serialize all tests.
@@ -51,8 +81,14 @@ fn test_admin_settings(_base: &str, stage: AdHoc, config: Config) {
         .dispatch();
     println!("login_response: {:?}", login_response);
 
-    // Update the market name.
+    // Update the market name, with the csrf token from the settings page.
+    let settings_page_string = client
+        .get("/update_market_name")
+        .dispatch()
+        .into_string()
+        .unwrap();
     let update_market_name_info = UpdateMarketNameInfo {
+        csrf_token: csrf_token_from_page(&settings_page_string),
         market_name: "test-market-name".to_string(),
     };
     let update_market_name_response = client
@@ -64,6 +100,7 @@ fn test_admin_settings(_base: &str, stage: AdHoc, config: Config) {
         "update_market_name_response: {:?}",
         update_market_name_response
     );
+    assert_eq!(update_market_name_response.status(), Status::SeeOther);
 
     // Get the index page and check the market name.
     let index_page_response = client.get("/").dispatch();
@@ -79,3 +116,945 @@ fn test_routes() {
 
     test_admin_settings("/", crate::routes::stage(config.clone()), config);
 }
+
+/// A database with the migrations and the users table, for the tests that call the
+/// models directly.
+async fn test_pool() -> SqlitePool {
+    // One connection, so that every query sees the same in-memory database.
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("db/migrations").run(&pool).await.unwrap();
+    let users: Users = pool.clone().into();
+    users.create_table().await.unwrap();
+    pool
+}
+
+async fn create_test_user(pool: &SqlitePool, email: &str) -> i32 {
+    let users: Users = pool.clone().into();
+    users
+        .create_user(email, "test-password", false)
+        .await
+        .unwrap();
+    users.get_by_email(email).await.unwrap().id()
+}
+
+/// Starts an SMTP server that accepts every message, and sends back the transcript of
+/// each session.
+fn start_smtp_sink() -> (u16, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if sender.send(smtp_session(stream)).is_err() {
+                break;
+            }
+        }
+    });
+    (port, receiver)
+}
+
+fn smtp_session(stream: TcpStream) -> String {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let mut transcript = String::new();
+    let mut in_data = false;
+    writer.write_all(b"220 localhost test sink\r\n").unwrap();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        transcript.push_str(&line);
+        let command = line.to_uppercase();
+        let reply = if in_data {
+            if line != ".\r\n" {
+                continue;
+            }
+            in_data = false;
+            "250 OK\r\n"
+        } else if command.starts_with("DATA") {
+            in_data = true;
+            "354 End data with <CR><LF>.<CR><LF>\r\n"
+        } else if command.starts_with("QUIT") {
+            writer.write_all(b"221 Bye\r\n").unwrap();
+            break;
+        } else {
+            "250 OK\r\n"
+        };
+        writer.write_all(reply.as_bytes()).unwrap();
+    }
+    transcript
+}
+
+#[rocket::async_test]
+async fn test_send_pending_emails() {
+    let pool = test_pool().await;
+    let user_id = create_test_user(&pool, "hunter@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("INSERT INTO emailsettings (user_id, enabled) VALUES (?, true)")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    Email::enqueue_for_connection(
+        &mut *conn,
+        user_id,
+        "case_awarded",
+        "Case awarded",
+        "/case/1",
+    )
+    .await
+    .unwrap();
+    Email::enqueue_for_connection(
+        &mut *conn,
+        user_id,
+        "password_reset",
+        "Reset your password",
+        "/reset_password/confirm?token=reset-token",
+    )
+    .await
+    .unwrap();
+    drop(conn);
+
+    let (port, transcripts) = start_smtp_sink();
+    let config = Config {
+        market_url: "http://market.test".to_string(),
+        smtp_host: "127.0.0.1".to_string(),
+        smtp_port: port,
+        smtp_tls: false,
+        ..Config::default()
+    };
+    let templates = email::email_templates().unwrap();
+    email::send_pending_emails(config.clone(), &templates, pool.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+    let transcript = [transcripts.recv().unwrap(), transcripts.recv().unwrap()].join("");
+    assert!(transcript.contains("MAIL FROM:<satbounty@localhost>"));
+    assert_eq!(
+        transcript.matches("RCPT TO:<hunter@example.com>").count(),
+        2
+    );
+    assert!(transcript.contains("Subject: Case awarded"));
+    assert!(transcript.contains("Subject: Reset your password"));
+    assert!(transcript.contains("http://market.test/case/1"));
+    assert!(transcript.contains("http://market.test/reset_password/confirm?token=reset-token"));
+
+    // The reset link is not kept once the email is sent.
+    let mut conn = pool.acquire().await.unwrap();
+    let rows = sqlx::query("select event_type, link, sent from emails ORDER BY id ASC;")
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+    let emails: Vec<(String, String, bool)> = rows
+        .iter()
+        .map(|r| {
+            (
+                r.try_get("event_type").unwrap(),
+                r.try_get("link").unwrap(),
+                r.try_get("sent").unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        emails,
+        vec![
+            ("case_awarded".to_string(), "/case/1".to_string(), true),
+            ("password_reset".to_string(), "".to_string(), true),
+        ]
+    );
+
+    // An email that cannot be delivered is kept for a retry.
+    Email::enqueue_for_connection(
+        &mut *conn,
+        user_id,
+        "case_awarded",
+        "Case awarded",
+        "/case/2",
+    )
+    .await
+    .unwrap();
+    drop(conn);
+    let closed_port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let unreachable_config = Config {
+        smtp_port: closed_port,
+        ..config
+    };
+    email::send_pending_emails(
+        unreachable_config,
+        &templates,
+        pool.acquire().await.unwrap(),
+    )
+    .await
+    .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+    let row = sqlx::query("select sent, num_attempts, last_error from emails WHERE link = ?;")
+        .bind("/case/2")
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    let sent: bool = row.try_get("sent").unwrap();
+    let num_attempts: i64 = row.try_get("num_attempts").unwrap();
+    let last_error: String = row.try_get("last_error").unwrap();
+    assert!(!sent);
+    assert_eq!(num_attempts, 1);
+    assert!(!last_error.is_empty());
+}
+
+/// Starts a relay that answers every event with the given acceptance, and sends back
+/// each event it gets.
+async fn start_relay_stand_in(accept: bool) -> (String, UnboundedReceiver<Event>) {
+    let listener = rocket::tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap();
+    let relay_url = format!("ws://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = unbounded_channel();
+    rocket::tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let mut socket = match tokio_tungstenite::accept_async(stream).await {
+                Ok(socket) => socket,
+                Err(_) => continue,
+            };
+            while let Some(Ok(Message::Text(text))) = socket.next().await {
+                let client_message: Vec<Value> = serde_json::from_str(&text).unwrap();
+                let reply = json!(["OK", client_message[1]["id"], accept, ""]);
+                socket.send(Message::Text(reply.to_string())).await.ok();
+                let event: Event = serde_json::from_value(client_message[1].clone()).unwrap();
+                sender.send(event).ok();
+            }
+        }
+    });
+    (relay_url, receiver)
+}
+
+#[rocket::async_test]
+async fn test_send_pending_nostr_events() {
+    let pool = test_pool().await;
+    let user_id = create_test_user(&pool, "hunter@example.com").await;
+    let recipient_secret_key = SecretKey::from_str(&"02".repeat(32)).unwrap();
+    let recipient_keys = Keys::new(recipient_secret_key);
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("INSERT INTO usernostrkeys (user_id, public_key) VALUES (?, ?)")
+        .bind(user_id)
+        .bind(recipient_keys.public_key().to_string())
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    NostrEvent::enqueue_note_for_connection(&mut *conn, "New bounty", "/bounty/1")
+        .await
+        .unwrap();
+    NostrEvent::enqueue_direct_message_for_connection(
+        &mut *conn,
+        user_id,
+        "Case awarded",
+        "/case/1",
+    )
+    .await
+    .unwrap();
+    drop(conn);
+
+    let (relay_url, mut events) = start_relay_stand_in(true).await;
+    let config = Config {
+        market_url: "http://market.test".to_string(),
+        nostr_relays: vec![relay_url],
+        nostr_secret_key: "01".repeat(32),
+        ..Config::default()
+    };
+    let market_public_key = nostr_publisher::market_keys(&config).unwrap().public_key();
+    nostr_publisher::send_pending_nostr_events(config.clone(), pool.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+    let published_events = [events.recv().await.unwrap(), events.recv().await.unwrap()];
+    for event in published_events.iter() {
+        assert!(event.verify().is_ok());
+        assert_eq!(event.pubkey, market_public_key);
+    }
+    let note = published_events
+        .iter()
+        .find(|event| event.kind == Kind::TextNote)
+        .unwrap();
+    assert_eq!(note.content, "New bounty\nhttp://market.test/bounty/1");
+    // The direct message can only be read with the key of the recipient.
+    let direct_message = published_events
+        .iter()
+        .find(|event| event.kind == Kind::EncryptedDirectMessage)
+        .unwrap();
+    assert!(!direct_message.content.contains("Case awarded"));
+    let content = nip04::decrypt(
+        &recipient_secret_key,
+        &market_public_key,
+        &direct_message.content,
+    )
+    .unwrap();
+    assert_eq!(content, "Case awarded\nhttp://market.test/case/1");
+
+    let mut conn = pool.acquire().await.unwrap();
+    let num_sent: i64 = sqlx::query("select COUNT(*) as num_sent from nostrevents WHERE sent;")
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap()
+        .try_get("num_sent")
+        .unwrap();
+    assert_eq!(num_sent, 2);
+
+    // An event that no relay accepts is kept for a retry.
+    NostrEvent::enqueue_note_for_connection(&mut *conn, "New bounty", "/bounty/2")
+        .await
+        .unwrap();
+    drop(conn);
+    let (rejecting_relay_url, mut rejected_events) = start_relay_stand_in(false).await;
+    let rejecting_config = Config {
+        nostr_relays: vec![rejecting_relay_url],
+        ..config
+    };
+    nostr_publisher::send_pending_nostr_events(rejecting_config, pool.acquire().await.unwrap())
+        .await
+        .unwrap();
+    assert!(rejected_events.recv().await.is_some());
+    let mut conn = pool.acquire().await.unwrap();
+    let row = sqlx::query("select sent, num_attempts, last_error from nostrevents WHERE link = ?;")
+        .bind("/bounty/2")
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    let sent: bool = row.try_get("sent").unwrap();
+    let num_attempts: i64 = row.try_get("num_attempts").unwrap();
+    let last_error: String = row.try_get("last_error").unwrap();
+    assert!(!sent);
+    assert_eq!(num_attempts, 1);
+    assert!(last_error.contains("rejected"));
+}
+
+async fn insert_test_bounty(
+    conn: &mut SqliteConnection,
+    poster_user_id: i32,
+    price_sat: u64,
+    fee_rate_basis_points: u32,
+) -> i32 {
+    let price_sat: i64 = price_sat.try_into().unwrap();
+    sqlx::query(
+        "INSERT INTO bounties (public_id, user_id, title, description, price_sat, fee_rate_basis_points, viewed, submitted, approved, deactivated_by_seller, deactivated_by_admin, created_time_ms) VALUES (?, ?, 'Test bounty', '', ?, ?, true, true, true, false, false, 0)",
+    )
+    .bind(util::create_uuid())
+    .bind(poster_user_id)
+    .bind(price_sat)
+    .bind(fee_rate_basis_points)
+    .execute(conn)
+    .await
+    .unwrap()
+    .last_insert_rowid() as i32
+}
+
+/// Inserts a paid case that is waiting on a decision from the poster.
+async fn insert_test_case(
+    conn: &mut SqliteConnection,
+    bounty_id: i32,
+    hunter_user_id: i32,
+    poster_user_id: i32,
+    amount_owed_sat: u64,
+) -> i32 {
+    let amount_owed_sat: i64 = amount_owed_sat.try_into().unwrap();
+    sqlx::query(
+        "INSERT INTO cases (public_id, buyer_user_id, seller_user_id, quantity, bounty_id, case_details, amount_owed_sat, seller_credit_sat, paid, awarded, canceled_by_seller, canceled_by_buyer, invoice_payment_request, invoice_hash, created_time_ms, payment_time_ms) VALUES (?, ?, ?, 1, ?, 'Test case', ?, ?, true, false, false, false, '', '', 0, 0)",
+    )
+    .bind(util::create_uuid())
+    .bind(hunter_user_id)
+    .bind(poster_user_id)
+    .bind(bounty_id)
+    .bind(amount_owed_sat)
+    .bind(amount_owed_sat)
+    .execute(conn)
+    .await
+    .unwrap()
+    .last_insert_rowid() as i32
+}
+
+/// Credits a user with the fee of an awarded case on another bounty.
+async fn fund_test_user(conn: &mut SqliteConnection, user_id: i32, amount_sat: u64) {
+    let case_id = insert_test_case(conn, 0, 0, user_id, amount_sat).await;
+    sqlx::query("UPDATE cases SET awarded = true WHERE id = ?")
+        .bind(case_id)
+        .execute(conn)
+        .await
+        .unwrap();
+}
+
+async fn balance_sat(conn: &mut SqliteConnection, user_id: i32) -> i64 {
+    AccountInfo::total_account_balance_for_connection(conn, user_id)
+        .await
+        .unwrap()
+}
+
+fn test_case_award(
+    bounty_id: i32,
+    case_id: i32,
+    poster_user_id: i32,
+    winner_user_id: i32,
+    amount_sat: u64,
+    fee_rate_basis_points: u64,
+) -> CaseAward {
+    let fee_sat = (amount_sat * fee_rate_basis_points + 9999) / 10000;
+    CaseAward {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id,
+        bounty_id,
+        poster_user_id,
+        winner_user_id,
+        milestone_id: None,
+        amount_sat,
+        fee_sat,
+        credit_sat: amount_sat - fee_sat,
+        created_time_ms: util::current_time_millis(),
+    }
+}
+
+#[test]
+fn test_split_reward_sat() {
+    assert_eq!(
+        split_reward_sat(1000, &[33, 33, 34], "percent"),
+        Ok(vec![330, 330, 340])
+    );
+    // The rounding remainder goes to the last winner.
+    assert_eq!(
+        split_reward_sat(1001, &[50, 50], "percent"),
+        Ok(vec![500, 501])
+    );
+    assert_eq!(
+        split_reward_sat(1000, &[50, 40], "percent"),
+        Err("Percentages must add up to 100.".to_string())
+    );
+    assert_eq!(
+        split_reward_sat(1000, &[300, 700], "amount"),
+        Ok(vec![300, 700])
+    );
+    assert_eq!(
+        split_reward_sat(1000, &[300, 600], "amount"),
+        Err("Amounts must add up to the reward of 1000 sats.".to_string())
+    );
+    // Shares that would overflow are refused instead of wrapping around.
+    assert_eq!(
+        split_reward_sat(1000, &[u64::MAX, 101], "percent"),
+        Err("Share is larger than the reward.".to_string())
+    );
+    assert_eq!(
+        split_reward_sat(u64::MAX, &[u64::MAX, 1], "amount"),
+        Err("Shares are too large.".to_string())
+    );
+    assert_eq!(
+        split_reward_sat(u64::MAX, &[50, 50], "percent"),
+        Err("Reward is too large to split.".to_string())
+    );
+}
+
+#[rocket::async_test]
+async fn test_split_award() {
+    let pool = test_pool().await;
+    let poster = create_test_user(&pool, "poster@example.com").await;
+    let hunter_1 = create_test_user(&pool, "hunter1@example.com").await;
+    let hunter_2 = create_test_user(&pool, "hunter2@example.com").await;
+    let broke_poster = create_test_user(&pool, "broke@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    fund_test_user(&mut *conn, poster, 10000).await;
+    let bounty_id = insert_test_bounty(&mut *conn, poster, 1000, 500).await;
+    let case_1 = insert_test_case(&mut *conn, bounty_id, hunter_1, poster, 50).await;
+    let case_2 = insert_test_case(&mut *conn, bounty_id, hunter_2, poster, 50).await;
+
+    // Each winner pays the fee on their own share.
+    let amounts_sat = split_reward_sat(1000, &[30, 70], "percent").unwrap();
+    let case_awards = vec![
+        test_case_award(bounty_id, case_1, poster, hunter_1, amounts_sat[0], 500),
+        test_case_award(bounty_id, case_2, poster, hunter_2, amounts_sat[1], 500),
+    ];
+    CaseAward::do_split_award_for_connection(&mut *conn, poster, None, case_awards.clone())
+        .await
+        .unwrap();
+    assert_eq!(balance_sat(&mut *conn, hunter_1).await, 285);
+    assert_eq!(balance_sat(&mut *conn, hunter_2).await, 665);
+    // The poster pays the reward, and keeps the case fees.
+    assert_eq!(
+        balance_sat(&mut *conn, poster).await,
+        10000 - 1000 + 50 + 50
+    );
+    assert!(
+        Case::single_for_connection(&mut *conn, case_1)
+            .await
+            .unwrap()
+            .awarded
+    );
+    assert!(
+        Case::single_for_connection(&mut *conn, case_2)
+            .await
+            .unwrap()
+            .awarded
+    );
+
+    // The reward cannot be paid twice.
+    assert!(
+        CaseAward::do_split_award_for_connection(&mut *conn, poster, None, case_awards)
+            .await
+            .is_err()
+    );
+    assert_eq!(balance_sat(&mut *conn, hunter_1).await, 285);
+
+    // A poster who cannot cover the reward pays nothing, and the case stays pending.
+    let other_bounty_id = insert_test_bounty(&mut *conn, broke_poster, 1000, 500).await;
+    let other_case =
+        insert_test_case(&mut *conn, other_bounty_id, hunter_1, broke_poster, 50).await;
+    let other_case_awards = vec![test_case_award(
+        other_bounty_id,
+        other_case,
+        broke_poster,
+        hunter_1,
+        1000,
+        500,
+    )];
+    assert_eq!(
+        CaseAward::do_split_award_for_connection(&mut *conn, broke_poster, None, other_case_awards)
+            .await,
+        Err("Insufficient funds to pay the reward.".to_string())
+    );
+    assert!(
+        !Case::single_for_connection(&mut *conn, other_case)
+            .await
+            .unwrap()
+            .awarded
+    );
+    assert_eq!(balance_sat(&mut *conn, hunter_1).await, 285);
+}
+
+async fn insert_test_milestone(
+    conn: &mut SqliteConnection,
+    bounty_id: i32,
+    position: u32,
+    reward_sat: u64,
+) -> i32 {
+    let reward_sat: i64 = reward_sat.try_into().unwrap();
+    sqlx::query(
+        "INSERT INTO bountymilestones (public_id, bounty_id, position, description, reward_sat, awarded, created_time_ms) VALUES (?, ?, ?, 'Test milestone', ?, false, 0)",
+    )
+    .bind(util::create_uuid())
+    .bind(bounty_id)
+    .bind(position)
+    .bind(reward_sat)
+    .execute(conn)
+    .await
+    .unwrap()
+    .last_insert_rowid() as i32
+}
+
+async fn insert_test_milestone_case(
+    conn: &mut SqliteConnection,
+    bounty_id: i32,
+    milestone_id: i32,
+    hunter_user_id: i32,
+    poster_user_id: i32,
+) -> i32 {
+    let case_id = insert_test_case(conn, bounty_id, hunter_user_id, poster_user_id, 50).await;
+    sqlx::query("INSERT INTO casemilestones (case_id, milestone_id) VALUES (?, ?)")
+        .bind(case_id)
+        .bind(milestone_id)
+        .execute(conn)
+        .await
+        .unwrap();
+    case_id
+}
+
+#[rocket::async_test]
+async fn test_milestone_awards() {
+    let pool = test_pool().await;
+    let poster = create_test_user(&pool, "poster@example.com").await;
+    let hunter = create_test_user(&pool, "hunter@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    fund_test_user(&mut *conn, poster, 10000).await;
+    let bounty_id = insert_test_bounty(&mut *conn, poster, 1000, 0).await;
+    let milestone_1 = insert_test_milestone(&mut *conn, bounty_id, 1, 400).await;
+    let milestone_2 = insert_test_milestone(&mut *conn, bounty_id, 2, 600).await;
+    let case_1 =
+        insert_test_milestone_case(&mut *conn, bounty_id, milestone_1, hunter, poster).await;
+    let case_2 =
+        insert_test_milestone_case(&mut *conn, bounty_id, milestone_1, hunter, poster).await;
+    let case_3 =
+        insert_test_milestone_case(&mut *conn, bounty_id, milestone_2, hunter, poster).await;
+
+    // Each milestone is paid on its own.
+    let milestone_1_awards = vec![CaseAward {
+        milestone_id: Some(milestone_1),
+        ..test_case_award(bounty_id, case_1, poster, hunter, 400, 0)
+    }];
+    CaseAward::do_split_award_for_connection(
+        &mut *conn,
+        poster,
+        Some(milestone_1),
+        milestone_1_awards,
+    )
+    .await
+    .unwrap();
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 400);
+
+    // A milestone cannot be paid twice, even for another case.
+    let repeated_awards = vec![CaseAward {
+        milestone_id: Some(milestone_1),
+        ..test_case_award(bounty_id, case_2, poster, hunter, 400, 0)
+    }];
+    assert_eq!(
+        CaseAward::do_split_award_for_connection(
+            &mut *conn,
+            poster,
+            Some(milestone_1),
+            repeated_awards
+        )
+        .await,
+        Err("Milestone has already been awarded.".to_string())
+    );
+    assert!(
+        !Case::single_for_connection(&mut *conn, case_2)
+            .await
+            .unwrap()
+            .awarded
+    );
+
+    let milestone_2_awards = vec![CaseAward {
+        milestone_id: Some(milestone_2),
+        ..test_case_award(bounty_id, case_3, poster, hunter, 600, 0)
+    }];
+    CaseAward::do_split_award_for_connection(
+        &mut *conn,
+        poster,
+        Some(milestone_2),
+        milestone_2_awards,
+    )
+    .await
+    .unwrap();
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 1000);
+    assert_eq!(
+        balance_sat(&mut *conn, poster).await,
+        10000 - 1000 + 50 + 50
+    );
+}
+
+/// Opens a dispute on a case that the poster rejected, and returns it with the reserved
+/// reward.
+async fn open_test_dispute(conn: &mut SqliteConnection, case_id: i32) -> CaseDispute {
+    sqlx::query("UPDATE cases SET canceled_by_seller = true WHERE id = ?")
+        .bind(case_id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    let dispute = CaseDispute {
+        id: None,
+        public_id: util::create_uuid(),
+        case_id,
+        case_public_id: None,
+        reason: "The case solves the bounty.".to_string(),
+        resolved: false,
+        resolution: "".to_string(),
+        submitter_share_basis_points: 0,
+        resolution_note: "".to_string(),
+        resolved_by_user_id: None,
+        reserved_sat: 0,
+        created_time_ms: util::current_time_millis(),
+        resolved_time_ms: 0,
+    };
+    CaseDispute::insert_for_connection(&mut *conn, &dispute)
+        .await
+        .unwrap();
+    let row = sqlx::query("select id, reserved_sat from casedisputes WHERE case_id = ?;")
+        .bind(case_id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    let reserved_sat: i64 = row.try_get("reserved_sat").unwrap();
+    CaseDispute {
+        id: Some(row.try_get("id").unwrap()),
+        reserved_sat: reserved_sat as u64,
+        ..dispute
+    }
+}
+
+fn resolved_test_dispute(
+    dispute: CaseDispute,
+    resolution: &str,
+    submitter_share_basis_points: u32,
+    admin_user_id: i32,
+) -> CaseDispute {
+    CaseDispute {
+        resolved: true,
+        resolution: resolution.to_string(),
+        submitter_share_basis_points,
+        resolved_by_user_id: Some(admin_user_id),
+        resolved_time_ms: util::current_time_millis(),
+        ..dispute
+    }
+}
+
+#[rocket::async_test]
+async fn test_dispute_split() {
+    let pool = test_pool().await;
+    let admin = create_test_user(&pool, "admin@example.com").await;
+    let poster = create_test_user(&pool, "poster@example.com").await;
+    let hunter = create_test_user(&pool, "hunter@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    fund_test_user(&mut *conn, poster, 600).await;
+    let bounty_id = insert_test_bounty(&mut *conn, poster, 1000, 0).await;
+    let case_id = insert_test_case(&mut *conn, bounty_id, hunter, poster, 50).await;
+
+    // The reserve is capped at what the poster has left, and held until the dispute
+    // is resolved.
+    let dispute = open_test_dispute(&mut *conn, case_id).await;
+    assert_eq!(dispute.reserved_sat, 600);
+    assert_eq!(balance_sat(&mut *conn, poster).await, 0);
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 50);
+
+    // The submitter is paid their share of the reserve, and keeps the refunded case fee.
+    let case = Case::single_for_connection(&mut *conn, case_id)
+        .await
+        .unwrap();
+    let amount_sat = dispute.reserved_sat * 5000 / 10000;
+    let case_awards = vec![test_case_award(
+        bounty_id, case_id, poster, hunter, amount_sat, 0,
+    )];
+    let resolved_dispute = resolved_test_dispute(dispute, "split", 5000, admin);
+    CaseDispute::resolve_for_connection(
+        &mut *conn,
+        resolved_dispute.clone(),
+        &case,
+        None,
+        case_awards.clone(),
+    )
+    .await
+    .unwrap();
+    let case = Case::single_for_connection(&mut *conn, case_id)
+        .await
+        .unwrap();
+    assert!(!case.awarded);
+    assert!(case.canceled_by_seller);
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 50 + 300);
+    assert_eq!(balance_sat(&mut *conn, poster).await, 600 - 300);
+
+    // A dispute is resolved only once.
+    assert_eq!(
+        CaseDispute::resolve_for_connection(&mut *conn, resolved_dispute, &case, None, case_awards)
+            .await,
+        Err("Dispute is already resolved.".to_string())
+    );
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 50 + 300);
+}
+
+#[rocket::async_test]
+async fn test_dispute_award_without_reserve() {
+    let pool = test_pool().await;
+    let admin = create_test_user(&pool, "admin@example.com").await;
+    let poster = create_test_user(&pool, "poster@example.com").await;
+    let hunter = create_test_user(&pool, "hunter@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    let bounty_id = insert_test_bounty(&mut *conn, poster, 1000, 0).await;
+    let case_id = insert_test_case(&mut *conn, bounty_id, hunter, poster, 50).await;
+
+    // The poster has nothing left, so nothing is reserved and the dispute can only be
+    // refunded.
+    let dispute = open_test_dispute(&mut *conn, case_id).await;
+    assert_eq!(dispute.reserved_sat, 0);
+    let case = Case::single_for_connection(&mut *conn, case_id)
+        .await
+        .unwrap();
+    let resolved_dispute = resolved_test_dispute(dispute.clone(), "award", 10000, admin);
+    assert_eq!(
+        CaseDispute::resolve_for_connection(&mut *conn, resolved_dispute, &case, None, vec![])
+            .await,
+        Err("Nothing was reserved for this dispute, so it can only be refunded.".to_string())
+    );
+    let case = Case::single_for_connection(&mut *conn, case_id)
+        .await
+        .unwrap();
+    assert!(!case.awarded);
+    assert!(case.canceled_by_seller);
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 50);
+
+    let resolved_dispute = resolved_test_dispute(dispute, "refund", 0, admin);
+    CaseDispute::resolve_for_connection(&mut *conn, resolved_dispute, &case, None, vec![])
+        .await
+        .unwrap();
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 50);
+    assert_eq!(balance_sat(&mut *conn, poster).await, 0);
+}
+
+#[rocket::async_test]
+async fn test_dispute_refund() {
+    let pool = test_pool().await;
+    let admin = create_test_user(&pool, "admin@example.com").await;
+    let poster = create_test_user(&pool, "poster@example.com").await;
+    let hunter = create_test_user(&pool, "hunter@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    fund_test_user(&mut *conn, poster, 2000).await;
+    let bounty_id = insert_test_bounty(&mut *conn, poster, 1000, 0).await;
+    let case_id = insert_test_case(&mut *conn, bounty_id, hunter, poster, 50).await;
+
+    let dispute = open_test_dispute(&mut *conn, case_id).await;
+    assert_eq!(dispute.reserved_sat, 1000);
+    assert_eq!(balance_sat(&mut *conn, poster).await, 1000);
+
+    // The rejection stands: the reserve goes back to the poster and the case fee to
+    // the submitter.
+    let case = Case::single_for_connection(&mut *conn, case_id)
+        .await
+        .unwrap();
+    let resolved_dispute = resolved_test_dispute(dispute, "refund", 0, admin);
+    CaseDispute::resolve_for_connection(&mut *conn, resolved_dispute, &case, None, vec![])
+        .await
+        .unwrap();
+    let case = Case::single_for_connection(&mut *conn, case_id)
+        .await
+        .unwrap();
+    assert!(!case.awarded);
+    assert!(case.canceled_by_seller);
+    assert_eq!(balance_sat(&mut *conn, poster).await, 2000);
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 50);
+}
+
+#[rocket::async_test]
+async fn test_review_deadline_escalation_without_reserve() {
+    let pool = test_pool().await;
+    let poster = create_test_user(&pool, "poster@example.com").await;
+    let hunter = create_test_user(&pool, "hunter@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    let bounty_id = insert_test_bounty(&mut *conn, poster, 1000, 0).await;
+    let case_id = insert_test_case(&mut *conn, bounty_id, hunter, poster, 50).await;
+    let deadline = CaseReviewDeadline {
+        case_id,
+        case_public_id: "".to_string(),
+        buyer_user_id: hunter,
+        seller_user_id: poster,
+        deadline_ms: 0,
+    };
+
+    // The poster has nothing left to reserve, so the case is refunded instead of
+    // escalated to a dispute.
+    CaseReviewDeadline::expire_for_connection(&mut *conn, &deadline, false, 0)
+        .await
+        .unwrap();
+    let case = Case::single_for_connection(&mut *conn, case_id)
+        .await
+        .unwrap();
+    assert!(case.canceled_by_seller);
+    let num_disputes: i64 = sqlx::query("select COUNT(id) as num_disputes from casedisputes;")
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap()
+        .try_get("num_disputes")
+        .unwrap();
+    assert_eq!(num_disputes, 0);
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 50);
+}
+
+fn test_bounty_claim(bounty_id: i32, hunter_user_id: i32, stake_sat: u64) -> BountyClaim {
+    let now = util::current_time_millis();
+    BountyClaim {
+        id: None,
+        public_id: util::create_uuid(),
+        bounty_id,
+        bounty_public_id: None,
+        bounty_title: None,
+        user_id: hunter_user_id,
+        username: None,
+        stake_sat,
+        released: false,
+        release_reason: "".to_string(),
+        created_time_ms: now,
+        expires_time_ms: now + 60 * 60 * 1000,
+        released_time_ms: 0,
+    }
+}
+
+async fn held_claim_stakes_sat(conn: &mut SqliteConnection) -> i64 {
+    AccountInfo::total_held_claim_stakes_sat_for_connection(conn)
+        .await
+        .unwrap()
+}
+
+#[rocket::async_test]
+async fn test_claim_stakes() {
+    let pool = test_pool().await;
+    let poster = create_test_user(&pool, "poster@example.com").await;
+    let hunter = create_test_user(&pool, "hunter@example.com").await;
+    let other_hunter = create_test_user(&pool, "other@example.com").await;
+    let mut conn = pool.acquire().await.unwrap();
+    fund_test_user(&mut *conn, hunter, 1000).await;
+    fund_test_user(&mut *conn, other_hunter, 1000).await;
+    let bounty_id = insert_test_bounty(&mut *conn, poster, 5000, 0).await;
+    let other_bounty_id = insert_test_bounty(&mut *conn, poster, 5000, 0).await;
+    let liabilities_sat = AccountInfo::total_market_liabilities_sat_for_connection(&mut *conn)
+        .await
+        .unwrap();
+
+    // The stake is taken out of the balance of the hunter, but is still owed to them.
+    BountyClaim::insert_for_connection(test_bounty_claim(bounty_id, hunter, 300), &mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 700);
+    assert_eq!(held_claim_stakes_sat(&mut *conn).await, 300);
+    assert_eq!(
+        AccountInfo::total_market_liabilities_sat_for_connection(&mut *conn)
+            .await
+            .unwrap(),
+        liabilities_sat
+    );
+
+    // Only one hunter holds a bounty at a time.
+    assert_eq!(
+        BountyClaim::insert_for_connection(
+            test_bounty_claim(bounty_id, other_hunter, 300),
+            &mut *conn
+        )
+        .await,
+        Err("Bounty is already claimed.".to_string())
+    );
+    // The balance has to cover the stake.
+    assert_eq!(
+        BountyClaim::insert_for_connection(
+            test_bounty_claim(other_bounty_id, hunter, 800),
+            &mut *conn
+        )
+        .await,
+        Err("Insufficient funds for the claim stake.".to_string())
+    );
+    assert_eq!(held_claim_stakes_sat(&mut *conn).await, 300);
+
+    // Releasing the claim, early or on expiry, gives back the whole stake.
+    let claim_id: i32 =
+        sqlx::query("select id from bountyclaims WHERE user_id = ? AND NOT released;")
+            .bind(hunter)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap()
+            .try_get("id")
+            .unwrap();
+    let now = util::current_time_millis();
+    assert!(
+        BountyClaim::release_for_connection(&mut *conn, claim_id, "expired", now)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !BountyClaim::release_for_connection(&mut *conn, claim_id, "expired", now)
+            .await
+            .unwrap()
+    );
+    assert_eq!(balance_sat(&mut *conn, hunter).await, 1000);
+    assert_eq!(held_claim_stakes_sat(&mut *conn).await, 0);
+    assert_eq!(
+        AccountInfo::total_market_liabilities_sat_for_connection(&mut *conn)
+            .await
+            .unwrap(),
+        liabilities_sat
+    );
+}
@@ -1,6 +1,6 @@
 use crate::base::BaseContext;
 use crate::db::Db;
-use crate::models::{Case, SellerInfo};
+use crate::models::{Case, Reputation, RocketAuthUser, SellerInfo};
 use rocket::fairing::AdHoc;
 use rocket::request::FlashMessage;
 use rocket::serde::Serialize;
@@ -16,6 +16,7 @@ struct Context {
     base_context: BaseContext,
     flash: Option<(String, String)>,
     seller_infos: Vec<SellerInfo>,
+    reputations: Vec<Reputation>,
     page_num: u32,
 }
 
@@ -34,10 +35,22 @@ impl Context {
         let seller_infos = Case::seller_info_for_all_users(&mut db, PAGE_SIZE, page_num)
             .await
             .map_err(|_| "failed to get seller infos for top users.")?;
+        let mut reputations = vec![];
+        for seller_info in seller_infos.iter() {
+            let seller_user =
+                RocketAuthUser::single_by_username(&mut db, seller_info.username.clone())
+                    .await
+                    .map_err(|_| "failed to get seller user.")?;
+            let reputation = Reputation::single_for_user(&mut db, seller_user.id.unwrap())
+                .await
+                .map_err(|_| "failed to get seller reputation.")?;
+            reputations.push(reputation);
+        }
         Ok(Context {
             base_context,
             flash,
             seller_infos,
+            reputations,
             page_num,
         })
     }
@@ -0,0 +1,121 @@
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rocket::http::RawStr;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+const SECRET_LENGTH_BYTES: usize = 20;
+const TIME_STEP_MS: u64 = 30 * 1000;
+const CODE_DIGITS: u32 = 6;
+// Codes from the steps just before and after the current one are also accepted, for clock drift.
+const ALLOWED_STEP_DRIFT: u64 = 1;
+const NUM_RECOVERY_CODES: usize = 10;
+const RECOVERY_CODE_LENGTH_BYTES: usize = 10;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Creates a new random secret, encoded in base32 for authenticator apps.
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_LENGTH_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+/// The otpauth URI that authenticator apps read from the enrollment QR code.
+pub fn provisioning_uri(secret: &str, issuer: &str, account_name: &str) -> String {
+    let issuer = RawStr::new(issuer).percent_encode();
+    let account_name = RawStr::new(account_name).percent_encode();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        account_name,
+        secret,
+        issuer,
+        CODE_DIGITS,
+        TIME_STEP_MS / 1000,
+    )
+}
+
+/// Returns the time step of the code if it is valid now, so that it cannot be used again.
+pub fn verify_code(secret: &str, code: &str, now_ms: u64) -> Option<u64> {
+    let secret = base32_decode(secret)?;
+    let code = code.trim().replace(' ', "");
+    if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    };
+    let code: u32 = code.parse().ok()?;
+    let current_step = now_ms / TIME_STEP_MS;
+    (current_step.saturating_sub(ALLOWED_STEP_DRIFT)..=current_step + ALLOWED_STEP_DRIFT)
+        .find(|step| code_at_step(&secret, *step) == code)
+}
+
+fn code_at_step(secret: &[u8], step: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC can take key of any size");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset],
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]) & 0x7fff_ffff;
+    binary % 10u32.pow(CODE_DIGITS)
+}
+
+/// Creates one-time codes that can be used instead of the authenticator app.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..NUM_RECOVERY_CODES)
+        .map(|_| {
+            let mut bytes = [0u8; RECOVERY_CODE_LENGTH_BYTES];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let code = base32_encode(&bytes).to_lowercase();
+            code.as_bytes()
+                .chunks(4)
+                .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// Recovery codes are only stored as hashes.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized = code.trim().to_lowercase().replace(['-', ' '], "");
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut num_bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        num_bits += 8;
+        while num_bits >= 5 {
+            num_bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> num_bits) & 0x1f) as usize] as char);
+        }
+    }
+    if num_bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - num_bits)) & 0x1f) as usize] as char);
+    };
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut buffer: u32 = 0;
+    let mut num_bits = 0;
+    for c in encoded.trim_end_matches('=').chars() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        num_bits += 5;
+        if num_bits >= 8 {
+            num_bits -= 8;
+            bytes.push((buffer >> num_bits) as u8);
+        };
+    }
+    Some(bytes)
+}
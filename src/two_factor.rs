@@ -0,0 +1,538 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{AdminSettings, TwoFactorCodeInput, TwoFactorPolicy, TwoFactorSettings};
+use crate::totp;
+use crate::util;
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::http::{Cookie, CookieJar, Method, Status};
+use rocket::outcome::Outcome;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket::{Data, Request};
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+// Private cookie with the id of the user who entered a code in this session.
+const VERIFIED_COOKIE_NAME: &str = "two_factor_verified";
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    two_factor_enabled: bool,
+    maybe_pending_secret: Option<String>,
+    maybe_qr_svg_base64: Option<String>,
+    num_unused_recovery_codes: u32,
+    new_recovery_codes: Vec<String>,
+    two_factor_policy: TwoFactorPolicy,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        new_recovery_codes: Vec<String>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let maybe_settings = TwoFactorSettings::single_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get two-factor settings.")?;
+        let two_factor_policy = TwoFactorPolicy::single(&mut db)
+            .await
+            .map_err(|_| "failed to get two-factor policy.")?;
+        let num_unused_recovery_codes =
+            TwoFactorSettings::num_unused_recovery_codes(&mut db, user.id())
+                .await
+                .map_err(|_| "failed to get number of recovery codes.")?;
+        let two_factor_enabled = maybe_settings
+            .as_ref()
+            .map(|settings| settings.enabled)
+            .unwrap_or(false);
+        let maybe_pending_secret = maybe_settings
+            .filter(|settings| !settings.enabled)
+            .map(|settings| settings.secret);
+        let maybe_qr_svg_base64 = match maybe_pending_secret {
+            Some(ref secret) => {
+                let admin_settings = AdminSettings::single(&mut db)
+                    .await
+                    .map_err(|_| "failed to get admin settings.")?;
+                let uri = totp::provisioning_uri(secret, &admin_settings.market_name, user.email());
+                let qr_svg_bytes = util::generate_qr(&uri);
+                Some(util::to_base64(&qr_svg_bytes))
+            }
+            None => None,
+        };
+
+        Ok(Context {
+            base_context,
+            flash,
+            two_factor_enabled,
+            maybe_pending_secret,
+            maybe_qr_svg_base64,
+            num_unused_recovery_codes,
+            new_recovery_codes,
+            two_factor_policy,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct VerifyContext {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, user, admin_user, vec![], csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("twofactor", context))
+}
+
+#[post("/enroll")]
+async fn enroll(mut db: Connection<Db>, user: User, _csrf: CsrfChecked) -> Flash<Redirect> {
+    match start_enrollment(&mut db, user).await {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/two_factor", index())),
+            "Scan the QR code with your authenticator app.",
+        ),
+        Err(e) => {
+            error_!("Two-factor enrollment error: {}", e);
+            Flash::error(Redirect::to(uri!("/two_factor", index())), e)
+        }
+    }
+}
+
+async fn start_enrollment(db: &mut Connection<Db>, user: User) -> Result<(), String> {
+    let now = util::current_time_millis();
+    let enabled = TwoFactorSettings::enabled_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to get two-factor settings.")?;
+
+    if enabled {
+        return Err("Two-factor authentication is already enabled.".to_string());
+    };
+
+    TwoFactorSettings::start_enrollment(db, user.id(), &totp::generate_secret(), now)
+        .await
+        .map_err(|_| "failed to save two-factor secret.")?;
+
+    Ok(())
+}
+
+#[post("/enable", data = "<code_form>")]
+async fn enable(
+    code_form: Form<TwoFactorCodeInput>,
+    mut db: Connection<Db>,
+    cookies: &CookieJar<'_>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+    _csrf: CsrfChecked,
+) -> Result<Template, Flash<Redirect>> {
+    let code_input = code_form.into_inner();
+
+    match enable_two_factor(&mut db, user.clone(), code_input).await {
+        Ok(recovery_codes) => {
+            // The session that turned on two-factor does not need to enter another code.
+            cookies.add_private(Cookie::new(VERIFIED_COOKIE_NAME, user.id().to_string()));
+            let flash = Some((
+                "success".to_string(),
+                "Two-factor authentication enabled.".to_string(),
+            ));
+            Context::raw(db, flash, user, admin_user, recovery_codes, csrf_token)
+                .await
+                .map(|context| Template::render("twofactor", context))
+                .map_err(|e| Flash::error(Redirect::to(uri!("/two_factor", index())), e))
+        }
+        Err(e) => {
+            error_!("Enable two-factor error: {}", e);
+            Err(Flash::error(Redirect::to(uri!("/two_factor", index())), e))
+        }
+    }
+}
+
+async fn enable_two_factor(
+    db: &mut Connection<Db>,
+    user: User,
+    code_input: TwoFactorCodeInput,
+) -> Result<Vec<String>, String> {
+    let now = util::current_time_millis();
+    let settings = TwoFactorSettings::single_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to get two-factor settings.")?
+        .ok_or("Start the two-factor setup first.")?;
+
+    if settings.enabled {
+        return Err("Two-factor authentication is already enabled.".to_string());
+    };
+    let step = totp::verify_code(&settings.secret, &code_input.code, now)
+        .ok_or("Two-factor code is not valid.")?;
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let recovery_code_hashes = recovery_codes
+        .iter()
+        .map(|code| totp::hash_recovery_code(code))
+        .collect::<Vec<_>>();
+    TwoFactorSettings::enable(db, user.id(), step, &recovery_code_hashes, now).await?;
+
+    Ok(recovery_codes)
+}
+
+#[post("/recovery_codes", data = "<code_form>")]
+async fn new_recovery_codes(
+    code_form: Form<TwoFactorCodeInput>,
+    mut db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+    _csrf: CsrfChecked,
+) -> Result<Template, Flash<Redirect>> {
+    let code_input = code_form.into_inner();
+
+    match replace_recovery_codes(&mut db, user.clone(), code_input).await {
+        Ok(recovery_codes) => {
+            let flash = Some((
+                "success".to_string(),
+                "New recovery codes created.".to_string(),
+            ));
+            Context::raw(db, flash, user, admin_user, recovery_codes, csrf_token)
+                .await
+                .map(|context| Template::render("twofactor", context))
+                .map_err(|e| Flash::error(Redirect::to(uri!("/two_factor", index())), e))
+        }
+        Err(e) => {
+            error_!("New recovery codes error: {}", e);
+            Err(Flash::error(Redirect::to(uri!("/two_factor", index())), e))
+        }
+    }
+}
+
+async fn replace_recovery_codes(
+    db: &mut Connection<Db>,
+    user: User,
+    code_input: TwoFactorCodeInput,
+) -> Result<Vec<String>, String> {
+    let now = util::current_time_millis();
+    let settings = enabled_settings(db, &user).await?;
+
+    if !check_totp_code(db, &settings, &code_input.code).await? {
+        return Err("Two-factor code is not valid.".to_string());
+    };
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let recovery_code_hashes = recovery_codes
+        .iter()
+        .map(|code| totp::hash_recovery_code(code))
+        .collect::<Vec<_>>();
+    TwoFactorSettings::replace_recovery_codes(db, user.id(), &recovery_code_hashes, now).await?;
+
+    Ok(recovery_codes)
+}
+
+#[delete("/", data = "<code_form>")]
+async fn disable(
+    code_form: Form<TwoFactorCodeInput>,
+    mut db: Connection<Db>,
+    user: User,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let code_input = code_form.into_inner();
+
+    match disable_two_factor(&mut db, user, code_input).await {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/two_factor", index())),
+            "Two-factor authentication disabled.",
+        ),
+        Err(e) => {
+            error_!("Disable two-factor error: {}", e);
+            Flash::error(Redirect::to(uri!("/two_factor", index())), e)
+        }
+    }
+}
+
+async fn disable_two_factor(
+    db: &mut Connection<Db>,
+    user: User,
+    code_input: TwoFactorCodeInput,
+) -> Result<(), String> {
+    let settings = enabled_settings(db, &user).await?;
+    let two_factor_policy = TwoFactorPolicy::single(db)
+        .await
+        .map_err(|_| "failed to get two-factor policy.")?;
+
+    if user.is_admin && two_factor_policy.require_for_admins {
+        return Err("This market requires two-factor authentication for admins.".to_string());
+    };
+    if !check_login_code(db, &settings, &code_input.code).await? {
+        return Err("Two-factor code is not valid.".to_string());
+    };
+
+    TwoFactorSettings::disable(db, user.id()).await?;
+
+    Ok(())
+}
+
+#[get("/verify")]
+async fn verify_index(
+    flash: Option<FlashMessage<'_>>,
+    mut db: Connection<Db>,
+    user: User,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let base_context = BaseContext::raw(&mut db, Some(user), None)
+        .await
+        .map_err(|_| "failed to get base template.")?
+        .with_csrf_token(csrf_token);
+    let context = VerifyContext {
+        base_context,
+        flash,
+    };
+    Ok(Template::render("twofactorverify", context))
+}
+
+#[post("/verify", data = "<code_form>")]
+async fn verify(
+    code_form: Form<TwoFactorCodeInput>,
+    mut db: Connection<Db>,
+    cookies: &CookieJar<'_>,
+    user: User,
+    _csrf: CsrfChecked,
+) -> Result<Redirect, Flash<Redirect>> {
+    let code_input = code_form.into_inner();
+
+    match verify_login(&mut db, &user, code_input).await {
+        Ok(_) => {
+            cookies.add_private(Cookie::new(VERIFIED_COOKIE_NAME, user.id().to_string()));
+            Ok(Redirect::to("/"))
+        }
+        Err(e) => {
+            error_!("Two-factor login error: {}", e);
+            Err(Flash::error(
+                Redirect::to(uri!("/two_factor", verify_index())),
+                e,
+            ))
+        }
+    }
+}
+
+async fn verify_login(
+    db: &mut Connection<Db>,
+    user: &User,
+    code_input: TwoFactorCodeInput,
+) -> Result<(), String> {
+    let settings = enabled_settings(db, user).await?;
+
+    if !check_login_code(db, &settings, &code_input.code).await? {
+        return Err("Two-factor code is not valid.".to_string());
+    };
+
+    Ok(())
+}
+
+async fn enabled_settings(
+    db: &mut Connection<Db>,
+    user: &User,
+) -> Result<TwoFactorSettings, String> {
+    TwoFactorSettings::single_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to get two-factor settings.")?
+        .filter(|settings| settings.enabled)
+        .ok_or_else(|| "Two-factor authentication is not enabled.".to_string())
+}
+
+/// Checks a code from the authenticator app, and uses it up so that it cannot be sent again.
+async fn check_totp_code(
+    db: &mut Connection<Db>,
+    settings: &TwoFactorSettings,
+    code: &str,
+) -> Result<bool, String> {
+    let now = util::current_time_millis();
+    match totp::verify_code(&settings.secret, code, now) {
+        Some(step) => TwoFactorSettings::use_step(db, settings.user_id, step)
+            .await
+            .map_err(|_| "failed to use two-factor code.".to_string()),
+        None => Ok(false),
+    }
+}
+
+/// Logins also accept a recovery code in place of a code from the app.
+async fn check_login_code(
+    db: &mut Connection<Db>,
+    settings: &TwoFactorSettings,
+    code: &str,
+) -> Result<bool, String> {
+    if check_totp_code(db, settings, code).await? {
+        return Ok(true);
+    };
+    let code_hash = totp::hash_recovery_code(code);
+    TwoFactorSettings::use_recovery_code(db, settings.user_id, &code_hash)
+        .await
+        .map_err(|_| "failed to use recovery code.".to_string())
+}
+
+/// Checks the fresh code that is sent with a withdrawal. Recovery codes are not accepted.
+pub async fn check_withdrawal_code(
+    db: &mut Connection<Db>,
+    user: &User,
+    maybe_code: Option<String>,
+) -> Result<(), String> {
+    let maybe_settings = TwoFactorSettings::single_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to get two-factor settings.")?;
+    let two_factor_policy = TwoFactorPolicy::single(db)
+        .await
+        .map_err(|_| "failed to get two-factor policy.")?;
+
+    match maybe_settings.filter(|settings| settings.enabled) {
+        Some(settings) => {
+            let code = maybe_code.unwrap_or_default();
+            if code.trim().is_empty() {
+                return Err("Two-factor code is required.".to_string());
+            };
+            if !check_totp_code(db, &settings, &code).await? {
+                return Err("Two-factor code is not valid.".to_string());
+            };
+            Ok(())
+        }
+        None if two_factor_policy.require_for_withdrawals => Err(
+            "This market requires two-factor authentication for withdrawals. Enable it from your account page."
+                .to_string(),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Ends the two-factor check of the current session, when the user logs in or out.
+pub fn forget_verified_session(cookies: &CookieJar<'_>) {
+    cookies.remove_private(Cookie::named(VERIFIED_COOKIE_NAME));
+}
+
+fn is_verified_session(cookies: &CookieJar<'_>, user: &User) -> bool {
+    cookies
+        .get_private(VERIFIED_COOKIE_NAME)
+        .map(|cookie| cookie.value() == user.id().to_string())
+        .unwrap_or(false)
+}
+
+/// Sends logged in users to the second login step until they enter a code, and admins to
+/// the setup page when the market requires two-factor for admins.
+pub struct TwoFactorGate;
+
+#[rocket::async_trait]
+impl Fairing for TwoFactorGate {
+    fn info(&self) -> Info {
+        Info {
+            name: "Two-factor gate",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let path = request.uri().path().as_str().to_string();
+        if [
+            "/logout",
+            "/rate_limited",
+            "/two_factor/verify",
+            "/two_factor/unavailable",
+        ]
+        .contains(&path.as_str())
+            || path.starts_with("/css/")
+            || path.starts_with("/images/")
+        {
+            return;
+        };
+        let user = match request.guard::<User>().await {
+            Outcome::Success(user) => user,
+            _ => return,
+        };
+        // When the settings cannot be read, the request is refused instead of let through.
+        let redirect_path = match gate_redirect_path(request, &user, &path).await {
+            Ok(Some(redirect_path)) => redirect_path,
+            Ok(None) => return,
+            Err(e) => {
+                error_!("Two-factor gate error: {}", e);
+                "/two_factor/unavailable"
+            }
+        };
+        request.set_method(Method::Get);
+        request.set_uri(Origin::parse(redirect_path).unwrap());
+    }
+}
+
+/// The page that a logged in user has to go to first, if any.
+async fn gate_redirect_path(
+    request: &Request<'_>,
+    user: &User,
+    path: &str,
+) -> Result<Option<&'static str>, String> {
+    let mut db = match request.guard::<Connection<Db>>().await {
+        Outcome::Success(db) => db,
+        _ => return Err("failed to get database connection.".to_string()),
+    };
+    let two_factor_enabled = TwoFactorSettings::enabled_for_user(&mut db, user.id())
+        .await
+        .map_err(|_| "failed to get two-factor settings.")?;
+
+    if two_factor_enabled {
+        if is_verified_session(request.cookies(), user) {
+            return Ok(None);
+        };
+        Ok(Some("/two_factor/verify"))
+    } else if user.is_admin && !path.starts_with("/two_factor") {
+        let two_factor_policy = TwoFactorPolicy::single(&mut db)
+            .await
+            .map_err(|_| "failed to get two-factor policy.")?;
+        if !two_factor_policy.require_for_admins {
+            return Ok(None);
+        };
+        Ok(Some("/two_factor"))
+    } else {
+        Ok(None)
+    }
+}
+
+#[get("/unavailable")]
+fn unavailable() -> Status {
+    Status::ServiceUnavailable
+}
+
+pub fn two_factor_stage() -> AdHoc {
+    AdHoc::on_ignite("Two Factor Stage", |rocket| async {
+        rocket.attach(TwoFactorGate).mount(
+            "/two_factor",
+            routes![
+                index,
+                enroll,
+                enable,
+                new_recovery_codes,
+                disable,
+                verify_index,
+                verify,
+                unavailable
+            ],
+        )
+    })
+}
@@ -1,4 +1,5 @@
 use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::image_util;
 use crate::models::FileUploadForm;
@@ -31,10 +32,12 @@ impl Context {
         flash: Option<(String, String)>,
         user: User,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let bounty_display = BountyDisplay::single_by_public_id(&mut db, bounty_id)
             .await
             .map_err(|_| "failed to get bounty display.")?;
@@ -58,6 +61,7 @@ async fn new(
     mut db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Flash<Redirect> {
     let image_info = upload_image_form.into_inner();
     let file = image_info.file;
@@ -134,6 +138,7 @@ async fn delete(
     mut db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match delete_image_with_public_id(
         id,
@@ -196,6 +201,7 @@ async fn set_primary(
     mut db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match mark_as_primary(
         id,
@@ -258,9 +264,10 @@ async fn index(
     db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, id, flash, active_user.user, admin_user)
+    let context = Context::raw(db, id, flash, active_user.user, admin_user, csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("updatebountyimages", context))
@@ -0,0 +1,99 @@
+use crate::base::BaseContext;
+use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Email, EmailSettingsInput};
+use crate::user_account::ActiveUser;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket::State;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    email_address: String,
+    email_enabled: bool,
+    email_configured: bool,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        config: &Config,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let email_enabled = Email::enabled_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get email settings.")?;
+
+        Ok(Context {
+            base_context,
+            flash,
+            email_address: user.email().to_string(),
+            email_enabled,
+            email_configured: !config.smtp_host.is_empty(),
+        })
+    }
+}
+
+#[post("/change", data = "<email_settings_form>")]
+async fn update(
+    email_settings_form: Form<EmailSettingsInput>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let email_settings = email_settings_form.into_inner();
+
+    match Email::set_enabled_for_user(&mut db, active_user.user.id(), email_settings.enabled).await
+    {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/update_email_settings", index())),
+            "Email settings successfully updated.",
+        ),
+        Err(e) => {
+            error_!("Update email settings error: {}", e);
+            Flash::error(
+                Redirect::to(uri!("/update_email_settings", index())),
+                "failed to update email settings.",
+            )
+        }
+    }
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    active_user: ActiveUser,
+    admin_user: Option<AdminUser>,
+    config: &State<Config>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, active_user.user, admin_user, config, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("updateemailsettings", context))
+}
+
+pub fn update_email_settings_stage() -> AdHoc {
+    AdHoc::on_ignite("Update Email Settings Stage", |rocket| async {
+        rocket.mount("/update_email_settings", routes![index, update])
+    })
+}
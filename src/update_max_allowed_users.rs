@@ -1,4 +1,5 @@
 use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::models::{AdminSettings, MaxAllowedUsersInput};
 use rocket::fairing::AdHoc;
@@ -24,10 +25,12 @@ impl Context {
         flash: Option<(String, String)>,
         user: User,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let admin_settings = AdminSettings::single(&mut db)
             .await
             .map_err(|_| "failed to get admin settings.")?;
@@ -45,6 +48,7 @@ async fn update(
     mut db: Connection<Db>,
     _user: User,
     _admin_user: AdminUser,
+    _csrf: CsrfChecked,
 ) -> Flash<Redirect> {
     let max_allowed_users_input = max_allowed_users_form.into_inner();
     let new_max_allowed_users = max_allowed_users_input.max_allowed_users.unwrap_or(0);
@@ -75,9 +79,10 @@ async fn index(
     db: Connection<Db>,
     user: User,
     admin_user: AdminUser,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, flash, user, Some(admin_user))
+    let context = Context::raw(db, flash, user, Some(admin_user), csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("updatemaxallowedusers", context))
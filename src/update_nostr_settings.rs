@@ -0,0 +1,124 @@
+use crate::base::BaseContext;
+use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{NostrEvent, NostrKeyInput};
+use crate::nostr_publisher;
+use crate::user_account::ActiveUser;
+use nostr::nips::nip19::ToBech32;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket::State;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    maybe_npub: Option<String>,
+    maybe_market_npub: Option<String>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        config: &Config,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let maybe_public_key = NostrEvent::public_key_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get nostr public key.")?;
+        let maybe_npub = match maybe_public_key {
+            Some(public_key) => Some(
+                nostr_publisher::parse_public_key(&public_key)?
+                    .to_bech32()
+                    .map_err(|_| "failed to encode nostr public key.")?,
+            ),
+            None => None,
+        };
+        let maybe_market_npub = if nostr_publisher::nostr_enabled(config) {
+            Some(nostr_publisher::market_npub(config)?)
+        } else {
+            None
+        };
+
+        Ok(Context {
+            base_context,
+            flash,
+            maybe_npub,
+            maybe_market_npub,
+        })
+    }
+}
+
+#[post("/change", data = "<nostr_key_form>")]
+async fn update(
+    nostr_key_form: Form<NostrKeyInput>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let nostr_key = nostr_key_form.into_inner();
+
+    match change_nostr_key(active_user.user, nostr_key, &mut db).await {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/update_nostr_settings", index())),
+            "Nostr settings successfully updated.",
+        ),
+        Err(e) => Flash::error(Redirect::to(uri!("/update_nostr_settings", index())), e),
+    }
+}
+
+async fn change_nostr_key(
+    user: User,
+    nostr_key: NostrKeyInput,
+    db: &mut Connection<Db>,
+) -> Result<(), String> {
+    // An empty key unlinks the npub.
+    if nostr_key.public_key.trim().is_empty() {
+        NostrEvent::remove_public_key_for_user(db, user.id())
+            .await
+            .map_err(|_| "failed to remove nostr public key.")?;
+        return Ok(());
+    }
+    let public_key = nostr_publisher::parse_public_key(nostr_key.public_key.trim())?;
+    NostrEvent::set_public_key_for_user(db, user.id(), &public_key.to_string())
+        .await
+        .map_err(|_| "failed to update nostr public key.")?;
+    Ok(())
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    active_user: ActiveUser,
+    admin_user: Option<AdminUser>,
+    config: &State<Config>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, active_user.user, admin_user, config, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("updatenostrsettings", context))
+}
+
+pub fn update_nostr_settings_stage() -> AdHoc {
+    AdHoc::on_ignite("Update Nostr Settings Stage", |rocket| async {
+        rocket.mount("/update_nostr_settings", routes![index, update])
+    })
+}
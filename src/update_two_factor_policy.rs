@@ -0,0 +1,93 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{TwoFactorPolicy, TwoFactorPolicyInput};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    two_factor_policy: TwoFactorPolicy,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let two_factor_policy = TwoFactorPolicy::single(&mut db)
+            .await
+            .map_err(|_| "failed to get two-factor policy.")?;
+        Ok(Context {
+            base_context,
+            flash,
+            two_factor_policy,
+        })
+    }
+}
+
+#[post("/change", data = "<two_factor_policy_form>")]
+async fn update(
+    two_factor_policy_form: Form<TwoFactorPolicyInput>,
+    mut db: Connection<Db>,
+    _user: User,
+    _admin_user: AdminUser,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let two_factor_policy_input = two_factor_policy_form.into_inner();
+    let two_factor_policy = TwoFactorPolicy {
+        require_for_withdrawals: two_factor_policy_input.require_for_withdrawals,
+        require_for_admins: two_factor_policy_input.require_for_admins,
+    };
+
+    match TwoFactorPolicy::set(&mut db, two_factor_policy).await {
+        Ok(_) => Flash::success(
+            Redirect::to(uri!("/update_two_factor_policy", index())),
+            "Two-factor policy successfully updated.",
+        ),
+        Err(e) => {
+            error_!("Update two-factor policy error: {}", e);
+            Flash::error(
+                Redirect::to(uri!("/update_two_factor_policy", index())),
+                "failed to update two-factor policy.",
+            )
+        }
+    }
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    user: User,
+    admin_user: AdminUser,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, user, Some(admin_user), csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("updatetwofactorpolicy", context))
+}
+
+pub fn update_two_factor_policy_stage() -> AdHoc {
+    AdHoc::on_ignite("Update Two Factor Policy Stage", |rocket| async {
+        rocket.mount("/update_two_factor_policy", routes![index, update])
+    })
+}
@@ -1,4 +1,5 @@
 use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::models::{PGPInfoInput, UserSettings};
 use crate::user_account::ActiveUser;
@@ -27,10 +28,12 @@ impl Context {
         flash: Option<(String, String)>,
         user: User,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let user_settings = UserSettings::single(&mut db, user.id())
             .await
             .map_err(|_| "failed to get user settings.")?;
@@ -49,6 +52,7 @@ async fn update(
     mut db: Connection<Db>,
     active_user: ActiveUser,
     _admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
 ) -> Flash<Redirect> {
     let pgp_info = pgp_info_form.into_inner();
 
@@ -82,9 +86,10 @@ async fn index(
     db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, flash, active_user.user, admin_user)
+    let context = Context::raw(db, flash, active_user.user, admin_user, csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("updateuserpgpinfo", context))
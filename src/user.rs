@@ -1,4 +1,5 @@
 use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::models::{BountyCardDisplay, RocketAuthUser, UserAccount};
 use rocket::fairing::AdHoc;
@@ -33,10 +34,12 @@ impl Context {
         maybe_page_num: Option<u32>,
         user: Option<User>,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, user.clone(), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let visited_user = RocketAuthUser::single_by_username(&mut db, username)
             .await
             .map_err(|_| "failed to get visited user.")?;
@@ -70,6 +73,7 @@ async fn disable(
     mut db: Connection<Db>,
     _user: User,
     _admin_user: AdminUser,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match disable_user(&mut db, username).await {
         Ok(_) => Ok(Flash::success(
@@ -102,6 +106,7 @@ async fn enable(
     mut db: Connection<Db>,
     _user: User,
     _admin_user: AdminUser,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     match enable_user(&mut db, username).await {
         Ok(_) => Ok(Flash::success(
@@ -136,11 +141,20 @@ async fn index(
     page_num: Option<u32>,
     user: Option<User>,
     admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, username.to_string(), flash, page_num, user, admin_user)
-        .await
-        .map_err(|_| "failed to get template context.")?;
+    let context = Context::raw(
+        db,
+        username.to_string(),
+        flash,
+        page_num,
+        user,
+        admin_user,
+        csrf_token,
+    )
+    .await
+    .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("user", context))
 }
 
@@ -1,6 +1,9 @@
 use crate::base::BaseContext;
 use crate::db::Db;
-use crate::models::{RocketAuthUser, UserSettings};
+use crate::models::{
+    CaseDispute, CaseRating, DisputeStats, Reputation, ResponseTimeStats, RocketAuthUser,
+    UserSettings,
+};
 use rocket::fairing::AdHoc;
 use rocket::request::FlashMessage;
 use rocket::serde::Serialize;
@@ -9,6 +12,8 @@ use rocket_auth::User;
 use rocket_db_pools::Connection;
 use rocket_dyn_templates::Template;
 
+const NUM_RECENT_REVIEWS: u32 = 10;
+
 #[derive(Debug, Serialize)]
 #[serde(crate = "rocket::serde")]
 struct Context {
@@ -16,6 +21,10 @@ struct Context {
     flash: Option<(String, String)>,
     visited_user: RocketAuthUser,
     visited_user_settings: UserSettings,
+    dispute_stats: DisputeStats,
+    reputation: Reputation,
+    response_time_stats: ResponseTimeStats,
+    recent_ratings: Vec<CaseRating>,
 }
 
 impl Context {
@@ -35,11 +44,29 @@ impl Context {
         let visited_user_settings = UserSettings::single(&mut db, visited_user.id.unwrap())
             .await
             .map_err(|_| "failed to get visited user settings.")?;
+        let dispute_stats = CaseDispute::stats_for_user(&mut db, visited_user.id.unwrap())
+            .await
+            .map_err(|_| "failed to get dispute stats.")?;
+        let reputation = Reputation::single_for_user(&mut db, visited_user.id.unwrap())
+            .await
+            .map_err(|_| "failed to get reputation.")?;
+        let response_time_stats =
+            ResponseTimeStats::single_for_seller(&mut db, visited_user.id.unwrap())
+                .await
+                .map_err(|_| "failed to get response time stats.")?;
+        let recent_ratings =
+            CaseRating::all_for_ratee(&mut db, visited_user.id.unwrap(), NUM_RECENT_REVIEWS)
+                .await
+                .map_err(|_| "failed to get ratings.")?;
         Ok(Context {
             base_context,
             flash,
             visited_user,
             visited_user_settings,
+            dispute_stats,
+            reputation,
+            response_time_stats,
+            recent_ratings,
         })
     }
 }
@@ -1,5 +1,7 @@
 use qr_code::QrCode;
 use rocket::serde::uuid::Uuid;
+use rocket::time::OffsetDateTime;
+use sha2::{Digest, Sha256};
 use std::io::Cursor;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
@@ -15,6 +17,13 @@ pub fn current_time_millis() -> u64 {
         .as_millis() as u64
 }
 
+/// Formats a unix time in milliseconds as a UTC date, e.g. "2022-11-01".
+pub fn date_string_from_millis(time_ms: u64) -> String {
+    OffsetDateTime::from_unix_timestamp((time_ms / 1000) as i64)
+        .map(|t| t.date().to_string())
+        .unwrap_or_default()
+}
+
 pub fn short_num_format(int: u64) -> String {
     let num = int as f64;
     if num <= 9999.9 {
@@ -54,3 +63,7 @@ pub fn from_hex(hex_str: &str) -> Vec<u8> {
 pub fn to_base64(bytes: &Vec<u8>) -> String {
     base64::encode(bytes)
 }
+
+pub fn to_sha256_hex(text: &str) -> String {
+    hex::encode(Sha256::digest(text.as_bytes()))
+}
@@ -1,6 +1,6 @@
 use crate::base::BaseContext;
 use crate::db::Db;
-use crate::models::BountyCardDisplay;
+use crate::models::{BountyCardDisplay, BountyRevisionCard};
 use rocket::fairing::AdHoc;
 use rocket::request::FlashMessage;
 use rocket::serde::Serialize;
@@ -16,6 +16,7 @@ struct Context {
     base_context: BaseContext,
     flash: Option<(String, String)>,
     bounty_cards: Vec<BountyCardDisplay>,
+    revision_cards: Vec<BountyRevisionCard>,
     page_num: u32,
 }
 
@@ -34,10 +35,14 @@ impl Context {
         let bounty_cards = BountyCardDisplay::all_pending(&mut db, PAGE_SIZE, page_num)
             .await
             .map_err(|_| "failed to get pending bounties.")?;
+        let revision_cards = BountyRevisionCard::all_pending(&mut db, PAGE_SIZE, page_num)
+            .await
+            .map_err(|_| "failed to get pending bounty edits.")?;
         Ok(Context {
             base_context,
             flash,
             bounty_cards,
+            revision_cards,
             page_num,
         })
     }
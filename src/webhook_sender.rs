@@ -0,0 +1,171 @@
+use crate::config::Config;
+use crate::models::{Webhook, WebhookDelivery};
+use crate::util;
+use hmac::{Hmac, Mac};
+use reqwest::redirect;
+use rocket::tokio::net::lookup_host;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::pool::PoolConnection;
+use sqlx::Sqlite;
+use std::net::{IpAddr, SocketAddr};
+
+const MAX_WEBHOOK_ATTEMPTS: u32 = 8;
+const WEBHOOK_RETRY_BASE_DELAY_MS: u64 = 30 * 1000;
+const WEBHOOK_TIMEOUT_S: u64 = 10;
+
+/// Header with the hex HMAC-SHA256 of the request body, keyed with the webhook secret.
+pub const SIGNATURE_HEADER: &str = "X-Satbounty-Signature";
+
+/// Posts the queued deliveries that are due, and schedules a retry for each one that fails.
+pub async fn send_pending_webhook_deliveries(
+    config: Config,
+    mut conn: PoolConnection<Sqlite>,
+) -> Result<(), String> {
+    let now = util::current_time_millis();
+    let deliveries = WebhookDelivery::all_due(&mut conn, now, MAX_WEBHOOK_ATTEMPTS)
+        .await
+        .map_err(|_| "failed to get pending webhook deliveries.")?;
+
+    for (delivery, webhook) in deliveries {
+        match post_delivery(&config, &delivery, &webhook).await {
+            Ok(status_code) => {
+                WebhookDelivery::mark_as_delivered(&mut conn, delivery.id.unwrap(), status_code)
+                    .await
+                    .map_err(|_| "failed to mark webhook delivery as delivered.")?
+            }
+            Err((status_code, e)) => {
+                println!(
+                    "failed to deliver webhook event {}: {:?}",
+                    delivery.public_id, e
+                );
+                let retry_delay_ms = WEBHOOK_RETRY_BASE_DELAY_MS << delivery.num_attempts;
+                WebhookDelivery::mark_as_failed(
+                    &mut conn,
+                    delivery.id.unwrap(),
+                    status_code,
+                    &e,
+                    now + retry_delay_ms,
+                )
+                .await
+                .map_err(|_| "failed to mark webhook delivery as failed.")?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Posts a delivery, and returns the response status code. The status code is 0 if
+/// there was no response.
+async fn post_delivery(
+    config: &Config,
+    delivery: &WebhookDelivery,
+    webhook: &Webhook,
+) -> Result<u16, (u16, String)> {
+    // The host is checked again before every delivery, and the request goes to the
+    // checked address, in case the DNS record changed after the webhook was added.
+    let (host, addr) = resolve_public_host(&webhook.url)
+        .await
+        .map_err(|e| (0, e))?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_S))
+        .redirect(redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+        .map_err(|e| (0, format!("failed to build http client: {:?}", e)))?;
+    let data: Value = serde_json::from_str(&delivery.payload)
+        .map_err(|e| (0, format!("invalid payload: {:?}", e)))?;
+    let body = json!({
+        "id": delivery.public_id,
+        "event_type": delivery.event_type,
+        "created_time_ms": delivery.created_time_ms,
+        "market_url": config.market_url,
+        "data": data,
+    })
+    .to_string();
+    let signature = sign(&webhook.secret, &body).map_err(|e| (0, e))?;
+
+    let response = client
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| (0, format!("failed to send request: {:?}", e)))?;
+    let status_code = response.status().as_u16();
+    if response.status().is_success() {
+        Ok(status_code)
+    } else {
+        Err((
+            status_code,
+            format!("unexpected status: {}", response.status()),
+        ))
+    }
+}
+
+fn sign(secret: &str, body: &str) -> Result<String, String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|_| "failed to create signature.")?;
+    mac.update(body.as_bytes());
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Checks that a webhook URL points to a public host, so that webhooks cannot be used
+/// to reach the market server or its private network.
+pub async fn check_webhook_url(url: &str) -> Result<(), String> {
+    resolve_public_host(url).await.map(|_| ())
+}
+
+/// Resolves the host of a webhook URL, and returns it with the first of its addresses.
+/// Fails if any of the addresses is not public.
+async fn resolve_public_host(url: &str) -> Result<(String, SocketAddr), String> {
+    let parsed_url = reqwest::Url::parse(url).map_err(|_| "Webhook URL is not valid.")?;
+    let host = parsed_url
+        .host_str()
+        .ok_or("Webhook URL has no host.")?
+        .to_string();
+    let port = parsed_url
+        .port_or_known_default()
+        .ok_or("Webhook URL has no port.")?;
+    let addrs: Vec<SocketAddr> = lookup_host((host.trim_matches(|c| c == '[' || c == ']'), port))
+        .await
+        .map_err(|_| "Webhook host could not be resolved.")?
+        .collect();
+    if addrs.is_empty() {
+        return Err("Webhook host could not be resolved.".to_string());
+    };
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err("Webhook host must be a public address.".to_string());
+    };
+    Ok((host, addrs[0]))
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // Shared address space (100.64.0.0/10).
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ipv4) => is_public_ip(IpAddr::V4(ipv4)),
+            None => {
+                let first_segment = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local (fc00::/7) and link-local (fe80::/10) addresses.
+                    || (first_segment & 0xfe00) == 0xfc00
+                    || (first_segment & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
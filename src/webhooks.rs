@@ -0,0 +1,228 @@
+use crate::base::BaseContext;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::models::{Webhook, WebhookDelivery, WebhookInfo};
+use crate::user_account::ActiveUser;
+use crate::util;
+use crate::webhook_sender;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::serde::Serialize;
+use rocket_auth::{AdminUser, User};
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+const PAGE_SIZE: u32 = 10;
+const MAX_WEBHOOKS_PER_USER: u32 = 5;
+const MAX_URL_LENGTH: usize = 512;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    webhooks: Vec<Webhook>,
+    signature_header: String,
+    admin_user: Option<AdminUser>,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let webhooks = Webhook::all_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get webhooks.")?;
+        Ok(Context {
+            base_context,
+            flash,
+            webhooks,
+            signature_header: webhook_sender::SIGNATURE_HEADER.to_string(),
+            admin_user,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct DeliveriesContext {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    webhook: Webhook,
+    deliveries: Vec<WebhookDelivery>,
+    page_num: u32,
+}
+
+impl DeliveriesContext {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        webhook_id: &str,
+        flash: Option<(String, String)>,
+        maybe_page_num: Option<u32>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<DeliveriesContext, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let webhook = Webhook::single_by_public_id(&mut db, webhook_id)
+            .await
+            .map_err(|_| "failed to get webhook.")?;
+        if webhook.user_id != user.id() {
+            return Err("Webhook belongs to other user.".to_string());
+        };
+        let page_num = maybe_page_num.unwrap_or(1);
+        let deliveries =
+            WebhookDelivery::all_for_webhook(&mut db, webhook.id.unwrap(), PAGE_SIZE, page_num)
+                .await
+                .map_err(|_| "failed to get webhook deliveries.")?;
+        Ok(DeliveriesContext {
+            base_context,
+            flash,
+            webhook,
+            deliveries,
+            page_num,
+        })
+    }
+}
+
+#[post("/", data = "<webhook_form>")]
+async fn new(
+    webhook_form: Form<WebhookInfo>,
+    mut db: Connection<Db>,
+    active_user: ActiveUser,
+    admin_user: Option<AdminUser>,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    let webhook_info = webhook_form.into_inner();
+
+    match create_webhook(webhook_info, &mut db, active_user.user, admin_user).await {
+        Ok(_) => Flash::success(Redirect::to(uri!("/webhooks", index())), "Webhook added."),
+        Err(e) => {
+            error_!("Add webhook error: {}", e);
+            Flash::error(Redirect::to(uri!("/webhooks", index())), e)
+        }
+    }
+}
+
+async fn create_webhook(
+    webhook_info: WebhookInfo,
+    db: &mut Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+) -> Result<(), String> {
+    let num_webhooks = Webhook::num_for_user(db, user.id())
+        .await
+        .map_err(|_| "failed to get number of webhooks.")?;
+    let now = util::current_time_millis();
+    let url = webhook_info.url.trim().to_string();
+
+    if num_webhooks >= MAX_WEBHOOKS_PER_USER {
+        return Err("Too many webhooks.".to_string());
+    };
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err("Webhook URL must start with https:// or http://.".to_string());
+    };
+    if url.len() > MAX_URL_LENGTH {
+        return Err("Webhook URL is too long.".to_string());
+    };
+    // Only admins can receive the events of every user.
+    if webhook_info.all_events && admin_user.is_none() {
+        return Err("Only admins can receive all market events.".to_string());
+    };
+    webhook_sender::check_webhook_url(&url).await?;
+
+    let webhook = Webhook {
+        id: None,
+        public_id: util::create_uuid(),
+        user_id: user.id(),
+        url,
+        secret: util::create_uuid(),
+        all_events: webhook_info.all_events,
+        created_time_ms: now,
+    };
+    Webhook::insert(webhook, db)
+        .await
+        .map_err(|_| "failed to insert webhook.")?;
+
+    Ok(())
+}
+
+#[delete("/<id>")]
+async fn delete(
+    id: &str,
+    mut db: Connection<Db>,
+    user: User,
+    _csrf: CsrfChecked,
+) -> Flash<Redirect> {
+    match delete_webhook(id, &mut db, user).await {
+        Ok(_) => Flash::success(Redirect::to(uri!("/webhooks", index())), "Webhook removed."),
+        Err(e) => {
+            error_!("Remove webhook({}) error: {}", id, e);
+            Flash::error(Redirect::to(uri!("/webhooks", index())), e)
+        }
+    }
+}
+
+async fn delete_webhook(id: &str, db: &mut Connection<Db>, user: User) -> Result<(), String> {
+    let webhook = Webhook::single_by_public_id(db, id)
+        .await
+        .map_err(|_| "failed to get webhook.")?;
+    if webhook.user_id != user.id() {
+        return Err("Webhook belongs to other user.".to_string());
+    };
+    Webhook::delete(db, webhook.id.unwrap())
+        .await
+        .map_err(|_| "failed to delete webhook.")?;
+    Ok(())
+}
+
+#[get("/<id>?<page_num>")]
+async fn deliveries(
+    flash: Option<FlashMessage<'_>>,
+    id: &str,
+    db: Connection<Db>,
+    page_num: Option<u32>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = DeliveriesContext::raw(db, id, flash, page_num, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("webhookdeliveries", context))
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    user: User,
+    admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, user, admin_user, csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("webhooks", context))
+}
+
+pub fn webhooks_stage() -> AdHoc {
+    AdHoc::on_ignite("Webhooks Stage", |rocket| async {
+        rocket.mount("/webhooks", routes![index, new, delete, deliveries])
+    })
+}
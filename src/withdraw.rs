@@ -1,8 +1,10 @@
 use crate::base::BaseContext;
 use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
 use crate::db::Db;
 use crate::lightning;
-use crate::models::{AccountInfo, Withdrawal, WithdrawalInfo};
+use crate::models::{AccountInfo, TwoFactorSettings, Withdrawal, WithdrawalInfo};
+use crate::two_factor;
 use crate::user_account::ActiveUser;
 use crate::util;
 use rocket::fairing::AdHoc;
@@ -24,6 +26,7 @@ const MAX_WITHDRAWALS_PER_USER_PER_DAY: u32 = 5;
 struct Context {
     base_context: BaseContext,
     flash: Option<(String, String)>,
+    two_factor_enabled: bool,
     account_balance_sat: i64,
 }
 
@@ -33,17 +36,23 @@ impl Context {
         flash: Option<(String, String)>,
         user: User,
         admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
     ) -> Result<Context, String> {
         let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
             .await
-            .map_err(|_| "failed to get base template.")?;
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
         let account_info = AccountInfo::account_info_for_user(&mut db, user.id)
             .await
             .map_err(|_| "failed to get account info.")?;
         let account_balance_sat = account_info.account_balance_sat;
+        let two_factor_enabled = TwoFactorSettings::enabled_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get two-factor settings.")?;
         Ok(Context {
             base_context,
             flash,
+            two_factor_enabled,
             account_balance_sat,
         })
     }
@@ -56,6 +65,7 @@ async fn new(
     active_user: ActiveUser,
     _admin_user: Option<AdminUser>,
     config: &State<Config>,
+    _csrf: CsrfChecked,
 ) -> Result<Flash<Redirect>, Flash<Redirect>> {
     let withdrawal_info = withdrawal_form.into_inner();
     match withdraw(
@@ -92,6 +102,7 @@ async fn withdraw(
     if user.is_admin {
         return Err("Admin user cannot withdraw funds.".to_string());
     }
+    two_factor::check_withdrawal_code(db, &user, withdrawal_info.totp_code).await?;
 
     let mut lightning_client = lightning::get_lnd_lightning_client(
         config.lnd_host.clone(),
@@ -171,9 +182,10 @@ async fn index(
     db: Connection<Db>,
     active_user: ActiveUser,
     admin_user: Option<AdminUser>,
+    csrf_token: CsrfToken,
 ) -> Result<Template, String> {
     let flash = flash.map(FlashMessage::into_inner);
-    let context = Context::raw(db, flash, active_user.user, admin_user)
+    let context = Context::raw(db, flash, active_user.user, admin_user, csrf_token)
         .await
         .map_err(|_| "failed to get template context.")?;
     Ok(Template::render("withdraw", context))
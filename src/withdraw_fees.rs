@@ -0,0 +1,190 @@
+use crate::base::BaseContext;
+use crate::config::Config;
+use crate::csrf::{CsrfChecked, CsrfToken};
+use crate::db::Db;
+use crate::lightning;
+use crate::models::{FeeRevenueInfo, FeeWithdrawal, TwoFactorSettings, WithdrawalInfo};
+use crate::two_factor;
+use crate::util;
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::request::FlashMessage;
+use rocket::response::Flash;
+use rocket::response::Redirect;
+use rocket::serde::Serialize;
+use rocket::State;
+use rocket_auth::AdminUser;
+use rocket_auth::User;
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::Template;
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Context {
+    base_context: BaseContext,
+    flash: Option<(String, String)>,
+    two_factor_enabled: bool,
+    fee_revenue_info: FeeRevenueInfo,
+}
+
+impl Context {
+    pub async fn raw(
+        mut db: Connection<Db>,
+        flash: Option<(String, String)>,
+        user: User,
+        admin_user: Option<AdminUser>,
+        csrf_token: CsrfToken,
+    ) -> Result<Context, String> {
+        let base_context = BaseContext::raw(&mut db, Some(user.clone()), admin_user.clone())
+            .await
+            .map_err(|_| "failed to get base template.")?
+            .with_csrf_token(csrf_token);
+        let fee_revenue_info = FeeRevenueInfo::fee_revenue_info(&mut db)
+            .await
+            .map_err(|_| "failed to get fee revenue info.")?;
+        let two_factor_enabled = TwoFactorSettings::enabled_for_user(&mut db, user.id())
+            .await
+            .map_err(|_| "failed to get two-factor settings.")?;
+        Ok(Context {
+            base_context,
+            flash,
+            two_factor_enabled,
+            fee_revenue_info,
+        })
+    }
+}
+
+#[post("/new", data = "<withdrawal_form>")]
+async fn new(
+    withdrawal_form: Form<WithdrawalInfo>,
+    mut db: Connection<Db>,
+    user: User,
+    _admin_user: AdminUser,
+    config: &State<Config>,
+    _csrf: CsrfChecked,
+) -> Result<Flash<Redirect>, Flash<Redirect>> {
+    let withdrawal_info = withdrawal_form.into_inner();
+    match withdraw_fees(
+        withdrawal_info.clone(),
+        &mut db,
+        user.clone(),
+        config.inner().clone(),
+    )
+    .await
+    {
+        Ok(_) => Ok(Flash::success(
+            Redirect::to("/fee_revenue"),
+            "Fees successfully withdrawn.",
+        )),
+        Err(e) => {
+            error_!("Fee withdrawal error: {}", e);
+            Err(Flash::error(Redirect::to(uri!("/withdraw_fees", index())), e))
+        }
+    }
+}
+
+async fn withdraw_fees(
+    withdrawal_info: WithdrawalInfo,
+    db: &mut Connection<Db>,
+    user: User,
+    config: Config,
+) -> Result<(), String> {
+    let now = util::current_time_millis();
+
+    if withdrawal_info.invoice_payment_request.is_empty() {
+        return Err("Invoice payment request cannot be empty.".to_string());
+    };
+    if !user.is_admin {
+        return Err("Only admin user can withdraw fees.".to_string());
+    }
+    two_factor::check_withdrawal_code(db, &user, withdrawal_info.totp_code).await?;
+
+    let mut lightning_client = lightning::get_lnd_lightning_client(
+        config.lnd_host.clone(),
+        config.lnd_port,
+        config.lnd_tls_cert_path.clone(),
+        config.lnd_macaroon_path.clone(),
+    )
+    .await
+    .expect("failed to get lightning client");
+    let decoded_pay_req = lightning_client
+        .decode_pay_req(tonic_openssl_lnd::lnrpc::PayReqString {
+            pay_req: withdrawal_info.invoice_payment_request.clone(),
+        })
+        .await
+        .map_err(|_| "failed to decode payment request string.")?
+        .into_inner();
+    let amount_sat: u64 = decoded_pay_req.num_satoshis.try_into().unwrap();
+    if amount_sat == 0 {
+        return Err("Invoice amount must be a positive number.".to_string());
+    }
+    let invoice_payment_request = withdrawal_info.invoice_payment_request;
+    let fee_withdrawal = FeeWithdrawal {
+        id: None,
+        public_id: util::create_uuid(),
+        user_id: user.id(),
+        amount_sat,
+        invoice_hash: "".to_string(),
+        invoice_payment_request: invoice_payment_request.clone(),
+        created_time_ms: now,
+    };
+    let send_withdrawal_funds_ret = send_withdrawal_funds(invoice_payment_request, config);
+    FeeWithdrawal::do_withdrawal(fee_withdrawal, db, send_withdrawal_funds_ret)
+        .await
+        .map_err(|e| {
+            error_!("Failed fee withdrawal: {}", e);
+            e
+        })?;
+
+    Ok(())
+}
+
+async fn send_withdrawal_funds(
+    invoice_payment_request: String,
+    config: Config,
+) -> Result<tonic_openssl_lnd::lnrpc::SendResponse, String> {
+    let mut lightning_client = lightning::get_lnd_lightning_client(
+        config.lnd_host.clone(),
+        config.lnd_port,
+        config.lnd_tls_cert_path.clone(),
+        config.lnd_macaroon_path.clone(),
+    )
+    .await
+    .expect("failed to get lightning client");
+    let send_response = lightning_client
+        .send_payment_sync(tonic_openssl_lnd::lnrpc::SendRequest {
+            payment_request: invoice_payment_request,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| format!("failed to send payment: {:?}", e))?
+        .into_inner();
+    if send_response.payment_preimage.is_empty() {
+        return Err(format!(
+            "Send Payment failure: {:?}.",
+            send_response.payment_error
+        ));
+    }
+    Ok(send_response)
+}
+
+#[get("/")]
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    db: Connection<Db>,
+    user: User,
+    admin_user: AdminUser,
+    csrf_token: CsrfToken,
+) -> Result<Template, String> {
+    let flash = flash.map(FlashMessage::into_inner);
+    let context = Context::raw(db, flash, user, Some(admin_user), csrf_token)
+        .await
+        .map_err(|_| "failed to get template context.")?;
+    Ok(Template::render("withdrawfees", context))
+}
+
+pub fn withdraw_fees_stage() -> AdHoc {
+    AdHoc::on_ignite("Withdraw Fees Stage", |rocket| async {
+        rocket.mount("/withdraw_fees", routes![index, new])
+    })
+}